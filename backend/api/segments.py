@@ -1,9 +1,10 @@
 """
 API endpoints for segment management
 """
-from fastapi import APIRouter, Depends
+from fastapi import APIRouter, Depends, File, UploadFile
 from pydantic import BaseModel, ConfigDict
 from typing import Optional, List
+from pathlib import Path
 import sqlite3
 from loguru import logger
 
@@ -228,6 +229,67 @@ async def update_segment(
         raise ApplicationError("SEGMENT_UPDATE_FAILED", status_code=500, segmentId=segment_id, error=str(e))
 
 
+@router.post("/segments/{segment_id}/audio", response_model=SegmentResponse)
+async def upload_segment_audio(
+    segment_id: str,
+    file: UploadFile = File(...),
+    conn: sqlite3.Connection = Depends(get_db)
+):
+    """
+    Upload finished audio for a segment.
+
+    Used when a project bundle is imported: the WAV is stored in the output
+    directory under the same name the TTS worker would give it, and the segment
+    is marked completed. Broadcasts segment.updated SSE event.
+    """
+    segment_repo = SegmentRepository(conn)
+
+    segment = segment_repo.get_by_id(segment_id)
+    if not segment:
+        raise ApplicationError("SEGMENT_NOT_FOUND", status_code=404, segmentId=segment_id)
+
+    if not file.filename or not file.filename.lower().endswith('.wav'):
+        raise ApplicationError("SEGMENT_INVALID_AUDIO_FILE", status_code=400, segmentId=segment_id)
+
+    try:
+        from services.audio_service import AudioService
+
+        # Store only filename (frontend constructs full URL)
+        output_path = Path(OUTPUT_DIR) / f"{segment_id}.wav"
+        content = await file.read()
+        with open(output_path, 'wb') as f:
+            f.write(content)
+
+        duration = AudioService().get_audio_duration(output_path)
+
+        updated = segment_repo.update(
+            segment_id,
+            audio_path=output_path.name,
+            start_time=0.0,
+            end_time=duration,
+            status='completed'
+        )
+
+        logger.debug(f"Uploaded audio for segment {segment_id}: {len(content)} bytes, {duration:.2f}s")
+
+        await safe_broadcast(
+            broadcaster.broadcast_segment_update,
+            {
+                "segmentId": updated["id"],
+                "chapterId": updated["chapter_id"],
+                "audioPath": updated.get("audio_path"),
+            },
+            event_description="segment.updated after audio upload"
+        )
+
+        return updated
+    except ApplicationError:
+        raise
+    except Exception as e:
+        logger.error(f"Failed to upload audio for segment {segment_id}: {e}", exc_info=True)
+        raise ApplicationError("SEGMENT_AUDIO_UPLOAD_FAILED", status_code=500, segmentId=segment_id, error=str(e))
+
+
 @router.delete("/segments/{segment_id}", response_model=DeleteResponse)
 async def delete_segment(segment_id: str, conn: sqlite3.Connection = Depends(get_db)):
     """
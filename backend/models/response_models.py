@@ -30,6 +30,7 @@ Data Flow:
 
 from pydantic import BaseModel, ConfigDict, Field
 from typing import Optional, List, Dict, Any, Literal
+from version import API_VERSION, MIN_API_VERSION
 
 # NOTE: This function is intentionally duplicated in backend/engines/base_server.py
 # because engine servers run in isolated VENVs and need their own copy.
@@ -346,6 +347,8 @@ class HealthResponse(CamelCaseModel):
     tts_engines: List[str] = Field(default_factory=list, description="Available TTS engines")
     busy: bool = Field(default=False, description="Backend currently processing long-running operation")
     active_jobs: int = Field(default=0, description="Number of active generation/export jobs")
+    api_version: int = Field(default=API_VERSION, description="Newest API revision the backend serves")
+    min_api_version: int = Field(default=MIN_API_VERSION, description="Oldest API revision the backend still serves")
 
     # Engine availability (for feature-gating)
     # NOTE: These are Optional because they're now sent via engine.status SSE events
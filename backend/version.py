@@ -2,4 +2,10 @@
 Version information for Audiobook Maker Backend
 """
 
-__version__ = "1.1.2"
+__version__ = "1.2.0"
+
+# HTTP API revision, bumped on changes that break existing clients.
+# The backend serves clients speaking MIN_API_VERSION through API_VERSION;
+# both are reported in /health so the desktop app can check compatibility.
+API_VERSION = 1
+MIN_API_VERSION = 1
@@ -0,0 +1,122 @@
+//! Atomic file replacement: write to a hidden temp file next to the target,
+//! fsync, then rename over it
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Files staged as temp files and renamed into place together on `commit`
+///
+/// Anything not committed (an error part-way, or an early return) is removed on drop.
+#[derive(Debug, Default)]
+pub struct AtomicBatch {
+    staged: Vec<(PathBuf, PathBuf)>,
+}
+
+fn temp_path(target: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    target.with_file_name(name)
+}
+
+/// Flush the directory entry so a rename survives a power loss (no-op on Windows)
+pub fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+impl AtomicBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `data` to a temp file beside `target` and flush it to disk
+    pub fn stage(&mut self, target: &Path, data: &[u8]) -> io::Result<()> {
+        let temp = temp_path(target);
+        // Register first so a failed write still gets cleaned up
+        self.staged.push((temp.clone(), target.to_path_buf()));
+        let mut file = File::create(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()
+    }
+
+    /// Rename every staged file over its target
+    pub fn commit(mut self) -> io::Result<()> {
+        let staged = std::mem::take(&mut self.staged);
+        let mut remaining = staged.iter();
+        for (temp, target) in remaining.by_ref() {
+            if let Err(e) = fs::rename(temp, target) {
+                // Drop the rest rather than leave a half-applied batch's temp files around
+                for (temp, _) in remaining {
+                    let _ = fs::remove_file(temp);
+                }
+                let _ = fs::remove_file(temp);
+                return Err(e);
+            }
+        }
+        for (_, target) in &staged {
+            if let Some(dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                sync_dir(dir)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AtomicBatch {
+    fn drop(&mut self) {
+        for (temp, _) in &self.staged {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+/// A temp file beside `target` for content written piecewise, renamed over
+/// the target on `commit` and removed if dropped before that
+#[derive(Debug)]
+pub struct AtomicFile {
+    file: File,
+    temp: PathBuf,
+    target: PathBuf,
+}
+
+impl AtomicFile {
+    pub fn create(target: &Path) -> io::Result<Self> {
+        let temp = temp_path(target);
+        // Readable too, so the content can be tagged in place before the commit
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&temp)?;
+        Ok(Self { file, temp, target: target.to_path_buf() })
+    }
+
+    pub fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Flush the temp file to disk and rename it over the target
+    pub fn commit(self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.temp, &self.target)?;
+        match self.target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            Some(dir) => sync_dir(dir),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // After a successful commit the temp path no longer exists
+        let _ = fs::remove_file(&self.temp);
+    }
+}
+
+/// Replace `path` with `data` so readers see either the old or the new content
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut batch = AtomicBatch::new();
+    batch.stage(path, data)?;
+    batch.commit()
+}
@@ -0,0 +1,531 @@
+//! ACX (Audible) submission check for exported files
+//!
+//! Levels are measured on 10 ms windows while decoding, so long chapters are
+//! never held in memory as PCM. Bitrate rules come from the MP3 frame headers.
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::error::ExportError;
+
+const RMS_MIN_DB: f64 = -23.0;
+const RMS_MAX_DB: f64 = -18.0;
+const PEAK_MAX_DB: f64 = -3.0;
+const NOISE_FLOOR_MAX_DB: f64 = -60.0;
+const HEAD_MIN_SECS: f64 = 0.5;
+const HEAD_MAX_SECS: f64 = 1.0;
+const TAIL_MIN_SECS: f64 = 1.0;
+const TAIL_MAX_SECS: f64 = 5.0;
+const SAMPLE_RATE: u32 = 44100;
+const BITRATE_KBPS: u32 = 192;
+
+/// Level analysis resolution
+const WINDOW_SECS: f64 = 0.01;
+/// The noise floor is the quietest 500 ms stretch
+const NOISE_WINDOWS: usize = 50;
+/// Offending timestamps reported per rule
+const MAX_TIMESTAMPS: usize = 20;
+/// Floor for dB values so silence does not serialize as -inf
+const MIN_DB: f64 = -144.0;
+/// Files picked up when checking a folder
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "m4b", "flac", "wav"];
+
+/// Values measured for one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcxMeasurements {
+    pub duration_secs: f64,
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Constant bitrate in kbps, `None` for VBR or non-MP3 files
+    pub bitrate_kbps: Option<u32>,
+    pub rms_db: f64,
+    pub peak_db: f64,
+    pub peak_at_secs: f64,
+    pub noise_floor_db: f64,
+    pub noise_floor_at_secs: f64,
+    pub head_room_tone_secs: f64,
+    pub tail_room_tone_secs: f64,
+}
+
+/// A broken rule with the measured value and where in the file it happens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum AcxViolation {
+    Rms { value_db: f64, min_db: f64, max_db: f64 },
+    /// `at_secs` holds the start of each stretch above the limit
+    Peak { value_db: f64, max_db: f64, at_secs: Vec<f64> },
+    NoiseFloor { value_db: f64, max_db: f64, at_secs: f64 },
+    HeadRoomTone { value_secs: f64, min_secs: f64, max_secs: f64 },
+    /// `at_secs` is where the room tone at the end starts
+    TailRoomTone { value_secs: f64, min_secs: f64, max_secs: f64, at_secs: f64 },
+    SampleRate { value: u32, expected: u32 },
+    Codec { value: String, expected: String },
+    /// `at_secs` holds the first frames at a different bitrate
+    Bitrate { value_kbps: u32, expected_kbps: u32, at_secs: Vec<f64> },
+    VariableBitrate { min_kbps: u32, max_kbps: u32, at_secs: Vec<f64> },
+    /// Channel count differs from the other files (or is not mono/stereo)
+    Channels { value: u16, expected: u16 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcxFileReport {
+    pub path: String,
+    pub passed: bool,
+    pub measurements: Option<AcxMeasurements>,
+    pub violations: Vec<AcxViolation>,
+    /// Set when the file could not be analysed at all
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcxReport {
+    pub passed: bool,
+    pub files: Vec<AcxFileReport>,
+}
+
+fn power_db(mean_square: f64) -> f64 {
+    (10.0 * mean_square.log10()).max(MIN_DB)
+}
+
+fn amplitude_db(amplitude: f64) -> f64 {
+    (20.0 * amplitude.log10()).max(MIN_DB)
+}
+
+/// Running level statistics over fixed-size windows
+struct LevelMeter {
+    channels: usize,
+    window_frames: usize,
+    window_fill: usize,
+    window_sum: f64,
+    window_peak: f32,
+    /// Mean square per window
+    energy: Vec<f64>,
+    /// Sample peak per window
+    peaks: Vec<f32>,
+    total_sum: f64,
+    total_samples: u64,
+    peak: f32,
+    peak_frame: u64,
+    frames: u64,
+}
+
+impl LevelMeter {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            channels,
+            window_frames: ((sample_rate as f64 * WINDOW_SECS) as usize).max(1),
+            window_fill: 0,
+            window_sum: 0.0,
+            window_peak: 0.0,
+            energy: Vec::new(),
+            peaks: Vec::new(),
+            total_sum: 0.0,
+            total_samples: 0,
+            peak: 0.0,
+            peak_frame: 0,
+            frames: 0,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for sample in frame {
+                let square = (*sample as f64) * (*sample as f64);
+                self.window_sum += square;
+                self.total_sum += square;
+                let magnitude = sample.abs();
+                self.window_peak = self.window_peak.max(magnitude);
+                if magnitude > self.peak {
+                    self.peak = magnitude;
+                    self.peak_frame = self.frames;
+                }
+            }
+            self.total_samples += self.channels as u64;
+            self.frames += 1;
+            self.window_fill += 1;
+            if self.window_fill == self.window_frames {
+                self.close_window();
+            }
+        }
+    }
+
+    fn close_window(&mut self) {
+        self.energy.push(self.window_sum / (self.window_fill * self.channels) as f64);
+        self.peaks.push(self.window_peak);
+        self.window_fill = 0;
+        self.window_sum = 0.0;
+        self.window_peak = 0.0;
+    }
+
+    fn finish(&mut self) {
+        if self.window_fill > 0 {
+            self.close_window();
+        }
+    }
+}
+
+struct Decoded {
+    codec: String,
+    sample_rate: u32,
+    channels: u16,
+    meter: LevelMeter,
+}
+
+fn decode_levels(path: &Path) -> Result<Decoded, ExportError> {
+    let file = File::open(path).map_err(|e| ExportError::io(path.display().to_string(), e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    // Gapless trims encoder delay/padding, which would otherwise count as room tone
+    let format_options = FormatOptions { enable_gapless: true, ..Default::default() };
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &format_options, &MetadataOptions::default())
+        .map_err(ExportError::decode)?;
+    let mut format = probed.format;
+    let track = format.default_track().ok_or_else(|| ExportError::decode("no audio track"))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let codec = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+        .unwrap_or_default();
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .map_err(ExportError::decode)?;
+
+    let sample_rate = params.sample_rate.ok_or_else(|| ExportError::decode("unknown sample rate"))?;
+    let channels = params.channels.map(|channels| channels.count()).unwrap_or(1);
+    let mut meter = LevelMeter::new(sample_rate, channels);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(ExportError::decode(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame is skipped, like a player would
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(ExportError::decode(e)),
+        };
+        if buffer.as_ref().is_none_or(|buffer| buffer.capacity() < decoded.capacity() * channels) {
+            buffer = Some(SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+        }
+        if let Some(buffer) = buffer.as_mut() {
+            buffer.copy_interleaved_ref(decoded);
+            meter.push(buffer.samples());
+        }
+    }
+    meter.finish();
+
+    Ok(Decoded { codec, sample_rate, channels: channels as u16, meter })
+}
+
+/// Bitrates found while walking the MP3 frame headers
+struct Mp3Bitrates {
+    /// (bitrate in kbps, start time) of every frame where the bitrate changes
+    changes: Vec<(u32, f64)>,
+    min_kbps: u32,
+    max_kbps: u32,
+}
+
+const MP3_V1_BITRATES: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const MP3_V2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+const MP3_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Layer III frame header fields needed to walk the stream
+struct Mp3Frame {
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    samples: u32,
+    length: u64,
+    /// Offset of the Xing/Info tag within the frame
+    side_info_end: usize,
+}
+
+fn parse_mp3_header(header: [u8; 4]) -> Option<Mp3Frame> {
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    let version = (header[1] >> 3) & 0x03;
+    let layer = (header[1] >> 1) & 0x03;
+    let bitrate_index = (header[2] >> 4) as usize;
+    let rate_index = ((header[2] >> 2) & 0x03) as usize;
+    // Layer III only, no reserved version, no free-format or invalid bitrate
+    if layer != 1 || version == 1 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+
+    let mpeg1 = version == 3;
+    let bitrate_kbps = if mpeg1 { MP3_V1_BITRATES } else { MP3_V2_BITRATES }[bitrate_index];
+    let sample_rate = MP3_SAMPLE_RATES[rate_index] >> (3 - version).min(2);
+    let samples = if mpeg1 { 1152 } else { 576 };
+    let padding = ((header[2] >> 1) & 0x01) as u64;
+    let length = (samples as u64 / 8) * bitrate_kbps as u64 * 1000 / sample_rate as u64 + padding;
+    let mono = header[3] >> 6 == 3;
+    let side_info = match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+
+    Some(Mp3Frame { bitrate_kbps, sample_rate, samples, length, side_info_end: 4 + side_info })
+}
+
+fn scan_mp3_bitrates(path: &Path) -> Result<Mp3Bitrates, ExportError> {
+    let io_error = |e: std::io::Error| ExportError::io(path.display().to_string(), e);
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+
+    // Skip an ID3v2 tag (size is syncsafe, footer flag adds 10 bytes)
+    let mut id3 = [0u8; 10];
+    let mut offset = 0u64;
+    if reader.read_exact(&mut id3).is_ok() && &id3[..3] == b"ID3" {
+        let size = id3[6..10].iter().fold(0u64, |size, byte| (size << 7) | (*byte & 0x7F) as u64);
+        offset = 10 + size + if id3[5] & 0x10 != 0 { 10 } else { 0 };
+    }
+    reader.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+
+    let mut bitrates = Mp3Bitrates { changes: Vec::new(), min_kbps: u32::MAX, max_kbps: 0 };
+    let mut elapsed = 0f64;
+    let mut first = true;
+    let mut header = [0u8; 4];
+    while reader.read_exact(&mut header).is_ok() {
+        let Some(frame) = parse_mp3_header(header) else {
+            // Not a frame boundary: resync one byte further
+            reader.seek_relative(-3).map_err(io_error)?;
+            continue;
+        };
+
+        let mut body = vec![0u8; (frame.length as usize).saturating_sub(4)];
+        if reader.read_exact(&mut body).is_err() {
+            break;
+        }
+        // The Xing/Info frame carries no audio and LAME writes it at any bitrate
+        let tag_at = frame.side_info_end - 4;
+        let is_tag_frame = first && matches!(body.get(tag_at..tag_at + 4), Some(b"Xing") | Some(b"Info"));
+        first = false;
+        if !is_tag_frame {
+            if bitrates.changes.last().is_none_or(|(kbps, _)| *kbps != frame.bitrate_kbps) {
+                bitrates.changes.push((frame.bitrate_kbps, elapsed));
+            }
+            bitrates.min_kbps = bitrates.min_kbps.min(frame.bitrate_kbps);
+            bitrates.max_kbps = bitrates.max_kbps.max(frame.bitrate_kbps);
+            elapsed += frame.samples as f64 / frame.sample_rate as f64;
+        }
+    }
+
+    if bitrates.changes.is_empty() {
+        return Err(ExportError::decode("no MPEG audio frames found"));
+    }
+    Ok(bitrates)
+}
+
+/// Start times of runs of windows matching `predicate`
+fn runs(windows: impl Iterator<Item = bool>) -> Vec<f64> {
+    let mut starts = Vec::new();
+    let mut inside = false;
+    for (index, matched) in windows.enumerate() {
+        if matched && !inside && starts.len() < MAX_TIMESTAMPS {
+            starts.push(index as f64 * WINDOW_SECS);
+        }
+        inside = matched;
+    }
+    starts
+}
+
+fn check_file(path: &Path) -> Result<(AcxMeasurements, Vec<AcxViolation>), ExportError> {
+    let Decoded { codec, sample_rate, channels, meter } = decode_levels(path)?;
+    let mut violations = Vec::new();
+
+    let duration_secs = meter.frames as f64 / sample_rate as f64;
+    let rms_db = power_db(meter.total_sum / meter.total_samples.max(1) as f64);
+    let peak_db = amplitude_db(meter.peak as f64);
+    let peak_at_secs = meter.peak_frame as f64 / sample_rate as f64;
+
+    // Quietest 500 ms stretch, ignoring digital silence (which is not room tone)
+    let span = NOISE_WINDOWS.min(meter.energy.len()).max(1);
+    let (noise_index, noise_energy) = meter
+        .energy
+        .windows(span)
+        .enumerate()
+        .filter(|(_, windows)| windows.iter().all(|energy| *energy > 0.0))
+        .map(|(index, windows)| (index, windows.iter().sum::<f64>() / span as f64))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0));
+    let noise_floor_db = power_db(noise_energy);
+    let noise_floor_at_secs = noise_index as f64 * WINDOW_SECS;
+
+    // Anything 20 dB above the room tone counts as narration
+    let speech_threshold_db = (noise_floor_db + 20.0).clamp(-50.0, -30.0);
+    let is_speech = |energy: &f64| power_db(*energy) > speech_threshold_db;
+    let first_speech = meter.energy.iter().position(is_speech);
+    let last_speech = meter.energy.iter().rposition(is_speech);
+    let head_room_tone_secs = first_speech.map_or(duration_secs, |index| index as f64 * WINDOW_SECS);
+    let tail_start_secs = last_speech.map_or(0.0, |index| ((index + 1) as f64 * WINDOW_SECS).min(duration_secs));
+    let tail_room_tone_secs = duration_secs - tail_start_secs;
+
+    if !(RMS_MIN_DB..=RMS_MAX_DB).contains(&rms_db) {
+        violations.push(AcxViolation::Rms { value_db: rms_db, min_db: RMS_MIN_DB, max_db: RMS_MAX_DB });
+    }
+    if peak_db > PEAK_MAX_DB {
+        let limit = 10f64.powf(PEAK_MAX_DB / 20.0) as f32;
+        violations.push(AcxViolation::Peak {
+            value_db: peak_db,
+            max_db: PEAK_MAX_DB,
+            at_secs: runs(meter.peaks.iter().map(|peak| *peak > limit)),
+        });
+    }
+    if noise_floor_db > NOISE_FLOOR_MAX_DB {
+        violations.push(AcxViolation::NoiseFloor {
+            value_db: noise_floor_db,
+            max_db: NOISE_FLOOR_MAX_DB,
+            at_secs: noise_floor_at_secs,
+        });
+    }
+    if !(HEAD_MIN_SECS..=HEAD_MAX_SECS).contains(&head_room_tone_secs) {
+        violations.push(AcxViolation::HeadRoomTone {
+            value_secs: head_room_tone_secs,
+            min_secs: HEAD_MIN_SECS,
+            max_secs: HEAD_MAX_SECS,
+        });
+    }
+    if !(TAIL_MIN_SECS..=TAIL_MAX_SECS).contains(&tail_room_tone_secs) {
+        violations.push(AcxViolation::TailRoomTone {
+            value_secs: tail_room_tone_secs,
+            min_secs: TAIL_MIN_SECS,
+            max_secs: TAIL_MAX_SECS,
+            at_secs: tail_start_secs,
+        });
+    }
+    if sample_rate != SAMPLE_RATE {
+        violations.push(AcxViolation::SampleRate { value: sample_rate, expected: SAMPLE_RATE });
+    }
+
+    let mut bitrate_kbps = None;
+    if codec == "mp3" {
+        let bitrates = scan_mp3_bitrates(path)?;
+        if bitrates.min_kbps != bitrates.max_kbps {
+            violations.push(AcxViolation::VariableBitrate {
+                min_kbps: bitrates.min_kbps,
+                max_kbps: bitrates.max_kbps,
+                at_secs: bitrates.changes.iter().skip(1).take(MAX_TIMESTAMPS).map(|(_, at)| *at).collect(),
+            });
+        } else {
+            bitrate_kbps = Some(bitrates.min_kbps);
+            if bitrates.min_kbps != BITRATE_KBPS {
+                violations.push(AcxViolation::Bitrate {
+                    value_kbps: bitrates.min_kbps,
+                    expected_kbps: BITRATE_KBPS,
+                    at_secs: vec![0.0],
+                });
+            }
+        }
+    } else {
+        violations.push(AcxViolation::Codec { value: codec.clone(), expected: "mp3".to_string() });
+    }
+
+    let measurements = AcxMeasurements {
+        duration_secs,
+        codec,
+        sample_rate,
+        channels,
+        bitrate_kbps,
+        rms_db,
+        peak_db,
+        peak_at_secs,
+        noise_floor_db,
+        noise_floor_at_secs,
+        head_room_tone_secs,
+        tail_room_tone_secs,
+    };
+    Ok((measurements, violations))
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Check one file, or every audio file directly inside a folder
+pub fn check(path: &Path) -> Result<AcxReport, ExportError> {
+    let display = path.display().to_string();
+    let paths: Vec<PathBuf> = if path.is_dir() {
+        let mut paths: Vec<PathBuf> = fs::read_dir(path)
+            .map_err(|e| ExportError::io(&display, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_audio_file(path))
+            .collect();
+        paths.sort();
+        paths
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if paths.is_empty() {
+        return Err(ExportError::NoAudioFiles { path: display });
+    }
+
+    let mut files: Vec<AcxFileReport> = paths
+        .iter()
+        .map(|path| {
+            let (measurements, violations, error) = match check_file(path) {
+                Ok((measurements, violations)) => (Some(measurements), violations, None),
+                Err(e) => (None, Vec::new(), Some(e.to_string())),
+            };
+            AcxFileReport {
+                path: path.display().to_string(),
+                passed: false,
+                measurements,
+                violations,
+                error,
+            }
+        })
+        .collect();
+
+    // All files of one book must share a channel layout; the first file sets it
+    let expected = files
+        .iter()
+        .filter_map(|file| file.measurements.as_ref())
+        .map(|measurements| measurements.channels)
+        .find(|channels| (1..=2).contains(channels))
+        .unwrap_or(1);
+    for file in &mut files {
+        if let Some(channels) = file.measurements.as_ref().map(|measurements| measurements.channels) {
+            if channels != expected {
+                file.violations.push(AcxViolation::Channels { value: channels, expected });
+            }
+        }
+        file.passed = file.error.is_none() && file.violations.is_empty();
+    }
+
+    Ok(AcxReport {
+        passed: files.iter().all(|file| file.passed),
+        files,
+    })
+}
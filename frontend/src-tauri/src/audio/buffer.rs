@@ -0,0 +1,291 @@
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+
+use rubato::{FftFixedIn, Resampler};
+
+use super::error::ExportError;
+use super::format::PcmSpec;
+
+/// Interleaved floating point PCM in the range [-1.0, 1.0]
+#[derive(Debug, Clone, Default)]
+pub struct AudioBuffer {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+const RESAMPLE_CHUNK: usize = 4096;
+
+impl AudioBuffer {
+    /// Decode a RIFF/WAVE file, or headerless PCM when `raw` describes its layout
+    pub fn decode(data: &[u8], raw: Option<&PcmSpec>) -> Result<Self, ExportError> {
+        if data.starts_with(b"RIFF") || data.starts_with(b"RIFX") {
+            return Self::decode_wav(data);
+        }
+
+        match raw {
+            Some(spec) => Self::decode_pcm(data, spec),
+            None => Err(ExportError::decode("input is not a WAV file and no PCM layout was given")),
+        }
+    }
+
+    fn decode_wav(data: &[u8]) -> Result<Self, ExportError> {
+        let reader = hound::WavReader::new(Cursor::new(data)).map_err(ExportError::decode)?;
+        let spec = reader.spec();
+
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .into_samples::<f32>()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(ExportError::decode)?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|sample| sample.map(|value| value as f32 * scale))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ExportError::decode)?
+            }
+        };
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            samples,
+        })
+    }
+
+    fn decode_pcm(data: &[u8], spec: &PcmSpec) -> Result<Self, ExportError> {
+        let width = match spec.bits_per_sample {
+            8 | 16 | 24 | 32 => spec.bits_per_sample as usize / 8,
+            bits => return Err(ExportError::decode(format!("unsupported PCM bit depth {}", bits))),
+        };
+        if spec.channels == 0 || spec.sample_rate == 0 {
+            return Err(ExportError::decode("PCM layout needs a sample rate and channel count"));
+        }
+
+        let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+        let samples = data
+            .chunks_exact(width)
+            .map(|bytes| {
+                let value = match width {
+                    // 8-bit PCM is unsigned by convention
+                    1 => bytes[0] as i32 - 128,
+                    2 => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+                    3 => i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8,
+                    _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                };
+                value as f32 * scale
+            })
+            .collect();
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            samples,
+        })
+    }
+
+    /// Number of sample frames (samples per channel)
+    pub fn frames(&self) -> usize {
+        if self.channels == 0 {
+            0
+        } else {
+            self.samples.len() / self.channels as usize
+        }
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        if self.sample_rate == 0 {
+            0.0
+        } else {
+            self.frames() as f64 / self.sample_rate as f64
+        }
+    }
+
+    /// Split into one buffer per channel
+    pub fn deinterleave(&self) -> Vec<Vec<f32>> {
+        let channels = self.channels as usize;
+        (0..channels)
+            .map(|channel| self.samples.iter().skip(channel).step_by(channels).copied().collect())
+            .collect()
+    }
+
+    fn from_planar(sample_rate: u32, planes: &[Vec<f32>]) -> Self {
+        let frames = planes.iter().map(Vec::len).min().unwrap_or(0);
+        let mut samples = Vec::with_capacity(frames * planes.len());
+        for frame in 0..frames {
+            for plane in planes {
+                samples.push(plane[frame]);
+            }
+        }
+        Self {
+            sample_rate,
+            channels: planes.len() as u16,
+            samples,
+        }
+    }
+
+    /// Downmix to mono or duplicate mono into stereo
+    pub fn remix(self, channels: u16) -> Self {
+        if channels == self.channels || self.channels == 0 {
+            return self;
+        }
+
+        let source = self.channels as usize;
+        let samples = match channels {
+            1 => self
+                .samples
+                .chunks_exact(source)
+                .map(|frame| frame.iter().sum::<f32>() / source as f32)
+                .collect(),
+            _ => self
+                .samples
+                .chunks_exact(source)
+                .flat_map(|frame| {
+                    let mono = frame.iter().sum::<f32>() / source as f32;
+                    std::iter::repeat_n(mono, channels as usize)
+                })
+                .collect(),
+        };
+
+        Self {
+            sample_rate: self.sample_rate,
+            channels,
+            samples,
+        }
+    }
+
+    /// Resample to `sample_rate` with a band-limited FFT resampler
+    pub fn resample(self, sample_rate: u32) -> Result<Self, ExportError> {
+        if sample_rate == self.sample_rate || self.frames() == 0 {
+            return Ok(Self { sample_rate, ..self });
+        }
+
+        let channels = self.channels as usize;
+        let mut resampler = FftFixedIn::<f32>::new(
+            self.sample_rate as usize,
+            sample_rate as usize,
+            RESAMPLE_CHUNK,
+            2,
+            channels,
+        )
+        .map_err(|e| ExportError::encode("resampler", e))?;
+
+        let input = self.deinterleave();
+        let expected = (self.frames() as u64 * sample_rate as u64).div_ceil(self.sample_rate as u64) as usize;
+        let delay = resampler.output_delay();
+        let mut output: Vec<Vec<f32>> = vec![Vec::with_capacity(expected + delay); channels];
+
+        let mut position = 0;
+        while position < self.frames() {
+            let needed = resampler.input_frames_next();
+            let end = (position + needed).min(self.frames());
+            let chunk: Vec<&[f32]> = input.iter().map(|plane| &plane[position..end]).collect();
+            let processed = if end - position == needed {
+                resampler.process(&chunk, None)
+            } else {
+                resampler.process_partial(Some(&chunk), None)
+            }
+            .map_err(|e| ExportError::encode("resampler", e))?;
+            for (plane, data) in output.iter_mut().zip(processed) {
+                plane.extend(data);
+            }
+            position = end;
+        }
+
+        // Drain the filter until the delayed tail has come out
+        while output[0].len() < expected + delay {
+            let processed = resampler
+                .process_partial::<&[f32]>(None, None)
+                .map_err(|e| ExportError::encode("resampler", e))?;
+            for (plane, data) in output.iter_mut().zip(processed) {
+                plane.extend(data);
+            }
+        }
+
+        for plane in &mut output {
+            plane.drain(..delay);
+            plane.truncate(expected);
+        }
+
+        Ok(Self::from_planar(sample_rate, &output))
+    }
+
+    /// Convert to signed integers at `bits` resolution, clamping overs
+    pub fn to_int(&self, bits: u16) -> Vec<i32> {
+        let max = ((1i64 << (bits - 1)) - 1) as f32;
+        self.samples
+            .iter()
+            .map(|sample| (sample.clamp(-1.0, 1.0) * max).round() as i32)
+            .collect()
+    }
+
+    pub fn to_i16(&self) -> Vec<i16> {
+        self.to_int(16).into_iter().map(|sample| sample as i16).collect()
+    }
+}
+
+/// WAV file read in slices, so a whole rendered book never has to fit in memory
+pub struct WavStream {
+    reader: hound::WavReader<BufReader<File>>,
+    path: String,
+}
+
+impl WavStream {
+    pub fn open(path: &Path) -> Result<Self, ExportError> {
+        let display = path.display().to_string();
+        let file = File::open(path).map_err(|e| ExportError::io(&display, e))?;
+        let reader = hound::WavReader::new(BufReader::new(file)).map_err(ExportError::decode)?;
+        Ok(Self { reader, path: display })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.reader.spec().sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.reader.spec().channels
+    }
+
+    /// Length in sample frames
+    pub fn frames(&self) -> u64 {
+        self.reader.duration() as u64
+    }
+
+    /// Read frames `start..end` (clamped to the end of the file)
+    pub fn read(&mut self, start: u64, end: u64) -> Result<AudioBuffer, ExportError> {
+        let spec = self.reader.spec();
+        let end = end.min(self.frames());
+        let start = start.min(end);
+        self.reader
+            .seek(start as u32)
+            .map_err(|e| ExportError::io(&self.path, e))?;
+        let count = (end - start) as usize * spec.channels as usize;
+
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => self
+                .reader
+                .samples::<f32>()
+                .take(count)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(ExportError::decode)?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                self.reader
+                    .samples::<i32>()
+                    .take(count)
+                    .map(|sample| sample.map(|value| value as f32 * scale))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ExportError::decode)?
+            }
+        };
+
+        Ok(AudioBuffer {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            samples,
+        })
+    }
+}
@@ -0,0 +1,329 @@
+//! Chapter marker sidecar files: CUE, FFMETADATA1, WebVTT, Podlove JSON, mp4chaps
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::error::ExportError;
+use crate::atomic::AtomicBatch;
+
+/// Sidecar formats, each written next to the audio file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChapterFormat {
+    /// CUE sheet (`book.cue`)
+    Cue,
+    /// FFmpeg metadata (`book.ffmetadata`), for `ffmpeg -i book.ffmetadata -map_chapters`
+    Ffmetadata,
+    /// WebVTT chapter track (`book.chapters.vtt`)
+    Webvtt,
+    /// Podlove Simple Chapters as JSON (`book.podlove.json`)
+    Podlove,
+    /// mp4v2 `mp4chaps` import format (`book.chapters.txt`)
+    Mp4chaps,
+}
+
+impl ChapterFormat {
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Cue => ".cue",
+            Self::Ffmetadata => ".ffmetadata",
+            Self::Webvtt => ".chapters.vtt",
+            Self::Podlove => ".podlove.json",
+            Self::Mp4chaps => ".chapters.txt",
+        }
+    }
+}
+
+/// One chapter and the durations of its segments (dividers included), in playback order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterSpan {
+    pub title: String,
+    pub segment_durations_ms: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterExportOptions {
+    pub formats: Vec<ChapterFormat>,
+    /// Book title and author for formats with a global header
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Silence the export inserts between segments of a chapter
+    #[serde(default)]
+    pub pause_between_segments_ms: u64,
+    /// Silence the export inserts between chapters
+    #[serde(default)]
+    pub pause_between_chapters_ms: u64,
+}
+
+/// Chapter position in the exported audio
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterTime {
+    pub title: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterFile {
+    pub format: ChapterFormat,
+    pub path: String,
+}
+
+/// Where a chapter and each of its segments sit in the merged export, in ms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterLayout {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// `(start_ms, end_ms)` of every segment, without the pauses between them
+    pub segments: Vec<(u64, u64)>,
+}
+
+/// Lay chapters out back to back the way the export merges them: pauses go
+/// between segments and between chapters, never after the last one
+pub fn layout(
+    chapters: &[ChapterSpan],
+    pause_between_segments_ms: u64,
+    pause_between_chapters_ms: u64,
+) -> Vec<ChapterLayout> {
+    let mut position = 0;
+    chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            if index > 0 {
+                position += pause_between_chapters_ms;
+            }
+            let start_ms = position;
+            let segments = chapter
+                .segment_durations_ms
+                .iter()
+                .enumerate()
+                .map(|(segment, duration)| {
+                    if segment > 0 {
+                        position += pause_between_segments_ms;
+                    }
+                    let start = position;
+                    position += duration;
+                    (start, position)
+                })
+                .collect();
+            ChapterLayout { start_ms, end_ms: position, segments }
+        })
+        .collect()
+}
+
+pub fn chapter_times(chapters: &[ChapterSpan], options: &ChapterExportOptions) -> Vec<ChapterTime> {
+    layout(chapters, options.pause_between_segments_ms, options.pause_between_chapters_ms)
+        .into_iter()
+        .zip(chapters)
+        .map(|(layout, chapter)| ChapterTime {
+            title: chapter.title.clone(),
+            start_ms: layout.start_ms,
+            end_ms: layout.end_ms,
+        })
+        .collect()
+}
+
+/// `HH:MM:SS.mmm`, as used by WebVTT, Podlove and mp4chaps
+fn clock(ms: u64) -> String {
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+/// CUE `MM:SS:FF` with 75 frames per second; minutes are not wrapped into hours
+fn cue_time(ms: u64) -> String {
+    format!("{:02}:{:02}:{:02}", ms / 60_000, ms / 1000 % 60, ms % 1000 * 75 / 1000)
+}
+
+/// CUE strings cannot contain double quotes
+fn cue_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+fn ffmetadata_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn vtt_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\n', " ")
+}
+
+fn cue_file_type(audio: &Path) -> &'static str {
+    match audio.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("mp3") => "MP3",
+        Some("aif" | "aiff") => "AIFF",
+        _ => "WAVE",
+    }
+}
+
+fn render(format: ChapterFormat, audio: &Path, chapters: &[ChapterTime], options: &ChapterExportOptions) -> String {
+    let mut out = String::new();
+    match format {
+        ChapterFormat::Cue => {
+            if let Some(author) = &options.author {
+                let _ = writeln!(out, "PERFORMER {}", cue_quote(author));
+            }
+            if let Some(title) = &options.title {
+                let _ = writeln!(out, "TITLE {}", cue_quote(title));
+            }
+            let file_name = audio.file_name().unwrap_or_default().to_string_lossy();
+            let _ = writeln!(out, "FILE {} {}", cue_quote(&file_name), cue_file_type(audio));
+            for (index, chapter) in chapters.iter().enumerate() {
+                let _ = writeln!(out, "  TRACK {:02} AUDIO", index + 1);
+                let _ = writeln!(out, "    TITLE {}", cue_quote(&chapter.title));
+                let _ = writeln!(out, "    INDEX 01 {}", cue_time(chapter.start_ms));
+            }
+        }
+        ChapterFormat::Ffmetadata => {
+            out.push_str(";FFMETADATA1\n");
+            if let Some(title) = &options.title {
+                let _ = writeln!(out, "title={}", ffmetadata_escape(title));
+            }
+            if let Some(author) = &options.author {
+                let _ = writeln!(out, "artist={}", ffmetadata_escape(author));
+            }
+            for chapter in chapters {
+                let _ = write!(
+                    out,
+                    "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                    chapter.start_ms,
+                    chapter.end_ms,
+                    ffmetadata_escape(&chapter.title)
+                );
+            }
+        }
+        ChapterFormat::Webvtt => {
+            out.push_str("WEBVTT\n");
+            for (index, chapter) in chapters.iter().enumerate() {
+                let _ = write!(
+                    out,
+                    "\n{}\n{} --> {}\n{}\n",
+                    index + 1,
+                    clock(chapter.start_ms),
+                    clock(chapter.end_ms),
+                    vtt_escape(&chapter.title)
+                );
+            }
+        }
+        ChapterFormat::Podlove => {
+            let entries: Vec<serde_json::Value> = chapters
+                .iter()
+                .map(|chapter| serde_json::json!({ "start": clock(chapter.start_ms), "title": chapter.title }))
+                .collect();
+            out = serde_json::to_string_pretty(&entries).unwrap_or_default();
+            out.push('\n');
+        }
+        ChapterFormat::Mp4chaps => {
+            for chapter in chapters {
+                let _ = writeln!(out, "{} {}", clock(chapter.start_ms), chapter.title.replace('\n', " "));
+            }
+        }
+    }
+    out
+}
+
+/// `book.mp3` + `.cue` -> `book.cue`
+fn sidecar_path(audio: &Path, format: ChapterFormat) -> PathBuf {
+    let stem = audio.file_stem().unwrap_or_default().to_string_lossy();
+    audio.with_file_name(format!("{}{}", stem, format.suffix()))
+}
+
+/// Write the selected sidecar formats next to `audio`; every file is fully
+/// written and synced before any of them replaces an existing one
+pub fn write_chapter_files(
+    audio: &Path,
+    chapters: &[ChapterSpan],
+    options: &ChapterExportOptions,
+) -> Result<Vec<ChapterFile>, ExportError> {
+    if chapters.is_empty() {
+        return Err(ExportError::NoChapters);
+    }
+    let times = chapter_times(chapters, options);
+
+    let mut formats: Vec<ChapterFormat> = Vec::new();
+    for format in &options.formats {
+        if !formats.contains(format) {
+            formats.push(*format);
+        }
+    }
+    let mut batch = AtomicBatch::new();
+    let mut written = Vec::with_capacity(formats.len());
+    for format in formats {
+        let path = sidecar_path(audio, format);
+        let content = render(format, audio, &times, options);
+        batch
+            .stage(&path, content.as_bytes())
+            .map_err(|e| ExportError::io(path.display().to_string(), e))?;
+        written.push(ChapterFile { format, path: path.display().to_string() });
+    }
+    batch.commit().map_err(|e| ExportError::io(audio.display().to_string(), e))?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book() -> (Vec<ChapterSpan>, ChapterExportOptions) {
+        let chapters = vec![
+            ChapterSpan { title: "One".to_string(), segment_durations_ms: vec![1_000, 2_000] },
+            ChapterSpan { title: "Two; \"the end\"".to_string(), segment_durations_ms: vec![61_500] },
+        ];
+        let options = ChapterExportOptions {
+            title: Some("Book".to_string()),
+            author: Some("Author".to_string()),
+            pause_between_segments_ms: 500,
+            pause_between_chapters_ms: 1_000,
+            ..Default::default()
+        };
+        (chapters, options)
+    }
+
+    #[test]
+    fn pauses_go_between_segments_and_chapters_only() {
+        let (chapters, options) = book();
+        let layout = layout(&chapters, options.pause_between_segments_ms, options.pause_between_chapters_ms);
+        assert_eq!(layout[0], ChapterLayout { start_ms: 0, end_ms: 3_500, segments: vec![(0, 1_000), (1_500, 3_500)] });
+        assert_eq!(layout[1], ChapterLayout { start_ms: 4_500, end_ms: 66_000, segments: vec![(4_500, 66_000)] });
+    }
+
+    #[test]
+    fn formats_render_their_own_timestamps_and_escaping() {
+        let (chapters, options) = book();
+        let times = chapter_times(&chapters, &options);
+        let audio = Path::new("/out/book.mp3");
+
+        let cue = render(ChapterFormat::Cue, audio, &times, &options);
+        assert!(cue.contains("FILE \"book.mp3\" MP3\n"));
+        assert!(cue.contains("    TITLE \"Two; 'the end'\"\n    INDEX 01 00:04:37\n"));
+
+        let ffmetadata = render(ChapterFormat::Ffmetadata, audio, &times, &options);
+        assert!(ffmetadata.starts_with(";FFMETADATA1\ntitle=Book\nartist=Author\n"));
+        assert!(ffmetadata.contains("START=4500\nEND=66000\ntitle=Two\\; \"the end\"\n"));
+
+        let webvtt = render(ChapterFormat::Webvtt, audio, &times, &options);
+        assert!(webvtt.contains("\n2\n00:00:04.500 --> 00:01:06.000\n"));
+
+        let mp4chaps = render(ChapterFormat::Mp4chaps, audio, &times, &options);
+        assert_eq!(mp4chaps, "00:00:00.000 One\n00:00:04.500 Two; \"the end\"\n");
+    }
+
+    #[test]
+    fn sidecars_are_named_after_the_audio_file() {
+        let audio = Path::new("/out/book.m4b");
+        assert_eq!(sidecar_path(audio, ChapterFormat::Webvtt), Path::new("/out/book.chapters.vtt"));
+        assert_eq!(sidecar_path(audio, ChapterFormat::Cue), Path::new("/out/book.cue"));
+    }
+}
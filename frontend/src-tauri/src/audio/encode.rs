@@ -0,0 +1,381 @@
+use std::io::Cursor;
+
+use audiopus::coder::Encoder as OpusEncoder;
+use fdk_aac::enc::{AudioObjectType, BitRate as AacBitRate, ChannelMode, Encoder as AacEncoder, EncoderParams, Transport};
+use mp3lame_encoder::{Bitrate as LameBitrate, FlushNoGap, InterleavedPcm, Mode, MonoPcm, Quality, VbrMode};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+use super::buffer::AudioBuffer;
+use super::error::ExportError;
+use super::format::{BitrateMode, EncodeSettings, ExportFormat};
+use super::mp4::{AacTrackConfig, Brand, Mp4Muxer, Mp4Tags};
+
+/// PCM frames handed to LAME per call
+const MP3_CHUNK_FRAMES: usize = 1152 * 16;
+/// Opus frame duration of 20 ms expressed in 48 kHz ticks
+const OPUS_FRAME_48K: u64 = 960;
+const OPUS_MAX_PACKET: usize = 4000;
+
+/// Encode `audio` (already resampled/remixed to `settings`) into the target container
+pub fn encode(audio: &AudioBuffer, settings: &EncodeSettings) -> Result<Vec<u8>, ExportError> {
+    match settings.format {
+        ExportFormat::Mp3 => encode_mp3(audio, settings),
+        ExportFormat::M4a => encode_m4a(audio, settings),
+        ExportFormat::Flac => encode_flac(audio, settings),
+        ExportFormat::Opus => encode_opus(audio, settings),
+        ExportFormat::Wav => encode_wav(audio, settings),
+    }
+}
+
+fn encode_wav(audio: &AudioBuffer, settings: &EncodeSettings) -> Result<Vec<u8>, ExportError> {
+    let codec = settings.format.codec_name();
+    let spec = hound::WavSpec {
+        channels: audio.channels,
+        sample_rate: audio.sample_rate,
+        bits_per_sample: settings.bit_depth,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).map_err(|e| ExportError::encode(codec, e))?;
+        for sample in audio.to_int(settings.bit_depth) {
+            writer.write_sample(sample).map_err(|e| ExportError::encode(codec, e))?;
+        }
+        writer.finalize().map_err(|e| ExportError::encode(codec, e))?;
+    }
+    Ok(cursor.into_inner())
+}
+
+const FLAC_VORBIS_COMMENT: u8 = 4;
+
+fn encode_flac(audio: &AudioBuffer, settings: &EncodeSettings) -> Result<Vec<u8>, ExportError> {
+    use flacenc::component::BitRepr;
+    use flacenc::error::Verify;
+
+    let codec = settings.format.codec_name();
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| ExportError::encode(codec, e))?;
+    let source = flacenc::source::MemSource::from_samples(
+        &audio.to_int(settings.bit_depth),
+        audio.channels as usize,
+        settings.bit_depth as usize,
+        audio.sample_rate as usize,
+    );
+    let mut stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| ExportError::encode(codec, format!("{:?}", e)))?;
+    // Like the reference encoder, always write a VORBIS_COMMENT block so tags can be added in place
+    let comments = flacenc::component::MetadataBlockData::new_unknown(FLAC_VORBIS_COMMENT, &vendor_comments())
+        .map_err(|e| ExportError::encode(codec, e))?;
+    stream.add_metadata_block(comments);
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream.write(&mut sink).map_err(|e| ExportError::encode(codec, e))?;
+    Ok(sink.into_inner())
+}
+
+fn lame_bitrate(kbps: u32) -> Option<LameBitrate> {
+    Some(match kbps {
+        8 => LameBitrate::Kbps8,
+        16 => LameBitrate::Kbps16,
+        24 => LameBitrate::Kbps24,
+        32 => LameBitrate::Kbps32,
+        40 => LameBitrate::Kbps40,
+        48 => LameBitrate::Kbps48,
+        64 => LameBitrate::Kbps64,
+        80 => LameBitrate::Kbps80,
+        96 => LameBitrate::Kbps96,
+        112 => LameBitrate::Kbps112,
+        128 => LameBitrate::Kbps128,
+        160 => LameBitrate::Kbps160,
+        192 => LameBitrate::Kbps192,
+        224 => LameBitrate::Kbps224,
+        256 => LameBitrate::Kbps256,
+        320 => LameBitrate::Kbps320,
+        _ => return None,
+    })
+}
+
+fn lame_quality(value: u8) -> Quality {
+    match value {
+        0 => Quality::Best,
+        1 => Quality::SecondBest,
+        2 => Quality::NearBest,
+        3 => Quality::VeryNice,
+        4 => Quality::Nice,
+        5 => Quality::Good,
+        6 => Quality::Decent,
+        7 => Quality::Ok,
+        8 => Quality::SecondWorst,
+        _ => Quality::Worst,
+    }
+}
+
+fn encode_mp3(audio: &AudioBuffer, settings: &EncodeSettings) -> Result<Vec<u8>, ExportError> {
+    let codec = settings.format.codec_name();
+    let lame_error = |e: mp3lame_encoder::BuildError| ExportError::encode(codec, e);
+
+    let mut builder = mp3lame_encoder::Builder::new().ok_or_else(|| ExportError::encode(codec, "failed to initialise LAME"))?;
+    builder.set_num_channels(audio.channels as u8).map_err(lame_error)?;
+    builder.set_sample_rate(audio.sample_rate).map_err(lame_error)?;
+    builder
+        .set_mode(if audio.channels == 1 { Mode::Mono } else { Mode::JointStereo })
+        .map_err(lame_error)?;
+    builder.set_quality(Quality::NearBest).map_err(lame_error)?;
+
+    match settings.bitrate_mode {
+        BitrateMode::Cbr => {
+            let kbps = settings.bitrate.unwrap_or(128);
+            let bitrate = lame_bitrate(kbps).ok_or(ExportError::InvalidBitrate {
+                codec,
+                bitrate: kbps,
+                sample_rate: audio.sample_rate,
+            })?;
+            builder.set_vbr_mode(VbrMode::Off).map_err(lame_error)?;
+            builder.set_brate(bitrate).map_err(lame_error)?;
+        }
+        BitrateMode::Vbr => {
+            builder.set_vbr_mode(VbrMode::Mtrh).map_err(lame_error)?;
+            builder
+                .set_vbr_quality(lame_quality(settings.vbr_quality.unwrap_or(4)))
+                .map_err(lame_error)?;
+            builder.set_to_write_vbr_tag(true).map_err(lame_error)?;
+        }
+    }
+
+    let mut encoder = builder.build().map_err(lame_error)?;
+    let channels = audio.channels as usize;
+    let mut out = Vec::with_capacity(audio.samples.len() / 4);
+
+    for chunk in audio.samples.chunks(MP3_CHUNK_FRAMES * channels) {
+        out.reserve(mp3lame_encoder::max_required_buffer_size(chunk.len() / channels));
+        let result = if channels == 1 {
+            encoder.encode_to_vec(MonoPcm(chunk), &mut out)
+        } else {
+            encoder.encode_to_vec(InterleavedPcm(chunk), &mut out)
+        };
+        result.map_err(|e| ExportError::encode(codec, e))?;
+    }
+
+    out.reserve(7200);
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut out)
+        .map_err(|e| ExportError::encode(codec, e))?;
+    Ok(out)
+}
+
+/// Streaming AAC-LC encoder producing raw access units
+pub struct AacStreamEncoder {
+    encoder: AacEncoder,
+    config: AacTrackConfig,
+    pending: Vec<i16>,
+    output: Vec<u8>,
+    frames_in: u64,
+}
+
+impl AacStreamEncoder {
+    pub fn new(settings: &EncodeSettings) -> Result<Self, ExportError> {
+        let codec = settings.format.codec_name();
+        let bit_rate = match settings.bitrate_mode {
+            BitrateMode::Cbr => AacBitRate::Cbr(settings.bitrate.unwrap_or(64) * 1000),
+            BitrateMode::Vbr => match settings.vbr_quality.unwrap_or(4) {
+                1 => AacBitRate::VbrVeryLow,
+                2 => AacBitRate::VbrLow,
+                3 => AacBitRate::VbrMedium,
+                4 => AacBitRate::VbrHigh,
+                _ => AacBitRate::VbrVeryHigh,
+            },
+        };
+
+        let encoder = AacEncoder::new(EncoderParams {
+            bit_rate,
+            sample_rate: settings.sample_rate,
+            transport: Transport::Raw,
+            channels: if settings.channels == 1 { ChannelMode::Mono } else { ChannelMode::Stereo },
+            audio_object_type: AudioObjectType::Mpeg4LowComplexity,
+        })
+        .map_err(|e| ExportError::encode(codec, e))?;
+        let info = encoder.info().map_err(|e| ExportError::encode(codec, e))?;
+
+        Ok(Self {
+            config: AacTrackConfig {
+                config: info.confBuf[..info.confSize as usize].to_vec(),
+                sample_rate: settings.sample_rate,
+                channels: settings.channels,
+                frame_length: info.frameLength,
+                priming: info.nDelay,
+            },
+            output: vec![0u8; info.maxOutBufBytes.max(8192) as usize],
+            encoder,
+            pending: Vec::new(),
+            frames_in: 0,
+        })
+    }
+
+    pub fn track_config(&self) -> &AacTrackConfig {
+        &self.config
+    }
+
+    /// Playable PCM frames pushed so far
+    pub fn frames(&self) -> u64 {
+        self.frames_in
+    }
+
+    /// Feed interleaved samples, handing every finished access unit to `sink`
+    pub fn push<F>(&mut self, samples: &[i16], sink: &mut F) -> Result<(), ExportError>
+    where
+        F: FnMut(&[u8]) -> Result<(), ExportError>,
+    {
+        self.frames_in += (samples.len() / self.config.channels as usize) as u64;
+        self.pending.extend_from_slice(samples);
+        self.drain(false, sink)
+    }
+
+    /// Pad with silence until the delayed tail has been emitted
+    ///
+    /// The wrapper has no EOF flush, so the padding becomes trailing silence
+    /// that the `elst` duration hides from players.
+    pub fn finish<F>(mut self, sink: &mut F) -> Result<u64, ExportError>
+    where
+        F: FnMut(&[u8]) -> Result<(), ExportError>,
+    {
+        let frame_samples = self.config.frame_length as usize * self.config.channels as usize;
+        let padding = (self.config.priming as usize + self.config.frame_length as usize) * self.config.channels as usize;
+        self.pending.resize(self.pending.len() + padding, 0);
+        let remainder = self.pending.len() % frame_samples;
+        if remainder > 0 {
+            self.pending.resize(self.pending.len() + frame_samples - remainder, 0);
+        }
+        self.drain(true, sink)?;
+        Ok(self.frames_in)
+    }
+
+    fn drain<F>(&mut self, flushing: bool, sink: &mut F) -> Result<(), ExportError>
+    where
+        F: FnMut(&[u8]) -> Result<(), ExportError>,
+    {
+        let frame_samples = self.config.frame_length as usize * self.config.channels as usize;
+        let mut position = 0;
+        while self.pending.len() - position >= frame_samples {
+            let result = self
+                .encoder
+                .encode(&self.pending[position..position + frame_samples], &mut self.output)
+                .map_err(|e| ExportError::encode("aac", e))?;
+            if result.input_consumed == 0 && result.output_size == 0 {
+                return Err(ExportError::encode("aac", "encoder stalled"));
+            }
+            position += result.input_consumed;
+            if result.output_size > 0 {
+                sink(&self.output[..result.output_size])?;
+            }
+        }
+        if flushing {
+            self.pending.clear();
+        } else {
+            self.pending.drain(..position);
+        }
+        Ok(())
+    }
+}
+
+fn encode_m4a(audio: &AudioBuffer, settings: &EncodeSettings) -> Result<Vec<u8>, ExportError> {
+    let codec = settings.format.codec_name();
+    let mut encoder = AacStreamEncoder::new(settings)?;
+    let mut muxer = Mp4Muxer::new(Cursor::new(Vec::new()), Brand::M4a, encoder.track_config().clone())
+        .map_err(|e| ExportError::encode(codec, e))?;
+
+    let mut sink = |packet: &[u8]| muxer.write_packet(packet).map_err(|e| ExportError::encode(codec, e));
+    encoder.push(&audio.to_i16(), &mut sink)?;
+    let frames = encoder.finish(&mut sink)?;
+
+    let out = muxer
+        .finish(frames, &[], &Mp4Tags::default())
+        .map_err(|e| ExportError::encode(codec, e))?;
+    Ok(out.into_inner())
+}
+
+fn opus_header(channels: u16, pre_skip: u16, input_rate: u32) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(channels as u8);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&input_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // mapping family: mono/stereo
+    head
+}
+
+/// Empty Vorbis comment list carrying only our vendor string
+fn vendor_comments() -> Vec<u8> {
+    let vendor = concat!("audiobook-maker ", env!("CARGO_PKG_VERSION"));
+    let mut comments = Vec::new();
+    comments.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    comments.extend_from_slice(vendor.as_bytes());
+    comments.extend_from_slice(&0u32.to_le_bytes()); // user comments
+    comments
+}
+
+fn opus_tags() -> Vec<u8> {
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&vendor_comments());
+    tags
+}
+
+fn encode_opus(audio: &AudioBuffer, settings: &EncodeSettings) -> Result<Vec<u8>, ExportError> {
+    let codec = settings.format.codec_name();
+    let opus_error = |e: audiopus::Error| ExportError::encode(codec, e);
+
+    let sample_rate = audiopus::SampleRate::try_from(audio.sample_rate as i32).map_err(|_| {
+        ExportError::InvalidSampleRate { codec, sample_rate: audio.sample_rate }
+    })?;
+    let channels = if audio.channels == 1 { audiopus::Channels::Mono } else { audiopus::Channels::Stereo };
+
+    let mut encoder = OpusEncoder::new(sample_rate, channels, audiopus::Application::Audio).map_err(opus_error)?;
+    let bitrate = settings.bitrate.unwrap_or(32) as i32 * 1000;
+    encoder.set_bitrate(audiopus::Bitrate::BitsPerSecond(bitrate)).map_err(opus_error)?;
+    encoder.set_vbr(settings.bitrate_mode == BitrateMode::Vbr).map_err(opus_error)?;
+
+    // Granule positions are always counted at 48 kHz, whatever the input rate
+    let to_48k = 48_000 / audio.sample_rate as u64;
+    let pre_skip = encoder.lookahead().map_err(opus_error)? as u64 * to_48k;
+    let frame_size = (OPUS_FRAME_48K / to_48k) as usize * audio.channels as usize;
+    let end_granule = pre_skip + audio.frames() as u64 * to_48k;
+
+    let serial = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or(0x4142_4D31);
+
+    let mut writer = PacketWriter::new(Cursor::new(Vec::new()));
+    let ogg_error = |e: std::io::Error| ExportError::encode(codec, e);
+    writer
+        .write_packet(opus_header(audio.channels, pre_skip as u16, audio.sample_rate), serial, PacketWriteEndInfo::EndPage, 0)
+        .map_err(ogg_error)?;
+    writer
+        .write_packet(opus_tags(), serial, PacketWriteEndInfo::EndPage, 0)
+        .map_err(ogg_error)?;
+
+    // Pad so the pre-skip region is covered and the last frame is complete
+    let mut pcm = audio.samples.clone();
+    let padded = (audio.samples.len() + pre_skip as usize / to_48k as usize * audio.channels as usize)
+        .div_ceil(frame_size)
+        * frame_size;
+    pcm.resize(padded.max(frame_size), 0.0);
+
+    let frames = pcm.len() / frame_size;
+    let mut packet = vec![0u8; OPUS_MAX_PACKET];
+    let mut granule = 0u64;
+    for (index, frame) in pcm.chunks_exact(frame_size).enumerate() {
+        let size = encoder.encode_float(frame, &mut packet).map_err(opus_error)?;
+        granule += OPUS_FRAME_48K;
+        let last = index + 1 == frames;
+        let end_info = if last { PacketWriteEndInfo::EndStream } else { PacketWriteEndInfo::NormalPacket };
+        writer
+            .write_packet(packet[..size].to_vec(), serial, end_info, granule.min(end_granule))
+            .map_err(ogg_error)?;
+    }
+
+    Ok(writer.into_inner().into_inner())
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::error::write_coded;
+use crate::serialize_as_display;
+
+/// Errors raised by the native export pipeline
+#[derive(Debug)]
+pub enum ExportError {
+    /// The requested container/codec is not known
+    UnsupportedFormat { format: String },
+    /// The bitrate is outside what the codec supports at this sample rate
+    InvalidBitrate { codec: &'static str, bitrate: u32, sample_rate: u32 },
+    /// The codec cannot encode at this sample rate
+    InvalidSampleRate { codec: &'static str, sample_rate: u32 },
+    /// Only mono and stereo output is supported
+    InvalidChannels { codec: &'static str, channels: u16 },
+    /// The codec does not support this PCM bit depth
+    InvalidBitDepth { codec: &'static str, bit_depth: u16 },
+    /// The VBR quality value is outside the codec's scale
+    InvalidQuality { codec: &'static str, quality: u8 },
+    /// The loudness target is outside the supported range
+    InvalidLoudnessTarget { integrated: f64, true_peak: f64 },
+    /// Loudness measurement failed
+    Loudness { message: String },
+    /// A chapter selected for export has no rendered audio
+    MissingChapterAudio { chapter_id: String },
+    /// There is nothing to export
+    NoChapters,
+    /// Splitting by duration needs a positive maximum part length
+    InvalidSplitDuration { seconds: u64 },
+    /// The segment timeline runs past the end of the rendered audio
+    TimelineMismatch { expected_ms: u64, actual_ms: u64 },
+    /// The file name template has an unknown or malformed placeholder
+    InvalidNameTemplate { template: String },
+    /// Two split parts would be written to the same file name
+    DuplicateFileName { name: String },
+    /// The podcast feed base URL is not an absolute http(s) URL
+    InvalidFeedUrl { url: String },
+    /// A release date is not an RFC 3339 timestamp
+    InvalidReleaseDate { value: String },
+    /// A folder to check contains no audio files
+    NoAudioFiles { path: String },
+    /// The cover image is not a JPEG or PNG
+    InvalidCover { path: String },
+    /// The input could not be decoded as WAV or raw PCM
+    Decode { message: String },
+    /// The encoder rejected the input or failed mid-stream
+    Encode { codec: &'static str, message: String },
+    /// The tags of an exported file could not be read or written
+    Tag { path: String, message: String },
+    /// Reading or writing a file failed
+    Io { path: String, message: String },
+}
+
+impl ExportError {
+    pub fn decode(message: impl fmt::Display) -> Self {
+        Self::Decode { message: message.to_string() }
+    }
+
+    pub fn encode(codec: &'static str, message: impl fmt::Display) -> Self {
+        Self::Encode { codec, message: message.to_string() }
+    }
+
+    pub fn tag(path: impl Into<String>, error: impl fmt::Display) -> Self {
+        Self::Tag { path: path.into(), message: error.to_string() }
+    }
+
+    pub fn io(path: impl Into<String>, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.into(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat { format } => {
+                write_coded(f, "EXPORT_UNSUPPORTED_FORMAT", &[("format", format)])
+            }
+            Self::InvalidBitrate { codec, bitrate, sample_rate } => write_coded(
+                f,
+                "EXPORT_INVALID_BITRATE",
+                &[("codec", codec), ("bitrate", bitrate), ("sampleRate", sample_rate)],
+            ),
+            Self::InvalidSampleRate { codec, sample_rate } => write_coded(
+                f,
+                "EXPORT_INVALID_SAMPLE_RATE",
+                &[("codec", codec), ("sampleRate", sample_rate)],
+            ),
+            Self::InvalidChannels { codec, channels } => write_coded(
+                f,
+                "EXPORT_INVALID_CHANNELS",
+                &[("codec", codec), ("channels", channels)],
+            ),
+            Self::InvalidBitDepth { codec, bit_depth } => write_coded(
+                f,
+                "EXPORT_INVALID_BIT_DEPTH",
+                &[("codec", codec), ("bitDepth", bit_depth)],
+            ),
+            Self::InvalidQuality { codec, quality } => write_coded(
+                f,
+                "EXPORT_INVALID_QUALITY",
+                &[("codec", codec), ("quality", quality)],
+            ),
+            Self::InvalidLoudnessTarget { integrated, true_peak } => write_coded(
+                f,
+                "EXPORT_INVALID_LOUDNESS_TARGET",
+                &[("integrated", integrated), ("truePeak", true_peak)],
+            ),
+            Self::Loudness { message } => {
+                write_coded(f, "EXPORT_LOUDNESS_FAILED", &[("error", message)])
+            }
+            Self::MissingChapterAudio { chapter_id } => write_coded(
+                f,
+                "EXPORT_MISSING_CHAPTER_AUDIO",
+                &[("chapterId", chapter_id)],
+            ),
+            Self::NoChapters => write_coded(f, "EXPORT_NO_CHAPTERS", &[]),
+            Self::InvalidSplitDuration { seconds } => {
+                write_coded(f, "EXPORT_INVALID_SPLIT_DURATION", &[("seconds", seconds)])
+            }
+            Self::TimelineMismatch { expected_ms, actual_ms } => write_coded(
+                f,
+                "EXPORT_TIMELINE_MISMATCH",
+                &[("expectedMs", expected_ms), ("actualMs", actual_ms)],
+            ),
+            Self::InvalidNameTemplate { template } => {
+                write_coded(f, "EXPORT_INVALID_NAME_TEMPLATE", &[("template", template)])
+            }
+            Self::DuplicateFileName { name } => {
+                write_coded(f, "EXPORT_DUPLICATE_FILE_NAME", &[("name", name)])
+            }
+            Self::InvalidFeedUrl { url } => {
+                write_coded(f, "EXPORT_INVALID_FEED_URL", &[("url", url)])
+            }
+            Self::InvalidReleaseDate { value } => {
+                write_coded(f, "EXPORT_INVALID_RELEASE_DATE", &[("value", value)])
+            }
+            Self::NoAudioFiles { path } => {
+                write_coded(f, "EXPORT_NO_AUDIO_FILES", &[("path", path)])
+            }
+            Self::InvalidCover { path } => {
+                write_coded(f, "EXPORT_INVALID_COVER", &[("path", path)])
+            }
+            Self::Decode { message } => {
+                write_coded(f, "EXPORT_DECODE_FAILED", &[("error", message)])
+            }
+            Self::Encode { codec, message } => write_coded(
+                f,
+                "EXPORT_ENCODE_FAILED",
+                &[("codec", codec), ("error", message)],
+            ),
+            Self::Tag { path, message } => {
+                write_coded(f, "EXPORT_TAG_FAILED", &[("path", path), ("error", message)])
+            }
+            Self::Io { path, message } => {
+                write_coded(f, "EXPORT_IO_FAILED", &[("path", path), ("error", message)])
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+serialize_as_display!(ExportError);
@@ -0,0 +1,310 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::ExportError;
+use super::loudness::{LoudnessOptions, LoudnessTarget};
+
+/// Containers/codecs the native export pipeline can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// MPEG-1/2 Layer III (LAME)
+    Mp3,
+    /// AAC-LC in an MP4 container (FDK AAC)
+    M4a,
+    /// Free Lossless Audio Codec
+    Flac,
+    /// Opus in an Ogg container
+    Opus,
+    /// RIFF/WAVE PCM
+    Wav,
+}
+
+impl ExportFormat {
+    pub fn codec_name(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::M4a => "aac",
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+            Self::Wav => "pcm",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::M4a => "m4a",
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+            Self::Wav => "wav",
+        }
+    }
+
+    pub fn is_lossless(self) -> bool {
+        matches!(self, Self::Flac | Self::Wav)
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "mp3" => Ok(Self::Mp3),
+            "m4a" | "aac" | "mp4" => Ok(Self::M4a),
+            "flac" => Ok(Self::Flac),
+            "opus" | "ogg" => Ok(Self::Opus),
+            "wav" | "wave" => Ok(Self::Wav),
+            _ => Err(ExportError::UnsupportedFormat { format: value.to_string() }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BitrateMode {
+    #[default]
+    Cbr,
+    Vbr,
+}
+
+/// Layout of headerless PCM input (little-endian, signed integer)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PcmSpec {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+}
+
+/// Encoder options sent by the frontend; anything left out falls back to a per-codec default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeOptions {
+    /// Constant or variable bitrate (MP3, AAC, Opus)
+    pub bitrate_mode: Option<BitrateMode>,
+    /// Target bitrate in kbps for CBR (and Opus VBR)
+    pub bitrate: Option<u32>,
+    /// VBR quality: LAME scale 0 (best) - 9 for MP3, FDK scale 1 - 5 (best) for AAC
+    pub vbr_quality: Option<u8>,
+    /// Output sample rate in Hz; the input is resampled if it differs
+    pub sample_rate: Option<u32>,
+    /// Output channel count (1 or 2)
+    pub channels: Option<u16>,
+    /// PCM bit depth for WAV/FLAC (16 or 24)
+    pub bit_depth: Option<u16>,
+    /// Layout of the input when it is raw PCM instead of WAV
+    pub input: Option<PcmSpec>,
+    /// Normalize loudness before encoding; left out means no normalization
+    pub loudness: Option<LoudnessOptions>,
+}
+
+/// Fully resolved and validated encoder settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeSettings {
+    pub format: ExportFormat,
+    pub bitrate_mode: BitrateMode,
+    /// kbps; `None` for lossless formats and quality-driven VBR
+    pub bitrate: Option<u32>,
+    pub vbr_quality: Option<u8>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bit_depth: u16,
+    pub loudness: Option<LoudnessTarget>,
+}
+
+const MP3_BITRATES: &[u32] = &[8, 16, 24, 32, 40, 48, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const AAC_SAMPLE_RATES: &[u32] = &[8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000];
+const OPUS_SAMPLE_RATES: &[u32] = &[8000, 12000, 16000, 24000, 48000];
+
+impl EncodeSettings {
+    /// Resolve `options` against the decoded input and check the combination is encodable
+    pub fn resolve(
+        format: ExportFormat,
+        options: &EncodeOptions,
+        input_sample_rate: u32,
+        input_channels: u16,
+    ) -> Result<Self, ExportError> {
+        let codec = format.codec_name();
+        let channels = options.channels.unwrap_or(input_channels.min(2));
+        if !(1..=2).contains(&channels) {
+            return Err(ExportError::InvalidChannels { codec, channels });
+        }
+
+        let sample_rate = match (options.sample_rate, format) {
+            (Some(rate), _) => rate,
+            // Opus only runs at a handful of rates, so default to full band
+            (None, ExportFormat::Opus) if !OPUS_SAMPLE_RATES.contains(&input_sample_rate) => 48000,
+            (None, _) => input_sample_rate,
+        };
+
+        let bitrate_mode = if format.is_lossless() {
+            BitrateMode::Cbr
+        } else {
+            options.bitrate_mode.unwrap_or_default()
+        };
+
+        let mut settings = Self {
+            format,
+            bitrate_mode,
+            bitrate: None,
+            vbr_quality: None,
+            sample_rate,
+            channels,
+            bit_depth: options.bit_depth.unwrap_or(16),
+            loudness: options.loudness.as_ref().map(LoudnessTarget::resolve).transpose()?,
+        };
+
+        match format {
+            ExportFormat::Mp3 => settings.validate_mp3(options)?,
+            ExportFormat::M4a => settings.validate_aac(options)?,
+            ExportFormat::Opus => settings.validate_opus(options)?,
+            ExportFormat::Flac | ExportFormat::Wav => settings.validate_pcm()?,
+        }
+
+        Ok(settings)
+    }
+
+    fn validate_mp3(&mut self, options: &EncodeOptions) -> Result<(), ExportError> {
+        let codec = self.format.codec_name();
+        // MPEG-1, MPEG-2 and MPEG-2.5 each cover three rates with their own bitrate ceiling
+        let max_bitrate = match self.sample_rate {
+            32000 | 44100 | 48000 => 320,
+            16000 | 22050 | 24000 => 160,
+            8000 | 11025 | 12000 => 64,
+            sample_rate => return Err(ExportError::InvalidSampleRate { codec, sample_rate }),
+        };
+
+        match self.bitrate_mode {
+            BitrateMode::Cbr => {
+                let bitrate = options.bitrate.unwrap_or(128.min(max_bitrate));
+                if !MP3_BITRATES.contains(&bitrate) || bitrate > max_bitrate {
+                    return Err(ExportError::InvalidBitrate { codec, bitrate, sample_rate: self.sample_rate });
+                }
+                self.bitrate = Some(bitrate);
+            }
+            BitrateMode::Vbr => {
+                let quality = options.vbr_quality.unwrap_or(4);
+                if quality > 9 {
+                    return Err(ExportError::InvalidQuality { codec, quality });
+                }
+                self.vbr_quality = Some(quality);
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_aac(&mut self, options: &EncodeOptions) -> Result<(), ExportError> {
+        let codec = self.format.codec_name();
+        if !AAC_SAMPLE_RATES.contains(&self.sample_rate) {
+            return Err(ExportError::InvalidSampleRate { codec, sample_rate: self.sample_rate });
+        }
+
+        match self.bitrate_mode {
+            BitrateMode::Cbr => {
+                let bitrate = options.bitrate.unwrap_or(64 * self.channels as u32);
+                // An AAC-LC frame carries at most 6144 bits per channel for 1024 samples
+                let max_bitrate = 6 * self.sample_rate * self.channels as u32 / 1000;
+                let min_bitrate = 8 * self.channels as u32;
+                if bitrate < min_bitrate || bitrate > max_bitrate.min(320 * self.channels as u32) {
+                    return Err(ExportError::InvalidBitrate { codec, bitrate, sample_rate: self.sample_rate });
+                }
+                self.bitrate = Some(bitrate);
+            }
+            BitrateMode::Vbr => {
+                let quality = options.vbr_quality.unwrap_or(4);
+                if !(1..=5).contains(&quality) {
+                    return Err(ExportError::InvalidQuality { codec, quality });
+                }
+                self.vbr_quality = Some(quality);
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_opus(&mut self, options: &EncodeOptions) -> Result<(), ExportError> {
+        let codec = self.format.codec_name();
+        if !OPUS_SAMPLE_RATES.contains(&self.sample_rate) {
+            return Err(ExportError::InvalidSampleRate { codec, sample_rate: self.sample_rate });
+        }
+
+        // Opus treats the bitrate as a target in VBR mode as well
+        let bitrate = options.bitrate.unwrap_or(32 * self.channels as u32);
+        if !(6..=510).contains(&bitrate) {
+            return Err(ExportError::InvalidBitrate { codec, bitrate, sample_rate: self.sample_rate });
+        }
+        self.bitrate = Some(bitrate);
+        Ok(())
+    }
+
+    fn validate_pcm(&mut self) -> Result<(), ExportError> {
+        let codec = self.format.codec_name();
+        if !(1..=655_350).contains(&self.sample_rate) {
+            return Err(ExportError::InvalidSampleRate { codec, sample_rate: self.sample_rate });
+        }
+        if self.bit_depth != 16 && self.bit_depth != 24 {
+            return Err(ExportError::InvalidBitDepth { codec, bit_depth: self.bit_depth });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(format: ExportFormat, options: EncodeOptions) -> Result<EncodeSettings, ExportError> {
+        EncodeSettings::resolve(format, &options, 44100, 6)
+    }
+
+    #[test]
+    fn format_names_and_aliases_parse() {
+        assert_eq!(" AAC ".parse::<ExportFormat>().unwrap(), ExportFormat::M4a);
+        assert_eq!("ogg".parse::<ExportFormat>().unwrap(), ExportFormat::Opus);
+        assert!(matches!("aiff".parse::<ExportFormat>(), Err(ExportError::UnsupportedFormat { .. })));
+    }
+
+    #[test]
+    fn defaults_follow_the_input_and_codec() {
+        let mp3 = resolve(ExportFormat::Mp3, EncodeOptions::default()).unwrap();
+        assert_eq!((mp3.sample_rate, mp3.channels, mp3.bitrate), (44100, 2, Some(128)));
+
+        let opus = resolve(ExportFormat::Opus, EncodeOptions::default()).unwrap();
+        assert_eq!((opus.sample_rate, opus.bitrate), (48000, Some(64)));
+
+        let vbr = EncodeOptions { bitrate_mode: Some(BitrateMode::Vbr), ..Default::default() };
+        assert_eq!(resolve(ExportFormat::Flac, vbr).unwrap().bitrate_mode, BitrateMode::Cbr);
+    }
+
+    #[test]
+    fn mp3_bitrates_are_capped_per_mpeg_version() {
+        let options = |sample_rate, bitrate| EncodeOptions {
+            sample_rate: Some(sample_rate),
+            bitrate: Some(bitrate),
+            ..Default::default()
+        };
+        assert!(resolve(ExportFormat::Mp3, options(22050, 160)).is_ok());
+        assert!(matches!(resolve(ExportFormat::Mp3, options(22050, 192)), Err(ExportError::InvalidBitrate { .. })));
+        assert!(matches!(resolve(ExportFormat::Mp3, options(44100, 100)), Err(ExportError::InvalidBitrate { .. })));
+        let result = resolve(ExportFormat::Mp3, options(96000, 128));
+        assert!(matches!(result, Err(ExportError::InvalidSampleRate { .. })));
+    }
+
+    #[test]
+    fn invalid_combinations_are_rejected() {
+        let aac_vbr = EncodeOptions { bitrate_mode: Some(BitrateMode::Vbr), vbr_quality: Some(0), ..Default::default() };
+        assert!(matches!(resolve(ExportFormat::M4a, aac_vbr), Err(ExportError::InvalidQuality { .. })));
+
+        let aac_low_rate = EncodeOptions { sample_rate: Some(8000), bitrate: Some(128), ..Default::default() };
+        assert!(matches!(resolve(ExportFormat::M4a, aac_low_rate), Err(ExportError::InvalidBitrate { .. })));
+
+        let surround = EncodeOptions { channels: Some(6), ..Default::default() };
+        assert!(matches!(resolve(ExportFormat::Wav, surround), Err(ExportError::InvalidChannels { .. })));
+
+        let wav_32 = EncodeOptions { bit_depth: Some(32), ..Default::default() };
+        assert!(matches!(resolve(ExportFormat::Wav, wav_32), Err(ExportError::InvalidBitDepth { .. })));
+    }
+}
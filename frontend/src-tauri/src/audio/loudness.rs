@@ -0,0 +1,422 @@
+//! Loudness measurement (ITU-R BS.1770 / EBU R128) and normalization
+//!
+//! Normalization is a static gain to the integrated loudness target followed by
+//! a lookahead limiter that keeps the true peak under the ceiling.
+
+use ebur128::{EbuR128, Mode};
+use serde::{Deserialize, Serialize};
+
+use super::buffer::AudioBuffer;
+use super::error::ExportError;
+
+/// Common delivery targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LoudnessPreset {
+    /// Audible/ACX: −18 LUFS, −3 dBTP
+    Acx,
+    /// Podcast platforms: −16 LUFS, −1 dBTP
+    Podcast,
+    /// EBU R128 broadcast: −23 LUFS, −1 dBTP
+    EbuR128,
+}
+
+impl LoudnessPreset {
+    fn target(self) -> LoudnessTarget {
+        let (integrated_lufs, true_peak_dbtp) = match self {
+            Self::Acx => (-18.0, -3.0),
+            Self::Podcast => (-16.0, -1.0),
+            Self::EbuR128 => (-23.0, -1.0),
+        };
+        LoudnessTarget { integrated_lufs, true_peak_dbtp }
+    }
+}
+
+/// Loudness options sent by the frontend; explicit values override the preset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessOptions {
+    pub preset: Option<LoudnessPreset>,
+    pub integrated_lufs: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+}
+
+/// Resolved normalization target
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessTarget {
+    pub integrated_lufs: f64,
+    pub true_peak_dbtp: f64,
+}
+
+impl LoudnessTarget {
+    /// Fill in missing values from the preset (ACX if none) and check the range
+    pub fn resolve(options: &LoudnessOptions) -> Result<Self, ExportError> {
+        let preset = options.preset.unwrap_or(LoudnessPreset::Acx).target();
+        let target = Self {
+            integrated_lufs: options.integrated_lufs.unwrap_or(preset.integrated_lufs),
+            true_peak_dbtp: options.true_peak_dbtp.unwrap_or(preset.true_peak_dbtp),
+        };
+        if !(-40.0..=-5.0).contains(&target.integrated_lufs) || !(-9.0..=0.0).contains(&target.true_peak_dbtp) {
+            return Err(ExportError::InvalidLoudnessTarget {
+                integrated: target.integrated_lufs,
+                true_peak: target.true_peak_dbtp,
+            });
+        }
+        Ok(target)
+    }
+}
+
+/// One measurement; `None` where the signal is too quiet or short to measure
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessStats {
+    pub integrated_lufs: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+    pub loudness_range_lu: Option<f64>,
+}
+
+/// Before/after measurements of a normalization pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessReport {
+    pub target: LoudnessTarget,
+    pub before: LoudnessStats,
+    pub after: LoudnessStats,
+    pub gain_db: f64,
+    /// Peak gain reduction applied by the limiter, 0 if it never engaged
+    pub limiter_reduction_db: f64,
+}
+
+/// Limiter lookahead; gain starts ramping down this long before a peak
+const LOOKAHEAD_SECS: f64 = 0.005;
+/// Time for the limiter gain to recover fully after a peak
+const RELEASE_SECS: f64 = 0.1;
+/// Limiter passes with a lowered ceiling if inter-sample peaks still overshoot
+const LIMITER_PASSES: usize = 3;
+/// Gain/limiter rounds to make up the loudness the limiter took away
+const GAIN_ROUNDS: usize = 3;
+
+fn finite(value: f64) -> Option<f64> {
+    value.is_finite().then_some(value)
+}
+
+fn to_db(linear: f64) -> f64 {
+    20.0 * linear.log10()
+}
+
+fn from_db(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+fn loudness_error(e: ebur128::Error) -> ExportError {
+    ExportError::Loudness { message: e.to_string() }
+}
+
+/// Loudness of a programme fed in piece by piece, e.g. the chapters of one book
+pub struct ProgrammeMeter {
+    meter: EbuR128,
+    channels: u32,
+}
+
+impl ProgrammeMeter {
+    pub fn new(channels: u16, sample_rate: u32) -> Result<Self, ExportError> {
+        let meter = EbuR128::new(channels as u32, sample_rate, Mode::I | Mode::LRA | Mode::TRUE_PEAK)
+            .map_err(loudness_error)?;
+        Ok(Self { meter, channels: channels as u32 })
+    }
+
+    /// Append `audio`, which must have the channel count and sample rate the meter was made for
+    pub fn add(&mut self, audio: &AudioBuffer) -> Result<(), ExportError> {
+        self.meter.add_frames_f32(&audio.samples).map_err(loudness_error)
+    }
+
+    /// Measurements over everything added so far
+    pub fn stats(&self) -> Result<LoudnessStats, ExportError> {
+        let mut peak = 0f64;
+        for channel in 0..self.channels {
+            peak = peak.max(self.meter.true_peak(channel).map_err(loudness_error)?);
+        }
+
+        Ok(LoudnessStats {
+            integrated_lufs: self.meter.loudness_global().ok().and_then(finite),
+            true_peak_dbtp: finite(to_db(peak)),
+            loudness_range_lu: self.meter.loudness_range().ok().and_then(finite),
+        })
+    }
+}
+
+/// Measure integrated loudness, true peak and loudness range
+pub fn measure(audio: &AudioBuffer) -> Result<LoudnessStats, ExportError> {
+    let mut meter = ProgrammeMeter::new(audio.channels, audio.sample_rate)?;
+    meter.add(audio)?;
+    meter.stats()
+}
+
+/// Gain envelope that keeps every frame's sample peak at or below `ceiling`
+///
+/// Gain may fall by at most 1/lookahead per frame (so it reaches the required
+/// value exactly at the peak) and rise by at most 1/release per frame.
+fn limiter_gain(audio: &AudioBuffer, ceiling: f32) -> Vec<f32> {
+    let channels = audio.channels as usize;
+    let mut gain: Vec<f32> = audio
+        .samples
+        .chunks_exact(channels)
+        .map(|frame| {
+            let peak = frame.iter().fold(0f32, |peak, sample| peak.max(sample.abs()));
+            if peak > ceiling { ceiling / peak } else { 1.0 }
+        })
+        .collect();
+
+    let attack_step = 1.0 / (LOOKAHEAD_SECS * audio.sample_rate as f64).max(1.0) as f32;
+    for index in (0..gain.len().saturating_sub(1)).rev() {
+        gain[index] = gain[index].min(gain[index + 1] + attack_step);
+    }
+
+    let release_step = 1.0 / (RELEASE_SECS * audio.sample_rate as f64).max(1.0) as f32;
+    for index in 1..gain.len() {
+        gain[index] = gain[index].min(gain[index - 1] + release_step);
+    }
+    gain
+}
+
+/// Scale every sample, remembering the accumulated per-frame limiter gain
+fn apply_envelope(audio: &mut AudioBuffer, envelope: &[f32], total: &mut [f32]) {
+    let channels = audio.channels as usize;
+    for ((frame, gain), total) in audio.samples.chunks_exact_mut(channels).zip(envelope).zip(total) {
+        for sample in frame {
+            *sample *= gain;
+        }
+        *total *= gain;
+    }
+}
+
+/// Limit until the true peak is at or below `target.true_peak_dbtp`
+fn limit(
+    audio: &mut AudioBuffer,
+    target: LoudnessTarget,
+    mut stats: LoudnessStats,
+    total: &mut [f32],
+) -> Result<LoudnessStats, ExportError> {
+    let mut ceiling_db = target.true_peak_dbtp;
+    for _ in 0..LIMITER_PASSES {
+        if !stats.true_peak_dbtp.is_some_and(|peak| peak > target.true_peak_dbtp) {
+            break;
+        }
+        let envelope = limiter_gain(audio, from_db(ceiling_db) as f32);
+        apply_envelope(audio, &envelope, total);
+        stats = measure(audio)?;
+        // Sample peaks are now under the ceiling; lower it by what the
+        // inter-sample peaks still overshoot in case another pass is needed
+        if let Some(peak) = stats.true_peak_dbtp {
+            ceiling_db -= (peak - target.true_peak_dbtp).max(0.1);
+        }
+    }
+    Ok(stats)
+}
+
+/// Apply gain to reach `target.integrated_lufs`, limiting the true peak to `target.true_peak_dbtp`
+///
+/// Limiting lowers the integrated loudness again, so gain and limiter run for a
+/// few rounds until the result is within half an LU of the target.
+pub fn normalize(audio: AudioBuffer, target: LoudnessTarget) -> Result<(AudioBuffer, LoudnessReport), ExportError> {
+    let before = measure(&audio)?;
+    let Some(mut integrated) = before.integrated_lufs else {
+        // Silence (or less than one 400 ms block): nothing to normalize against
+        return Ok((audio, LoudnessReport { target, before, after: before, gain_db: 0.0, limiter_reduction_db: 0.0 }));
+    };
+
+    let mut audio = audio;
+    let mut after = before;
+    let mut gain_db = 0.0;
+    let mut total = vec![1f32; audio.frames()];
+    for _ in 0..GAIN_ROUNDS {
+        let step_db = target.integrated_lufs - integrated;
+        if gain_db != 0.0 && step_db.abs() < 0.5 {
+            break;
+        }
+        let gain = from_db(step_db) as f32;
+        for sample in &mut audio.samples {
+            *sample *= gain;
+        }
+        gain_db += step_db;
+
+        let stats = measure(&audio)?;
+        after = limit(&mut audio, target, stats, &mut total)?;
+        match after.integrated_lufs {
+            Some(value) => integrated = value,
+            None => break,
+        }
+    }
+    // `total` holds the limiter's share only; make-up gain is part of `gain_db`
+    let min_gain = total.iter().copied().fold(1f32, f32::min);
+
+    Ok((
+        audio,
+        LoudnessReport {
+            target,
+            before,
+            after,
+            gain_db,
+            limiter_reduction_db: -to_db(min_gain as f64),
+        },
+    ))
+}
+
+/// Apply a gain computed for a whole programme to one piece of it, limiting
+/// the true peak to `target.true_peak_dbtp`
+///
+/// Used where the programme is too long to normalize in one buffer; the gain
+/// comes from a `ProgrammeMeter` pass over all pieces, so every piece gets the
+/// same level change. Returns the peak gain reduction of the limiter in dB.
+pub fn apply_gain(audio: AudioBuffer, gain_db: f64, target: LoudnessTarget) -> Result<(AudioBuffer, f64), ExportError> {
+    let mut audio = audio;
+    let gain = from_db(gain_db) as f32;
+    for sample in &mut audio.samples {
+        *sample *= gain;
+    }
+    let mut total = vec![1f32; audio.frames()];
+    let stats = measure(&audio)?;
+    limit(&mut audio, target, stats, &mut total)?;
+    let min_gain = total.iter().copied().fold(1f32, f32::min);
+    Ok((audio, -to_db(min_gain as f64)))
+}
+
+/// One gain for a whole programme, applied piece by piece after a
+/// `ProgrammeMeter` pass, so the level does not jump between pieces
+pub struct ProgrammeGain {
+    target: LoudnessTarget,
+    before: LoudnessStats,
+    /// `None` for silence, which has no integrated loudness to correct
+    gain_db: Option<f64>,
+    after: ProgrammeMeter,
+    limiter_reduction_db: f64,
+}
+
+impl ProgrammeGain {
+    pub fn new(target: LoudnessTarget, before: LoudnessStats, channels: u16, sample_rate: u32) -> Result<Self, ExportError> {
+        Ok(Self {
+            target,
+            before,
+            gain_db: before.integrated_lufs.map(|integrated| target.integrated_lufs - integrated),
+            after: ProgrammeMeter::new(channels, sample_rate)?,
+            limiter_reduction_db: 0.0,
+        })
+    }
+
+    /// Bring the next piece to the programme's level
+    pub fn apply(&mut self, audio: AudioBuffer) -> Result<AudioBuffer, ExportError> {
+        let audio = match self.gain_db {
+            Some(gain_db) => {
+                let (audio, reduction_db) = apply_gain(audio, gain_db, self.target)?;
+                self.limiter_reduction_db = self.limiter_reduction_db.max(reduction_db);
+                audio
+            }
+            None => audio,
+        };
+        self.after.add(&audio)?;
+        Ok(audio)
+    }
+
+    /// Before/after measurements over every piece applied so far
+    pub fn report(&self) -> Result<LoudnessReport, ExportError> {
+        Ok(LoudnessReport {
+            target: self.target,
+            before: self.before,
+            after: self.after.stats()?,
+            gain_db: self.gain_db.unwrap_or(0.0),
+            limiter_reduction_db: self.limiter_reduction_db,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    /// Stereo sine at `amplitude`, `secs` long
+    fn sine(amplitude: f32, secs: f32) -> AudioBuffer {
+        let frames = (RATE as f32 * secs) as usize;
+        let samples = (0..frames)
+            .flat_map(|frame| {
+                let sample = amplitude * (frame as f32 * 440.0 * std::f32::consts::TAU / RATE as f32).sin();
+                [sample, sample]
+            })
+            .collect();
+        AudioBuffer { sample_rate: RATE, channels: 2, samples }
+    }
+
+    fn target(preset: LoudnessPreset) -> LoudnessTarget {
+        LoudnessTarget::resolve(&LoudnessOptions { preset: Some(preset), ..Default::default() }).unwrap()
+    }
+
+    fn target_of(integrated_lufs: f64, true_peak_dbtp: f64) -> LoudnessTarget {
+        LoudnessTarget { integrated_lufs, true_peak_dbtp }
+    }
+
+    #[test]
+    fn explicit_values_override_the_preset() {
+        let options = LoudnessOptions {
+            preset: Some(LoudnessPreset::Podcast),
+            integrated_lufs: Some(-20.0),
+            ..Default::default()
+        };
+        assert_eq!(LoudnessTarget::resolve(&options).unwrap(), target_of(-20.0, -1.0));
+        assert_eq!(LoudnessTarget::resolve(&LoudnessOptions::default()).unwrap(), target_of(-18.0, -3.0));
+
+        let too_hot = LoudnessOptions { true_peak_dbtp: Some(1.0), ..Default::default() };
+        assert!(matches!(LoudnessTarget::resolve(&too_hot), Err(ExportError::InvalidLoudnessTarget { .. })));
+    }
+
+    #[test]
+    fn quiet_audio_is_raised_to_the_target() {
+        let target = target(LoudnessPreset::Acx);
+        let (_, report) = normalize(sine(0.01, 3.0), target).unwrap();
+        let integrated = report.after.integrated_lufs.unwrap();
+        assert!((integrated - target.integrated_lufs).abs() < 0.5, "{}", integrated);
+        assert!(report.after.true_peak_dbtp.unwrap() <= target.true_peak_dbtp + 0.1);
+        assert!(report.gain_db > 0.0);
+        assert_eq!(report.limiter_reduction_db, 0.0);
+    }
+
+    #[test]
+    fn peaks_over_the_ceiling_are_limited() {
+        // A mono sine at -16 LUFS peaks around -13 dBTP, so a -15 dBTP ceiling needs the limiter
+        let target = target_of(-16.0, -15.0);
+        let (audio, report) = normalize(sine(0.01, 3.0).remix(1), target).unwrap();
+        assert!(report.limiter_reduction_db > 0.0);
+        assert!(measure(&audio).unwrap().true_peak_dbtp.unwrap() <= target.true_peak_dbtp + 0.1);
+    }
+
+    #[test]
+    fn silence_is_left_alone() {
+        let silence = AudioBuffer { sample_rate: RATE, channels: 2, samples: vec![0.0; RATE as usize * 2] };
+        let (audio, report) = normalize(silence, target(LoudnessPreset::Podcast)).unwrap();
+        assert!(audio.samples.iter().all(|sample| *sample == 0.0));
+        assert_eq!((report.gain_db, report.after.integrated_lufs), (0.0, None));
+    }
+
+    #[test]
+    fn programme_gain_is_the_same_for_every_piece() {
+        let target = target(LoudnessPreset::EbuR128);
+        let pieces = [sine(0.05, 2.0), sine(0.2, 2.0)];
+        let mut meter = ProgrammeMeter::new(2, RATE).unwrap();
+        for piece in &pieces {
+            meter.add(piece).unwrap();
+        }
+        let mut gain = ProgrammeGain::new(target, meter.stats().unwrap(), 2, RATE).unwrap();
+        let ratios: Vec<f32> = pieces
+            .iter()
+            .map(|piece| {
+                let applied = gain.apply(piece.clone()).unwrap();
+                applied.samples[100] / piece.samples[100]
+            })
+            .collect();
+        assert!((ratios[0] - ratios[1]).abs() < 1e-4, "{:?}", ratios);
+
+        let report = gain.report().unwrap();
+        assert!((report.after.integrated_lufs.unwrap() - target.integrated_lufs).abs() < 0.5);
+    }
+}
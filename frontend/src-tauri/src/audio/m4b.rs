@@ -0,0 +1,182 @@
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::AtomicFile;
+
+use super::buffer::AudioBuffer;
+use super::encode::AacStreamEncoder;
+use super::error::ExportError;
+use super::format::{EncodeOptions, EncodeSettings, ExportFormat};
+use super::loudness::{LoudnessReport, LoudnessStats, ProgrammeGain, ProgrammeMeter};
+use super::mp4::{Brand, CoverArt, Mp4Chapter, Mp4Muxer, Mp4Tags, MEDIA_KIND_AUDIOBOOK};
+use super::tags::{self, AudioMetadata};
+
+/// Rendered audio (WAV) for one chapter, in playback order
+#[derive(Debug, Clone)]
+pub struct BookChapter {
+    pub title: String,
+    pub path: PathBuf,
+}
+
+/// Options for a single-file audiobook export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct M4bOptions {
+    /// AAC encoder settings; the format is always AAC
+    #[serde(default)]
+    pub encode: EncodeOptions,
+    /// Book title, defaults to the project name
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// JPEG or PNG cover image
+    pub cover_path: Option<String>,
+}
+
+/// Chapter as it ended up in the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterMarker {
+    pub title: String,
+    pub start_ms: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct M4bSummary {
+    pub path: String,
+    pub settings: EncodeSettings,
+    pub chapters: Vec<ChapterMarker>,
+    pub duration_secs: f64,
+    /// Present when the export normalizes loudness; measured over the whole book
+    pub loudness: Option<LoudnessReport>,
+}
+
+fn read_chapter(chapter: &BookChapter) -> Result<AudioBuffer, ExportError> {
+    let data = fs::read(&chapter.path).map_err(|e| ExportError::io(chapter.path.display().to_string(), e))?;
+    AudioBuffer::decode(&data, None)
+}
+
+/// Load a cover image, detecting JPEG/PNG from its signature
+pub fn read_cover(path: &str) -> Result<CoverArt, ExportError> {
+    let data = fs::read(path).map_err(|e| ExportError::io(path, e))?;
+    let mime_type = if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else {
+        return Err(ExportError::InvalidCover { path: path.to_string() });
+    };
+    Ok(CoverArt { data, mime_type: mime_type.to_string() })
+}
+
+/// Loudness of the whole book, measured chapter by chapter
+fn measure_book(chapters: &[BookChapter], settings: &EncodeSettings) -> Result<LoudnessStats, ExportError> {
+    let mut meter = ProgrammeMeter::new(settings.channels, settings.sample_rate)?;
+    for chapter in chapters {
+        meter.add(&super::conform(read_chapter(chapter)?, settings)?)?;
+    }
+    meter.stats()
+}
+
+/// Encode all chapters into one AAC stream and mux it as an M4B with chapter markers
+///
+/// Chapters are decoded one at a time so only a single chapter is ever held as PCM.
+/// With a loudness target the book is measured in a first pass and every chapter
+/// gets the same gain, so the level does not jump at chapter boundaries.
+pub fn export_m4b(
+    path: &Path,
+    book_title: &str,
+    chapters: &[BookChapter],
+    options: &M4bOptions,
+    metadata: Option<&AudioMetadata>,
+) -> Result<M4bSummary, ExportError> {
+    let first = chapters.first().ok_or(ExportError::NoChapters)?;
+    let cover = options.cover_path.as_deref().map(read_cover).transpose()?;
+
+    let mut audio = read_chapter(first)?;
+    let settings = EncodeSettings::resolve(ExportFormat::M4a, &options.encode, audio.sample_rate, audio.channels)?;
+    let codec = settings.format.codec_name();
+
+    let mut gain = match settings.loudness {
+        Some(target) => {
+            let before = measure_book(chapters, &settings)?;
+            Some(ProgrammeGain::new(target, before, settings.channels, settings.sample_rate)?)
+        }
+        None => None,
+    };
+
+    let display = path.display().to_string();
+    let mut encoder = AacStreamEncoder::new(&settings)?;
+    // The old file stays in place until the new one is complete
+    let mut file = AtomicFile::create(path).map_err(|e| ExportError::io(&display, e))?;
+    let mut muxer = Mp4Muxer::new(BufWriter::new(file.file()), Brand::M4b, encoder.track_config().clone())
+        .map_err(|e| ExportError::io(&display, e))?;
+    let mut sink = |packet: &[u8]| muxer.write_packet(packet).map_err(|e| ExportError::encode(codec, e));
+
+    let mut starts = Vec::with_capacity(chapters.len());
+    for (index, chapter) in chapters.iter().enumerate() {
+        if index > 0 {
+            audio = read_chapter(chapter)?;
+        }
+        starts.push(encoder.frames());
+        let mut prepared = super::conform(std::mem::take(&mut audio), &settings)?;
+        if let Some(gain) = &mut gain {
+            prepared = gain.apply(prepared)?;
+        }
+        encoder.push(&prepared.to_i16(), &mut sink)?;
+    }
+    let total_frames = encoder.finish(&mut sink)?;
+
+    let to_ms = |frames: u64| frames * 1000 / settings.sample_rate as u64;
+    let markers: Vec<ChapterMarker> = chapters
+        .iter()
+        .zip(&starts)
+        .enumerate()
+        .map(|(index, (chapter, start))| {
+            let end = starts.get(index + 1).copied().unwrap_or(total_frames);
+            ChapterMarker {
+                title: chapter.title.clone(),
+                start_ms: to_ms(*start),
+                duration_ms: to_ms(end - start),
+            }
+        })
+        .collect();
+
+    let mp4_chapters: Vec<Mp4Chapter> = markers
+        .iter()
+        .map(|marker| Mp4Chapter { title: marker.title.clone(), start_ms: marker.start_ms })
+        .collect();
+    let title = options.title.clone().unwrap_or_else(|| book_title.to_string());
+    let tags = Mp4Tags {
+        album: Some(title.clone()),
+        title: Some(title),
+        artist: options.author.clone(),
+        genre: Some("Audiobook".to_string()),
+        media_kind: Some(MEDIA_KIND_AUDIOBOOK),
+        cover,
+    };
+
+    muxer
+        .finish(total_frames, &mp4_chapters, &tags)
+        .and_then(|out| out.into_inner().map_err(|e| e.into_error()))
+        .map_err(|e| ExportError::io(&display, e))?;
+    // Tagged before the rename, so a tagging error leaves the previous export in place
+    if let Some(metadata) = metadata {
+        tags::write_tags(file.file(), &display, metadata)?;
+    }
+    file.commit().map_err(|e| ExportError::io(&display, e))?;
+
+    let loudness = gain.map(|gain| gain.report()).transpose()?;
+
+    Ok(M4bSummary {
+        path: display,
+        duration_secs: total_frames as f64 / settings.sample_rate as f64,
+        settings,
+        chapters: markers,
+        loudness,
+    })
+}
@@ -0,0 +1,62 @@
+//! Native audio export pipeline: decode, resample/remix, normalize, encode, tag
+
+pub mod acx;
+pub mod buffer;
+pub mod chapters;
+pub mod encode;
+pub mod error;
+pub mod format;
+pub mod loudness;
+pub mod m4b;
+pub mod mp4;
+pub mod podcast;
+pub mod split;
+pub mod tags;
+
+pub use buffer::AudioBuffer;
+pub use error::ExportError;
+pub use format::{BitrateMode, EncodeOptions, EncodeSettings, ExportFormat, PcmSpec};
+pub use loudness::{LoudnessOptions, LoudnessReport, LoudnessTarget};
+
+/// Bring `audio` to the channel layout and sample rate chosen in `settings`
+pub fn conform(audio: AudioBuffer, settings: &EncodeSettings) -> Result<AudioBuffer, ExportError> {
+    audio.remix(settings.channels).resample(settings.sample_rate)
+}
+
+/// Conform `audio`, then normalize it if `settings` asks for a loudness target
+pub fn prepare(
+    audio: AudioBuffer,
+    settings: &EncodeSettings,
+) -> Result<(AudioBuffer, Option<LoudnessReport>), ExportError> {
+    let audio = conform(audio, settings)?;
+    match settings.loudness {
+        Some(target) => {
+            let (audio, report) = loudness::normalize(audio, target)?;
+            Ok((audio, Some(report)))
+        }
+        None => Ok((audio, None)),
+    }
+}
+
+/// Result of a transcode: the encoded file and what it was encoded as
+#[derive(Debug)]
+pub struct Transcoded {
+    pub data: Vec<u8>,
+    pub settings: EncodeSettings,
+    pub duration_secs: f64,
+    pub loudness: Option<LoudnessReport>,
+}
+
+/// Decode WAV/PCM input and encode it to `format`
+pub fn transcode(input: &[u8], format: ExportFormat, options: &EncodeOptions) -> Result<Transcoded, ExportError> {
+    let audio = AudioBuffer::decode(input, options.input.as_ref())?;
+    let settings = EncodeSettings::resolve(format, options, audio.sample_rate, audio.channels)?;
+    let (audio, loudness) = prepare(audio, &settings)?;
+    let data = encode::encode(&audio, &settings)?;
+    Ok(Transcoded {
+        data,
+        settings,
+        duration_secs: audio.duration_secs(),
+        loudness,
+    })
+}
@@ -0,0 +1,616 @@
+//! Minimal streaming ISO-BMFF (MP4) writer for one AAC track plus optional chapters
+//!
+//! Layout is `ftyp`, `free`, `mdat` (size patched on finish), then `moov`, so
+//! access units can be appended as they come out of the encoder.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// AAC track parameters known before the first access unit
+#[derive(Debug, Clone)]
+pub struct AacTrackConfig {
+    /// AudioSpecificConfig from the encoder
+    pub config: Vec<u8>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// PCM frames per access unit (1024 for AAC-LC)
+    pub frame_length: u32,
+    /// Encoder delay in PCM frames, skipped through an edit list
+    pub priming: u32,
+}
+
+/// Chapter marker for the QuickTime text track and Nero `chpl`
+#[derive(Debug, Clone)]
+pub struct Mp4Chapter {
+    pub title: String,
+    pub start_ms: u64,
+}
+
+/// Cover image embedded as `covr`
+#[derive(Debug, Clone)]
+pub struct CoverArt {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// Tags written into `moov/udta/meta/ilst`
+#[derive(Debug, Clone, Default)]
+pub struct Mp4Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    /// iTunes media kind (`stik`); 2 marks an audiobook
+    pub media_kind: Option<u8>,
+    pub cover: Option<CoverArt>,
+}
+
+/// File type brand written into `ftyp`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Brand {
+    M4a,
+    M4b,
+}
+
+impl Brand {
+    fn major(self) -> &'static [u8; 4] {
+        match self {
+            Self::M4a => b"M4A ",
+            Self::M4b => b"M4B ",
+        }
+    }
+}
+
+/// iTunes `stik` value for audiobooks; players enable bookmarking for it
+pub const MEDIA_KIND_AUDIOBOOK: u8 = 2;
+
+const MOVIE_TIMESCALE: u32 = 1000;
+/// Access units per chunk; one chunk is roughly one second at 44.1 kHz
+const PACKETS_PER_CHUNK: usize = 43;
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+const AUDIO_TRACK_ID: u32 = 1;
+const CHAPTER_TRACK_ID: u32 = 2;
+
+/// Big-endian box writer that patches sizes when a box is closed
+#[derive(Debug, Default)]
+pub struct BoxWriter {
+    buf: Vec<u8>,
+    open: Vec<usize>,
+}
+
+impl BoxWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin(&mut self, kind: &[u8; 4]) {
+        self.open.push(self.buf.len());
+        self.u32(0);
+        self.bytes(kind);
+    }
+
+    pub fn begin_full(&mut self, kind: &[u8; 4], version: u8, flags: u32) {
+        self.begin(kind);
+        self.u32(((version as u32) << 24) | (flags & 0x00FF_FFFF));
+    }
+
+    pub fn end(&mut self) {
+        let start = self.open.pop().expect("unbalanced box");
+        let size = (self.buf.len() - start) as u32;
+        self.buf[start..start + 4].copy_from_slice(&size.to_be_bytes());
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    pub fn zeros(&mut self, count: usize) {
+        self.buf.resize(self.buf.len() + count, 0);
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        debug_assert!(self.open.is_empty(), "unclosed box");
+        self.buf
+    }
+}
+
+/// Streaming M4A/M4B muxer
+pub struct Mp4Muxer<W: Write + Seek> {
+    out: W,
+    track: AacTrackConfig,
+    mdat_start: u64,
+    position: u64,
+    packet_sizes: Vec<u32>,
+    chunk_offsets: Vec<u64>,
+    max_packet: usize,
+}
+
+impl<W: Write + Seek> Mp4Muxer<W> {
+    /// Write `ftyp` and the `mdat` header
+    pub fn new(mut out: W, brand: Brand, track: AacTrackConfig) -> io::Result<Self> {
+        let mut header = BoxWriter::new();
+        header.begin(b"ftyp");
+        header.bytes(brand.major());
+        header.u32(0);
+        header.bytes(brand.major());
+        header.bytes(b"mp42");
+        header.bytes(b"isom");
+        header.end();
+        let header = header.into_inner();
+
+        out.write_all(&header)?;
+        let mdat_start = header.len() as u64;
+        // Reserve a `free` box in front of `mdat`; it becomes the 64-bit size
+        // header if the payload outgrows 4 GiB
+        out.write_all(&8u32.to_be_bytes())?;
+        out.write_all(b"free")?;
+        out.write_all(&0u32.to_be_bytes())?;
+        out.write_all(b"mdat")?;
+
+        Ok(Self {
+            out,
+            track,
+            mdat_start,
+            position: mdat_start + 16,
+            packet_sizes: Vec::new(),
+            chunk_offsets: Vec::new(),
+            max_packet: 0,
+        })
+    }
+
+    /// Append one AAC access unit
+    pub fn write_packet(&mut self, packet: &[u8]) -> io::Result<()> {
+        if self.packet_sizes.len().is_multiple_of(PACKETS_PER_CHUNK) {
+            self.chunk_offsets.push(self.position);
+        }
+        self.out.write_all(packet)?;
+        self.position += packet.len() as u64;
+        self.packet_sizes.push(packet.len() as u32);
+        self.max_packet = self.max_packet.max(packet.len());
+        Ok(())
+    }
+
+    /// Write chapter samples, patch `mdat` and append `moov`
+    ///
+    /// `duration` is the playable length in PCM frames, excluding priming and padding.
+    pub fn finish(mut self, duration: u64, chapters: &[Mp4Chapter], tags: &Mp4Tags) -> io::Result<W> {
+        let duration_ms = duration * MOVIE_TIMESCALE as u64 / self.track.sample_rate as u64;
+
+        // Chapter titles live in mdat as QuickTime text samples
+        let chapter_samples = chapter_samples(chapters, duration_ms);
+        let chapter_offset = self.position;
+        for (sample, _) in &chapter_samples {
+            self.out.write_all(sample)?;
+            self.position += sample.len() as u64;
+        }
+
+        let mdat_size = self.position - self.mdat_start - 8;
+        if mdat_size <= u32::MAX as u64 {
+            self.out.seek(SeekFrom::Start(self.mdat_start + 8))?;
+            self.out.write_all(&(mdat_size as u32).to_be_bytes())?;
+        } else {
+            self.out.seek(SeekFrom::Start(self.mdat_start))?;
+            self.out.write_all(&1u32.to_be_bytes())?;
+            self.out.write_all(b"mdat")?;
+            self.out.write_all(&(mdat_size + 8).to_be_bytes())?;
+        }
+        self.out.seek(SeekFrom::Start(self.position))?;
+
+        let mut moov = BoxWriter::new();
+        moov.begin(b"moov");
+        write_mvhd(&mut moov, duration_ms, !chapter_samples.is_empty());
+        self.write_audio_trak(&mut moov, duration_ms, !chapter_samples.is_empty());
+        if !chapter_samples.is_empty() {
+            write_chapter_trak(&mut moov, &chapter_samples, chapter_offset, duration_ms);
+        }
+        write_udta(&mut moov, chapters, tags);
+        moov.end();
+
+        self.out.write_all(&moov.into_inner())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_audio_trak(&self, out: &mut BoxWriter, duration_ms: u64, has_chapters: bool) {
+        let track = &self.track;
+        let media_duration = self.packet_sizes.len() as u64 * track.frame_length as u64;
+
+        out.begin(b"trak");
+        write_tkhd(out, AUDIO_TRACK_ID, duration_ms, 0x7, 0x0100); // enabled, in movie, in preview
+
+        if has_chapters {
+            out.begin(b"tref");
+            out.begin(b"chap");
+            out.u32(CHAPTER_TRACK_ID);
+            out.end();
+            out.end();
+        }
+
+        // Skip the encoder priming so playback starts on the first real sample
+        out.begin(b"edts");
+        out.begin_full(b"elst", 0, 0);
+        out.u32(1);
+        out.u32(duration_ms as u32);
+        out.u32(track.priming);
+        out.u32(0x0001_0000);
+        out.end();
+        out.end();
+
+        out.begin(b"mdia");
+        write_mdhd(out, track.sample_rate, media_duration);
+        write_hdlr(out, b"soun", "SoundHandler");
+
+        out.begin(b"minf");
+        out.begin_full(b"smhd", 0, 0);
+        out.u16(0);
+        out.u16(0);
+        out.end();
+        write_dinf(out);
+
+        out.begin(b"stbl");
+        self.write_stsd_mp4a(out);
+
+        out.begin_full(b"stts", 0, 0);
+        out.u32(1);
+        out.u32(self.packet_sizes.len() as u32);
+        out.u32(track.frame_length);
+        out.end();
+
+        write_sample_tables(out, &self.packet_sizes, &self.chunk_offsets, PACKETS_PER_CHUNK);
+
+        out.end(); // stbl
+        out.end(); // minf
+        out.end(); // mdia
+        out.end(); // trak
+    }
+
+    fn write_stsd_mp4a(&self, out: &mut BoxWriter) {
+        let track = &self.track;
+        let media_secs = (self.packet_sizes.len() as u64 * track.frame_length as u64) as f64 / track.sample_rate as f64;
+        let payload: u64 = self.packet_sizes.iter().map(|size| *size as u64).sum();
+        let avg_bitrate = if media_secs > 0.0 { (payload as f64 * 8.0 / media_secs) as u32 } else { 0 };
+        let max_bitrate = (self.max_packet as u64 * 8 * track.sample_rate as u64 / track.frame_length as u64) as u32;
+
+        out.begin_full(b"stsd", 0, 0);
+        out.u32(1);
+
+        out.begin(b"mp4a");
+        out.zeros(6);
+        out.u16(1); // data reference index
+        out.zeros(8);
+        out.u16(track.channels);
+        out.u16(16);
+        out.u16(0);
+        out.u16(0);
+        out.u32(track.sample_rate.min(0xFFFF) << 16);
+
+        out.begin_full(b"esds", 0, 0);
+        let decoder_specific = descriptor(0x05, &track.config);
+        let mut decoder_config = vec![0x40, 0x15]; // MPEG-4 audio, audio stream
+        decoder_config.extend_from_slice(&[0, 0x18, 0]); // buffer size (6144 bytes)
+        decoder_config.extend_from_slice(&max_bitrate.to_be_bytes());
+        decoder_config.extend_from_slice(&avg_bitrate.to_be_bytes());
+        decoder_config.extend_from_slice(&decoder_specific);
+        let mut es = vec![0, 0, 0]; // ES id, flags
+        es.extend_from_slice(&descriptor(0x04, &decoder_config));
+        es.extend_from_slice(&descriptor(0x06, &[0x02]));
+        out.bytes(&descriptor(0x03, &es));
+        out.end();
+
+        out.end(); // mp4a
+        out.end(); // stsd
+    }
+}
+
+/// Encode chapter titles as QuickTime text samples paired with their duration in ms
+fn chapter_samples(chapters: &[Mp4Chapter], duration_ms: u64) -> Vec<(Vec<u8>, u32)> {
+    chapters
+        .iter()
+        .enumerate()
+        .filter(|(_, chapter)| chapter.start_ms < duration_ms)
+        .map(|(index, chapter)| {
+            let end = chapters
+                .get(index + 1)
+                .map(|next| next.start_ms.min(duration_ms))
+                .unwrap_or(duration_ms);
+            let title = truncate_utf8(&chapter.title, u16::MAX as usize - 16);
+
+            let mut sample = Vec::with_capacity(title.len() + 14);
+            sample.extend_from_slice(&(title.len() as u16).to_be_bytes());
+            sample.extend_from_slice(title.as_bytes());
+            // `encd` atom: text is UTF-8
+            sample.extend_from_slice(&[0, 0, 0, 12]);
+            sample.extend_from_slice(b"encd");
+            sample.extend_from_slice(&[0, 0, 1, 0]);
+
+            (sample, end.saturating_sub(chapter.start_ms) as u32)
+        })
+        .collect()
+}
+
+fn truncate_utf8(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn write_mvhd(out: &mut BoxWriter, duration_ms: u64, has_chapters: bool) {
+    out.begin_full(b"mvhd", 0, 0);
+    out.u32(0); // creation time
+    out.u32(0); // modification time
+    out.u32(MOVIE_TIMESCALE);
+    out.u32(duration_ms as u32);
+    out.u32(0x0001_0000); // rate 1.0
+    out.u16(0x0100); // volume 1.0
+    out.zeros(10);
+    for value in UNITY_MATRIX {
+        out.u32(value);
+    }
+    out.zeros(24);
+    out.u32(if has_chapters { CHAPTER_TRACK_ID + 1 } else { AUDIO_TRACK_ID + 1 });
+    out.end();
+}
+
+fn write_tkhd(out: &mut BoxWriter, track_id: u32, duration_ms: u64, flags: u32, volume: u16) {
+    out.begin_full(b"tkhd", 0, flags);
+    out.u32(0);
+    out.u32(0);
+    out.u32(track_id);
+    out.u32(0);
+    out.u32(duration_ms as u32);
+    out.zeros(8);
+    out.u16(0); // layer
+    out.u16(0); // alternate group
+    out.u16(volume);
+    out.u16(0);
+    for value in UNITY_MATRIX {
+        out.u32(value);
+    }
+    out.u32(0); // width
+    out.u32(0); // height
+    out.end();
+}
+
+fn write_mdhd(out: &mut BoxWriter, timescale: u32, duration: u64) {
+    // Version 1 keeps the duration exact for books longer than a day at 48 kHz
+    out.begin_full(b"mdhd", 1, 0);
+    out.u64(0);
+    out.u64(0);
+    out.u32(timescale);
+    out.u64(duration);
+    out.u16(0x55C4); // "und"
+    out.u16(0);
+    out.end();
+}
+
+fn write_hdlr(out: &mut BoxWriter, handler: &[u8; 4], name: &str) {
+    out.begin_full(b"hdlr", 0, 0);
+    out.u32(0);
+    out.bytes(handler);
+    out.zeros(12);
+    out.bytes(name.as_bytes());
+    out.u8(0);
+    out.end();
+}
+
+fn write_dinf(out: &mut BoxWriter) {
+    out.begin(b"dinf");
+    out.begin_full(b"dref", 0, 0);
+    out.u32(1);
+    out.begin_full(b"url ", 0, 1); // media data is in this file
+    out.end();
+    out.end();
+    out.end();
+}
+
+/// Write `stsc`, `stsz` and `stco`/`co64` for fixed-size chunks
+fn write_sample_tables(out: &mut BoxWriter, sizes: &[u32], chunk_offsets: &[u64], per_chunk: usize) {
+    let remainder = sizes.len() % per_chunk;
+    let full_chunks = sizes.len() / per_chunk;
+
+    out.begin_full(b"stsc", 0, 0);
+    let mut entries = Vec::new();
+    if full_chunks > 0 {
+        entries.push((1u32, per_chunk as u32));
+    }
+    if remainder > 0 {
+        entries.push((full_chunks as u32 + 1, remainder as u32));
+    }
+    out.u32(entries.len() as u32);
+    for (first_chunk, samples) in entries {
+        out.u32(first_chunk);
+        out.u32(samples);
+        out.u32(1);
+    }
+    out.end();
+
+    out.begin_full(b"stsz", 0, 0);
+    out.u32(0);
+    out.u32(sizes.len() as u32);
+    for size in sizes {
+        out.u32(*size);
+    }
+    out.end();
+
+    if chunk_offsets.iter().all(|offset| *offset <= u32::MAX as u64) {
+        out.begin_full(b"stco", 0, 0);
+        out.u32(chunk_offsets.len() as u32);
+        for offset in chunk_offsets {
+            out.u32(*offset as u32);
+        }
+    } else {
+        out.begin_full(b"co64", 0, 0);
+        out.u32(chunk_offsets.len() as u32);
+        for offset in chunk_offsets {
+            out.u64(*offset);
+        }
+    }
+    out.end();
+}
+
+/// QuickTime chapter track, referenced from the audio track through `tref/chap`
+fn write_chapter_trak(out: &mut BoxWriter, samples: &[(Vec<u8>, u32)], offset: u64, duration_ms: u64) {
+    out.begin(b"trak");
+    // Disabled so players list it as chapters instead of rendering subtitles
+    write_tkhd(out, CHAPTER_TRACK_ID, duration_ms, 0, 0);
+
+    out.begin(b"mdia");
+    write_mdhd(out, MOVIE_TIMESCALE, duration_ms);
+    write_hdlr(out, b"text", "ChapterHandler");
+
+    out.begin(b"minf");
+    out.begin(b"gmhd");
+    out.begin_full(b"gmin", 0, 0);
+    out.u16(0x0040); // graphics mode: copy
+    out.u16(0x8000);
+    out.u16(0x8000);
+    out.u16(0x8000);
+    out.u16(0); // balance
+    out.u16(0);
+    out.end();
+    out.begin(b"text");
+    out.u16(0x0001);
+    out.zeros(12);
+    out.u32(0x0001);
+    out.zeros(12);
+    out.u32(0x0000_4000);
+    out.u16(0);
+    out.end();
+    out.end(); // gmhd
+    write_dinf(out);
+
+    out.begin(b"stbl");
+    out.begin_full(b"stsd", 0, 0);
+    out.u32(1);
+    out.begin(b"text");
+    out.zeros(6);
+    out.u16(1); // data reference index
+    out.u32(0x0000_0001); // display flags
+    out.zeros(6); // justification, background colour
+    out.zeros(8); // default text box
+    out.u16(0); // style: start char
+    out.u16(0); // style: end char
+    out.u16(1); // style: font id
+    out.zeros(6); // style: flags, size, colour
+    out.begin(b"ftab");
+    out.u16(1);
+    out.u16(1);
+    out.u8(0);
+    out.end();
+    out.end(); // text
+    out.end(); // stsd
+
+    out.begin_full(b"stts", 0, 0);
+    out.u32(samples.len() as u32);
+    for (_, duration) in samples {
+        out.u32(1);
+        out.u32(*duration);
+    }
+    out.end();
+
+    let sizes: Vec<u32> = samples.iter().map(|(sample, _)| sample.len() as u32).collect();
+    write_sample_tables(out, &sizes, &[offset], sizes.len().max(1));
+
+    out.end(); // stbl
+    out.end(); // minf
+    out.end(); // mdia
+    out.end(); // trak
+}
+
+/// Nero `chpl` chapters and the iTunes `ilst` tags
+fn write_udta(out: &mut BoxWriter, chapters: &[Mp4Chapter], tags: &Mp4Tags) {
+    out.begin(b"udta");
+
+    if !chapters.is_empty() {
+        // Nero chapters count with a single byte
+        let count = chapters.len().min(u8::MAX as usize);
+        out.begin_full(b"chpl", 1, 0);
+        out.u32(0);
+        out.u8(count as u8);
+        for chapter in &chapters[..count] {
+            // Start times are in 100 ns units
+            out.u64(chapter.start_ms * 10_000);
+            let title = truncate_utf8(&chapter.title, u8::MAX as usize);
+            out.u8(title.len() as u8);
+            out.bytes(title.as_bytes());
+        }
+        out.end();
+    }
+
+    out.begin_full(b"meta", 0, 0);
+    out.begin_full(b"hdlr", 0, 0);
+    out.u32(0);
+    out.bytes(b"mdir");
+    out.bytes(b"appl");
+    out.zeros(8);
+    out.u8(0);
+    out.end();
+
+    out.begin(b"ilst");
+    let text_items: [(&[u8; 4], &Option<String>); 4] = [
+        (b"\xA9nam", &tags.title),
+        (b"\xA9ART", &tags.artist),
+        (b"\xA9alb", &tags.album),
+        (b"\xA9gen", &tags.genre),
+    ];
+    for (kind, value) in text_items {
+        if let Some(value) = value {
+            write_ilst_item(out, kind, 1, value.as_bytes());
+        }
+    }
+    if let Some(kind) = tags.media_kind {
+        write_ilst_item(out, b"stik", 21, &[kind]);
+    }
+    if let Some(cover) = &tags.cover {
+        let data_type = if cover.mime_type == "image/png" { 14 } else { 13 };
+        write_ilst_item(out, b"covr", data_type, &cover.data);
+    }
+    out.end(); // ilst
+
+    out.end(); // meta
+    out.end(); // udta
+}
+
+fn write_ilst_item(out: &mut BoxWriter, kind: &[u8; 4], data_type: u32, payload: &[u8]) {
+    out.begin(kind);
+    out.begin(b"data");
+    out.u32(data_type);
+    out.u32(0); // locale
+    out.bytes(payload);
+    out.end();
+    out.end();
+}
+
+/// MPEG-4 descriptor with a fixed four byte length field
+fn descriptor(tag: u8, body: &[u8]) -> Vec<u8> {
+    let len = body.len() as u32;
+    let mut out = vec![
+        tag,
+        0x80 | ((len >> 21) & 0x7F) as u8,
+        0x80 | ((len >> 14) & 0x7F) as u8,
+        0x80 | ((len >> 7) & 0x7F) as u8,
+        (len & 0x7F) as u8,
+    ];
+    out.extend_from_slice(body);
+    out
+}
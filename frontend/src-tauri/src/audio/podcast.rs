@@ -0,0 +1,451 @@
+//! Podcast feed for books released chapter by chapter
+//!
+//! Writes a self-contained folder (RSS 2.0 feed with iTunes and Podcasting 2.0
+//! tags, the episode files, cover and JSON chapters) meant to be uploaded as-is
+//! to a static web host at `base_url`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::error::ExportError;
+use super::m4b::read_cover;
+use super::tags;
+use crate::atomic::write_atomic;
+
+/// Namespace for `podcast:guid`, fixed by the Podcasting 2.0 specification
+const PODCAST_GUID_NAMESPACE: Uuid = Uuid::from_u128(0xead4c236_bf58_58c6_a2c6_a6b28d128cb6);
+
+/// Chapter mark inside one episode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeChapter {
+    pub title: String,
+    pub start_ms: u64,
+}
+
+/// An exported chapter file published as one episode, in release order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedEpisode {
+    pub path: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// RFC 3339 timestamp overriding the schedule for this episode
+    pub publish_at: Option<String>,
+    /// Written as a `podcast:chapters` JSON file when present
+    pub chapters: Option<Vec<EpisodeChapter>>,
+}
+
+/// First release plus a fixed interval between episodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseSchedule {
+    /// RFC 3339 timestamp of the first episode
+    pub start: String,
+    #[serde(default = "default_interval_days")]
+    pub interval_days: u32,
+}
+
+fn default_interval_days() -> u32 {
+    7
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedOptions {
+    pub title: String,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    /// RSS language code, e.g. `de` or `en-us`
+    pub language: Option<String>,
+    /// Public URL of the output folder
+    pub base_url: String,
+    /// JPEG/PNG cover, copied into the folder
+    pub image_path: Option<String>,
+    pub owner_name: Option<String>,
+    pub owner_email: Option<String>,
+    /// iTunes category, e.g. `Arts`
+    pub category: Option<String>,
+    #[serde(default)]
+    pub explicit: bool,
+    pub schedule: ReleaseSchedule,
+    /// Also list episodes whose release date is still ahead; otherwise the feed
+    /// has to be regenerated as episodes come due
+    #[serde(default)]
+    pub include_unreleased: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedItem {
+    pub guid: String,
+    pub title: String,
+    pub url: String,
+    pub length: u64,
+    pub duration_secs: f64,
+    /// RFC 3339
+    pub pub_date: String,
+    pub chapters_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedSummary {
+    pub feed_path: String,
+    pub feed_url: String,
+    pub podcast_guid: String,
+    pub items: Vec<FeedItem>,
+    /// Episodes left out because their release date is in the future
+    pub unreleased: usize,
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab/newline are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("mp3") => "audio/mpeg",
+        Some("m4a" | "m4b" | "mp4") => "audio/mp4",
+        Some("opus" | "ogg") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        _ => "audio/wav",
+    }
+}
+
+fn parse_date(value: &str) -> Result<DateTime<FixedOffset>, ExportError> {
+    DateTime::parse_from_rfc3339(value).map_err(|_| ExportError::InvalidReleaseDate { value: value.to_string() })
+}
+
+/// `podcast:guid` is derived from the feed URL without scheme and trailing slashes
+fn podcast_guid(feed_url: &Url) -> Uuid {
+    let url = feed_url.as_str();
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    Uuid::new_v5(&PODCAST_GUID_NAMESPACE, url.trim_end_matches('/').as_bytes())
+}
+
+/// Copy `source` into `directory` unless it already lives there
+fn copy_into(source: &Path, directory: &Path, file_name: &str) -> Result<(), ExportError> {
+    let target = directory.join(file_name);
+    let same = match (fs::canonicalize(source), fs::canonicalize(&target)) {
+        (Ok(source), Ok(target)) => source == target,
+        _ => false,
+    };
+    if !same {
+        fs::copy(source, &target).map_err(|e| ExportError::io(target.display().to_string(), e))?;
+    }
+    Ok(())
+}
+
+/// Podcasting 2.0 JSON chapters
+fn chapters_json(chapters: &[EpisodeChapter]) -> String {
+    let chapters: Vec<serde_json::Value> = chapters
+        .iter()
+        .map(|chapter| serde_json::json!({ "startTime": chapter.start_ms as f64 / 1000.0, "title": chapter.title }))
+        .collect();
+    let mut json =
+        serde_json::to_string_pretty(&serde_json::json!({ "version": "1.2.0", "chapters": chapters })).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// Write `feed.xml` and everything it links to into `directory`
+pub fn write_feed(directory: &Path, episodes: &[FeedEpisode], options: &FeedOptions) -> Result<FeedSummary, ExportError> {
+    if episodes.is_empty() {
+        return Err(ExportError::NoChapters);
+    }
+    let invalid_url = || ExportError::InvalidFeedUrl { url: options.base_url.clone() };
+    // A trailing slash makes `join` resolve file names inside the folder
+    let base_url = Url::parse(&format!("{}/", options.base_url.trim_end_matches('/'))).map_err(|_| invalid_url())?;
+    if !matches!(base_url.scheme(), "http" | "https") {
+        return Err(invalid_url());
+    }
+    // Pushed as a single path segment, so `#`, `?` or `%` in a chapter title stay part of the name
+    let url_for = |file_name: &str| -> Result<String, ExportError> {
+        let mut url = base_url.clone();
+        url.path_segments_mut().map_err(|_| invalid_url())?.pop_if_empty().push(file_name);
+        Ok(String::from(url))
+    };
+    let feed_url = base_url.join("feed.xml").map_err(|_| invalid_url())?;
+    let guid = podcast_guid(&feed_url);
+
+    let start = parse_date(&options.schedule.start)?;
+    let now = Utc::now();
+    fs::create_dir_all(directory).map_err(|e| ExportError::io(directory.display().to_string(), e))?;
+
+    let image_url = match &options.image_path {
+        Some(image_path) => {
+            let extension = if read_cover(image_path)?.mime_type == "image/png" { "png" } else { "jpg" };
+            let file_name = format!("cover.{}", extension);
+            copy_into(Path::new(image_path), directory, &file_name)?;
+            Some(url_for(&file_name)?)
+        }
+        None => None,
+    };
+
+    let mut items = Vec::with_capacity(episodes.len());
+    let mut entries = String::new();
+    let mut unreleased = 0;
+    for (index, episode) in episodes.iter().enumerate() {
+        let pub_date = match &episode.publish_at {
+            Some(value) => parse_date(value)?,
+            None => start + Duration::days(options.schedule.interval_days as i64 * index as i64),
+        };
+        if !options.include_unreleased && pub_date > now {
+            unreleased += 1;
+            continue;
+        }
+
+        let source = Path::new(&episode.path);
+        let file_name = source
+            .file_name()
+            .ok_or_else(|| ExportError::io(&episode.path, "not a file"))?
+            .to_string_lossy()
+            .into_owned();
+        let length = fs::metadata(source).map_err(|e| ExportError::io(&episode.path, e))?.len();
+        let duration_secs = tags::read_tags(source)?.duration_secs;
+        copy_into(source, directory, &file_name)?;
+        let url = url_for(&file_name)?;
+
+        let chapters_url = match &episode.chapters {
+            Some(chapters) if !chapters.is_empty() => {
+                let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+                let chapters_name = format!("{}.chapters.json", stem);
+                let chapters_path = directory.join(&chapters_name);
+                write_atomic(&chapters_path, chapters_json(chapters).as_bytes())
+                    .map_err(|e| ExportError::io(chapters_path.display().to_string(), e))?;
+                Some(url_for(&chapters_name)?)
+            }
+            _ => None,
+        };
+
+        // Stable across regenerations as long as the file name stays the same
+        let item_guid = Uuid::new_v5(&guid, file_name.as_bytes()).to_string();
+
+        let mut entry = String::new();
+        let _ = writeln!(entry, "    <item>");
+        let _ = writeln!(entry, "      <title>{}</title>", xml_escape(&episode.title));
+        if let Some(description) = &episode.description {
+            let _ = writeln!(entry, "      <description>{}</description>", xml_escape(description));
+        }
+        let _ = writeln!(
+            entry,
+            "      <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>",
+            xml_escape(&url),
+            length,
+            mime_type(source)
+        );
+        let _ = writeln!(entry, "      <guid isPermaLink=\"false\">{}</guid>", item_guid);
+        let _ = writeln!(entry, "      <pubDate>{}</pubDate>", pub_date.to_rfc2822());
+        let _ = writeln!(entry, "      <itunes:duration>{}</itunes:duration>", duration_secs.round() as u64);
+        let _ = writeln!(entry, "      <itunes:episode>{}</itunes:episode>", index + 1);
+        let _ = writeln!(entry, "      <itunes:episodeType>full</itunes:episodeType>");
+        if let Some(chapters_url) = &chapters_url {
+            let _ = writeln!(
+                entry,
+                "      <podcast:chapters url=\"{}\" type=\"application/json+chapters\"/>",
+                xml_escape(chapters_url)
+            );
+        }
+        let _ = writeln!(entry, "    </item>");
+        // Newest first, as podcast apps expect
+        entries.insert_str(0, &entry);
+
+        items.push(FeedItem {
+            guid: item_guid,
+            title: episode.title.clone(),
+            url,
+            length,
+            duration_secs,
+            pub_date: pub_date.to_rfc3339(),
+            chapters_url,
+        });
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(concat!(
+        "<rss version=\"2.0\"",
+        " xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\"",
+        " xmlns:podcast=\"https://podcastindex.org/namespace/1.0\"",
+        " xmlns:atom=\"http://www.w3.org/2005/Atom\">\n",
+        "  <channel>\n"
+    ));
+    let _ = writeln!(xml, "    <title>{}</title>", xml_escape(&options.title));
+    let _ = writeln!(xml, "    <link>{}</link>", xml_escape(base_url.as_str()));
+    let _ = writeln!(
+        xml,
+        "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>",
+        xml_escape(feed_url.as_str())
+    );
+    let description = options.description.as_deref().unwrap_or(&options.title);
+    let _ = writeln!(xml, "    <description>{}</description>", xml_escape(description));
+    if let Some(language) = &options.language {
+        let _ = writeln!(xml, "    <language>{}</language>", xml_escape(language));
+    }
+    let _ = writeln!(xml, "    <lastBuildDate>{}</lastBuildDate>", now.to_rfc2822());
+    let _ = writeln!(xml, "    <podcast:guid>{}</podcast:guid>", guid);
+    let _ = writeln!(xml, "    <itunes:type>serial</itunes:type>");
+    let _ = writeln!(xml, "    <itunes:explicit>{}</itunes:explicit>", options.explicit);
+    if let Some(author) = &options.author {
+        let _ = writeln!(xml, "    <itunes:author>{}</itunes:author>", xml_escape(author));
+    }
+    if options.owner_name.is_some() || options.owner_email.is_some() {
+        let _ = writeln!(xml, "    <itunes:owner>");
+        if let Some(name) = &options.owner_name {
+            let _ = writeln!(xml, "      <itunes:name>{}</itunes:name>", xml_escape(name));
+        }
+        if let Some(email) = &options.owner_email {
+            let _ = writeln!(xml, "      <itunes:email>{}</itunes:email>", xml_escape(email));
+        }
+        let _ = writeln!(xml, "    </itunes:owner>");
+    }
+    if let Some(category) = &options.category {
+        let _ = writeln!(xml, "    <itunes:category text=\"{}\"/>", xml_escape(category));
+    }
+    if let Some(image_url) = &image_url {
+        let _ = writeln!(xml, "    <itunes:image href=\"{}\"/>", xml_escape(image_url));
+        let _ = writeln!(
+            xml,
+            "    <image>\n      <url>{}</url>\n      <title>{}</title>\n      <link>{}</link>\n    </image>",
+            xml_escape(image_url),
+            xml_escape(&options.title),
+            xml_escape(base_url.as_str())
+        );
+    }
+    xml.push_str(&entries);
+    xml.push_str("  </channel>\n</rss>\n");
+
+    let feed_path = directory.join("feed.xml");
+    write_atomic(&feed_path, xml.as_bytes()).map_err(|e| ExportError::io(feed_path.display().to_string(), e))?;
+
+    Ok(FeedSummary {
+        feed_path: feed_path.display().to_string(),
+        feed_url: feed_url.to_string(),
+        podcast_guid: guid.to_string(),
+        items,
+        unreleased,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(path: &Path) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for _ in 0..8_000 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn options(base_url: &str) -> FeedOptions {
+        FeedOptions {
+            title: "Tom & Jerry".to_string(),
+            description: None,
+            author: Some("A <Writer>".to_string()),
+            language: Some("en".to_string()),
+            base_url: base_url.to_string(),
+            image_path: None,
+            owner_name: None,
+            owner_email: None,
+            category: None,
+            explicit: false,
+            schedule: ReleaseSchedule { start: "2020-01-01T09:00:00+01:00".to_string(), interval_days: 7 },
+            include_unreleased: false,
+        }
+    }
+
+    fn episode(path: &Path, title: &str) -> FeedEpisode {
+        FeedEpisode {
+            path: path.display().to_string(),
+            title: title.to_string(),
+            description: None,
+            publish_at: None,
+            chapters: None,
+        }
+    }
+
+    #[test]
+    fn podcast_guid_follows_the_specification() {
+        let url = Url::parse("https://mp3s.nashownotes.com/pc20rss.xml").unwrap();
+        assert_eq!(podcast_guid(&url).to_string(), "917393e3-1b1e-5cef-ace4-edaa54e1f810");
+    }
+
+    #[test]
+    fn xml_escape_drops_disallowed_control_characters() {
+        assert_eq!(xml_escape("a<b>&\"c'\u{7}\td"), "a&lt;b&gt;&amp;&quot;c&apos;\td");
+    }
+
+    #[test]
+    fn feed_links_files_by_encoded_url_and_holds_back_future_episodes() {
+        let source = std::env::temp_dir().join(format!("podcast-test-{}", Uuid::new_v4()));
+        let output = source.join("feed");
+        fs::create_dir_all(&source).unwrap();
+        let first = source.join("01 #1?.wav");
+        let second = source.join("02.wav");
+        write_wav(&first);
+        write_wav(&second);
+        let mut future = episode(&second, "Later");
+        future.publish_at = Some("2999-01-01T00:00:00Z".to_string());
+        let mut with_chapters = episode(&first, "One & only");
+        with_chapters.chapters = Some(vec![EpisodeChapter { title: "Start".to_string(), start_ms: 1_500 }]);
+
+        let summary = write_feed(&output, &[with_chapters, future], &options("https://example.com/book/")).unwrap();
+        assert_eq!(summary.feed_url, "https://example.com/book/feed.xml");
+        assert_eq!(summary.unreleased, 1);
+        assert_eq!(summary.items.len(), 1);
+        let item = &summary.items[0];
+        assert_eq!(item.url, "https://example.com/book/01%20%231%3F.wav");
+        assert_eq!(item.chapters_url.as_deref(), Some("https://example.com/book/01%20%231%3F.chapters.json"));
+        assert_eq!(item.pub_date, "2020-01-01T09:00:00+01:00");
+        assert!(output.join("01 #1?.wav").is_file());
+
+        let xml = fs::read_to_string(output.join("feed.xml")).unwrap();
+        assert!(xml.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(xml.contains("<itunes:author>A &lt;Writer&gt;</itunes:author>"));
+        assert!(xml.contains("<title>One &amp; only</title>"));
+        assert!(xml.contains("<itunes:duration>1</itunes:duration>"));
+        assert!(!xml.contains("Later"));
+        let chapters = fs::read_to_string(output.join("01 #1?.chapters.json")).unwrap();
+        assert!(chapters.contains("\"startTime\": 1.5"));
+
+        // Regenerating keeps the GUIDs podcast apps know the episodes by
+        let again = write_feed(&output, &[episode(&first, "Renamed")], &options("https://example.com/book")).unwrap();
+        assert_eq!(again.items[0].guid, item.guid);
+        assert_eq!(again.podcast_guid, summary.podcast_guid);
+        fs::remove_dir_all(&source).unwrap();
+    }
+
+    #[test]
+    fn feed_needs_an_http_base_url() {
+        let episodes = [episode(Path::new("a.mp3"), "A")];
+        let result = write_feed(Path::new("unused"), &episodes, &options("ftp://example.com"));
+        assert!(matches!(result, Err(ExportError::InvalidFeedUrl { .. })));
+    }
+}
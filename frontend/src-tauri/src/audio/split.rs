@@ -0,0 +1,407 @@
+//! Multi-file export: one file per chapter or per maximum duration, plus an
+//! extended M3U8 playlist
+//!
+//! Cuts only fall on segment boundaries; the pause between the two segments
+//! at a cut is dropped instead of ending up at the edge of a file.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::buffer::WavStream;
+use super::chapters::{self, ChapterSpan};
+use super::encode;
+use super::error::ExportError;
+use super::format::{EncodeOptions, EncodeSettings, ExportFormat};
+use super::loudness::{LoudnessReport, LoudnessStats, ProgrammeGain, ProgrammeMeter};
+use super::tags::{self, AudioMetadata};
+use crate::atomic::write_atomic;
+
+pub const DEFAULT_NAME_TEMPLATE: &str = "{index:02} - {chapter}";
+
+/// Timeline rounding the rendered audio may differ from the segment durations by
+const TIMELINE_TOLERANCE_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SplitMode {
+    /// One file per chapter; chapters longer than `max_duration_secs` (if set) are split further
+    #[default]
+    Chapter,
+    /// Fill each file up to `max_duration_secs`, ignoring chapter boundaries
+    Duration,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitOptions {
+    #[serde(default)]
+    pub mode: SplitMode,
+    pub max_duration_secs: Option<u64>,
+    /// Placeholders: `{index}` (or zero padded `{index:02}`), `{chapter}`, `{title}`
+    pub name_template: Option<String>,
+    #[serde(default)]
+    pub encode: EncodeOptions,
+    /// Silence the export inserts between segments of a chapter
+    #[serde(default)]
+    pub pause_between_segments_ms: u64,
+    /// Silence the export inserts between chapters
+    #[serde(default)]
+    pub pause_between_chapters_ms: u64,
+    /// Write `<title>.m3u8` next to the parts
+    #[serde(default = "default_true")]
+    pub playlist: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// One written file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitPart {
+    pub path: String,
+    pub title: String,
+    /// Position of the part in the unsplit book
+    pub start_ms: u64,
+    pub duration_ms: u64,
+    pub bytes_written: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitSummary {
+    pub directory: String,
+    pub settings: EncodeSettings,
+    pub parts: Vec<SplitPart>,
+    pub playlist_path: Option<String>,
+    pub duration_secs: f64,
+    /// Present when the export normalizes loudness; measured over all parts together
+    pub loudness: Option<LoudnessReport>,
+}
+
+/// A planned part, before any audio is read
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PartPlan {
+    title: String,
+    chapter: String,
+    start_ms: u64,
+    end_ms: u64,
+}
+
+/// Group consecutive segments so each group spans at most `max_ms`; a single
+/// segment longer than that still becomes a group of its own
+fn group_segments(segments: &[(u64, u64)], max_ms: Option<u64>) -> Vec<(u64, u64)> {
+    let mut groups: Vec<(u64, u64)> = Vec::new();
+    for &(start, end) in segments {
+        match groups.last_mut() {
+            Some(group) if max_ms.is_none_or(|max| end - group.0 <= max) => group.1 = end,
+            _ => groups.push((start, end)),
+        }
+    }
+    groups
+}
+
+fn plan(chapters: &[ChapterSpan], options: &SplitOptions) -> Result<Vec<PartPlan>, ExportError> {
+    let max_ms = match (options.mode, options.max_duration_secs) {
+        (_, Some(0)) | (SplitMode::Duration, None) => {
+            return Err(ExportError::InvalidSplitDuration { seconds: options.max_duration_secs.unwrap_or(0) });
+        }
+        (_, max) => max.map(|secs| secs * 1000),
+    };
+    let layout = chapters::layout(chapters, options.pause_between_segments_ms, options.pause_between_chapters_ms);
+
+    let mut parts = Vec::new();
+    match options.mode {
+        SplitMode::Chapter => {
+            for (chapter, layout) in chapters.iter().zip(&layout) {
+                let groups = group_segments(&layout.segments, max_ms);
+                let count = groups.len();
+                for (index, (start_ms, end_ms)) in groups.into_iter().enumerate() {
+                    let title = if count > 1 {
+                        format!("{} ({}/{})", chapter.title, index + 1, count)
+                    } else {
+                        chapter.title.clone()
+                    };
+                    parts.push(PartPlan { title, chapter: chapter.title.clone(), start_ms, end_ms });
+                }
+            }
+        }
+        SplitMode::Duration => {
+            // Remember which chapter each segment belongs to, to name the part after its first one
+            let segments: Vec<(u64, u64, &str)> = chapters
+                .iter()
+                .zip(&layout)
+                .flat_map(|(chapter, layout)| layout.segments.iter().map(|&(start, end)| (start, end, chapter.title.as_str())))
+                .collect();
+            let bounds: Vec<(u64, u64)> = segments.iter().map(|&(start, end, _)| (start, end)).collect();
+            for (start_ms, end_ms) in group_segments(&bounds, max_ms) {
+                let chapter = segments
+                    .iter()
+                    .find(|segment| segment.0 == start_ms)
+                    .map(|segment| segment.2.to_string())
+                    .unwrap_or_default();
+                parts.push(PartPlan { title: chapter.clone(), chapter, start_ms, end_ms });
+            }
+        }
+    }
+    if parts.is_empty() {
+        return Err(ExportError::NoChapters);
+    }
+    Ok(parts)
+}
+
+/// Replace characters that are not allowed in file names on any desktop platform
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect();
+    // Windows drops trailing dots and spaces
+    let cleaned = cleaned.trim().trim_end_matches('.').trim_end();
+    if cleaned.is_empty() { "_".to_string() } else { cleaned.to_string() }
+}
+
+/// Expand `template` for the part at `index` (1-based)
+pub(crate) fn render_name(template: &str, index: usize, chapter: &str, title: &str) -> Result<String, ExportError> {
+    let invalid = || ExportError::InvalidNameTemplate { template: template.to_string() };
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..].find('}').ok_or_else(invalid)? + open;
+        let placeholder = &rest[open + 1..close];
+        let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+        match (name, spec) {
+            ("index", "") => {
+                let _ = write!(out, "{}", index);
+            }
+            ("index", spec) if spec.starts_with('0') => {
+                let width: usize = spec.parse().map_err(|_| invalid())?;
+                let _ = write!(out, "{:0width$}", index, width = width);
+            }
+            ("chapter", "") => out.push_str(chapter),
+            ("title", "") => out.push_str(title),
+            _ => return Err(invalid()),
+        }
+        rest = &rest[close + 1..];
+    }
+    if rest.contains('}') {
+        return Err(invalid());
+    }
+    out.push_str(rest);
+    Ok(sanitize_file_name(&out))
+}
+
+/// `#EXTINF` titles and file names must stay on one line
+fn playlist_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+fn render_playlist(title: &str, author: Option<&str>, parts: &[SplitPart]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    let _ = writeln!(out, "#PLAYLIST:{}", playlist_line(title));
+    for part in parts {
+        let name = Path::new(&part.path).file_name().unwrap_or_default().to_string_lossy();
+        let display = match author {
+            Some(author) => format!("{} - {}", author, part.title),
+            None => part.title.clone(),
+        };
+        let _ = writeln!(
+            out,
+            "#EXTINF:{},{}\n{}",
+            (part.duration_ms + 500) / 1000,
+            playlist_line(&display),
+            playlist_line(&name)
+        );
+    }
+    out
+}
+
+/// Loudness of everything the parts cover, measured part by part
+fn measure_parts(source: &mut WavStream, ranges: &[(u64, u64)], settings: &EncodeSettings) -> Result<LoudnessStats, ExportError> {
+    let mut meter = ProgrammeMeter::new(settings.channels, settings.sample_rate)?;
+    for &(start, end) in ranges {
+        meter.add(&super::conform(source.read(start, end)?, settings)?)?;
+    }
+    meter.stats()
+}
+
+/// Split the rendered book at `audio` (WAV) into `format` files in `directory`
+///
+/// `chapters` describe the segment timeline of the rendered audio, so cuts land
+/// between segments. Parts are read and encoded one at a time; with a loudness
+/// target the book is measured in a first pass and every part gets the same gain.
+pub fn export_split(
+    audio: &Path,
+    directory: &Path,
+    format: ExportFormat,
+    chapters: &[ChapterSpan],
+    options: &SplitOptions,
+    metadata: Option<&AudioMetadata>,
+) -> Result<SplitSummary, ExportError> {
+    let plans = plan(chapters, options)?;
+    let mut source = WavStream::open(audio)?;
+    let settings = EncodeSettings::resolve(format, &options.encode, source.sample_rate(), source.channels())?;
+
+    let sample_rate = source.sample_rate() as u64;
+    let audio_ms = source.frames() * 1000 / sample_rate.max(1);
+    let timeline_ms = plans.last().map(|part| part.end_ms).unwrap_or(0);
+    if timeline_ms > audio_ms + TIMELINE_TOLERANCE_MS {
+        return Err(ExportError::TimelineMismatch { expected_ms: timeline_ms, actual_ms: audio_ms });
+    }
+
+    let book_title = metadata
+        .and_then(|metadata| metadata.title.clone())
+        .unwrap_or_else(|| audio.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+    let template = options.name_template.as_deref().unwrap_or(DEFAULT_NAME_TEMPLATE);
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(plans.len());
+    for (index, part) in plans.iter().enumerate() {
+        let name = format!("{}.{}", render_name(template, index + 1, &part.chapter, &book_title)?, format.extension());
+        let path = directory.join(&name);
+        if paths.contains(&path) {
+            return Err(ExportError::DuplicateFileName { name });
+        }
+        paths.push(path);
+    }
+
+    std::fs::create_dir_all(directory).map_err(|e| ExportError::io(directory.display().to_string(), e))?;
+
+    let total = plans.len();
+    let ranges: Vec<(u64, u64)> = plans
+        .iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let start = part.start_ms * sample_rate / 1000;
+            // The last part runs to the end of the file so trailing audio is not lost
+            let end = if index + 1 == total { source.frames() } else { part.end_ms * sample_rate / 1000 };
+            (start, end)
+        })
+        .collect();
+    let mut gain = match settings.loudness {
+        Some(target) => {
+            let before = measure_parts(&mut source, &ranges, &settings)?;
+            Some(ProgrammeGain::new(target, before, settings.channels, settings.sample_rate)?)
+        }
+        None => None,
+    };
+
+    let mut parts = Vec::with_capacity(total);
+    let mut duration_secs = 0.0;
+    for (index, ((part, path), &(start, end))) in plans.iter().zip(&paths).zip(&ranges).enumerate() {
+        let mut prepared = super::conform(source.read(start, end)?, &settings)?;
+        if let Some(gain) = &mut gain {
+            prepared = gain.apply(prepared)?;
+        }
+        let mut data = encode::encode(&prepared, &settings)?;
+        let display = path.display().to_string();
+        if let Some(metadata) = metadata {
+            let metadata = AudioMetadata {
+                track_title: Some(part.title.clone()),
+                track_number: Some(index as u32 + 1),
+                track_total: Some(total as u32),
+                ..metadata.clone()
+            };
+            data = tags::tag_data(data, &display, &metadata)?;
+        }
+        write_atomic(path, &data).map_err(|e| ExportError::io(&display, e))?;
+
+        duration_secs += prepared.duration_secs();
+        parts.push(SplitPart {
+            bytes_written: std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(data.len() as u64),
+            path: display,
+            title: part.title.clone(),
+            start_ms: part.start_ms,
+            duration_ms: (prepared.duration_secs() * 1000.0).round() as u64,
+        });
+    }
+
+    let playlist_path = if options.playlist {
+        let path = directory.join(format!("{}.m3u8", sanitize_file_name(&book_title)));
+        let author = metadata.and_then(|metadata| metadata.author.as_deref());
+        let playlist = render_playlist(&book_title, author, &parts);
+        write_atomic(&path, playlist.as_bytes()).map_err(|e| ExportError::io(path.display().to_string(), e))?;
+        Some(path.display().to_string())
+    } else {
+        None
+    };
+
+    Ok(SplitSummary {
+        directory: directory.display().to_string(),
+        settings,
+        parts,
+        playlist_path,
+        duration_secs,
+        loudness: gain.map(|gain| gain.report()).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> Vec<ChapterSpan> {
+        vec![
+            ChapterSpan { title: "Long".to_string(), segment_durations_ms: vec![40_000, 40_000, 40_000] },
+            ChapterSpan { title: "Short".to_string(), segment_durations_ms: vec![10_000] },
+        ]
+    }
+
+    fn options(mode: SplitMode, max_duration_secs: Option<u64>) -> SplitOptions {
+        SplitOptions { mode, max_duration_secs, pause_between_chapters_ms: 2_000, ..Default::default() }
+    }
+
+    fn spans(parts: &[PartPlan]) -> Vec<(&str, u64, u64)> {
+        parts.iter().map(|part| (part.title.as_str(), part.start_ms, part.end_ms)).collect()
+    }
+
+    #[test]
+    fn chapters_longer_than_the_limit_are_cut_at_segment_boundaries() {
+        assert_eq!(
+            spans(&plan(&chapters(), &options(SplitMode::Chapter, None)).unwrap()),
+            [("Long", 0, 120_000), ("Short", 122_000, 132_000)]
+        );
+        assert_eq!(
+            spans(&plan(&chapters(), &options(SplitMode::Chapter, Some(90))).unwrap()),
+            [("Long (1/2)", 0, 80_000), ("Long (2/2)", 80_000, 120_000), ("Short", 122_000, 132_000)]
+        );
+    }
+
+    #[test]
+    fn duration_parts_are_named_after_their_first_chapter() {
+        let parts = plan(&chapters(), &options(SplitMode::Duration, Some(100))).unwrap();
+        assert_eq!(spans(&parts), [("Long", 0, 80_000), ("Long", 80_000, 132_000)]);
+
+        for max in [None, Some(0)] {
+            let result = plan(&chapters(), &options(SplitMode::Duration, max));
+            assert!(matches!(result, Err(ExportError::InvalidSplitDuration { .. })));
+        }
+    }
+
+    #[test]
+    fn name_templates_expand_and_sanitize() {
+        assert_eq!(render_name(DEFAULT_NAME_TEMPLATE, 3, "Intro", "Book").unwrap(), "03 - Intro");
+        assert_eq!(render_name("{title} {index}: {chapter}?", 12, "A/B", "Book").unwrap(), "Book 12_ A_B_");
+        assert_eq!(render_name("{chapter}...", 1, " ", "Book").unwrap(), "_");
+        for template in ["{index", "{index:2}", "{author}", "index}"] {
+            assert!(matches!(render_name(template, 1, "c", "t"), Err(ExportError::InvalidNameTemplate { .. })));
+        }
+    }
+
+    #[test]
+    fn playlist_entries_stay_on_one_line() {
+        let parts = [SplitPart {
+            path: "/out/01 - Intro.mp3".to_string(),
+            title: "Intro\nPart".to_string(),
+            start_ms: 0,
+            duration_ms: 61_600,
+            bytes_written: 0,
+        }];
+        assert_eq!(
+            render_playlist("Book", Some("Author"), &parts),
+            "#EXTM3U\n#PLAYLIST:Book\n#EXTINF:62,Author - Intro Part\n01 - Intro.mp3\n"
+        );
+    }
+}
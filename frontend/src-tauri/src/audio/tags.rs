@@ -0,0 +1,250 @@
+//! Book metadata on exported files: ID3v2.4 for MP3/WAV, `ilst` for M4A/M4B,
+//! Vorbis comments for FLAC/Opus
+
+use std::fs::File;
+use std::io::Cursor;
+use std::path::Path;
+
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::error::LoftyError;
+use lofty::file::{AudioFile, FileType, TaggedFileExt};
+use lofty::io::{FileLike, Length, Truncate};
+use lofty::flac::FlacFile;
+use lofty::iff::wav::WavFile;
+use lofty::mp4::Mp4File;
+use lofty::mpeg::MpegFile;
+use lofty::ogg::OpusFile;
+use lofty::picture::{Picture, PictureType};
+use lofty::probe::Probe;
+use lofty::tag::{Accessor, ItemKey, MergeTag, SplitTag, Tag, TagExt, TagType};
+use serde::{Deserialize, Serialize};
+
+use super::error::ExportError;
+
+/// Book metadata written to (and read back from) an exported file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioMetadata {
+    /// Book title, also written as the album
+    pub title: Option<String>,
+    /// Written as artist and album artist
+    pub author: Option<String>,
+    /// Written as composer, which audiobook players show as the narrator
+    pub narrator: Option<String>,
+    /// Written as the grouping/content group
+    pub series: Option<String>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    pub description: Option<String>,
+    /// JPEG/PNG image embedded as the front cover; ignored when reading
+    pub cover_path: Option<String>,
+    /// Title of this file when the book is split into several; the book title stays the album
+    #[serde(default)]
+    pub track_title: Option<String>,
+    #[serde(default)]
+    pub track_number: Option<u32>,
+    #[serde(default)]
+    pub track_total: Option<u32>,
+}
+
+/// Tag format used for a file's container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TagFormat {
+    Id3v2,
+    Ilst,
+    VorbisComments,
+}
+
+/// Embedded cover as found in the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverInfo {
+    pub mime_type: Option<String>,
+    pub size: usize,
+}
+
+/// What a file actually carries, for display after an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagReport {
+    pub path: String,
+    pub format: Option<TagFormat>,
+    pub metadata: AudioMetadata,
+    pub cover: Option<CoverInfo>,
+    pub duration_secs: f64,
+}
+
+/// Only `ilst` has a dedicated description atom; elsewhere it goes into the comment
+fn description_key(tag_type: TagType) -> ItemKey {
+    match tag_type {
+        TagType::Mp4Ilst => ItemKey::Description,
+        _ => ItemKey::Comment,
+    }
+}
+
+fn tag_format(tag_type: TagType) -> Option<TagFormat> {
+    match tag_type {
+        TagType::Id3v2 => Some(TagFormat::Id3v2),
+        TagType::Mp4Ilst => Some(TagFormat::Ilst),
+        TagType::VorbisComments => Some(TagFormat::VorbisComments),
+        _ => None,
+    }
+}
+
+fn read_picture(path: &str) -> Result<Picture, ExportError> {
+    let mut file = File::open(path).map_err(|e| ExportError::io(path, e))?;
+    let mut picture = Picture::from_reader(&mut file).map_err(|_| ExportError::InvalidCover { path: path.to_string() })?;
+    picture.set_pic_type(PictureType::CoverFront);
+    Ok(picture)
+}
+
+/// Overwrite the fields set in `metadata`, leaving everything else in the tag alone
+fn apply(tag: &mut Tag, metadata: &AudioMetadata, cover: Option<Picture>) {
+    let description_key = description_key(tag.tag_type());
+    let fields = [
+        (ItemKey::TrackTitle, metadata.title.clone()),
+        (ItemKey::AlbumTitle, metadata.title.clone()),
+        (ItemKey::TrackArtist, metadata.author.clone()),
+        (ItemKey::AlbumArtist, metadata.author.clone()),
+        (ItemKey::Composer, metadata.narrator.clone()),
+        (ItemKey::ContentGroup, metadata.series.clone()),
+        (ItemKey::RecordingDate, metadata.year.map(|year| year.to_string())),
+        (ItemKey::Genre, metadata.genre.clone()),
+        (description_key, metadata.description.clone()),
+        (ItemKey::TrackTitle, metadata.track_title.clone()),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            tag.insert_text(key, value);
+        }
+    }
+    if let Some(number) = metadata.track_number {
+        tag.set_track(number);
+    }
+    if let Some(total) = metadata.track_total {
+        tag.set_track_total(total);
+    }
+
+    if let Some(cover) = cover {
+        tag.remove_picture_type(PictureType::CoverFront);
+        tag.push_picture(cover);
+    }
+}
+
+/// Split a container's native tag, apply `metadata` and merge it back, so items
+/// without a generic equivalent (e.g. the M4B `stik` atom) survive
+fn retag<T: SplitTag>(native: T, metadata: &AudioMetadata, cover: Option<Picture>) -> <T::Remainder as MergeTag>::Merged {
+    let (remainder, mut tag) = native.split_tag();
+    apply(&mut tag, metadata, cover);
+    remainder.merge_tag(tag)
+}
+
+/// Write `metadata` into `file` using the tag format of its container
+///
+/// Works on an open file or an in-memory buffer, so an export can be tagged
+/// before it replaces the previous one.
+pub fn write_tags<F>(file: &mut F, display: &str, metadata: &AudioMetadata) -> Result<(), ExportError>
+where
+    F: FileLike,
+    LoftyError: From<<F as Truncate>::Error> + From<<F as Length>::Error>,
+{
+    let tag_error = |e: LoftyError| ExportError::tag(display, e);
+    let cover = metadata.cover_path.as_deref().map(read_picture).transpose()?;
+
+    let rewind = |file: &mut F| file.rewind().map_err(|e| ExportError::io(display, e));
+    rewind(file)?;
+    let file_type = Probe::new(&mut *file).guess_file_type().map_err(|e| tag_error(e.into()))?.file_type();
+    let options = ParseOptions::new();
+    let write_options = WriteOptions::default();
+
+    match file_type {
+        Some(FileType::Mpeg) => {
+            let native = MpegFile::read_from(file, options).map_err(tag_error)?.id3v2().cloned().unwrap_or_default();
+            rewind(file)?;
+            retag(native, metadata, cover).save_to(file, write_options)
+        }
+        Some(FileType::Wav) => {
+            let native = WavFile::read_from(file, options).map_err(tag_error)?.id3v2().cloned().unwrap_or_default();
+            rewind(file)?;
+            retag(native, metadata, cover).save_to(file, write_options)
+        }
+        Some(FileType::Mp4) => {
+            let native = Mp4File::read_from(file, options).map_err(tag_error)?.ilst().cloned().unwrap_or_default();
+            rewind(file)?;
+            retag(native, metadata, cover).save_to(file, write_options)
+        }
+        Some(FileType::Flac) => {
+            let native = FlacFile::read_from(file, options)
+                .map_err(tag_error)?
+                .vorbis_comments()
+                .cloned()
+                .unwrap_or_default();
+            rewind(file)?;
+            retag(native, metadata, cover).save_to(file, write_options)
+        }
+        Some(FileType::Opus) => {
+            let native = OpusFile::read_from(file, options).map_err(tag_error)?.vorbis_comments().clone();
+            rewind(file)?;
+            retag(native, metadata, cover).save_to(file, write_options)
+        }
+        _ => return Err(ExportError::UnsupportedFormat { format: display.to_string() }),
+    }
+    .map_err(tag_error)
+}
+
+/// Tag an encoded file held in memory, e.g. before it is written with `write_atomic`
+pub fn tag_data(data: Vec<u8>, display: &str, metadata: &AudioMetadata) -> Result<Vec<u8>, ExportError> {
+    let mut buffer = Cursor::new(data);
+    write_tags(&mut buffer, display, metadata)?;
+    Ok(buffer.into_inner())
+}
+
+/// Read back the metadata of the file at `path`
+pub fn read_tags(path: &Path) -> Result<TagReport, ExportError> {
+    let display = path.display().to_string();
+    let file = lofty::read_from_path(path).map_err(|e| ExportError::tag(&display, e))?;
+    let duration_secs = file.properties().duration().as_secs_f64();
+
+    let Some(tag) = file.primary_tag() else {
+        return Ok(TagReport {
+            path: display,
+            format: None,
+            metadata: AudioMetadata::default(),
+            cover: None,
+            duration_secs,
+        });
+    };
+
+    let text = |key: &ItemKey| tag.get_string(key).map(str::to_string);
+    let title = text(&ItemKey::AlbumTitle).or_else(|| text(&ItemKey::TrackTitle));
+    let metadata = AudioMetadata {
+        track_title: text(&ItemKey::TrackTitle).filter(|track_title| Some(track_title) != title.as_ref()),
+        track_number: tag.track(),
+        track_total: tag.track_total(),
+        title,
+        author: text(&ItemKey::TrackArtist).or_else(|| text(&ItemKey::AlbumArtist)),
+        narrator: text(&ItemKey::Composer),
+        series: text(&ItemKey::ContentGroup),
+        // Dates may be full timestamps ("2024-05-01T..."), only the year is kept
+        year: text(&ItemKey::RecordingDate).and_then(|date| date.get(..4)?.parse().ok()),
+        genre: text(&ItemKey::Genre),
+        description: text(&description_key(tag.tag_type())),
+        cover_path: None,
+    };
+    let cover = tag
+        .get_picture_type(PictureType::CoverFront)
+        .or_else(|| tag.pictures().first())
+        .map(|picture| CoverInfo {
+            mime_type: picture.mime_type().map(|mime| mime.to_string()),
+            size: picture.data().len(),
+        });
+
+    Ok(TagReport {
+        path: display,
+        format: tag_format(tag.tag_type()),
+        metadata,
+        cover,
+        duration_secs,
+    })
+}
@@ -0,0 +1,624 @@
+//! Self-contained project bundles (`.abmproj`)
+//!
+//! A bundle is a zip archive holding everything a project needs on another
+//! machine or backend: the typed project, every generated segment audio file,
+//! the samples of the speakers the segments use and the pronunciation rules.
+//! `manifest.json` lists the SHA-256 of each entry; import checks all of them
+//! before anything is created, then rebuilds the project through the REST API
+//! and maps the old IDs to the ones the backend hands out. If a step fails,
+//! the project and speakers created up to then are deleted again.
+//!
+//! A plain project file (`.abm`) is imported the same way, minus the audio
+//! and samples it does not carry.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::atomic::AtomicFile;
+use crate::client::{path_id, ApiClient, ApiError};
+use crate::error::write_coded;
+use crate::project::{ProjectData, ProjectError, SegmentType, Speaker, SCHEMA_VERSION};
+use crate::serialize_as_display;
+
+/// File extension of project bundles
+pub const BUNDLE_EXTENSION: &str = "abmproj";
+/// Version of the archive layout written by this build
+const BUNDLE_VERSION: u32 = 1;
+const BUNDLE_FORMAT: &str = "abmproj";
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const PROJECT_ENTRY: &str = "project.json";
+/// Rules in the format `/api/pronunciation/rules/import` accepts
+const RULES_ENTRY: &str = "pronunciation-rules.json";
+/// Most memory reserved up front for an entry; larger ones grow as they are read
+const PREALLOCATE_LIMIT: u64 = 16 << 20;
+
+/// Errors raised while writing or importing a bundle
+#[derive(Debug)]
+pub enum BundleError {
+    /// A backend call failed, passed through with its own code
+    Api(ApiError),
+    /// The file is not a readable zip archive or its manifest is malformed
+    InvalidArchive { message: String },
+    /// The bundle was written by a newer version of the app
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// An entry the manifest or project refers to is not in the archive
+    MissingEntry { entry: String },
+    /// The archive holds an entry the manifest does not list
+    UnlistedEntry { entry: String },
+    /// An entry does not hash to the SHA-256 in the manifest
+    ChecksumMismatch { entry: String, expected: String, actual: String },
+    /// The bundled project file is invalid, passed through with its own code
+    Project(ProjectError),
+    Io { path: String, message: String },
+}
+
+impl BundleError {
+    fn io(path: &Path, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.display().to_string(), message: error.to_string() }
+    }
+
+    fn archive(error: impl fmt::Display) -> Self {
+        Self::InvalidArchive { message: error.to_string() }
+    }
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Api(error) => fmt::Display::fmt(error, f),
+            Self::InvalidArchive { message } => write_coded(f, "BUNDLE_INVALID_ARCHIVE", &[("error", message)]),
+            Self::UnsupportedVersion { found, supported } => write_coded(
+                f,
+                "BUNDLE_UNSUPPORTED_VERSION",
+                &[("found", found), ("supported", supported)],
+            ),
+            Self::MissingEntry { entry } => write_coded(f, "BUNDLE_MISSING_ENTRY", &[("entry", entry)]),
+            Self::UnlistedEntry { entry } => write_coded(f, "BUNDLE_UNLISTED_ENTRY", &[("entry", entry)]),
+            Self::ChecksumMismatch { entry, expected, actual } => write_coded(
+                f,
+                "BUNDLE_CHECKSUM_MISMATCH",
+                &[("entry", entry), ("expected", expected), ("actual", actual)],
+            ),
+            Self::Project(error) => fmt::Display::fmt(error, f),
+            Self::Io { path, message } => {
+                write_coded(f, "BUNDLE_IO_FAILED", &[("path", path), ("error", message)])
+            }
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+serialize_as_display!(BundleError);
+
+impl From<ApiError> for BundleError {
+    fn from(error: ApiError) -> Self {
+        Self::Api(error)
+    }
+}
+
+impl From<ProjectError> for BundleError {
+    fn from(error: ProjectError) -> Self {
+        Self::Project(error)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    /// App version that wrote the bundle
+    pub app_version: String,
+    /// Project schema version of `project.json`
+    pub schema_version: u32,
+    /// RFC 3339
+    pub created_at: String,
+    /// Backend ID of the exported project
+    pub source_project_id: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSummary {
+    pub path: String,
+    pub project_name: String,
+    pub chapters: usize,
+    pub segments: usize,
+    pub audio_files: usize,
+    pub speakers: usize,
+    pub samples: usize,
+    pub rules: usize,
+    pub bytes_written: u64,
+}
+
+/// Old ID to new ID, per kind of object
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdMap {
+    pub projects: BTreeMap<String, String>,
+    pub chapters: BTreeMap<String, String>,
+    pub segments: BTreeMap<String, String>,
+    pub speakers: BTreeMap<String, String>,
+    pub samples: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub project_id: String,
+    /// The imported project with the IDs the backend assigned
+    pub project: ProjectData,
+    pub ids: IdMap,
+    pub audio_files: usize,
+    /// Speakers that already existed on the backend (matched by name) and were
+    /// used as they are instead of being created again
+    pub reused_speakers: Vec<String>,
+    pub rules_imported: u64,
+    /// Rules the backend already had, plus any it refused
+    pub rules_skipped: u64,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Extension of `name` including the dot, or `fallback`
+fn extension<'a>(name: &'a str, fallback: &'a str) -> &'a str {
+    match name.rfind('.') {
+        Some(dot) if !name[dot..].contains(['/', '\\']) => &name[dot..],
+        _ => fallback,
+    }
+}
+
+/// Response of any create call; only the new ID is needed
+#[derive(Deserialize)]
+struct Created {
+    id: String,
+}
+
+/// The fields of a segment the backend fills in when audio is uploaded
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SegmentState {
+    audio_path: Option<String>,
+    #[serde(default)]
+    start_time: f64,
+    #[serde(default)]
+    end_time: f64,
+    status: Option<String>,
+}
+
+/// The audio URL path of a segment, whether it stores a bare file name or a full URL
+fn audio_file_name(audio_path: &str) -> &str {
+    audio_path.rsplit('/').next().unwrap_or(audio_path)
+}
+
+struct BundleWriter<'a> {
+    zip: ZipWriter<&'a mut File>,
+    entries: Vec<ManifestEntry>,
+}
+
+impl BundleWriter<'_> {
+    fn add(&mut self, path: &str, data: &[u8], compress: bool) -> Result<(), BundleError> {
+        // Audio barely shrinks, so it is stored as-is
+        let method = if compress { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+        let options = SimpleFileOptions::default().compression_method(method).large_file(data.len() as u64 >= u32::MAX as u64);
+        self.zip.start_file(path, options).map_err(BundleError::archive)?;
+        self.zip.write_all(data).map_err(BundleError::archive)?;
+        self.entries.push(ManifestEntry { path: path.to_string(), sha256: sha256_hex(data), size: data.len() as u64 });
+        Ok(())
+    }
+
+    /// Write the central directory
+    fn finish(self) -> zip::result::ZipResult<()> {
+        self.zip.finish().map(|_| ())
+    }
+}
+
+/// Write the project `project_id` on the backend `client` talks to to a bundle at `path`
+pub async fn export_bundle(client: &ApiClient, project_id: &str, path: &Path) -> Result<BundleSummary, BundleError> {
+    let remote: Map<String, Value> = client.get(&format!("/api/projects/{}", path_id(project_id)), &[]).await?;
+    let mut project_json = Map::new();
+    project_json.insert("schemaVersion".to_string(), SCHEMA_VERSION.into());
+    project_json.insert("name".to_string(), remote.get("title").cloned().unwrap_or_default());
+    project_json.insert("description".to_string(), remote.get("description").cloned().unwrap_or_default());
+    project_json.insert("chapters".to_string(), remote.get("chapters").cloned().unwrap_or_else(|| json!([])));
+    let (mut project, _) = ProjectData::from_value(Value::Object(project_json))?;
+    // Parent IDs are implied by the nesting and would go stale on import
+    for chapter in &mut project.chapters {
+        chapter.extra.remove("projectId");
+        for segment in &mut chapter.segments {
+            segment.extra.remove("chapterId");
+        }
+    }
+
+    // Segments refer to speakers by name; only the ones in use travel with the project
+    let used: BTreeSet<&str> = project
+        .chapters
+        .iter()
+        .flat_map(|chapter| &chapter.segments)
+        .filter_map(|segment| segment.tts_speaker_name.as_deref())
+        .collect();
+    let speakers: Vec<Speaker> = client.get("/api/speakers/", &[]).await?;
+    project.speakers = speakers.into_iter().filter(|speaker| used.contains(speaker.name.as_str())).collect();
+
+    let rules: Vec<Value> = client.get("/api/pronunciation/rules/export", &[]).await?;
+    let rules: Vec<Value> = rules
+        .into_iter()
+        .filter(|rule| match rule.get("projectId").and_then(Value::as_str) {
+            Some(id) => id == project_id,
+            None => true,
+        })
+        .collect();
+
+    let mut file = AtomicFile::create(path).map_err(|e| BundleError::io(path, e))?;
+    let mut writer = BundleWriter { zip: ZipWriter::new(file.file()), entries: Vec::new() };
+
+    // Entries are fetched one at a time, so memory use stays at a single file
+    let mut audio_files = 0;
+    for chapter in &mut project.chapters {
+        for segment in &mut chapter.segments {
+            let Some(audio_path) = segment.audio_path.take() else {
+                continue;
+            };
+            let name = audio_file_name(&audio_path);
+            let data = client.get_bytes(&format!("/api/audio/{}", path_id(name))).await?;
+            let entry = format!("audio/{}{}", segment.id, extension(name, ".wav"));
+            writer.add(&entry, &data, false)?;
+            // Inside the bundle, paths point at archive entries
+            segment.audio_path = Some(entry);
+            audio_files += 1;
+        }
+    }
+
+    let mut samples = 0;
+    for speaker in &mut project.speakers {
+        for sample in &mut speaker.samples {
+            let data = client
+                .get_bytes(&format!("/api/speakers/{}/samples/{}/audio", path_id(&speaker.id), path_id(&sample.id)))
+                .await?;
+            let entry = format!("speakers/{}/{}{}", speaker.id, sample.id, extension(&sample.file_name, ".wav"));
+            writer.add(&entry, &data, false)?;
+            sample.file_path = entry;
+            samples += 1;
+        }
+    }
+
+    let rules_json = serde_json::to_vec_pretty(&rules).map_err(BundleError::archive)?;
+    writer.add(RULES_ENTRY, &rules_json, true)?;
+    writer.add(PROJECT_ENTRY, project.to_json()?.as_bytes(), true)?;
+
+    let manifest = Manifest {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: SCHEMA_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        source_project_id: project_id.to_string(),
+        entries: std::mem::take(&mut writer.entries),
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(BundleError::archive)?;
+    writer.add(MANIFEST_ENTRY, &manifest_json, true)?;
+    writer.finish().map_err(|e| BundleError::io(path, e))?;
+    file.commit().map_err(|e| BundleError::io(path, e))?;
+
+    Ok(BundleSummary {
+        path: path.display().to_string(),
+        project_name: project.name.clone(),
+        chapters: project.chapters.len(),
+        segments: project.chapters.iter().map(|chapter| chapter.segments.len()).sum(),
+        audio_files,
+        speakers: project.speakers.len(),
+        samples,
+        rules: rules.len(),
+        bytes_written: std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0),
+    })
+}
+
+/// An opened bundle whose entries all match the manifest
+struct BundleReader {
+    zip: ZipArchive<File>,
+    manifest: Manifest,
+}
+
+impl BundleReader {
+    fn open(path: &Path) -> Result<Self, BundleError> {
+        let file = File::open(path).map_err(|e| BundleError::io(path, e))?;
+        let mut zip = ZipArchive::new(file).map_err(BundleError::archive)?;
+
+        let manifest = Self::read_entry(&mut zip, MANIFEST_ENTRY)?;
+        let manifest: Manifest = serde_json::from_slice(&manifest).map_err(BundleError::archive)?;
+        if manifest.format != BUNDLE_FORMAT {
+            return Err(BundleError::archive(format!("unknown format {}", manifest.format)));
+        }
+        if manifest.version > BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion { found: manifest.version, supported: BUNDLE_VERSION });
+        }
+
+        let listed: BTreeSet<&str> = manifest.entries.iter().map(|entry| entry.path.as_str()).collect();
+        for name in zip.file_names() {
+            if name != MANIFEST_ENTRY && !name.ends_with('/') && !listed.contains(name) {
+                return Err(BundleError::UnlistedEntry { entry: name.to_string() });
+            }
+        }
+        for entry in &manifest.entries {
+            let data = Self::read_entry(&mut zip, &entry.path)?;
+            let actual = sha256_hex(&data);
+            if actual != entry.sha256 {
+                return Err(BundleError::ChecksumMismatch {
+                    entry: entry.path.clone(),
+                    expected: entry.sha256.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(Self { zip, manifest })
+    }
+
+    fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, BundleError> {
+        let mut file = zip.by_name(name).map_err(|_| BundleError::MissingEntry { entry: name.to_string() })?;
+        // The size comes from the archive, so it only sizes the buffer up to a limit
+        let mut data = Vec::with_capacity(file.size().min(PREALLOCATE_LIMIT) as usize);
+        file.read_to_end(&mut data).map_err(|e| BundleError::archive(format!("{}: {}", name, e)))?;
+        Ok(data)
+    }
+
+    fn read(&mut self, name: &str) -> Result<Vec<u8>, BundleError> {
+        // Only entries the manifest vouches for are ever read
+        if !self.manifest.entries.iter().any(|entry| entry.path == name) {
+            return Err(BundleError::MissingEntry { entry: name.to_string() });
+        }
+        Self::read_entry(&mut self.zip, name)
+    }
+}
+
+/// Status a segment is created with; finished audio is uploaded separately
+fn imported_status(segment_type: SegmentType, status: Option<&str>) -> String {
+    match (segment_type, status) {
+        (SegmentType::Divider, Some(status)) => status.to_string(),
+        (_, Some("failed")) => "failed".to_string(),
+        // Jobs do not move with the project, and completed without audio is not a thing
+        _ => "pending".to_string(),
+    }
+}
+
+/// Key two rules are the same by, with `projectId` as it is on the target backend
+fn rule_key(rule: &Value) -> String {
+    let field = |name: &str| rule.get(name).cloned().unwrap_or(Value::Null);
+    json!([field("pattern"), field("scope"), field("projectId"), field("engineName"), field("language")]).to_string()
+}
+
+/// Recreate the bundle at `path` as a new project on the backend `client` talks to
+pub async fn import_bundle(client: &ApiClient, path: &Path) -> Result<ImportSummary, BundleError> {
+    let mut bundle = BundleReader::open(path)?;
+    let project_text = bundle.read(PROJECT_ENTRY)?;
+    let project = ProjectData::from_json(&String::from_utf8_lossy(&project_text))?;
+    let rules: Vec<Value> = serde_json::from_slice(&bundle.read(RULES_ENTRY)?).map_err(BundleError::archive)?;
+
+    let source_project_id = bundle.manifest.source_project_id.clone();
+    rebuild_or_undo(client, Some(&mut bundle), Some(source_project_id), project, rules).await
+}
+
+/// Recreate a project read from a project file as a new project on the backend
+/// `client` talks to; segments come without audio and speakers without samples
+pub async fn import_project(client: &ApiClient, project: ProjectData) -> Result<ImportSummary, BundleError> {
+    let rules = project
+        .pronunciation_rules
+        .iter()
+        .map(|rule| {
+            let mut rule = serde_json::to_value(rule).map_err(BundleError::archive)?;
+            if let Some(rule) = rule.as_object_mut() {
+                rule.remove("id");
+            }
+            Ok(rule)
+        })
+        .collect::<Result<Vec<Value>, BundleError>>()?;
+    rebuild_or_undo(client, None, None, project, rules).await
+}
+
+async fn rebuild_or_undo(
+    client: &ApiClient,
+    bundle: Option<&mut BundleReader>,
+    source_project_id: Option<String>,
+    project: ProjectData,
+    rules: Vec<Value>,
+) -> Result<ImportSummary, BundleError> {
+    let mut rollback = Rollback::default();
+    let result = rebuild(client, bundle, source_project_id, project, rules, &mut rollback).await;
+    if result.is_err() {
+        // Leave the backend as it was rather than with a half-imported project
+        rollback.undo(client).await;
+    }
+    result
+}
+
+/// What an import created so far, removed again if a later step fails
+#[derive(Default)]
+struct Rollback {
+    project: Option<String>,
+    speakers: Vec<String>,
+}
+
+impl Rollback {
+    async fn undo(self, client: &ApiClient) {
+        // Chapters, segments and their audio go with the project, samples with their speaker
+        if let Some(id) = self.project {
+            if let Err(e) = client.delete_project(&id).await {
+                eprintln!("Failed to remove partly imported project {}: {}", id, e);
+            }
+        }
+        for id in self.speakers {
+            if let Err(e) = client.delete_speaker(&id).await {
+                eprintln!("Failed to remove imported speaker {}: {}", id, e);
+            }
+        }
+    }
+}
+
+/// Create the project, its speakers and rules on the backend, with the audio
+/// and samples from `bundle` if there is one
+async fn rebuild(
+    client: &ApiClient,
+    mut bundle: Option<&mut BundleReader>,
+    source_project_id: Option<String>,
+    mut project: ProjectData,
+    rules: Vec<Value>,
+    rollback: &mut Rollback,
+) -> Result<ImportSummary, BundleError> {
+    let mut ids = IdMap::default();
+
+    let existing: Vec<Speaker> = client.get("/api/speakers/", &[]).await?;
+    let mut reused_speakers = Vec::new();
+    for speaker in &mut project.speakers {
+        if let Some(found) = existing.iter().find(|found| found.name == speaker.name) {
+            ids.speakers.insert(speaker.id.clone(), found.id.clone());
+            reused_speakers.push(speaker.name.clone());
+            speaker.id = found.id.clone();
+            speaker.samples = found.samples.clone();
+            continue;
+        }
+
+        let body = json!({
+            "name": speaker.name,
+            "description": speaker.description,
+            "gender": speaker.gender,
+            "languages": speaker.languages,
+            "tags": speaker.tags,
+        });
+        let created: Created = client.post("/api/speakers/", &body).await?;
+        rollback.speakers.push(created.id.clone());
+        match bundle.as_deref_mut() {
+            Some(bundle) => {
+                for sample in &mut speaker.samples {
+                    let data = bundle.read(&sample.file_path)?;
+                    let fields = sample.transcript.iter().map(|transcript| ("transcript", transcript.clone())).collect();
+                    let uploaded: Created = client
+                        .upload(&format!("/api/speakers/{}/samples", path_id(&created.id)), &sample.file_name, data, fields)
+                        .await?;
+                    let old_id = std::mem::replace(&mut sample.id, uploaded.id);
+                    ids.samples.insert(old_id, sample.id.clone());
+                    sample.file_path.clear();
+                }
+            }
+            // The sample paths point into the other backend's storage
+            None => speaker.samples.clear(),
+        }
+        let old_id = std::mem::replace(&mut speaker.id, created.id);
+        ids.speakers.insert(old_id, speaker.id.clone());
+    }
+
+    let body = json!({ "title": project.name, "description": project.description.clone().unwrap_or_default() });
+    let created: Created = client.post("/api/projects", &body).await?;
+    let project_id = created.id;
+    rollback.project = Some(project_id.clone());
+    if let Some(source_project_id) = source_project_id {
+        ids.projects.insert(source_project_id, project_id.clone());
+    }
+
+    let mut audio_files = 0;
+    for chapter in &mut project.chapters {
+        let body = json!({ "projectId": project_id, "title": chapter.title, "orderIndex": chapter.order_index });
+        let created: Created = client.post("/api/chapters", &body).await?;
+        let old_id = std::mem::replace(&mut chapter.id, created.id);
+        ids.chapters.insert(old_id, chapter.id.clone());
+
+        for segment in &mut chapter.segments {
+            let body = json!({
+                "chapterId": chapter.id,
+                "text": segment.text,
+                "orderIndex": segment.order_index,
+                "segmentType": segment.segment_type,
+                "pauseDuration": segment.pause_duration,
+                "ttsEngine": segment.tts_engine,
+                "ttsModelName": segment.tts_model_name,
+                "ttsSpeakerName": segment.tts_speaker_name,
+                "language": segment.language,
+                "status": imported_status(segment.segment_type, segment.status.as_deref()),
+            });
+            let created: Created = client.post("/api/segments", &body).await?;
+            let old_id = std::mem::replace(&mut segment.id, created.id);
+
+            let mut updated: Option<SegmentState> = None;
+            if let (Some(entry), Some(bundle)) = (segment.audio_path.take(), bundle.as_deref_mut()) {
+                let data = bundle.read(&entry)?;
+                let name = format!("{}{}", segment.id, extension(&entry, ".wav"));
+                updated = Some(client.upload(&format!("/api/segments/{}/audio", path_id(&segment.id)), &name, data, Vec::new()).await?);
+                audio_files += 1;
+            }
+            if segment.is_frozen {
+                let path = format!("/api/segments/{}/freeze", path_id(&segment.id));
+                updated = Some(client.patch(&path, &json!({ "freeze": true })).await?);
+            }
+            match updated {
+                Some(state) => {
+                    segment.audio_path = state.audio_path;
+                    segment.start_time = state.start_time;
+                    segment.end_time = state.end_time;
+                    segment.status = state.status;
+                }
+                None => segment.status = Some(imported_status(segment.segment_type, segment.status.as_deref())),
+            }
+            ids.segments.insert(old_id, segment.id.clone());
+        }
+    }
+
+    // Project-scoped rules move to the new project; ones the backend already has are left out
+    let existing: Vec<Value> = client.get("/api/pronunciation/rules/export", &[]).await?;
+    let known: BTreeSet<String> = existing.iter().map(rule_key).collect();
+    let mut skipped = 0;
+    let rules: Vec<Value> = rules
+        .into_iter()
+        .map(|mut rule| {
+            // Only rules of the imported project come along, so project-scoped ones move to the new one
+            if rule.get("scope").and_then(Value::as_str) == Some("project_engine") {
+                if let Some(rule) = rule.as_object_mut() {
+                    rule.insert("projectId".to_string(), Value::String(project_id.clone()));
+                }
+            }
+            rule
+        })
+        .filter(|rule| {
+            let new = !known.contains(&rule_key(rule));
+            skipped += u64::from(!new);
+            new
+        })
+        .collect();
+    let mut rules_imported = 0;
+    if !rules.is_empty() {
+        let result: Value = client
+            .post("/api/pronunciation/rules/import", &json!({ "rules": rules, "mode": "merge" }))
+            .await?;
+        rules_imported = result.get("imported").and_then(Value::as_u64).unwrap_or(0);
+        skipped += result.get("skipped").and_then(Value::as_u64).unwrap_or(0);
+    }
+
+    Ok(ImportSummary {
+        project_id,
+        project,
+        ids,
+        audio_files,
+        reused_speakers,
+        rules_imported,
+        rules_skipped: skipped,
+    })
+}
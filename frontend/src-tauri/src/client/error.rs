@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::compat::Incompatible;
+use crate::error::write_coded;
+use crate::serialize_as_display;
+
+/// Errors raised while calling the backend API
+#[derive(Debug)]
+pub enum ApiError {
+    /// The backend rejected the request with an ApplicationError (or another `detail`), passed through as-is
+    Backend { status: u16, detail: String },
+    /// FastAPI rejected the request parameters or body (HTTP 422)
+    Validation { url: String, message: String },
+    /// The backend answered with an unexpected status and no error detail
+    HttpStatus { url: String, status: u16 },
+    Timeout { url: String },
+    /// The backend could not be reached
+    Request { url: String, message: String },
+    /// A successful answer that does not have the expected shape
+    InvalidResponse { url: String, message: String },
+    /// The version handshake refused the backend, so no request was sent
+    Incompatible(Incompatible),
+}
+
+impl ApiError {
+    pub(super) fn request(url: &str, error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Self::Timeout { url: url.to_string() }
+        } else {
+            Self::Request { url: url.to_string(), message: error.to_string() }
+        }
+    }
+
+    /// HTTP status of an error response, if there was one
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Backend { status, .. } | Self::HttpStatus { status, .. } => Some(*status),
+            Self::Validation { .. } => Some(422),
+            _ => None,
+        }
+    }
+
+    /// The backend's structured error, for code that reacts to particular codes
+    pub fn application_error(&self) -> Option<ApplicationError> {
+        match self {
+            Self::Backend { detail, .. } => ApplicationError::parse(detail),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Backend { detail, .. } => f.write_str(detail),
+            Self::Validation { url, message } => {
+                write_coded(f, "API_VALIDATION_FAILED", &[("url", url), ("error", message)])
+            }
+            Self::HttpStatus { url, status } => write_coded(f, "API_HTTP_STATUS", &[("url", url), ("status", status)]),
+            Self::Timeout { url } => write_coded(f, "API_TIMEOUT", &[("url", url)]),
+            Self::Request { url, message } => {
+                write_coded(f, "API_REQUEST_FAILED", &[("url", url), ("error", message)])
+            }
+            Self::InvalidResponse { url, message } => {
+                write_coded(f, "API_INVALID_RESPONSE", &[("url", url), ("error", message)])
+            }
+            Self::Incompatible(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+serialize_as_display!(ApiError);
+
+/// A backend `ApplicationError`: `[CODE]key:value;key:value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationError {
+    pub code: String,
+    pub params: BTreeMap<String, String>,
+}
+
+impl ApplicationError {
+    pub fn parse(detail: &str) -> Option<Self> {
+        let (code, params) = detail.strip_prefix('[')?.split_once(']')?;
+        if code.is_empty() {
+            return None;
+        }
+        let params = params
+            .split(';')
+            .filter_map(|param| param.split_once(':'))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Some(Self { code: code.to_string(), params })
+    }
+}
@@ -0,0 +1,74 @@
+//! Chapter audio export (`/api/audio/export`)
+
+use serde::{Deserialize, Serialize};
+
+use super::{path_id, ApiClient, ApiError, Message};
+
+/// Fields left `None` get the backend's defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    pub chapter_id: String,
+    /// mp3, m4a or wav
+    pub output_format: String,
+    /// low, medium or high
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    /// e.g. 192k
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    /// Milliseconds of silence between segments
+    #[serde(default)]
+    pub pause_between_segments: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_filename: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStarted {
+    pub job_id: String,
+    pub status: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub job_id: String,
+    /// pending, running, completed, failed or cancelled
+    pub status: String,
+    /// 0.0 to 1.0
+    pub progress: f64,
+    pub current_segment: u32,
+    pub total_segments: u32,
+    pub message: String,
+    /// Where the backend wrote the file, once completed
+    pub output_path: Option<String>,
+    /// Bytes
+    pub file_size: Option<u64>,
+    /// Seconds
+    pub duration: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl ApiClient {
+    pub async fn start_export(&self, request: &ExportRequest) -> Result<ExportStarted, ApiError> {
+        self.post("/api/audio/export", request).await
+    }
+
+    pub async fn export_progress(&self, job_id: &str) -> Result<ExportProgress, ApiError> {
+        self.get(&format!("/api/audio/export/{}/progress", path_id(job_id)), &[]).await
+    }
+
+    pub async fn cancel_export(&self, job_id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/audio/export/{}/cancel", path_id(job_id))).await
+    }
+
+    /// Delete an export job and its output file on the backend
+    pub async fn delete_export(&self, job_id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/audio/export/{}", path_id(job_id))).await
+    }
+}
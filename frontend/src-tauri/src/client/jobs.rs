@@ -0,0 +1,286 @@
+//! TTS generation and quality analysis, and the jobs they queue (`/api/tts`, `/api/quality`, `/api/jobs`)
+
+use serde::{Deserialize, Serialize};
+
+use super::{path_id, query, ApiClient, ApiError, Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentQueued {
+    pub success: bool,
+    pub job_id: String,
+    pub segment_id: String,
+    pub message: String,
+}
+
+/// Engine options that override the engine's defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateChapter {
+    pub chapter_id: String,
+    /// Also regenerate segments that already have audio
+    #[serde(default)]
+    pub force_regenerate: bool,
+    /// Use the engine, model, speaker and language below instead of each segment's own
+    #[serde(default)]
+    pub override_segment_settings: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_speaker_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_engine: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_model_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<TtsOptions>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterGenerationStarted {
+    /// started or already_running
+    pub status: String,
+    pub chapter_id: String,
+    pub engine: Option<String>,
+    pub message: String,
+    pub progress: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsJob {
+    pub id: String,
+    pub chapter_id: Option<String>,
+    /// `{id, jobStatus}` per segment
+    pub segment_ids: Option<Vec<serde_json::Value>>,
+    pub chapter_title: Option<String>,
+    pub project_title: Option<String>,
+    pub tts_engine: String,
+    pub tts_model_name: String,
+    pub tts_speaker_name: String,
+    pub language: String,
+    pub force_regenerate: bool,
+    /// pending, running, cancelling, cancelled, completed or failed
+    pub status: String,
+    pub total_segments: u32,
+    #[serde(default)]
+    pub processed_segments: u32,
+    #[serde(default)]
+    pub failed_segments: u32,
+    pub current_segment_id: Option<String>,
+    pub error_message: Option<String>,
+    #[serde(default)]
+    pub retry_count: u32,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityJob {
+    pub id: String,
+    /// segment or chapter
+    pub job_type: String,
+    pub status: String,
+    pub stt_engine: Option<String>,
+    pub stt_model_name: Option<String>,
+    pub audio_engine: Option<String>,
+    pub language: String,
+    pub total_segments: u32,
+    pub processed_segments: u32,
+    #[serde(default)]
+    pub failed_segments: u32,
+    pub current_segment_id: Option<String>,
+    pub chapter_id: Option<String>,
+    pub segment_id: Option<String>,
+    /// `{id, jobStatus}` per segment
+    pub segment_ids: Option<Vec<serde_json::Value>>,
+    pub trigger_source: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub chapter_title: Option<String>,
+    pub project_title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobList<J> {
+    pub jobs: Vec<J>,
+    pub count: u32,
+}
+
+/// Filters for listing jobs; the backend caps `limit` at 100
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobFilter {
+    pub status: Option<String>,
+    pub chapter_id: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+impl JobFilter {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        query([
+            ("status", self.status.clone()),
+            ("chapter_id", self.chapter_id.clone()),
+            ("limit", self.limit.map(|limit| limit.to_string())),
+            ("offset", self.offset.map(|offset| offset.to_string())),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCancelled {
+    /// cancelled, cancelling, cannot_cancel or not_found
+    pub status: String,
+    pub job_id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobDeleted {
+    pub success: bool,
+    pub deleted: bool,
+    pub job_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobsCleanedUp {
+    pub success: bool,
+    pub deleted: u32,
+}
+
+/// Engines for a quality analysis; the backend's defaults where `None`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityEngines {
+    pub stt_engine: Option<String>,
+    pub stt_model_name: Option<String>,
+    pub audio_engine: Option<String>,
+}
+
+impl QualityEngines {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        query([
+            ("stt_engine", self.stt_engine.clone()),
+            ("stt_model_name", self.stt_model_name.clone()),
+            ("audio_engine", self.audio_engine.clone()),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityJobCreated {
+    pub job_id: String,
+    pub message: String,
+    pub status: String,
+}
+
+impl ApiClient {
+    /// Queue one segment for (re)generation with its own engine settings
+    pub async fn generate_segment(&self, segment_id: &str) -> Result<SegmentQueued, ApiError> {
+        self.post(&format!("/api/tts/generate-segment/{}", path_id(segment_id)), &serde_json::json!({})).await
+    }
+
+    pub async fn generate_chapter(&self, request: &GenerateChapter) -> Result<ChapterGenerationStarted, ApiError> {
+        self.post("/api/tts/generate-chapter", request).await
+    }
+
+    pub async fn list_tts_jobs(&self, filter: &JobFilter) -> Result<JobList<TtsJob>, ApiError> {
+        self.get("/api/jobs/tts/", &filter.query()).await
+    }
+
+    /// Pending and running TTS jobs
+    pub async fn active_tts_jobs(&self) -> Result<JobList<TtsJob>, ApiError> {
+        self.get("/api/jobs/tts/active", &[]).await
+    }
+
+    pub async fn get_tts_job(&self, id: &str) -> Result<TtsJob, ApiError> {
+        self.get(&format!("/api/jobs/tts/{}", path_id(id)), &[]).await
+    }
+
+    pub async fn cancel_tts_job(&self, id: &str) -> Result<JobCancelled, ApiError> {
+        self.post(&format!("/api/jobs/tts/{}/cancel", path_id(id)), &serde_json::json!({})).await
+    }
+
+    /// Continue a cancelled job with the segments it has not done yet
+    pub async fn resume_tts_job(&self, id: &str) -> Result<TtsJob, ApiError> {
+        self.post(&format!("/api/jobs/tts/{}/resume", path_id(id)), &serde_json::json!({})).await
+    }
+
+    pub async fn delete_tts_job(&self, id: &str) -> Result<JobDeleted, ApiError> {
+        self.delete(&format!("/api/jobs/tts/{}", path_id(id))).await
+    }
+
+    /// Delete completed and failed TTS jobs
+    pub async fn cleanup_tts_jobs(&self) -> Result<JobsCleanedUp, ApiError> {
+        self.delete("/api/jobs/tts/cleanup").await
+    }
+
+    pub async fn analyze_segment(&self, segment_id: &str, engines: &QualityEngines) -> Result<QualityJobCreated, ApiError> {
+        let path = format!("/api/quality/analyze/segment/{}", path_id(segment_id));
+        self.send(reqwest::Method::POST, &path, &engines.query(), None).await
+    }
+
+    pub async fn analyze_chapter(&self, chapter_id: &str, engines: &QualityEngines) -> Result<QualityJobCreated, ApiError> {
+        let path = format!("/api/quality/analyze/chapter/{}", path_id(chapter_id));
+        self.send(reqwest::Method::POST, &path, &engines.query(), None).await
+    }
+
+    pub async fn list_quality_jobs(&self, filter: &JobFilter) -> Result<JobList<QualityJob>, ApiError> {
+        self.get("/api/jobs/quality/", &filter.query()).await
+    }
+
+    /// Pending and running quality jobs
+    pub async fn active_quality_jobs(&self) -> Result<JobList<QualityJob>, ApiError> {
+        self.get("/api/jobs/quality/active", &[]).await
+    }
+
+    pub async fn get_quality_job(&self, id: &str) -> Result<QualityJob, ApiError> {
+        self.get(&format!("/api/jobs/quality/{}", path_id(id)), &[]).await
+    }
+
+    pub async fn cancel_quality_job(&self, id: &str) -> Result<Message, ApiError> {
+        self.post(&format!("/api/jobs/quality/{}/cancel", path_id(id)), &serde_json::json!({})).await
+    }
+
+    pub async fn resume_quality_job(&self, id: &str) -> Result<QualityJob, ApiError> {
+        self.post(&format!("/api/jobs/quality/{}/resume", path_id(id)), &serde_json::json!({})).await
+    }
+
+    pub async fn delete_quality_job(&self, id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/jobs/quality/{}", path_id(id))).await
+    }
+
+    /// Delete completed and failed quality jobs
+    pub async fn cleanup_quality_jobs(&self) -> Result<JobsCleanedUp, ApiError> {
+        self.delete("/api/jobs/quality/cleanup").await
+    }
+}
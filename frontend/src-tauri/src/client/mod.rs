@@ -0,0 +1,496 @@
+//! Typed client for the backend's REST API
+//!
+//! Every call goes through `ApiClient::send`, which applies the profile's
+//! timeout and headers, retries what is safe to retry and maps error
+//! responses onto the backend's `ApplicationError` shape
+//! (`{"detail": "[CODE]key:value;..."}`). Rust code can act on the code, and
+//! commands hand the frontend the same string `fetch` would have seen.
+//!
+//! The client only needs a base URL, so it works against any server that
+//! speaks the API, a local mock included.
+
+pub mod error;
+pub mod export;
+pub mod jobs;
+pub mod projects;
+pub mod pronunciation;
+pub mod speakers;
+
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::profiles::BackendProfile;
+use crate::state::AppState;
+
+pub use error::{ApiError, ApplicationError};
+
+/// Timeout for a whole request when the profile does not set one
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts after the first one
+const MAX_RETRIES: u32 = 2;
+/// Wait before the first retry; doubles with each further one
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+/// Timeout for uploading or downloading a whole audio file, which can take
+/// longer than the profile's timeout for ordinary calls
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// `success` and `message`, as most write routes answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    #[serde(default = "default_true")]
+    pub success: bool,
+    pub message: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Answer of the reorder routes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reordered {
+    #[serde(default = "default_true")]
+    pub success: bool,
+    pub message: String,
+    pub count: Option<u32>,
+}
+
+/// Request body, kept as data so a retry can send it again
+enum Body {
+    Json(serde_json::Value),
+    /// A multipart form with the file as its `file` part
+    File { file_name: String, data: Vec<u8>, fields: Vec<(&'static str, String)> },
+}
+
+impl Body {
+    fn attach(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Self::Json(body) => request.json(body),
+            Self::File { file_name, data, fields } => {
+                let form = fields
+                    .iter()
+                    .fold(Form::new(), |form, (name, value)| form.text(*name, value.clone()))
+                    .part("file", Part::bytes(data.clone()).file_name(file_name.clone()));
+                request.multipart(form).timeout(TRANSFER_TIMEOUT)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    base_url: String,
+    http: reqwest::Client,
+    max_retries: u32,
+}
+
+impl ApiClient {
+    /// A client for the backend at `base_url`; `headers` go with every request
+    pub fn new(base_url: &str, timeout: Duration, headers: HeaderMap) -> Result<Self, ApiError> {
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(timeout)
+            .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+            .build()
+            .map_err(|e| ApiError::request(base_url, e))?;
+        Ok(Self { base_url: base_url.trim_end_matches('/').to_string(), http, max_retries: MAX_RETRIES })
+    }
+
+    /// A client for the active backend, with its profile's request timeout and headers
+    ///
+    /// Fails if the health monitor last found the backend incompatible.
+    pub fn for_active(state: &AppState) -> Result<Self, ApiError> {
+        let url = state.get_backend_url();
+        state.backend_monitor().ensure_compatible(&url).map_err(ApiError::Incompatible)?;
+        let profile = state.backend_profiles().active().cloned();
+        let timeout = profile.as_ref().and_then(BackendProfile::request_timeout).unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+        let headers = profile.map(|profile| profile.header_map(&state.vault())).unwrap_or_default();
+        Self::new(&url, timeout, headers)
+    }
+
+    /// A client for `url`, with the request timeout and headers of the profile it belongs to
+    pub fn for_url(state: &AppState, url: &str) -> Result<Self, ApiError> {
+        let profile = state.backend_profile_for(url);
+        let timeout = profile.as_ref().and_then(BackendProfile::request_timeout).unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+        let headers = profile.map(|profile| profile.header_map(&state.vault())).unwrap_or_default();
+        Self::new(url, timeout, headers)
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Send a request and decode the JSON answer
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<Body>,
+    ) -> Result<T, ApiError> {
+        let (url, response) = self.respond(method, path, query, body.as_ref(), None).await?;
+        read(&url, response).await
+    }
+
+    /// Send a request, retrying it where that is safe, and return the final response
+    ///
+    /// A request that never reached the backend (connection refused) is
+    /// retried whatever its method. Timeouts and 502/503/504 answers are only
+    /// retried for GET, PUT and DELETE, which the backend treats as idempotent;
+    /// a POST that timed out may well have created its job.
+    async fn respond(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<&Body>,
+        timeout: Option<Duration>,
+    ) -> Result<(String, reqwest::Response), ApiError> {
+        let url = format!("{}{}", self.base_url, path);
+        let idempotent = matches!(method, Method::GET | Method::PUT | Method::DELETE);
+        let mut attempt = 0;
+        loop {
+            let mut request = self.http.request(method.clone(), &url).query(query);
+            if let Some(body) = body {
+                request = body.attach(request);
+            }
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            let result = request.send().await;
+            let retry = match &result {
+                Err(e) => e.is_connect() || (idempotent && e.is_timeout()),
+                Ok(response) => {
+                    idempotent
+                        && matches!(
+                            response.status(),
+                            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+                        )
+                }
+            };
+            if retry && attempt < self.max_retries {
+                tokio::time::sleep(RETRY_DELAY * 2u32.pow(attempt)).await;
+                attempt += 1;
+                continue;
+            }
+            let response = result.map_err(|e| ApiError::request(&url, e))?;
+            return Ok((url, response));
+        }
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, ApiError> {
+        self.send(Method::GET, path, query, None).await
+    }
+
+    pub(crate) async fn post<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T, ApiError> {
+        self.send(Method::POST, path, &[], Some(Body::Json(to_body(path, body)?))).await
+    }
+
+    pub(crate) async fn put<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T, ApiError> {
+        self.send(Method::PUT, path, &[], Some(Body::Json(to_body(path, body)?))).await
+    }
+
+    pub(crate) async fn patch<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T, ApiError> {
+        self.send(Method::PATCH, path, &[], Some(Body::Json(to_body(path, body)?))).await
+    }
+
+    pub(crate) async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        self.send(Method::DELETE, path, &[], None).await
+    }
+
+    /// The raw body of a GET, e.g. an audio file
+    pub(crate) async fn get_bytes(&self, path: &str) -> Result<Vec<u8>, ApiError> {
+        let (url, response) = self.respond(Method::GET, path, &[], None, Some(TRANSFER_TIMEOUT)).await?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| ApiError::request(&url, e))?;
+        if !status.is_success() {
+            return Err(error_response(&url, status, &body));
+        }
+        Ok(body.to_vec())
+    }
+
+    /// POST `data` as the `file` part of a multipart form, with `fields` as text parts
+    pub(crate) async fn upload<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        file_name: &str,
+        data: Vec<u8>,
+        fields: Vec<(&'static str, String)>,
+    ) -> Result<T, ApiError> {
+        let body = Body::File { file_name: file_name.to_string(), data, fields };
+        self.send(Method::POST, path, &[], Some(body)).await
+    }
+}
+
+fn to_body(path: &str, body: &impl Serialize) -> Result<serde_json::Value, ApiError> {
+    serde_json::to_value(body).map_err(|e| ApiError::Request { url: path.to_string(), message: e.to_string() })
+}
+
+/// Query parameters for the optional filters that are set
+fn query<const N: usize>(params: [(&'static str, Option<String>); N]) -> Vec<(&'static str, String)> {
+    params.into_iter().filter_map(|(key, value)| Some((key, value?))).collect()
+}
+
+/// Percent-encode an ID for use as a path segment
+pub(crate) fn path_id(id: &str) -> String {
+    id.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+async fn read<T: DeserializeOwned>(url: &str, response: reqwest::Response) -> Result<T, ApiError> {
+    let status = response.status();
+    let body = response.bytes().await.map_err(|e| ApiError::request(url, e))?;
+    if status.is_success() {
+        return serde_json::from_slice(&body)
+            .map_err(|e| ApiError::InvalidResponse { url: url.to_string(), message: e.to_string() });
+    }
+    Err(error_response(url, status, &body))
+}
+
+/// Map an error response onto the `detail` the backend sent with it
+fn error_response(url: &str, status: StatusCode, body: &[u8]) -> ApiError {
+    let detail = serde_json::from_slice::<serde_json::Value>(body).ok().and_then(|mut body| body.get_mut("detail").map(serde_json::Value::take));
+    match detail {
+        Some(serde_json::Value::String(detail)) => ApiError::Backend { status: status.as_u16(), detail },
+        // FastAPI's request validation: a list of {loc, msg, type}
+        Some(serde_json::Value::Array(errors)) if status == StatusCode::UNPROCESSABLE_ENTITY => {
+            let message = errors
+                .iter()
+                .map(|error| {
+                    let location = error["loc"]
+                        .as_array()
+                        .map(|loc| loc.iter().map(|part| part.to_string().trim_matches('"').to_string()).collect::<Vec<_>>().join("."))
+                        .unwrap_or_default();
+                    format!("{} {}", location, error["msg"].as_str().unwrap_or_default())
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            ApiError::Validation { url: url.to_string(), message }
+        }
+        _ => ApiError::HttpStatus { url: url.to_string(), status: status.as_u16() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    /// What the mock server does with a request
+    enum Reply {
+        Json(u16, &'static str),
+        /// Never answer
+        Hang,
+    }
+
+    type Handler = fn(usize) -> Reply;
+
+    /// A local HTTP server answering the nth request with `reply(n)`;
+    /// records `METHOD /path?query` of every request
+    struct Mock {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Mock {
+        async fn start(reply: Handler) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            Self::serve(listener, reply)
+        }
+
+        fn serve(listener: TcpListener, reply: Handler) -> Self {
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let seen = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let seen = seen.clone();
+                    tokio::spawn(async move { Self::answer(stream, seen, reply).await });
+                }
+            });
+            Self { url, requests }
+        }
+
+        async fn answer(mut stream: TcpStream, seen: Arc<Mutex<Vec<String>>>, reply: Handler) {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            let head_end = loop {
+                let read = stream.read(&mut buffer).await.unwrap_or(0);
+                if read == 0 {
+                    return;
+                }
+                request.extend_from_slice(&buffer[..read]);
+                if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                    break end + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+            let length: usize = head
+                .lines()
+                .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|value| value.trim().parse().unwrap_or(0)))
+                .unwrap_or(0);
+            while request.len() < head_end + length {
+                let read = stream.read(&mut buffer).await.unwrap_or(0);
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            let line = head.lines().next().unwrap_or_default();
+            let index = {
+                let mut seen = seen.lock().unwrap();
+                seen.push(line.rsplit_once(' ').map_or(line, |(start, _)| start).to_string());
+                seen.len() - 1
+            };
+            match reply(index) {
+                Reply::Json(status, body) => {
+                    let response = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                }
+                Reply::Hang => tokio::time::sleep(Duration::from_secs(30)).await,
+            }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        fn client(&self, timeout: Duration) -> ApiClient {
+            ApiClient::new(&self.url, timeout, HeaderMap::new()).unwrap()
+        }
+    }
+
+    const MESSAGE: &str = r#"{"success": true, "message": "ok"}"#;
+
+    /// Answer the first request with `STATUS`, the rest with success
+    fn failing_once<const STATUS: u16>(index: usize) -> Reply {
+        match index {
+            0 => Reply::Json(STATUS, "{}"),
+            _ => Reply::Json(200, MESSAGE),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_refused_connections_even_for_post() {
+        // Find a free port, then only start listening on it after the first attempt failed
+        let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let client = ApiClient::new(&format!("http://127.0.0.1:{}", port), Duration::from_secs(5), HeaderMap::new()).unwrap();
+        let server = tokio::spawn(async move {
+            tokio::time::sleep(RETRY_DELAY / 2).await;
+            Mock::serve(TcpListener::bind(("127.0.0.1", port)).await.unwrap(), |_| Reply::Json(200, MESSAGE))
+        });
+
+        let message: Message = client.post("/api/things", &serde_json::json!({})).await.unwrap();
+        assert_eq!(message.message, "ok");
+        assert_eq!(server.await.unwrap().requests(), ["POST /api/things"]);
+    }
+
+    #[tokio::test]
+    async fn retries_gateway_errors_for_idempotent_methods() {
+        let replies: [(u16, Handler); 3] =
+            [(502, failing_once::<502>), (503, failing_once::<503>), (504, failing_once::<504>)];
+        for (status, reply) in replies {
+            for method in [Method::GET, Method::PUT, Method::DELETE] {
+                let mock = Mock::start(reply).await;
+                let result: Result<Message, _> = mock.client(Duration::from_secs(5)).send(method.clone(), "/api/things", &[], None).await;
+                assert!(result.is_ok(), "{} {} should be retried: {:?}", method, status, result);
+                assert_eq!(mock.requests().len(), 2);
+            }
+
+            let mock = Mock::start(reply).await;
+            let error = mock.client(Duration::from_secs(5)).post::<Message>("/api/things", &serde_json::json!({})).await.unwrap_err();
+            assert_eq!(error.status(), Some(status), "{:?}", error);
+            assert_eq!(mock.requests().len(), 1, "POST {} must not be retried", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_timed_out_post() {
+        let mock = Mock::start(|_| Reply::Hang).await;
+        let client = mock.client(Duration::from_millis(200));
+
+        let error = client.post::<Message>("/api/tts/generate-chapter", &serde_json::json!({})).await.unwrap_err();
+        assert!(matches!(error, ApiError::Timeout { .. }), "{:?}", error);
+        assert_eq!(mock.requests().len(), 1);
+
+        // A GET that timed out is safe to send again
+        let error = client.get::<Message>("/api/things", &[]).await.unwrap_err();
+        assert!(matches!(error, ApiError::Timeout { .. }), "{:?}", error);
+        assert_eq!(mock.requests().len(), 2 + MAX_RETRIES as usize);
+    }
+
+    #[tokio::test]
+    async fn passes_application_errors_through() {
+        let mock = Mock::start(|_| Reply::Json(404, r#"{"detail": "[PROJECT_NOT_FOUND]projectId:p1"}"#)).await;
+        let error = mock.client(Duration::from_secs(5)).get_project("p1").await.unwrap_err();
+
+        assert!(matches!(&error, ApiError::Backend { status: 404, .. }), "{:?}", error);
+        assert_eq!(error.to_string(), "[PROJECT_NOT_FOUND]projectId:p1");
+        let application = error.application_error().unwrap();
+        assert_eq!(application.code, "PROJECT_NOT_FOUND");
+        assert_eq!(application.params.get("projectId").map(String::as_str), Some("p1"));
+    }
+
+    #[tokio::test]
+    async fn maps_fastapi_validation_errors() {
+        let mock = Mock::start(|_| {
+            Reply::Json(
+                422,
+                r#"{"detail": [{"loc": ["body", "chapterId"], "msg": "Field required", "type": "missing"},
+                               {"loc": ["query", "limit"], "msg": "Input should be a valid integer", "type": "int_parsing"}]}"#,
+            )
+        })
+        .await;
+        let error = mock.client(Duration::from_secs(5)).post::<Message>("/api/tts/generate-chapter", &serde_json::json!({})).await.unwrap_err();
+
+        match &error {
+            ApiError::Validation { message, .. } => {
+                assert_eq!(message, "body.chapterId Field required, query.limit Input should be a valid integer")
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+        assert_eq!(error.status(), Some(422));
+        assert!(error.application_error().is_none());
+    }
+
+    #[test]
+    fn path_id_encodes_everything_but_unreserved_characters() {
+        assert_eq!(path_id("a1-B2_c3.d4~"), "a1-B2_c3.d4~");
+        assert_eq!(path_id("a b/c?d#e%f"), "a%20b%2Fc%3Fd%23e%25f");
+        assert_eq!(path_id("ü"), "%C3%BC");
+    }
+
+    #[tokio::test]
+    async fn sends_ids_as_single_path_segments() {
+        let mock = Mock::start(|_| Reply::Json(200, MESSAGE)).await;
+        mock.client(Duration::from_secs(5)).delete_project("../speakers/x?y").await.unwrap();
+        assert_eq!(mock.requests(), ["DELETE /api/projects/..%2Fspeakers%2Fx%3Fy"]);
+    }
+}
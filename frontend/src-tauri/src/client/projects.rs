@@ -0,0 +1,268 @@
+//! Projects, chapters and segments (`/api/projects`, `/api/chapters`, `/api/segments`)
+
+use serde::{Deserialize, Serialize};
+
+use super::{path_id, ApiClient, ApiError, Message, Reordered};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub order_index: i64,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Empty where a route answers without them
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    pub id: String,
+    pub project_id: String,
+    pub title: String,
+    pub order_index: i64,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Empty where a route answers without them
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    pub id: String,
+    pub chapter_id: String,
+    pub text: String,
+    pub tts_engine: String,
+    pub tts_model_name: String,
+    pub tts_speaker_name: Option<String>,
+    pub language: String,
+    /// standard or divider
+    #[serde(default)]
+    pub segment_type: String,
+    /// Milliseconds, for dividers
+    #[serde(default)]
+    pub pause_duration: u32,
+    pub audio_path: Option<String>,
+    pub order_index: i64,
+    #[serde(default)]
+    pub start_time: f64,
+    #[serde(default)]
+    pub end_time: f64,
+    /// pending, queued, processing, completed or failed
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub is_frozen: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    pub quality_analyzed: Option<bool>,
+    pub quality_score: Option<u8>,
+    /// perfect, warning or defect
+    pub quality_status: Option<String>,
+    /// One entry per quality engine, in the engines' own format
+    pub engine_results: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewProject {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Fields left `None` keep their value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewChapter {
+    pub project_id: String,
+    pub title: String,
+    pub order_index: i64,
+}
+
+/// Fields left `None` keep their value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_index: Option<i64>,
+}
+
+/// Fields left `None` get the backend's defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSegment {
+    pub chapter_id: String,
+    pub text: String,
+    pub order_index: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_engine: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_model_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_speaker_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// Fields left `None` keep their value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_engine: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_model_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tts_speaker_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReorderProjects<'a> {
+    project_ids: &'a [String],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReorderChapters<'a> {
+    project_id: &'a str,
+    chapter_ids: &'a [String],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReorderSegments<'a> {
+    chapter_id: &'a str,
+    segment_ids: &'a [String],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveChapter<'a> {
+    new_project_id: &'a str,
+    new_order_index: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveSegment<'a> {
+    new_chapter_id: &'a str,
+    new_order_index: i64,
+}
+
+#[derive(Serialize)]
+struct Freeze {
+    freeze: bool,
+}
+
+impl ApiClient {
+    /// All projects with their chapters and segments
+    pub async fn list_projects(&self) -> Result<Vec<Project>, ApiError> {
+        self.get("/api/projects", &[]).await
+    }
+
+    pub async fn get_project(&self, id: &str) -> Result<Project, ApiError> {
+        self.get(&format!("/api/projects/{}", path_id(id)), &[]).await
+    }
+
+    pub async fn create_project(&self, project: &NewProject) -> Result<Project, ApiError> {
+        self.post("/api/projects", project).await
+    }
+
+    pub async fn update_project(&self, id: &str, update: &ProjectUpdate) -> Result<Project, ApiError> {
+        self.put(&format!("/api/projects/{}", path_id(id)), update).await
+    }
+
+    pub async fn delete_project(&self, id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/projects/{}", path_id(id))).await
+    }
+
+    pub async fn reorder_projects(&self, project_ids: &[String]) -> Result<Reordered, ApiError> {
+        self.post("/api/projects/reorder", &ReorderProjects { project_ids }).await
+    }
+
+    pub async fn create_chapter(&self, chapter: &NewChapter) -> Result<Chapter, ApiError> {
+        self.post("/api/chapters", chapter).await
+    }
+
+    /// The chapter with its segments
+    pub async fn get_chapter(&self, id: &str) -> Result<Chapter, ApiError> {
+        self.get(&format!("/api/chapters/{}", path_id(id)), &[]).await
+    }
+
+    pub async fn update_chapter(&self, id: &str, update: &ChapterUpdate) -> Result<Chapter, ApiError> {
+        self.put(&format!("/api/chapters/{}", path_id(id)), update).await
+    }
+
+    pub async fn delete_chapter(&self, id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/chapters/{}", path_id(id))).await
+    }
+
+    pub async fn reorder_chapters(&self, project_id: &str, chapter_ids: &[String]) -> Result<Reordered, ApiError> {
+        self.post("/api/chapters/reorder", &ReorderChapters { project_id, chapter_ids }).await
+    }
+
+    pub async fn move_chapter(&self, id: &str, project_id: &str, order_index: i64) -> Result<Chapter, ApiError> {
+        let body = MoveChapter { new_project_id: project_id, new_order_index: order_index };
+        self.put(&format!("/api/chapters/{}/move", path_id(id)), &body).await
+    }
+
+    pub async fn create_segment(&self, new_segment: &NewSegment) -> Result<Segment, ApiError> {
+        self.post("/api/segments", new_segment).await
+    }
+
+    pub async fn get_segment(&self, id: &str) -> Result<Segment, ApiError> {
+        self.get(&format!("/api/segments/{}", path_id(id)), &[]).await
+    }
+
+    pub async fn update_segment(&self, id: &str, update: &SegmentUpdate) -> Result<Segment, ApiError> {
+        self.put(&format!("/api/segments/{}", path_id(id)), update).await
+    }
+
+    pub async fn delete_segment(&self, id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/segments/{}", path_id(id))).await
+    }
+
+    pub async fn reorder_segments(&self, chapter_id: &str, segment_ids: &[String]) -> Result<Reordered, ApiError> {
+        self.post("/api/segments/reorder", &ReorderSegments { chapter_id, segment_ids }).await
+    }
+
+    pub async fn move_segment(&self, id: &str, chapter_id: &str, order_index: i64) -> Result<Segment, ApiError> {
+        let body = MoveSegment { new_chapter_id: chapter_id, new_order_index: order_index };
+        self.put(&format!("/api/segments/{}/move", path_id(id)), &body).await
+    }
+
+    /// Frozen segments are left alone by regeneration and analysis
+    pub async fn freeze_segment(&self, id: &str, freeze: bool) -> Result<Segment, ApiError> {
+        self.patch(&format!("/api/segments/{}/freeze", path_id(id)), &Freeze { freeze }).await
+    }
+}
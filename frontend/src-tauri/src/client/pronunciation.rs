@@ -0,0 +1,108 @@
+//! Pronunciation rules (`/api/pronunciation/rules`)
+
+use serde::{Deserialize, Serialize};
+
+use super::{path_id, query, ApiClient, ApiError, Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PronunciationRule {
+    pub id: String,
+    pub pattern: String,
+    pub replacement: String,
+    pub is_regex: bool,
+    /// project_engine or engine
+    pub scope: String,
+    /// Set for project_engine rules
+    pub project_id: Option<String>,
+    pub engine_name: String,
+    pub language: String,
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleList {
+    pub rules: Vec<PronunciationRule>,
+    pub total: u32,
+}
+
+/// Filters for listing rules; with `project_id` the project's rules come
+/// first, followed by the engine-wide ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFilter {
+    pub engine: Option<String>,
+    pub language: Option<String>,
+    pub project_id: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPronunciationRule {
+    pub pattern: String,
+    pub replacement: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    /// project_engine or engine
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub engine_name: String,
+    pub language: String,
+    #[serde(default = "super::default_true")]
+    pub is_active: bool,
+}
+
+/// Fields left `None` keep their value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PronunciationRuleUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_regex: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_active: Option<bool>,
+}
+
+impl ApiClient {
+    pub async fn list_pronunciation_rules(&self, filter: &RuleFilter) -> Result<RuleList, ApiError> {
+        let query = query([
+            ("engine", filter.engine.clone()),
+            ("language", filter.language.clone()),
+            ("project_id", filter.project_id.clone()),
+            ("scope", filter.scope.clone()),
+        ]);
+        self.get("/api/pronunciation/rules", &query).await
+    }
+
+    pub async fn create_pronunciation_rule(&self, rule: &NewPronunciationRule) -> Result<PronunciationRule, ApiError> {
+        self.post("/api/pronunciation/rules", rule).await
+    }
+
+    pub async fn update_pronunciation_rule(
+        &self,
+        id: &str,
+        update: &PronunciationRuleUpdate,
+    ) -> Result<PronunciationRule, ApiError> {
+        self.put(&format!("/api/pronunciation/rules/{}", path_id(id)), update).await
+    }
+
+    pub async fn delete_pronunciation_rule(&self, id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/pronunciation/rules/{}", path_id(id))).await
+    }
+}
@@ -0,0 +1,109 @@
+//! Speakers and their voice samples (`/api/speakers`)
+
+use serde::{Deserialize, Serialize};
+
+use super::{path_id, ApiClient, ApiError, Message};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Speaker {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// male, female or neutral
+    pub gender: Option<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    /// Has at least one sample
+    pub is_active: bool,
+    #[serde(default)]
+    pub sample_count: u32,
+    pub created_at: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub samples: Vec<SpeakerSample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakerSample {
+    pub id: String,
+    pub speaker_id: Option<String>,
+    pub file_path: String,
+    pub file_name: String,
+    pub file_size: u64,
+    /// Seconds
+    pub duration: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub transcript: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSpeaker {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Fields left `None` keep their value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakerUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl ApiClient {
+    pub async fn list_speakers(&self) -> Result<Vec<Speaker>, ApiError> {
+        self.get("/api/speakers/", &[]).await
+    }
+
+    pub async fn get_speaker(&self, id: &str) -> Result<Speaker, ApiError> {
+        self.get(&format!("/api/speakers/{}", path_id(id)), &[]).await
+    }
+
+    /// `None` if no speaker is marked as default
+    pub async fn default_speaker(&self) -> Result<Option<Speaker>, ApiError> {
+        self.get("/api/speakers/default/get", &[]).await
+    }
+
+    pub async fn create_speaker(&self, speaker: &NewSpeaker) -> Result<Speaker, ApiError> {
+        self.post("/api/speakers/", speaker).await
+    }
+
+    pub async fn update_speaker(&self, id: &str, update: &SpeakerUpdate) -> Result<Speaker, ApiError> {
+        self.put(&format!("/api/speakers/{}", path_id(id)), update).await
+    }
+
+    pub async fn set_default_speaker(&self, id: &str) -> Result<Speaker, ApiError> {
+        self.post(&format!("/api/speakers/{}/set-default", path_id(id)), &serde_json::json!({})).await
+    }
+
+    pub async fn delete_speaker(&self, id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/speakers/{}", path_id(id))).await
+    }
+
+    pub async fn delete_speaker_sample(&self, speaker_id: &str, sample_id: &str) -> Result<Message, ApiError> {
+        self.delete(&format!("/api/speakers/{}/samples/{}", path_id(speaker_id), path_id(sample_id))).await
+    }
+}
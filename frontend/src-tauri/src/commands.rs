@@ -1,17 +1,52 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::ipc::Channel;
+use tauri::{Emitter, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+use zeroize::Zeroizing;
+use crate::atomic;
+use crate::audio::acx::{self, AcxReport};
+use crate::audio::chapters::{self, ChapterExportOptions, ChapterFile, ChapterSpan};
+use crate::audio::m4b::{self, BookChapter, M4bOptions, M4bSummary};
+use crate::audio::podcast::{self, FeedEpisode, FeedOptions, FeedSummary};
+use crate::audio::split::{self, SplitOptions, SplitSummary};
+use crate::audio::tags::{self, AudioMetadata, TagReport};
+use crate::audio::{self, EncodeOptions, EncodeSettings, ExportError, ExportFormat};
+use crate::bundle::{self, BundleError, BundleSummary, ImportSummary};
+use crate::client::export::{ExportProgress, ExportRequest, ExportStarted};
+use crate::client::jobs::{
+    ChapterGenerationStarted, GenerateChapter, JobCancelled, JobFilter, JobList, QualityEngines, QualityJob,
+    QualityJobCreated, SegmentQueued, TtsJob,
+};
+use crate::client::projects::{Chapter, Project, Segment, SegmentUpdate};
+use crate::client::pronunciation::{NewPronunciationRule, PronunciationRule, PronunciationRuleUpdate, RuleFilter, RuleList};
+use crate::client::speakers::Speaker;
+use crate::client::{self, ApiClient, ApiError, Message};
+use crate::compat;
+use crate::diff::{self, MergeResult, ProjectDiff, Resolution};
+use crate::download::{self, DownloadError, DownloadEvent, DownloadSummary};
+use crate::health::{self, HealthCheck};
+use crate::journal::{self, RecoveryCandidate};
+use crate::launch::OpenRequest;
+use crate::monitor::{self, MonitorStats};
+use crate::presets::{ExportPreset, PresetError};
+use crate::profiles::{BackendProfile, LegacyProfile, ProfileError, DEFAULT_BACKEND_URL};
+use crate::project::{ProjectData, ProjectError, PROJECT_EXTENSION};
+use crate::recent::RecentProject;
+use crate::save::{self, ProjectBackup, SavedProject};
 use crate::state::AppState;
+use crate::tunnel::{self, TunnelError, TunnelState, TunnelStatus};
+use crate::vault::{self, VaultError, VaultStatus};
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ProjectData {
-    pub name: String,
-    pub chapters: Vec<serde_json::Value>,
-    pub settings: serde_json::Value,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AppInfo {
     pub version: String,
+    /// API revision this app speaks to backends
+    pub api_version: u32,
     pub name: String,
     pub platform: String,
     pub arch: String,
@@ -23,36 +58,338 @@ pub fn ping() -> String {
 }
 
 #[tauri::command]
-pub async fn check_backend_health() -> Result<bool, String> {
-    // Check if Python backend is running on port 8765
-    match reqwest::get("http://127.0.0.1:8765/health").await {
-        Ok(response) => Ok(response.status().is_success()),
-        Err(_) => Ok(false), // Backend not running, but this is not an error
+pub async fn check_backend_health(
+    backend_url: Option<String>,
+    timeout_ms: Option<u64>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<HealthCheck, String> {
+    let timeout = timeout_ms.map(Duration::from_millis);
+    // Checks the active backend (with its profile's timeout and headers) unless given another URL
+    match backend_url {
+        Some(backend_url) => {
+            // The profile this URL belongs to, if any, may need its headers to get through
+            let profile = state.backend_profile_for(&backend_url);
+            let timeout = timeout.or_else(|| profile.as_ref().and_then(BackendProfile::health_timeout));
+            let headers = profile.map(|profile| profile.header_map(&state.vault())).unwrap_or_default();
+            Ok(health::check(&backend_url, health::clamp_timeout(timeout), headers).await)
+        }
+        None => {
+            let (backend_url, check) = monitor::check_active(&state, timeout).await;
+            monitor::report(&app, &backend_url, &check);
+            Ok(check)
+        }
     }
 }
 
 #[tauri::command]
-pub async fn open_project_file() -> Result<String, String> {
-    // This will be handled by the frontend using tauri-plugin-dialog
-    // Returning a placeholder for now
-    Ok("".to_string())
+pub fn get_backend_monitor_stats(state: State<'_, AppState>) -> MonitorStats {
+    state.backend_monitor().stats()
+}
+
+/// A project file as loaded from disk
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedProject {
+    pub path: String,
+    pub project: ProjectData,
+    /// Schema version the file was written with; older files are migrated in memory
+    pub file_schema_version: u32,
+}
+
+#[tauri::command]
+pub async fn open_project_file(
+    app: tauri::AppHandle,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<OpenedProject>, ProjectError> {
+    // Open `path` (e.g. from the recent list), or ask for a file; `None` if the dialog is cancelled
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let mut dialog = app
+                .dialog()
+                .file()
+                .set_title("Open Project")
+                .add_filter("Audiobook Maker Project", &[PROJECT_EXTENSION]);
+            let last_dir = state
+                .get_last_project_path()
+                .and_then(|last| Path::new(&last).parent().map(Path::to_path_buf));
+            if let Some(dir) = last_dir {
+                dialog = dialog.set_directory(dir);
+            }
+            let picked = tokio::task::spawn_blocking(move || dialog.blocking_pick_file())
+                .await
+                .map_err(|e| ProjectError::Dialog { message: e.to_string() })?;
+            match picked.map(|file| file.into_path()) {
+                Some(Ok(path)) => path.display().to_string(),
+                Some(Err(e)) => return Err(ProjectError::Dialog { message: e.to_string() }),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| ProjectError::io(&path, e))?;
+    let (project, file_schema_version) = ProjectData::parse(&content)?;
+
+    state.set_project_revision(&path, save::revision(content.as_bytes()));
+    state.set_last_project_path(Some(path.clone()));
+    // The project is open either way; a failure to update the list is not worth reporting
+    let _ = state.recent_projects().touch(&path, &project.name);
+
+    Ok(Some(OpenedProject { path, project, file_schema_version }))
 }
 
 #[tauri::command]
 pub async fn save_project_file(
     path: String,
     content: String,
-    _state: State<'_, AppState>,
-) -> Result<(), String> {
-    // Save project data to file
-    use std::fs;
-    fs::write(&path, content)
-        .map_err(|e| format!("Failed to save project file: {}", e))?;
+    force: Option<bool>,
+    keep_backups: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<SavedProject, ProjectError> {
+    // Validate against the project schema (migrating older content) before writing
+    let project = ProjectData::from_json(&content)?;
+    let data = project.to_json()?;
+    // Refuse to overwrite edits made elsewhere since this app last read or wrote the file
+    let expected = state.get_project_revision(&path);
+    let target = path.clone();
+    let saved = tokio::task::spawn_blocking(move || {
+        save::save(
+            Path::new(&target),
+            data.as_bytes(),
+            expected.as_deref(),
+            force.unwrap_or(false),
+            keep_backups.unwrap_or(save::DEFAULT_KEEP_BACKUPS),
+        )
+    })
+    .await
+    .map_err(|e| ProjectError::io(&path, e))??;
 
     // Update state with last saved path
-    _state.set_last_project_path(Some(path));
+    state.set_project_revision(&path, saved.revision.clone());
+    state.autosave().saved(&path);
+    state.set_last_project_path(Some(path.clone()));
+    let _ = state.recent_projects().touch(&path, &project.name);
 
-    Ok(())
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn list_project_backups(path: String) -> Result<Vec<ProjectBackup>, ProjectError> {
+    save::list_backups(Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn restore_project_backup(
+    path: String,
+    index: u32,
+    keep_backups: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<OpenedProject, ProjectError> {
+    // Put a backup back in place; the replaced version becomes the newest backup
+    let target = path.clone();
+    let keep = keep_backups.unwrap_or(save::DEFAULT_KEEP_BACKUPS);
+    let (content, saved) = tokio::task::spawn_blocking(move || save::restore_backup(Path::new(&target), index, keep))
+        .await
+        .map_err(|e| ProjectError::io(&path, e))??;
+    let (project, file_schema_version) = ProjectData::parse(&content)?;
+
+    state.set_project_revision(&path, saved.revision);
+    state.set_last_project_path(Some(path.clone()));
+
+    Ok(OpenedProject { path, project, file_schema_version })
+}
+
+#[tauri::command]
+pub fn update_autosave(path: Option<String>, content: String, state: State<'_, AppState>) {
+    // Latest unsaved state of the open project; the autosave task journals it
+    state.autosave().update(path, content);
+}
+
+#[tauri::command]
+pub fn discard_autosave(state: State<'_, AppState>) {
+    // The user closed the project without saving
+    state.autosave().clear();
+}
+
+#[tauri::command]
+pub fn list_recoverable_projects(state: State<'_, AppState>) -> Vec<RecoveryCandidate> {
+    state
+        .autosave()
+        .recoverable()
+        .iter()
+        .map(|(id, journal)| journal::describe(id, journal))
+        .collect()
+}
+
+/// A project restored from the autosave journal
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredProject {
+    /// File the edits belong to; `None` if the project was never saved
+    pub path: Option<String>,
+    pub project: ProjectData,
+    pub saved_at: String,
+}
+
+#[tauri::command]
+pub fn recover_project(id: String, state: State<'_, AppState>) -> Result<RecoveredProject, ProjectError> {
+    let mut autosave = state.autosave();
+    let journal = autosave
+        .recoverable_journal(&id)
+        .ok_or_else(|| ProjectError::NotRecoverable { id: id.clone() })?;
+    let (project, _) = journal::parse(journal)?;
+    // Parsed fine, so it becomes the open project's unsaved state
+    let journal = autosave.recover(&id).ok_or(ProjectError::NotRecoverable { id })?;
+
+    Ok(RecoveredProject { path: journal.project_path, project, saved_at: journal.saved_at })
+}
+
+#[tauri::command]
+pub fn discard_recovery(id: String, state: State<'_, AppState>) -> Result<(), ProjectError> {
+    if state.autosave().discard(&id) {
+        Ok(())
+    } else {
+        Err(ProjectError::NotRecoverable { id })
+    }
+}
+
+#[tauri::command]
+pub fn take_open_requests(state: State<'_, AppState>) -> Vec<OpenRequest> {
+    // Files the app was launched with; call once the `project://open` listener is in place
+    state.launch().take()
+}
+
+async fn read_project(path: &str) -> Result<ProjectData, ProjectError> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| ProjectError::io(path, e))?;
+    ProjectData::from_json(&content)
+}
+
+#[tauri::command]
+pub async fn diff_project_files(before_path: String, after_path: String) -> Result<ProjectDiff, ProjectError> {
+    // Chapter/segment level changes from one project file to another
+    let before = read_project(&before_path).await?;
+    let after = read_project(&after_path).await?;
+    Ok(diff::diff(&before, &after))
+}
+
+#[tauri::command]
+pub async fn merge_project_files(
+    base_path: String,
+    ours_path: String,
+    theirs_path: String,
+    resolutions: Option<HashMap<String, Resolution>>,
+) -> Result<MergeResult, ProjectError> {
+    // Three-way merge against the common ancestor; nothing is written, the UI
+    // resolves the reported conflicts and saves the result
+    let base = read_project(&base_path).await?;
+    let ours = read_project(&ours_path).await?;
+    let theirs = read_project(&theirs_path).await?;
+    diff::merge(&base, &ours, &theirs, &resolutions.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn list_recent_projects(state: State<'_, AppState>) -> Vec<RecentProject> {
+    state.recent_projects().list()
+}
+
+#[tauri::command]
+pub fn pin_recent_project(path: String, pinned: bool, state: State<'_, AppState>) -> Result<(), ProjectError> {
+    state.recent_projects().set_pinned(&path, pinned)
+}
+
+#[tauri::command]
+pub fn remove_recent_project(path: String, state: State<'_, AppState>) -> Result<(), ProjectError> {
+    state.recent_projects().remove(&path)
+}
+
+#[tauri::command]
+pub async fn export_project_bundle(
+    project_id: String,
+    path: String,
+    backend_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<BundleSummary, BundleError> {
+    // Pack the project with its audio, speaker samples and pronunciation rules into one .abmproj file
+    let client = bundle_client(backend_url, &state)?;
+    bundle::export_bundle(&client, &project_id, Path::new(&path)).await
+}
+
+#[tauri::command]
+pub async fn import_project_bundle(
+    path: String,
+    backend_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ImportSummary, BundleError> {
+    // Recreate a bundled project as a new project on the connected backend
+    let client = bundle_client(backend_url, &state)?;
+    bundle::import_bundle(&client, Path::new(&path)).await
+}
+
+/// A project file recreated on the backend
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedProjectFile {
+    pub path: String,
+    /// The project as the file has it, with the file's IDs
+    pub project: ProjectData,
+    pub import: ImportSummary,
+}
+
+#[tauri::command]
+pub async fn import_project_file(
+    path: String,
+    backend_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ImportedProjectFile, BundleError> {
+    // Projects live on the backend, so opening a project file creates it there
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| ProjectError::io(&path, e))?;
+    let project = ProjectData::from_json(&content)?;
+    let client = bundle_client(backend_url, &state)?;
+    let import = bundle::import_project(&client, project.clone()).await?;
+
+    state.set_project_revision(&path, save::revision(content.as_bytes()));
+    state.set_last_project_path(Some(path.clone()));
+    let _ = state.recent_projects().touch(&path, &project.name);
+
+    Ok(ImportedProjectFile { path, project, import })
+}
+
+#[tauri::command]
+pub async fn import_project_data(
+    project: ProjectData,
+    backend_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ImportSummary, BundleError> {
+    // Create a project held in memory, e.g. one recovered from the autosave journal
+    project.validate()?;
+    let client = bundle_client(backend_url, &state)?;
+    bundle::import_project(&client, project).await
+}
+
+/// The active backend, or the one at `backend_url`
+fn bundle_client(backend_url: Option<String>, state: &AppState) -> Result<ApiClient, ApiError> {
+    match backend_url {
+        Some(backend_url) => ApiClient::for_url(state, &backend_url),
+        None => ApiClient::for_active(state),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub settings: EncodeSettings,
+    pub duration_secs: f64,
+    pub bytes_written: u64,
+    pub loudness: Option<audio::LoudnessReport>,
 }
 
 #[tauri::command]
@@ -60,24 +397,563 @@ pub async fn export_audio(
     format: String,
     path: String,
     audio_data: Vec<u8>,
-) -> Result<(), String> {
-    // Export audio to specified format
-    use std::fs;
+    options: Option<EncodeOptions>,
+    metadata: Option<AudioMetadata>,
+) -> Result<ExportSummary, ExportError> {
+    // Decode the incoming WAV/PCM and encode it to the requested format
+    let format: ExportFormat = format.parse()?;
+    let options = options.unwrap_or_default();
+
+    // Encoding is CPU bound, keep it off the async runtime
+    let transcoded = tokio::task::spawn_blocking(move || audio::transcode(&audio_data, format, &options))
+        .await
+        .map_err(|e| ExportError::encode(format.codec_name(), e))??;
+
+    // Tagged in memory, so the previous export stays in place until the new one is complete
+    let target = path.clone();
+    let data = transcoded.data;
+    let encoded_len = tokio::task::spawn_blocking(move || {
+        let data = match metadata {
+            Some(metadata) => tags::tag_data(data, &target, &metadata)?,
+            None => data,
+        };
+        atomic::write_atomic(Path::new(&target), &data).map_err(|e| ExportError::io(&target, e))?;
+        Ok::<_, ExportError>(data.len() as u64)
+    })
+    .await
+    .map_err(|e| ExportError::io(&path, e))??;
+
+    let bytes_written = tokio::fs::metadata(&path)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or(encoded_len);
+
+    Ok(ExportSummary {
+        path,
+        bytes_written,
+        settings: transcoded.settings,
+        duration_secs: transcoded.duration_secs,
+        loudness: transcoded.loudness,
+    })
+}
+
+#[tauri::command]
+pub async fn check_acx(path: String) -> Result<AcxReport, ExportError> {
+    // Check an exported file (or a folder of them) against the ACX submission rules
+    let target = path.clone();
+    tokio::task::spawn_blocking(move || acx::check(Path::new(&target)))
+        .await
+        .map_err(|e| ExportError::io(path, e))?
+}
+
+/// Rendered WAV for one of the project's chapters
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterAudio {
+    pub chapter_id: String,
+    pub path: String,
+}
+
+/// Pair each project chapter (in project order) with its rendered audio
+fn book_chapters(project: &ProjectData, audio: &[ChapterAudio]) -> Result<Vec<BookChapter>, ExportError> {
+    project
+        .chapters
+        .iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            let source = audio
+                .iter()
+                .find(|source| source.chapter_id == chapter.id)
+                .ok_or_else(|| ExportError::MissingChapterAudio { chapter_id: chapter.id.clone() })?;
+            let title = if chapter.title.trim().is_empty() {
+                format!("Chapter {}", index + 1)
+            } else {
+                chapter.title.clone()
+            };
+            Ok(BookChapter { title, path: source.path.clone().into() })
+        })
+        .collect()
+}
 
-    // For now, just save the raw audio data
-    // In production, you would convert based on format (mp3, wav, m4a, etc.)
-    fs::write(&path, audio_data)
-        .map_err(|e| format!("Failed to export audio: {}", e))?;
+#[tauri::command]
+pub async fn export_m4b(
+    path: String,
+    project: ProjectData,
+    chapter_audio: Vec<ChapterAudio>,
+    options: Option<M4bOptions>,
+    metadata: Option<AudioMetadata>,
+) -> Result<M4bSummary, ExportError> {
+    // Mux all chapters into one M4B with chapter markers from the project
+    let chapters = book_chapters(&project, &chapter_audio)?;
+    let options = options.unwrap_or_default();
 
-    println!("Audio exported to {} in {} format", path, format);
+    tokio::task::spawn_blocking(move || {
+        m4b::export_m4b(Path::new(&path), &project.name, &chapters, &options, metadata.as_ref())
+    })
+    .await
+    .map_err(|e| ExportError::encode("aac", e))?
+}
 
+#[tauri::command]
+pub async fn read_audio_tags(path: String) -> Result<TagReport, ExportError> {
+    // Let the UI show what actually ended up in an exported file
+    let target = path.clone();
+    tokio::task::spawn_blocking(move || tags::read_tags(Path::new(&target)))
+        .await
+        .map_err(|e| ExportError::tag(path, e))?
+}
+
+#[tauri::command]
+pub async fn export_chapter_markers(
+    audio_path: String,
+    chapters: Vec<ChapterSpan>,
+    options: ChapterExportOptions,
+) -> Result<Vec<ChapterFile>, ExportError> {
+    // Write chapter sidecar files (CUE, FFMETADATA, ...) next to an exported file
+    let target = audio_path.clone();
+    tokio::task::spawn_blocking(move || chapters::write_chapter_files(Path::new(&target), &chapters, &options))
+        .await
+        .map_err(|e| ExportError::io(audio_path, e))?
+}
+
+#[tauri::command]
+pub async fn export_split(
+    format: String,
+    audio_path: String,
+    directory: String,
+    chapters: Vec<ChapterSpan>,
+    options: Option<SplitOptions>,
+    metadata: Option<AudioMetadata>,
+) -> Result<SplitSummary, ExportError> {
+    // Split the rendered book into one file per chapter (or per max duration) plus a playlist
+    let format: ExportFormat = format.parse()?;
+    let options = options.unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        split::export_split(
+            Path::new(&audio_path),
+            Path::new(&directory),
+            format,
+            &chapters,
+            &options,
+            metadata.as_ref(),
+        )
+    })
+    .await
+    .map_err(|e| ExportError::encode(format.codec_name(), e))?
+}
+
+#[tauri::command]
+pub async fn export_podcast_feed(
+    directory: String,
+    episodes: Vec<FeedEpisode>,
+    options: FeedOptions,
+) -> Result<FeedSummary, ExportError> {
+    // Write an RSS feed plus its episode files into a folder ready for a static web host
+    let target = directory.clone();
+    tokio::task::spawn_blocking(move || podcast::write_feed(Path::new(&target), &episodes, &options))
+        .await
+        .map_err(|e| ExportError::io(directory, e))?
+}
+
+#[tauri::command]
+pub async fn download_export(
+    job_id: String,
+    path: String,
+    backend_url: Option<String>,
+    expected_sha256: Option<String>,
+    on_event: Channel<DownloadEvent>,
+    state: State<'_, AppState>,
+) -> Result<DownloadSummary, DownloadError> {
+    // Stream the finished export to disk instead of passing it through the webview
+    let backend_url = backend_url.unwrap_or_else(|| state.get_backend_url());
+    let url = format!("{}/api/audio/export/{}/download", backend_url.trim_end_matches('/'), client::path_id(&job_id));
+    let profile = state.backend_profile_for(&backend_url);
+    let timeout = profile.as_ref().and_then(BackendProfile::request_timeout).unwrap_or(client::DEFAULT_REQUEST_TIMEOUT);
+    let headers = profile.map(|profile| profile.header_map(&state.vault())).unwrap_or_default();
+
+    download::download_to_file(&url, headers, timeout, Path::new(&path), expected_sha256.as_deref(), |event| {
+        // The webview may have gone away; the download itself still completes
+        let _ = on_event.send(event);
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn init_backend_profiles(
+    legacy: Option<Vec<LegacyProfile>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<BackendProfile>, ProfileError> {
+    // Takes over the localStorage profiles the first time, then ensures a default profile exists
+    state.backend_profiles().init(legacy.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn list_backend_profiles(state: State<'_, AppState>) -> Vec<BackendProfile> {
+    state.backend_profiles().list()
+}
+
+#[tauri::command]
+pub fn get_backend_profile(id: String, state: State<'_, AppState>) -> Result<BackendProfile, ProfileError> {
+    state.backend_profiles().get(&id)
+}
+
+#[tauri::command]
+pub fn create_backend_profile(profile: BackendProfile, state: State<'_, AppState>) -> Result<BackendProfile, ProfileError> {
+    state.backend_profiles().create(profile)
+}
+
+#[tauri::command]
+pub fn update_backend_profile(profile: BackendProfile, state: State<'_, AppState>) -> Result<BackendProfile, ProfileError> {
+    state.backend_profiles().update(profile)
+}
+
+#[tauri::command]
+pub fn delete_backend_profile(id: String, state: State<'_, AppState>) -> Result<(), ProfileError> {
+    let was_active = {
+        let mut profiles = state.backend_profiles();
+        let was_active = profiles.active().is_some_and(|profile| profile.id == id);
+        profiles.delete(&id)?;
+        was_active
+    };
+    state.tunnels().stop(&id);
+    // Native commands must not keep talking to a backend that no longer has a profile
+    if was_active {
+        state.set_backend_url(DEFAULT_BACKEND_URL.to_string());
+    }
     Ok(())
 }
 
+#[tauri::command]
+pub async fn activate_backend_profile(
+    id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BackendProfile, ProfileError> {
+    // Native commands that talk to the backend use this profile's URL from now on; the active
+    // profile only changes once its tunnel is up and the backend passed the version handshake
+    let profile = state.backend_profiles().get(&id)?;
+    let url = match profile.tunnel {
+        // The tunnel keeps its local port while it reconnects
+        Some(_) => start_tunnel(&app, &state, &profile.id).map_err(ProfileError::Tunnel)?.borrow().local_url.clone(),
+        None => profile.url.clone(),
+    };
+    let was_active = state.backend_profiles().active().is_some_and(|active| active.id == id);
+
+    // A backend that does not answer yet is judged by the monitor once it does
+    let headers = profile.header_map(&state.vault());
+    let check = health::check(&url, health::clamp_timeout(profile.health_timeout()), headers).await;
+    let activated = match check.compatibility.as_ref().map(|compatibility| compatibility.ensure()) {
+        Some(Err(e)) => Err(ProfileError::Incompatible(e)),
+        _ => state.backend_profiles().activate(&id),
+    };
+    let profile = match activated {
+        Ok(profile) => profile,
+        Err(e) => {
+            if !was_active {
+                state.tunnels().stop(&id);
+            }
+            return Err(e);
+        }
+    };
+    state.tunnels().stop_others(&profile.id);
+    state.set_backend_url(url.clone());
+    monitor::report(&app, &url, &check);
+    Ok(profile)
+}
+
+/// Start the profile's SSH tunnel, or keep the running one, reporting its state as events
+fn start_tunnel(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    profile_id: &str,
+) -> Result<tokio::sync::watch::Receiver<TunnelStatus>, TunnelError> {
+    let profile = state
+        .backend_profiles()
+        .get(profile_id)
+        .map_err(|_| TunnelError::NotConfigured { profile_id: profile_id.to_string() })?;
+    let app = app.clone();
+    state.tunnels().start(&profile, &state.vault(), move |status| {
+        let state = app.state::<AppState>();
+        // Have the health monitor look again now rather than after its backoff
+        if status.state == TunnelState::Connected && state.get_backend_url() == status.local_url {
+            state.backend_changed.notify_one();
+        }
+        if let Err(e) = app.emit(tunnel::TUNNEL_STATE_EVENT, status) {
+            eprintln!("Failed to report SSH tunnel state: {}", e);
+        }
+    })
+}
+
+/// Open the profile's SSH tunnel and wait until it is connected; its `localUrl` reaches the backend
+#[tauri::command]
+pub async fn open_backend_tunnel(
+    profile_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<TunnelStatus, TunnelError> {
+    let status = start_tunnel(&app, &state, &profile_id)?;
+    tunnel::wait_connected(status, tunnel::OPEN_TIMEOUT).await
+}
+
+#[tauri::command]
+pub fn close_backend_tunnel(profile_id: String, state: State<'_, AppState>) -> bool {
+    state.tunnels().stop(&profile_id)
+}
+
+#[tauri::command]
+pub fn list_backend_tunnels(state: State<'_, AppState>) -> Vec<TunnelStatus> {
+    state.tunnels().list()
+}
+
+#[tauri::command]
+pub fn get_vault_status(state: State<'_, AppState>) -> VaultStatus {
+    state.vault().status()
+}
+
+/// Creates the vault with this passphrase if there is none yet
+#[tauri::command]
+pub async fn unlock_vault(passphrase: String, state: State<'_, AppState>) -> Result<VaultStatus, VaultError> {
+    let passphrase = Zeroizing::new(passphrase);
+    let path = state.vault().path()?;
+    let target = path.clone();
+    let opened = tokio::task::spawn_blocking(move || vault::open(&target, &passphrase))
+        .await
+        .map_err(|e| VaultError::io(&path, e))??;
+    let mut vault = state.vault();
+    vault.unlock(opened);
+    Ok(vault.status())
+}
+
+#[tauri::command]
+pub fn lock_vault(state: State<'_, AppState>) -> VaultStatus {
+    let mut vault = state.vault();
+    vault.lock();
+    vault.status()
+}
+
+/// Store a secret; profiles use it as `{{secret:name}}` in a header value
+#[tauri::command]
+pub fn set_secret(name: String, value: String, state: State<'_, AppState>) -> Result<VaultStatus, VaultError> {
+    let mut vault = state.vault();
+    vault.set(&name, Zeroizing::new(value))?;
+    Ok(vault.status())
+}
+
+#[tauri::command]
+pub fn delete_secret(name: String, state: State<'_, AppState>) -> Result<VaultStatus, VaultError> {
+    let mut vault = state.vault();
+    vault.delete(&name)?;
+    Ok(vault.status())
+}
+
+/// Re-encrypt the vault under a new passphrase; leaves it unlocked
+#[tauri::command]
+pub async fn rotate_vault_passphrase(
+    current: String,
+    new_passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<VaultStatus, VaultError> {
+    let (current, new_passphrase) = (Zeroizing::new(current), Zeroizing::new(new_passphrase));
+    // Held throughout, so a secret stored meanwhile cannot be lost or left under the old key
+    tokio::task::block_in_place(|| {
+        let mut vault = state.vault();
+        vault.rotate(&current, &new_passphrase)?;
+        Ok(vault.status())
+    })
+}
+
+#[tauri::command]
+pub async fn api_list_projects(state: State<'_, AppState>) -> Result<Vec<Project>, ApiError> {
+    ApiClient::for_active(&state)?.list_projects().await
+}
+
+#[tauri::command]
+pub async fn api_get_project(id: String, state: State<'_, AppState>) -> Result<Project, ApiError> {
+    ApiClient::for_active(&state)?.get_project(&id).await
+}
+
+#[tauri::command]
+pub async fn api_get_chapter(id: String, state: State<'_, AppState>) -> Result<Chapter, ApiError> {
+    ApiClient::for_active(&state)?.get_chapter(&id).await
+}
+
+#[tauri::command]
+pub async fn api_update_segment(
+    id: String,
+    update: SegmentUpdate,
+    state: State<'_, AppState>,
+) -> Result<Segment, ApiError> {
+    ApiClient::for_active(&state)?.update_segment(&id, &update).await
+}
+
+#[tauri::command]
+pub async fn api_freeze_segment(id: String, freeze: bool, state: State<'_, AppState>) -> Result<Segment, ApiError> {
+    ApiClient::for_active(&state)?.freeze_segment(&id, freeze).await
+}
+
+#[tauri::command]
+pub async fn api_list_speakers(state: State<'_, AppState>) -> Result<Vec<Speaker>, ApiError> {
+    ApiClient::for_active(&state)?.list_speakers().await
+}
+
+#[tauri::command]
+pub async fn api_generate_segment(segment_id: String, state: State<'_, AppState>) -> Result<SegmentQueued, ApiError> {
+    ApiClient::for_active(&state)?.generate_segment(&segment_id).await
+}
+
+#[tauri::command]
+pub async fn api_generate_chapter(
+    request: GenerateChapter,
+    state: State<'_, AppState>,
+) -> Result<ChapterGenerationStarted, ApiError> {
+    ApiClient::for_active(&state)?.generate_chapter(&request).await
+}
+
+#[tauri::command]
+pub async fn api_list_tts_jobs(
+    filter: Option<JobFilter>,
+    state: State<'_, AppState>,
+) -> Result<JobList<TtsJob>, ApiError> {
+    ApiClient::for_active(&state)?.list_tts_jobs(&filter.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn api_get_tts_job(id: String, state: State<'_, AppState>) -> Result<TtsJob, ApiError> {
+    ApiClient::for_active(&state)?.get_tts_job(&id).await
+}
+
+#[tauri::command]
+pub async fn api_cancel_tts_job(id: String, state: State<'_, AppState>) -> Result<JobCancelled, ApiError> {
+    ApiClient::for_active(&state)?.cancel_tts_job(&id).await
+}
+
+#[tauri::command]
+pub async fn api_resume_tts_job(id: String, state: State<'_, AppState>) -> Result<TtsJob, ApiError> {
+    ApiClient::for_active(&state)?.resume_tts_job(&id).await
+}
+
+#[tauri::command]
+pub async fn api_analyze_segment(
+    segment_id: String,
+    engines: Option<QualityEngines>,
+    state: State<'_, AppState>,
+) -> Result<QualityJobCreated, ApiError> {
+    ApiClient::for_active(&state)?.analyze_segment(&segment_id, &engines.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn api_analyze_chapter(
+    chapter_id: String,
+    engines: Option<QualityEngines>,
+    state: State<'_, AppState>,
+) -> Result<QualityJobCreated, ApiError> {
+    ApiClient::for_active(&state)?.analyze_chapter(&chapter_id, &engines.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn api_list_quality_jobs(
+    filter: Option<JobFilter>,
+    state: State<'_, AppState>,
+) -> Result<JobList<QualityJob>, ApiError> {
+    ApiClient::for_active(&state)?.list_quality_jobs(&filter.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn api_cancel_quality_job(id: String, state: State<'_, AppState>) -> Result<Message, ApiError> {
+    ApiClient::for_active(&state)?.cancel_quality_job(&id).await
+}
+
+#[tauri::command]
+pub async fn api_start_export(request: ExportRequest, state: State<'_, AppState>) -> Result<ExportStarted, ApiError> {
+    ApiClient::for_active(&state)?.start_export(&request).await
+}
+
+#[tauri::command]
+pub async fn api_get_export_progress(job_id: String, state: State<'_, AppState>) -> Result<ExportProgress, ApiError> {
+    ApiClient::for_active(&state)?.export_progress(&job_id).await
+}
+
+#[tauri::command]
+pub async fn api_cancel_export(job_id: String, state: State<'_, AppState>) -> Result<Message, ApiError> {
+    ApiClient::for_active(&state)?.cancel_export(&job_id).await
+}
+
+#[tauri::command]
+pub async fn api_list_pronunciation_rules(
+    filter: Option<RuleFilter>,
+    state: State<'_, AppState>,
+) -> Result<RuleList, ApiError> {
+    ApiClient::for_active(&state)?.list_pronunciation_rules(&filter.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub async fn api_create_pronunciation_rule(
+    rule: NewPronunciationRule,
+    state: State<'_, AppState>,
+) -> Result<PronunciationRule, ApiError> {
+    ApiClient::for_active(&state)?.create_pronunciation_rule(&rule).await
+}
+
+#[tauri::command]
+pub async fn api_update_pronunciation_rule(
+    id: String,
+    update: PronunciationRuleUpdate,
+    state: State<'_, AppState>,
+) -> Result<PronunciationRule, ApiError> {
+    ApiClient::for_active(&state)?.update_pronunciation_rule(&id, &update).await
+}
+
+#[tauri::command]
+pub async fn api_delete_pronunciation_rule(id: String, state: State<'_, AppState>) -> Result<Message, ApiError> {
+    ApiClient::for_active(&state)?.delete_pronunciation_rule(&id).await
+}
+
+#[tauri::command]
+pub fn list_export_presets(state: State<'_, AppState>) -> Vec<ExportPreset> {
+    state.export_presets().list()
+}
+
+#[tauri::command]
+pub fn get_export_preset(id: String, state: State<'_, AppState>) -> Result<ExportPreset, PresetError> {
+    state.export_presets().get(&id)
+}
+
+#[tauri::command]
+pub fn create_export_preset(preset: ExportPreset, state: State<'_, AppState>) -> Result<ExportPreset, PresetError> {
+    state.export_presets().create(preset)
+}
+
+#[tauri::command]
+pub fn update_export_preset(preset: ExportPreset, state: State<'_, AppState>) -> Result<ExportPreset, PresetError> {
+    state.export_presets().update(preset)
+}
+
+#[tauri::command]
+pub fn delete_export_preset(id: String, state: State<'_, AppState>) -> Result<(), PresetError> {
+    state.export_presets().delete(&id)
+}
+
+#[tauri::command]
+pub fn export_export_presets(
+    path: String,
+    ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<usize, PresetError> {
+    // Share presets as a JSON file; defaults to all user presets
+    state.export_presets().export(Path::new(&path), ids.as_deref())
+}
+
+#[tauri::command]
+pub fn import_export_presets(path: String, state: State<'_, AppState>) -> Result<Vec<ExportPreset>, PresetError> {
+    state.export_presets().import(Path::new(&path))
+}
+
 #[tauri::command]
 pub fn get_app_info() -> AppInfo {
     AppInfo {
-        version: env!("CARGO_PKG_VERSION").to_string(),
+        version: compat::CLIENT_VERSION.to_string(),
+        api_version: compat::CLIENT_API_VERSION,
         name: env!("CARGO_PKG_NAME").to_string(),
         platform: std::env::consts::OS.to_string(),
         arch: std::env::consts::ARCH.to_string(),
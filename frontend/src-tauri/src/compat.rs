@@ -0,0 +1,241 @@
+//! Version handshake between this app and a backend
+//!
+//! The desktop app and the backend image are released separately. A backend
+//! reports its release and the range of API revisions it serves in `/health`;
+//! this app speaks `CLIENT_API_VERSION` and carries a table of backend releases
+//! with known problems. Backends before 1.2.0 report no API range,
+//! so the table decides for them.
+
+use std::fmt;
+
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use crate::error::write_coded;
+use crate::health::BackendHealth;
+
+/// This app's release
+pub const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// API revision this app speaks
+pub const CLIENT_API_VERSION: u32 = 1;
+
+/// What to do about a backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    Compatible,
+    /// The backend is newer than this app understands
+    UpgradeClient,
+    /// The backend lacks what this app needs
+    UpgradeBackend,
+}
+
+/// A row of the compatibility table
+struct Rule {
+    /// Backend releases the row applies to
+    backend: &'static str,
+    verdict: Verdict,
+    /// Refuse to connect rather than warn
+    blocking: bool,
+    note: &'static str,
+}
+
+/// Known backend releases, first match wins; the rows cover every release
+/// before 1.2.0, as those report no API range
+const RULES: &[Rule] = &[
+    Rule {
+        backend: "<1.0.0",
+        verdict: Verdict::UpgradeBackend,
+        blocking: true,
+        note: "Backends before 1.0.0 serve the jobs and engine routes under their old paths",
+    },
+    Rule {
+        backend: ">=1.0.0, <1.1.2",
+        verdict: Verdict::UpgradeBackend,
+        blocking: false,
+        note: "Backends before 1.1.2 send errors without codes, so their messages are not translated",
+    },
+    Rule {
+        backend: ">=1.1.2, <1.2.0",
+        verdict: Verdict::Compatible,
+        blocking: false,
+        note: "Backend serves API v1 but predates the API range",
+    },
+];
+
+/// API revisions a backend serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Compatibility {
+    pub client_version: String,
+    pub client_api_version: u32,
+    pub backend_version: String,
+    /// `None` for backends that do not report it
+    pub backend_api: Option<ApiRange>,
+    pub verdict: Verdict,
+    /// The app should not connect; otherwise a verdict other than `Compatible` is a warning
+    pub blocking: bool,
+    pub message: String,
+}
+
+impl Compatibility {
+    fn new(health: &BackendHealth, backend_api: Option<ApiRange>, verdict: Verdict, blocking: bool, message: String) -> Self {
+        Compatibility {
+            client_version: CLIENT_VERSION.to_string(),
+            client_api_version: CLIENT_API_VERSION,
+            backend_version: health.version.clone(),
+            backend_api,
+            verdict,
+            blocking,
+            message,
+        }
+    }
+}
+
+impl Compatibility {
+    /// `Err` if the app must not talk to this backend
+    pub fn ensure(&self) -> Result<(), Incompatible> {
+        if !self.blocking {
+            return Ok(());
+        }
+        Err(Incompatible {
+            backend_version: self.backend_version.clone(),
+            verdict: self.verdict,
+            message: self.message.clone(),
+        })
+    }
+}
+
+/// A backend the compatibility check refuses
+#[derive(Debug, Clone)]
+pub struct Incompatible {
+    pub backend_version: String,
+    pub verdict: Verdict,
+    pub message: String,
+}
+
+impl fmt::Display for Incompatible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: [(&str, &dyn fmt::Display); 3] =
+            [("backendVersion", &self.backend_version), ("clientVersion", &CLIENT_VERSION), ("reason", &self.message)];
+        match self.verdict {
+            Verdict::UpgradeClient => write_coded(f, "COMPAT_UPGRADE_CLIENT", &params),
+            _ => write_coded(f, "COMPAT_UPGRADE_BACKEND", &params),
+        }
+    }
+}
+
+impl std::error::Error for Incompatible {}
+
+/// Judge the backend that sent `health`
+pub fn evaluate(health: &BackendHealth) -> Compatibility {
+    let backend_api = health.api_version.map(|max| ApiRange { min: health.min_api_version.unwrap_or(max), max });
+
+    // A reported API range is authoritative
+    if let Some(api) = backend_api {
+        if CLIENT_API_VERSION > api.max {
+            let message = format!("Backend {} serves API up to v{}, this app needs v{}", health.version, api.max, CLIENT_API_VERSION);
+            return Compatibility::new(health, backend_api, Verdict::UpgradeBackend, true, message);
+        }
+        if CLIENT_API_VERSION < api.min {
+            let message = format!("Backend {} no longer serves API v{} (oldest is v{})", health.version, CLIENT_API_VERSION, api.min);
+            return Compatibility::new(health, backend_api, Verdict::UpgradeClient, true, message);
+        }
+    }
+
+    let version = match Version::parse(health.version.trim_start_matches('v')) {
+        Ok(version) => version,
+        Err(_) if backend_api.is_some() => {
+            let message = format!("Backend serves API v{}", CLIENT_API_VERSION);
+            return Compatibility::new(health, backend_api, Verdict::Compatible, false, message);
+        }
+        Err(e) => {
+            let message = format!("Backend version \"{}\" is not understood: {}", health.version, e);
+            return Compatibility::new(health, backend_api, Verdict::UpgradeBackend, false, message);
+        }
+    };
+    let rule = RULES.iter().find(|rule| VersionReq::parse(rule.backend).is_ok_and(|req| req.matches(&version)));
+    if let Some(rule) = rule {
+        return Compatibility::new(health, backend_api, rule.verdict, rule.blocking, rule.note.to_string());
+    }
+
+    // Releases since 1.2.0 report their range; without one, only the major version can tell
+    if backend_api.is_none() {
+        let client_major = Version::parse(CLIENT_VERSION).map_or(0, |client| client.major);
+        if version.major != client_major {
+            let verdict = if version.major > client_major { Verdict::UpgradeClient } else { Verdict::UpgradeBackend };
+            let message = format!("Backend {} is not supported by this app ({})", health.version, CLIENT_VERSION);
+            return Compatibility::new(health, backend_api, verdict, true, message);
+        }
+        let message = format!("Backend {} does not report its API range; some features may not work", health.version);
+        return Compatibility::new(health, backend_api, Verdict::UpgradeBackend, false, message);
+    }
+    let message = format!("Backend {} is compatible with this app ({})", health.version, CLIENT_VERSION);
+    Compatibility::new(health, backend_api, Verdict::Compatible, false, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(version: &str, api: Option<(u32, u32)>) -> BackendHealth {
+        let mut health = serde_json::json!({ "status": "ok", "version": version, "timestamp": "" });
+        if let Some((min, max)) = api {
+            health["minApiVersion"] = min.into();
+            health["apiVersion"] = max.into();
+        }
+        serde_json::from_value(health).unwrap()
+    }
+
+    fn verdict(version: &str, api: Option<(u32, u32)>) -> (Verdict, bool) {
+        let compatibility = evaluate(&health(version, api));
+        (compatibility.verdict, compatibility.blocking)
+    }
+
+    #[test]
+    fn every_release_without_a_range_matches_a_rule() {
+        for version in ["0.3.0", "1.0.0", "1.1.1", "1.1.2", "1.1.9"] {
+            let version = Version::parse(version).unwrap();
+            assert!(
+                RULES.iter().any(|rule| VersionReq::parse(rule.backend).unwrap().matches(&version)),
+                "{} is not covered",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn judges_releases_before_the_api_range_by_the_table() {
+        assert_eq!(verdict("0.3.0", None), (Verdict::UpgradeBackend, true));
+        assert_eq!(verdict("1.1.1", None), (Verdict::UpgradeBackend, false));
+        assert_eq!(verdict("1.1.2", None), (Verdict::Compatible, false));
+    }
+
+    #[test]
+    fn a_missing_range_from_a_later_release_is_not_taken_as_compatible() {
+        assert_eq!(verdict("1.2.0", None), (Verdict::UpgradeBackend, false));
+        assert_eq!(verdict("2.0.0", None), (Verdict::UpgradeClient, true));
+    }
+
+    #[test]
+    fn a_reported_range_decides() {
+        assert_eq!(verdict("1.2.0", Some((1, 1))), (Verdict::Compatible, false));
+        assert_eq!(verdict("2.0.0", Some((1, 2))), (Verdict::Compatible, false));
+        assert_eq!(verdict("2.0.0", Some((2, 2))), (Verdict::UpgradeClient, true));
+        assert_eq!(verdict("1.2.0", Some((0, 0))), (Verdict::UpgradeBackend, true));
+    }
+
+    #[test]
+    fn refusals_carry_a_coded_message() {
+        let error = evaluate(&health("2.0.0", Some((2, 2)))).ensure().unwrap_err();
+        assert!(error.to_string().starts_with("[COMPAT_UPGRADE_CLIENT]backendVersion:2.0.0;"), "{}", error);
+        assert!(evaluate(&health("1.1.1", None)).ensure().is_ok());
+    }
+}
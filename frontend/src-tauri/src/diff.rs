@@ -0,0 +1,868 @@
+//! Chapter/segment level diff and three-way merge of projects
+//!
+//! Chapters and segments are matched by ID, so reordering them or moving a
+//! segment to another chapter shows up as a move rather than a delete plus an
+//! add. The merge works field by field against the common ancestor: a field
+//! changed on one side takes that side's value, a field changed differently on
+//! both sides is a conflict. Unresolved conflicts keep "ours"; running the
+//! merge again with resolutions for the reported conflict IDs applies them.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::project::{Chapter, ProjectData, ProjectError, Segment, SCHEMA_VERSION};
+
+/// Pseudo field carrying a segment's chapter through the merge
+const CHAPTER_KEY: &str = "@chapter";
+
+/// One difference between two versions of a project
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "kind")]
+pub enum Change {
+    /// Name, description or another top-level field
+    ProjectChanged { field: String, before: Value, after: Value },
+    SettingChanged { key: String, before: Value, after: Value },
+    ChapterAdded { chapter_id: String, title: String, index: usize },
+    ChapterRemoved { chapter_id: String, title: String },
+    ChapterRenamed { chapter_id: String, before: String, after: String },
+    ChapterMoved { chapter_id: String, from: usize, to: usize },
+    SegmentAdded { chapter_id: String, segment_id: String, index: usize, text: String },
+    SegmentRemoved { chapter_id: String, segment_id: String, text: String },
+    /// Moved within its chapter or to another one
+    SegmentMoved { segment_id: String, from_chapter: String, to_chapter: String, from: usize, to: usize },
+    TextChanged { chapter_id: String, segment_id: String, before: String, after: String },
+    SpeakerChanged { chapter_id: String, segment_id: String, before: Option<String>, after: Option<String> },
+    /// Any other segment field: engine, model, language, pause, audio, status...
+    SegmentChanged { chapter_id: String, segment_id: String, fields: Vec<String> },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSummary {
+    pub project_fields: usize,
+    pub settings: usize,
+    pub chapters_added: usize,
+    pub chapters_removed: usize,
+    pub chapters_renamed: usize,
+    pub chapters_moved: usize,
+    pub segments_added: usize,
+    pub segments_removed: usize,
+    pub segments_moved: usize,
+    pub text_changed: usize,
+    pub speaker_changed: usize,
+    pub segments_changed: usize,
+}
+
+impl DiffSummary {
+    fn count(changes: &[Change]) -> Self {
+        let mut summary = Self::default();
+        for change in changes {
+            let counter = match change {
+                Change::ProjectChanged { .. } => &mut summary.project_fields,
+                Change::SettingChanged { .. } => &mut summary.settings,
+                Change::ChapterAdded { .. } => &mut summary.chapters_added,
+                Change::ChapterRemoved { .. } => &mut summary.chapters_removed,
+                Change::ChapterRenamed { .. } => &mut summary.chapters_renamed,
+                Change::ChapterMoved { .. } => &mut summary.chapters_moved,
+                Change::SegmentAdded { .. } => &mut summary.segments_added,
+                Change::SegmentRemoved { .. } => &mut summary.segments_removed,
+                Change::SegmentMoved { .. } => &mut summary.segments_moved,
+                Change::TextChanged { .. } => &mut summary.text_changed,
+                Change::SpeakerChanged { .. } => &mut summary.speaker_changed,
+                Change::SegmentChanged { .. } => &mut summary.segments_changed,
+            };
+            *counter += 1;
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectDiff {
+    pub changes: Vec<Change>,
+    pub summary: DiffSummary,
+}
+
+fn object(value: impl Serialize) -> Map<String, Value> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Top-level fields other than the lists and settings, which are compared item by item
+fn project_fields(project: &ProjectData) -> Map<String, Value> {
+    let mut fields = object(project);
+    for key in ["schemaVersion", "chapters", "speakers", "pronunciationRules", "settings"] {
+        fields.remove(key);
+    }
+    fields
+}
+
+/// A chapter's own fields, without identity, position or segments
+fn chapter_fields(chapter: &Chapter) -> Map<String, Value> {
+    let mut fields = object(chapter);
+    for key in ["id", "orderIndex", "segments"] {
+        fields.remove(key);
+    }
+    fields
+}
+
+/// A segment's content, without identity and position
+fn segment_fields(segment: &Segment) -> Map<String, Value> {
+    let mut fields = object(segment);
+    fields.remove("id");
+    fields.remove("orderIndex");
+    fields
+}
+
+fn keys<'a>(maps: &[Option<&'a Map<String, Value>>]) -> Vec<&'a String> {
+    let mut seen = HashSet::new();
+    maps.iter()
+        .flatten()
+        .flat_map(|map| map.keys())
+        .filter(|key| seen.insert(*key))
+        .collect()
+}
+
+/// Where a segment sits in one version of the project
+struct Location<'a> {
+    chapter: &'a str,
+    index: usize,
+    segment: &'a Segment,
+}
+
+fn locate(project: &ProjectData) -> HashMap<&str, Location<'_>> {
+    project
+        .chapters
+        .iter()
+        .flat_map(|chapter| {
+            chapter.segments.iter().enumerate().map(move |(index, segment)| {
+                (segment.id.as_str(), Location { chapter: &chapter.id, index, segment })
+            })
+        })
+        .collect()
+}
+
+/// IDs of `after` that kept their order relative to `before`; the others moved
+///
+/// Both lists hold the same IDs. The kept ones are a longest increasing run of
+/// old positions, so one item dragged elsewhere counts as one move.
+fn in_order<'a>(before: &[&'a str], after: &[&'a str]) -> HashSet<&'a str> {
+    let position: HashMap<&str, usize> = before.iter().enumerate().map(|(index, id)| (*id, index)).collect();
+    let items: Vec<(&str, usize)> = after.iter().filter_map(|id| Some((*id, *position.get(id)?))).collect();
+
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; items.len()];
+    for (index, &(_, old)) in items.iter().enumerate() {
+        let slot = tails.partition_point(|&tail| items[tail].1 < old);
+        if slot > 0 {
+            previous[index] = Some(tails[slot - 1]);
+        }
+        if slot == tails.len() {
+            tails.push(index);
+        } else {
+            tails[slot] = index;
+        }
+    }
+
+    let mut kept = HashSet::new();
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        kept.insert(items[index].0);
+        next = previous[index];
+    }
+    kept
+}
+
+/// Everything that changed from `before` to `after`
+pub fn diff(before: &ProjectData, after: &ProjectData) -> ProjectDiff {
+    let mut changes = Vec::new();
+
+    let (old, new) = (project_fields(before), project_fields(after));
+    for key in keys(&[Some(&old), Some(&new)]) {
+        if old.get(key) != new.get(key) {
+            changes.push(Change::ProjectChanged {
+                field: key.clone(),
+                before: old.get(key).cloned().unwrap_or_default(),
+                after: new.get(key).cloned().unwrap_or_default(),
+            });
+        }
+    }
+    let (old, new) = (object(&before.settings), object(&after.settings));
+    for key in keys(&[Some(&old), Some(&new)]) {
+        if old.get(key) != new.get(key) {
+            changes.push(Change::SettingChanged {
+                key: key.clone(),
+                before: old.get(key).cloned().unwrap_or_default(),
+                after: new.get(key).cloned().unwrap_or_default(),
+            });
+        }
+    }
+
+    let old_chapters: HashMap<&str, (usize, &Chapter)> =
+        before.chapters.iter().enumerate().map(|(index, chapter)| (chapter.id.as_str(), (index, chapter))).collect();
+    let new_chapters: HashMap<&str, (usize, &Chapter)> =
+        after.chapters.iter().enumerate().map(|(index, chapter)| (chapter.id.as_str(), (index, chapter))).collect();
+    for chapter in &before.chapters {
+        if !new_chapters.contains_key(chapter.id.as_str()) {
+            changes.push(Change::ChapterRemoved { chapter_id: chapter.id.clone(), title: chapter.title.clone() });
+        }
+    }
+    let common_old: Vec<&str> = before.chapters.iter().map(|c| c.id.as_str()).filter(|id| new_chapters.contains_key(id)).collect();
+    let common_new: Vec<&str> = after.chapters.iter().map(|c| c.id.as_str()).filter(|id| old_chapters.contains_key(id)).collect();
+    let kept = in_order(&common_old, &common_new);
+    for (index, chapter) in after.chapters.iter().enumerate() {
+        let chapter_id = chapter.id.clone();
+        let Some(&(old_index, previous)) = old_chapters.get(chapter.id.as_str()) else {
+            changes.push(Change::ChapterAdded { chapter_id, title: chapter.title.clone(), index });
+            continue;
+        };
+        if !kept.contains(chapter.id.as_str()) {
+            changes.push(Change::ChapterMoved { chapter_id: chapter_id.clone(), from: old_index, to: index });
+        }
+        if previous.title != chapter.title {
+            changes.push(Change::ChapterRenamed { chapter_id, before: previous.title.clone(), after: chapter.title.clone() });
+        }
+    }
+
+    let old_segments = locate(before);
+    let new_segments = locate(after);
+    for chapter in &before.chapters {
+        for segment in &chapter.segments {
+            if !new_segments.contains_key(segment.id.as_str()) {
+                changes.push(Change::SegmentRemoved {
+                    chapter_id: chapter.id.clone(),
+                    segment_id: segment.id.clone(),
+                    text: segment.text.clone(),
+                });
+            }
+        }
+    }
+    for chapter in &after.chapters {
+        // Order only matters among segments that were in this chapter before too
+        let stayed = |segment: &&Segment| {
+            old_segments.get(segment.id.as_str()).is_some_and(|old| old.chapter == chapter.id)
+        };
+        let common_new: Vec<&str> = chapter.segments.iter().filter(stayed).map(|s| s.id.as_str()).collect();
+        let common_old: Vec<&str> = old_chapters
+            .get(chapter.id.as_str())
+            .map(|(_, previous)| {
+                previous
+                    .segments
+                    .iter()
+                    .filter(|s| new_segments.get(s.id.as_str()).is_some_and(|new| new.chapter == chapter.id))
+                    .map(|s| s.id.as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let kept = in_order(&common_old, &common_new);
+
+        for (index, segment) in chapter.segments.iter().enumerate() {
+            let (chapter_id, segment_id) = (chapter.id.clone(), segment.id.clone());
+            let Some(old) = old_segments.get(segment.id.as_str()) else {
+                changes.push(Change::SegmentAdded { chapter_id, segment_id, index, text: segment.text.clone() });
+                continue;
+            };
+            if old.chapter != chapter.id || !kept.contains(segment.id.as_str()) {
+                changes.push(Change::SegmentMoved {
+                    segment_id: segment_id.clone(),
+                    from_chapter: old.chapter.to_string(),
+                    to_chapter: chapter_id.clone(),
+                    from: old.index,
+                    to: index,
+                });
+            }
+            let previous = old.segment;
+            if previous.text != segment.text {
+                changes.push(Change::TextChanged {
+                    chapter_id: chapter_id.clone(),
+                    segment_id: segment_id.clone(),
+                    before: previous.text.clone(),
+                    after: segment.text.clone(),
+                });
+            }
+            if previous.tts_speaker_name != segment.tts_speaker_name {
+                changes.push(Change::SpeakerChanged {
+                    chapter_id: chapter_id.clone(),
+                    segment_id: segment_id.clone(),
+                    before: previous.tts_speaker_name.clone(),
+                    after: segment.tts_speaker_name.clone(),
+                });
+            }
+            let (old_fields, new_fields) = (segment_fields(previous), segment_fields(segment));
+            let fields: Vec<String> = keys(&[Some(&old_fields), Some(&new_fields)])
+                .into_iter()
+                .filter(|key| !matches!(key.as_str(), "text" | "ttsSpeakerName"))
+                .filter(|key| old_fields.get(*key) != new_fields.get(*key))
+                .cloned()
+                .collect();
+            if !fields.is_empty() {
+                changes.push(Change::SegmentChanged { chapter_id, segment_id, fields });
+            }
+        }
+    }
+
+    let summary = DiffSummary::count(&changes);
+    ProjectDiff { changes, summary }
+}
+
+/// Which version a conflict is settled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    Base,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    /// Both sides changed the same field to different values
+    BothModified,
+    /// One side deleted what the other changed
+    ModifiedAndDeleted,
+    /// Both sides reordered the same list differently
+    Order,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    /// Key to send a resolution back under, e.g. `segment:<id>:text`
+    pub id: String,
+    pub kind: ConflictKind,
+    pub chapter_id: Option<String>,
+    pub segment_id: Option<String>,
+    /// The field, list or object the conflict is about
+    pub field: String,
+    /// `None` where that version does not have it
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+    /// The resolution that was applied; unresolved conflicts keep ours
+    pub resolution: Option<Resolution>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub project: ProjectData,
+    pub conflicts: Vec<Conflict>,
+    /// Conflicts without a resolution
+    pub unresolved: usize,
+    /// What each side changed relative to the base
+    pub ours: ProjectDiff,
+    pub theirs: ProjectDiff,
+}
+
+/// Where a conflict is, for the report
+struct Site {
+    id: String,
+    chapter_id: Option<String>,
+    segment_id: Option<String>,
+    field: String,
+}
+
+impl Site {
+    fn new(id: String, field: &str) -> Self {
+        Self { id, chapter_id: None, segment_id: None, field: field.to_string() }
+    }
+
+    fn chapter(mut self, chapter_id: &str) -> Self {
+        self.chapter_id = Some(chapter_id.to_string());
+        self
+    }
+
+    fn segment(mut self, segment_id: &str) -> Self {
+        self.segment_id = Some(segment_id.to_string());
+        self
+    }
+}
+
+/// One value in each of the three versions
+#[derive(Clone, Copy)]
+struct Sides<'a, T> {
+    base: Option<&'a T>,
+    ours: Option<&'a T>,
+    theirs: Option<&'a T>,
+}
+
+struct Merger<'a> {
+    resolutions: &'a HashMap<String, Resolution>,
+    conflicts: Vec<Conflict>,
+}
+
+impl Merger<'_> {
+    fn conflict(&mut self, site: Site, kind: ConflictKind, sides: Sides<'_, Value>) -> Resolution {
+        let resolution = self.resolutions.get(&site.id).copied();
+        self.conflicts.push(Conflict {
+            id: site.id,
+            kind,
+            chapter_id: site.chapter_id,
+            segment_id: site.segment_id,
+            field: site.field,
+            base: sides.base.cloned(),
+            ours: sides.ours.cloned(),
+            theirs: sides.theirs.cloned(),
+            resolution,
+        });
+        resolution.unwrap_or(Resolution::Ours)
+    }
+
+    /// Three-way merge of one value; `None` is absent or deleted
+    fn value(&mut self, site: Site, sides: Sides<'_, Value>) -> Option<Value> {
+        let Sides { base, ours, theirs } = sides;
+        if ours == theirs || theirs == base {
+            return ours.cloned();
+        }
+        if ours == base {
+            return theirs.cloned();
+        }
+        let kind = if ours.is_none() || theirs.is_none() { ConflictKind::ModifiedAndDeleted } else { ConflictKind::BothModified };
+        match self.conflict(site, kind, sides) {
+            Resolution::Ours => ours.cloned(),
+            Resolution::Theirs => theirs.cloned(),
+            Resolution::Base => base.cloned(),
+        }
+    }
+
+    /// Merge two objects key by key
+    fn fields(&mut self, site: impl Fn(&str) -> Site, sides: Sides<'_, Map<String, Value>>) -> Map<String, Value> {
+        let mut merged = Map::new();
+        for key in keys(&[sides.base, sides.ours, sides.theirs]) {
+            let values = Sides {
+                base: sides.base.and_then(|map| map.get(key)),
+                ours: sides.ours.and_then(|map| map.get(key)),
+                theirs: sides.theirs.and_then(|map| map.get(key)),
+            };
+            if let Some(value) = self.value(site(key), values) {
+                merged.insert(key.clone(), value);
+            }
+        }
+        merged
+    }
+
+    /// An object that may have been added or deleted: merged field by field if
+    /// all three have it, as a whole otherwise
+    fn object(&mut self, site: impl Fn(&str) -> Site, whole: Site, sides: Sides<'_, Map<String, Value>>) -> Option<Map<String, Value>> {
+        if let Sides { base: Some(_), ours: Some(_), theirs: Some(_) } = sides {
+            return Some(self.fields(site, sides));
+        }
+        self.whole(whole, sides)
+    }
+
+    /// Three-way merge of an object as one value
+    fn whole(&mut self, whole: Site, sides: Sides<'_, Map<String, Value>>) -> Option<Map<String, Value>> {
+        let wrap = |map: Option<&Map<String, Value>>| map.map(|map| Value::Object(map.clone()));
+        let (base, ours, theirs) = (wrap(sides.base), wrap(sides.ours), wrap(sides.theirs));
+        match self.value(whole, Sides { base: base.as_ref(), ours: ours.as_ref(), theirs: theirs.as_ref() }) {
+            Some(Value::Object(map)) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Final order of the `kept` IDs
+    ///
+    /// The order of whichever side reordered the list wins; additions are placed
+    /// after the item they follow in the version that has them.
+    fn order(&mut self, site: Site, base: &[&str], ours: &[&str], theirs: &[&str], kept: &HashSet<&str>) -> Vec<String> {
+        let relative = |list: &[&str], other: &[&str]| -> Vec<String> {
+            let other: HashSet<&str> = other.iter().copied().collect();
+            list.iter().filter(|id| other.contains(*id)).map(|id| id.to_string()).collect()
+        };
+        let ours_changed = relative(ours, base) != relative(base, ours);
+        let theirs_changed = relative(theirs, base) != relative(base, theirs);
+
+        let primary = match (ours_changed, theirs_changed) {
+            (_, false) => ours,
+            (false, true) => theirs,
+            (true, true) => {
+                let shared: Vec<&str> = base.iter().copied().filter(|id| ours.contains(id) && theirs.contains(id)).collect();
+                if relative(ours, &shared) == relative(theirs, &shared) {
+                    ours
+                } else {
+                    let list = |ids: &[&str]| Value::from(ids.iter().map(|id| id.to_string()).collect::<Vec<_>>());
+                    let (b, o, t) = (list(base), list(ours), list(theirs));
+                    let sides = Sides { base: Some(&b), ours: Some(&o), theirs: Some(&t) };
+                    match self.conflict(site, ConflictKind::Order, sides) {
+                        Resolution::Ours => ours,
+                        Resolution::Theirs => theirs,
+                        Resolution::Base => base,
+                    }
+                }
+            }
+        };
+
+        let mut result: Vec<&str> = Vec::with_capacity(kept.len());
+        let mut placed: HashSet<&str> = HashSet::new();
+        for &id in primary {
+            if kept.contains(id) && placed.insert(id) {
+                result.push(id);
+            }
+        }
+        for list in [ours, theirs, base] {
+            for (index, &id) in list.iter().enumerate() {
+                if !kept.contains(id) || placed.contains(id) {
+                    continue;
+                }
+                let after = list[..index].iter().rev().find(|previous| placed.contains(*previous));
+                let at = after
+                    .and_then(|previous| result.iter().position(|placed| placed == previous))
+                    .map_or(0, |position| position + 1);
+                result.insert(at, id);
+                placed.insert(id);
+            }
+        }
+        result.into_iter().map(str::to_string).collect()
+    }
+
+    /// Speakers or pronunciation rules: whole items keyed by `id`
+    fn list(&mut self, prefix: &str, field: &str, sides: Sides<'_, Vec<Value>>) -> Vec<Value> {
+        let by_id = |list: Option<&'_ Vec<Value>>| -> Vec<(String, Value)> {
+            list.into_iter()
+                .flatten()
+                .filter_map(|item| Some((item.get("id")?.as_str()?.to_string(), item.clone())))
+                .collect()
+        };
+        let (base, ours, theirs) = (by_id(sides.base), by_id(sides.ours), by_id(sides.theirs));
+        let find = |list: &'_ [(String, Value)], id: &str| -> Option<Value> {
+            list.iter().find(|(found, _)| found == id).map(|(_, item)| item.clone())
+        };
+
+        let mut merged: HashMap<String, Value> = HashMap::new();
+        for (id, _) in ours.iter().chain(&theirs).chain(&base) {
+            if merged.contains_key(id) {
+                continue;
+            }
+            let (b, o, t) = (find(&base, id), find(&ours, id), find(&theirs, id));
+            let site = Site::new(format!("{}:{}", prefix, id), field);
+            if let Some(item) = self.value(site, Sides { base: b.as_ref(), ours: o.as_ref(), theirs: t.as_ref() }) {
+                merged.insert(id.clone(), item);
+            }
+        }
+
+        let ids = |list: &'_ [(String, Value)]| -> Vec<String> { list.iter().map(|(id, _)| id.clone()).collect() };
+        let (base_ids, ours_ids, theirs_ids) = (ids(&base), ids(&ours), ids(&theirs));
+        let kept: HashSet<&str> = merged.keys().map(String::as_str).collect();
+        let order = self.order(
+            Site::new(format!("{}:order", field), field),
+            &refs(&base_ids),
+            &refs(&ours_ids),
+            &refs(&theirs_ids),
+            &kept,
+        );
+        order.into_iter().filter_map(|id| merged.get(&id).cloned()).collect()
+    }
+}
+
+fn refs(list: &[String]) -> Vec<&str> {
+    list.iter().map(String::as_str).collect()
+}
+
+/// `[base, ours, theirs]` as `Sides`
+fn view(values: &[Option<Map<String, Value>>; 3]) -> Sides<'_, Map<String, Value>> {
+    Sides { base: values[0].as_ref(), ours: values[1].as_ref(), theirs: values[2].as_ref() }
+}
+
+/// A chapter's content including its segments, for whole-chapter add/delete checks
+fn chapter_content(chapter: &Chapter) -> Map<String, Value> {
+    let mut fields = chapter_fields(chapter);
+    fields.insert("segments".to_string(), serde_json::to_value(&chapter.segments).unwrap_or_default());
+    fields
+}
+
+/// Merge `ours` and `theirs`, which both started from `base`
+pub fn merge(
+    base: &ProjectData,
+    ours: &ProjectData,
+    theirs: &ProjectData,
+    resolutions: &HashMap<String, Resolution>,
+) -> Result<MergeResult, ProjectError> {
+    let mut merger = Merger { resolutions, conflicts: Vec::new() };
+    let versions = [base, ours, theirs];
+
+    let top = versions.map(|project| Some(project_fields(project)));
+    let mut root = merger.fields(|key| Site::new(format!("project:{}", key), key), view(&top));
+    let settings = versions.map(|project| Some(object(&project.settings)));
+    let settings = merger.fields(|key| Site::new(format!("settings:{}", key), key), view(&settings));
+    root.insert("settings".to_string(), Value::Object(settings));
+
+    let list_sides = |list: fn(&ProjectData) -> Value| {
+        versions.map(|project| match list(project) {
+            Value::Array(items) => items,
+            _ => Vec::new(),
+        })
+    };
+    let speakers = list_sides(|project| serde_json::to_value(&project.speakers).unwrap_or_default());
+    let speakers = merger.list(
+        "speaker",
+        "speakers",
+        Sides { base: Some(&speakers[0]), ours: Some(&speakers[1]), theirs: Some(&speakers[2]) },
+    );
+    root.insert("speakers".to_string(), Value::Array(speakers));
+    let rules = list_sides(|project| serde_json::to_value(&project.pronunciation_rules).unwrap_or_default());
+    let rules = merger.list(
+        "rule",
+        "pronunciationRules",
+        Sides { base: Some(&rules[0]), ours: Some(&rules[1]), theirs: Some(&rules[2]) },
+    );
+    root.insert("pronunciationRules".to_string(), Value::Array(rules));
+
+    // Chapters: existence and own fields
+    let chapter_ids: Vec<Vec<&str>> = versions
+        .iter()
+        .map(|project| project.chapters.iter().map(|chapter| chapter.id.as_str()).collect())
+        .collect();
+    let find_chapter = |project: &ProjectData, id: &str| project.chapters.iter().find(|chapter| chapter.id == id).cloned();
+    let mut chapters: HashMap<String, Map<String, Value>> = HashMap::new();
+    for id in [&chapter_ids[1], &chapter_ids[2], &chapter_ids[0]].into_iter().flatten() {
+        if chapters.contains_key(*id) {
+            continue;
+        }
+        let found = versions.map(|project| find_chapter(project, id));
+        let fields = [0, 1, 2].map(|side| found[side].as_ref().map(chapter_fields));
+        let content = [0, 1, 2].map(|side| found[side].as_ref().map(chapter_content));
+        // Field by field when all three have it; otherwise add or delete the whole chapter, segments included
+        let merged = if fields.iter().all(Option::is_some) {
+            Some(merger.fields(|key| Site::new(format!("chapter:{}:{}", id, key), key).chapter(id), view(&fields)))
+        } else {
+            merger
+                .whole(Site::new(format!("chapter:{}", id), "chapter").chapter(id), view(&content))
+                .map(|mut map| {
+                    map.remove("segments");
+                    map
+                })
+        };
+        if let Some(merged) = merged {
+            chapters.insert(id.to_string(), merged);
+        }
+    }
+    let kept: HashSet<&str> = chapters.keys().map(String::as_str).collect();
+    let chapter_order = merger.order(
+        Site::new("chapters:order".to_string(), "chapters"),
+        &chapter_ids[0],
+        &chapter_ids[1],
+        &chapter_ids[2],
+        &kept,
+    );
+
+    // Segments: existence, fields and the chapter they belong to
+    let locations = versions.map(locate);
+    let segment_sides = |id: &str| -> [Option<Map<String, Value>>; 3] {
+        [0, 1, 2].map(|side| {
+            locations[side].get(id).map(|location| {
+                let mut fields = segment_fields(location.segment);
+                fields.insert(CHAPTER_KEY.to_string(), Value::String(location.chapter.to_string()));
+                fields
+            })
+        })
+    };
+    let mut segments: HashMap<String, Map<String, Value>> = HashMap::new();
+    let all_segments = [ours, theirs, base]
+        .into_iter()
+        .flat_map(|project| project.chapters.iter().flat_map(|chapter| &chapter.segments));
+    for segment in all_segments {
+        let id = segment.id.as_str();
+        if segments.contains_key(id) {
+            continue;
+        }
+        let found = segment_sides(id);
+        let chapter = found.iter().flatten().next().and_then(|fields| fields.get(CHAPTER_KEY)?.as_str().map(str::to_string)).unwrap_or_default();
+        let merged = merger.object(
+            |key| {
+                let field = if key == CHAPTER_KEY { "chapterId" } else { key };
+                Site::new(format!("segment:{}:{}", id, field), field).chapter(&chapter).segment(id)
+            },
+            Site::new(format!("segment:{}", id), "segment").chapter(&chapter).segment(id),
+            view(&found),
+        );
+        if let Some(merged) = merged {
+            segments.insert(id.to_string(), merged);
+        }
+    }
+
+    let mut by_chapter: HashMap<String, HashSet<&str>> = HashMap::new();
+    for (id, fields) in &segments {
+        if let Some(chapter) = fields.get(CHAPTER_KEY).and_then(Value::as_str) {
+            by_chapter.entry(chapter.to_string()).or_default().insert(id.as_str());
+        }
+    }
+
+    let mut chapter_values = Vec::with_capacity(chapter_order.len());
+    for (index, chapter_id) in chapter_order.iter().enumerate() {
+        let in_chapter = |project: &ProjectData| -> Vec<String> {
+            project
+                .chapters
+                .iter()
+                .find(|chapter| chapter.id == *chapter_id)
+                .map(|chapter| chapter.segments.iter().map(|segment| segment.id.clone()).collect())
+                .unwrap_or_default()
+        };
+        let lists = versions.map(in_chapter);
+        let kept = by_chapter.remove(chapter_id).unwrap_or_default();
+        let order = merger.order(
+            Site::new(format!("chapter:{}:order", chapter_id), "segments").chapter(chapter_id),
+            &refs(&lists[0]),
+            &refs(&lists[1]),
+            &refs(&lists[2]),
+            &kept,
+        );
+
+        let segment_values: Vec<Value> = order
+            .iter()
+            .enumerate()
+            .filter_map(|(position, id)| {
+                let mut fields = segments.get(id)?.clone();
+                fields.remove(CHAPTER_KEY);
+                fields.insert("id".to_string(), Value::String(id.clone()));
+                fields.insert("orderIndex".to_string(), position.into());
+                Some(Value::Object(fields))
+            })
+            .collect();
+        let mut fields = chapters.get(chapter_id).cloned().unwrap_or_default();
+        fields.insert("id".to_string(), Value::String(chapter_id.clone()));
+        fields.insert("orderIndex".to_string(), index.into());
+        fields.insert("segments".to_string(), Value::Array(segment_values));
+        chapter_values.push(Value::Object(fields));
+    }
+    // Segments left in `by_chapter` belonged to chapters the merge dropped; they go with them
+
+    root.insert("chapters".to_string(), Value::Array(chapter_values));
+    root.insert("schemaVersion".to_string(), SCHEMA_VERSION.into());
+    let (project, _) = ProjectData::from_value(Value::Object(root))?;
+
+    let unresolved = merger.conflicts.iter().filter(|conflict| conflict.resolution.is_none()).count();
+    Ok(MergeResult {
+        project,
+        conflicts: merger.conflicts,
+        unresolved,
+        ours: diff(base, ours),
+        theirs: diff(base, theirs),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn project(chapters: Value) -> ProjectData {
+        ProjectData::from_value(json!({ "name": "Book", "chapters": chapters })).unwrap().0
+    }
+
+    fn base() -> ProjectData {
+        project(json!([
+            { "id": "c1", "title": "One", "segments": [
+                { "id": "s1", "text": "first" },
+                { "id": "s2", "text": "second" },
+                { "id": "s3", "text": "third" },
+            ] },
+            { "id": "c2", "title": "Two", "segments": [{ "id": "s4", "text": "fourth" }] },
+        ]))
+    }
+
+    fn segment_ids(project: &ProjectData, chapter: usize) -> Vec<&str> {
+        project.chapters[chapter].segments.iter().map(|segment| segment.id.as_str()).collect()
+    }
+
+    #[test]
+    fn both_sides_editing_a_field_conflict() {
+        let base = base();
+        let (mut ours, mut theirs) = (base.clone(), base.clone());
+        ours.chapters[0].segments[1].text = "ours".to_string();
+        theirs.chapters[0].segments[1].text = "theirs".to_string();
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new()).unwrap();
+        assert_eq!(result.unresolved, 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.id, "segment:s2:text");
+        assert_eq!(conflict.kind, ConflictKind::BothModified);
+        assert_eq!(conflict.base, Some(json!("second")));
+        assert_eq!(result.project.chapters[0].segments[1].text, "ours");
+
+        let resolutions = HashMap::from([("segment:s2:text".to_string(), Resolution::Theirs)]);
+        let result = merge(&base, &ours, &theirs, &resolutions).unwrap();
+        assert_eq!(result.unresolved, 0);
+        assert_eq!(result.project.chapters[0].segments[1].text, "theirs");
+    }
+
+    #[test]
+    fn move_and_edit_combine() {
+        let base = base();
+        let (mut ours, mut theirs) = (base.clone(), base.clone());
+        let moved = ours.chapters[0].segments.remove(0);
+        ours.chapters[1].segments.push(moved);
+        theirs.chapters[0].segments[0].text = "edited".to_string();
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new()).unwrap();
+        assert!(result.conflicts.is_empty());
+        assert_eq!(segment_ids(&result.project, 0), ["s2", "s3"]);
+        assert_eq!(segment_ids(&result.project, 1), ["s4", "s1"]);
+        assert_eq!(result.project.chapters[1].segments[1].text, "edited");
+    }
+
+    #[test]
+    fn different_reorders_conflict() {
+        let base = base();
+        let (mut ours, mut theirs) = (base.clone(), base.clone());
+        ours.chapters[0].segments.swap(0, 1);
+        theirs.chapters[0].segments.swap(1, 2);
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new()).unwrap();
+        assert_eq!(result.unresolved, 1);
+        assert_eq!(result.conflicts[0].id, "chapter:c1:order");
+        assert_eq!(result.conflicts[0].kind, ConflictKind::Order);
+        assert_eq!(segment_ids(&result.project, 0), ["s2", "s1", "s3"]);
+
+        let resolutions = HashMap::from([("chapter:c1:order".to_string(), Resolution::Theirs)]);
+        let result = merge(&base, &ours, &theirs, &resolutions).unwrap();
+        assert_eq!(segment_ids(&result.project, 0), ["s1", "s3", "s2"]);
+    }
+
+    #[test]
+    fn same_reorder_on_both_sides_is_not_a_conflict() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.chapters.swap(0, 1);
+        ours.chapters[1].segments.reverse();
+        let theirs = ours.clone();
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new()).unwrap();
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.project.chapters[0].id, "c2");
+        assert_eq!(segment_ids(&result.project, 1), ["s3", "s2", "s1"]);
+    }
+
+    #[test]
+    fn order_places_additions_after_their_predecessor() {
+        let mut merger = Merger { resolutions: &HashMap::new(), conflicts: Vec::new() };
+        let kept = HashSet::from(["a", "b", "c", "x", "y"]);
+        let order = merger.order(
+            Site::new("order".to_string(), "list"),
+            &["a", "b", "c"],
+            &["c", "a", "b", "x"],
+            &["a", "y", "b", "c"],
+            &kept,
+        );
+        assert!(merger.conflicts.is_empty());
+        assert_eq!(order, ["c", "a", "y", "b", "x"]);
+    }
+
+    #[test]
+    fn chapter_deleted_while_edited_conflicts_as_a_whole() {
+        let base = base();
+        let (mut ours, mut theirs) = (base.clone(), base.clone());
+        ours.chapters.remove(1);
+        theirs.chapters[1].segments[0].text = "edited".to_string();
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new()).unwrap();
+        let conflict = result.conflicts.iter().find(|conflict| conflict.id == "chapter:c2").unwrap();
+        assert_eq!(conflict.kind, ConflictKind::ModifiedAndDeleted);
+        assert_eq!(result.project.chapters.len(), 1);
+    }
+}
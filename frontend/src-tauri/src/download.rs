@@ -0,0 +1,344 @@
+//! Streaming download of finished exports from the backend straight to disk
+//!
+//! The body is written to `<path>.part` and renamed into place once its size and
+//! checksum check out. A leftover `.part` file (from a dropped connection or an
+//! earlier run) is resumed with an HTTP Range request.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::atomic;
+use crate::error::write_coded;
+use crate::serialize_as_display;
+
+/// Attempts after the first one when the connection drops mid-transfer
+const MAX_RETRIES: u32 = 5;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// A response that sends nothing for this long counts as dropped and is resumed
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// Minimum time between two progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Errors raised while downloading an export
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The backend rejected the request with an ApplicationError, passed through as-is
+    Backend { detail: String },
+    /// The backend answered with an unexpected status and no error detail
+    HttpStatus { url: String, status: u16 },
+    /// The backend could not be reached, or the connection kept dropping
+    Request { url: String, message: String },
+    /// The finished file is not as long as the backend announced
+    SizeMismatch { expected: u64, actual: u64 },
+    /// The finished file does not hash to the expected SHA-256
+    ChecksumMismatch { expected: String, actual: String },
+    /// Reading or writing the target file failed
+    Io { path: String, message: String },
+}
+
+impl DownloadError {
+    fn io(path: &Path, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.display().to_string(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Backend { detail } => f.write_str(detail),
+            Self::HttpStatus { url, status } => {
+                write_coded(f, "DOWNLOAD_HTTP_STATUS", &[("url", url), ("status", status)])
+            }
+            Self::Request { url, message } => {
+                write_coded(f, "DOWNLOAD_REQUEST_FAILED", &[("url", url), ("error", message)])
+            }
+            Self::SizeMismatch { expected, actual } => write_coded(
+                f,
+                "DOWNLOAD_SIZE_MISMATCH",
+                &[("expected", expected), ("actual", actual)],
+            ),
+            Self::ChecksumMismatch { expected, actual } => write_coded(
+                f,
+                "DOWNLOAD_CHECKSUM_MISMATCH",
+                &[("expected", expected), ("actual", actual)],
+            ),
+            Self::Io { path, message } => {
+                write_coded(f, "DOWNLOAD_IO_FAILED", &[("path", path), ("error", message)])
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+serialize_as_display!(DownloadError);
+
+/// Progress reported to the frontend over a Tauri channel
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "event", content = "data")]
+pub enum DownloadEvent {
+    /// A response started streaming; `resumed_from` is non-zero for a Range request
+    Started { total_bytes: Option<u64>, resumed_from: u64 },
+    Progress { downloaded_bytes: u64, total_bytes: Option<u64> },
+    /// The connection dropped and the download continues from `downloaded_bytes`
+    Retrying { attempt: u32, downloaded_bytes: u64 },
+    /// All bytes are on disk, size and checksum are being checked
+    Verifying,
+    Finished { path: String, total_bytes: u64, sha256: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadSummary {
+    pub path: String,
+    pub total_bytes: u64,
+    pub sha256: String,
+    /// Whether bytes from an earlier, interrupted attempt were reused
+    pub resumed: bool,
+}
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Pull the ApplicationError detail out of an error response, if there is one
+async fn backend_error(url: &str, response: reqwest::Response) -> DownloadError {
+    let status = response.status().as_u16();
+    let detail = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("detail")?.as_str().map(str::to_string));
+    match detail {
+        Some(detail) => DownloadError::Backend { detail },
+        None => DownloadError::HttpStatus { url: url.to_string(), status },
+    }
+}
+
+/// Start offset and total length from `Content-Range: bytes <start>-<end>/<total>`
+fn parse_content_range(response: &reqwest::Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.parse().ok()?;
+    Some((start, total.parse().ok()))
+}
+
+async fn sha256_file(path: &Path) -> Result<String, DownloadError> {
+    let mut file = fs::File::open(path).await.map_err(|e| DownloadError::io(path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = file.read(&mut buffer).await.map_err(|e| DownloadError::io(path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Outcome of streaming one response into the `.part` file
+enum Attempt {
+    Complete { total: Option<u64> },
+    Dropped { message: String },
+}
+
+/// Download `url` to `path`, resuming a previous `.part` file when the server allows it
+///
+/// `headers` go with every request; `request_timeout` bounds the wait for each response to start.
+pub async fn download_to_file(
+    url: &str,
+    headers: HeaderMap,
+    request_timeout: Duration,
+    path: &Path,
+    expected_sha256: Option<&str>,
+    on_event: impl Fn(DownloadEvent),
+) -> Result<DownloadSummary, DownloadError> {
+    let part = sidecar(path, ".part");
+    // ETag/Last-Modified of the response the .part file came from, sent back as If-Range
+    let validator_path = sidecar(path, ".part.validator");
+    let request_error = |e: reqwest::Error| DownloadError::Request { url: url.to_string(), message: e.to_string() };
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .default_headers(headers)
+        .build()
+        .map_err(request_error)?;
+
+    let mut resumed = false;
+    let mut attempt = 0;
+    let total = loop {
+        let offset = fs::metadata(&part).await.map(|meta| meta.len()).unwrap_or(0);
+        let validator = fs::read_to_string(&validator_path).await.ok();
+
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+            if let Some(validator) = &validator {
+                request = request.header(IF_RANGE, validator.as_str());
+            }
+        }
+
+        let outcome = match tokio::time::timeout(request_timeout, request.send()).await {
+            Ok(Ok(response)) => {
+                stream_response(response, url, offset, &part, &validator_path, &mut resumed, &on_event).await?
+            }
+            Ok(Err(e)) => Attempt::Dropped { message: e.to_string() },
+            Err(_) => Attempt::Dropped { message: format!("no response within {} s", request_timeout.as_secs()) },
+        };
+
+        match outcome {
+            Attempt::Complete { total } => break total,
+            Attempt::Dropped { message } if attempt >= MAX_RETRIES => {
+                return Err(DownloadError::Request { url: url.to_string(), message });
+            }
+            Attempt::Dropped { .. } => {
+                attempt += 1;
+                let downloaded_bytes = fs::metadata(&part).await.map(|meta| meta.len()).unwrap_or(0);
+                on_event(DownloadEvent::Retrying { attempt, downloaded_bytes });
+                tokio::time::sleep(Duration::from_millis(500 * 2u64.pow(attempt - 1))).await;
+            }
+        }
+    };
+
+    on_event(DownloadEvent::Verifying);
+    let actual = fs::metadata(&part).await.map_err(|e| DownloadError::io(&part, e))?.len();
+    if let Some(expected) = total.filter(|expected| *expected != actual) {
+        discard(&part, &validator_path).await;
+        return Err(DownloadError::SizeMismatch { expected, actual });
+    }
+
+    let sha256 = sha256_file(&part).await?;
+    if let Some(expected) = expected_sha256.filter(|expected| !expected.eq_ignore_ascii_case(&sha256)) {
+        discard(&part, &validator_path).await;
+        return Err(DownloadError::ChecksumMismatch { expected: expected.to_string(), actual: sha256 });
+    }
+
+    fs::rename(&part, path).await.map_err(|e| DownloadError::io(path, e))?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        atomic::sync_dir(dir).map_err(|e| DownloadError::io(dir, e))?;
+    }
+    let _ = fs::remove_file(&validator_path).await;
+
+    let summary = DownloadSummary {
+        path: path.display().to_string(),
+        total_bytes: actual,
+        sha256,
+        resumed,
+    };
+    on_event(DownloadEvent::Finished {
+        path: summary.path.clone(),
+        total_bytes: summary.total_bytes,
+        sha256: summary.sha256.clone(),
+    });
+    Ok(summary)
+}
+
+async fn discard(part: &Path, validator_path: &Path) {
+    let _ = fs::remove_file(part).await;
+    let _ = fs::remove_file(validator_path).await;
+}
+
+async fn stream_response(
+    response: reqwest::Response,
+    url: &str,
+    offset: u64,
+    part: &Path,
+    validator_path: &Path,
+    resumed: &mut bool,
+    on_event: &impl Fn(DownloadEvent),
+) -> Result<Attempt, DownloadError> {
+    let status = response.status();
+    if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file no longer matches what the server has, start over
+        discard(part, validator_path).await;
+        return Ok(Attempt::Dropped { message: status.to_string() });
+    }
+    if !status.is_success() {
+        return Err(backend_error(url, response).await);
+    }
+
+    // A 200 means the server ignored the Range (or If-Range failed): the body is the whole file
+    let (start, total) = match (status, parse_content_range(&response)) {
+        (StatusCode::PARTIAL_CONTENT, Some((start, total))) if start == offset => (start, total),
+        (StatusCode::PARTIAL_CONTENT, _) => {
+            discard(part, validator_path).await;
+            return Ok(Attempt::Dropped { message: "unexpected Content-Range".to_string() });
+        }
+        _ => (0, response.content_length()),
+    };
+    *resumed |= start > 0;
+
+    let validator = response.headers().get(ETAG).or_else(|| response.headers().get(LAST_MODIFIED));
+    match validator.and_then(|value| value.to_str().ok()) {
+        Some(validator) => fs::write(validator_path, validator).await.map_err(|e| DownloadError::io(validator_path, e))?,
+        None => {
+            let _ = fs::remove_file(validator_path).await;
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(start > 0)
+        .truncate(start == 0)
+        .open(part)
+        .await
+        .map_err(|e| DownloadError::io(part, e))?;
+
+    on_event(DownloadEvent::Started { total_bytes: total, resumed_from: start });
+
+    let mut downloaded = start;
+    let mut last_event = Instant::now();
+    let mut stream = response.bytes_stream();
+    let mut dropped = None;
+    loop {
+        // A backend that stalls without closing the connection would otherwise hang here forever
+        let chunk = match tokio::time::timeout(STALL_TIMEOUT, stream.next()).await {
+            Ok(Some(Ok(chunk))) => chunk,
+            Ok(Some(Err(e))) => {
+                dropped = Some(e.to_string());
+                break;
+            }
+            Ok(None) => break,
+            Err(_) => {
+                dropped = Some(format!("no data for {} s", STALL_TIMEOUT.as_secs()));
+                break;
+            }
+        };
+        file.write_all(&chunk).await.map_err(|e| DownloadError::io(part, e))?;
+        downloaded += chunk.len() as u64;
+        if last_event.elapsed() >= PROGRESS_INTERVAL {
+            on_event(DownloadEvent::Progress { downloaded_bytes: downloaded, total_bytes: total });
+            last_event = Instant::now();
+        }
+    }
+
+    // Whatever arrived is kept on disk so the next attempt can resume from it
+    file.flush().await.map_err(|e| DownloadError::io(part, e))?;
+    file.sync_all().await.map_err(|e| DownloadError::io(part, e))?;
+    on_event(DownloadEvent::Progress { downloaded_bytes: downloaded, total_bytes: total });
+
+    Ok(match dropped {
+        Some(message) => Attempt::Dropped { message },
+        None if total.is_some_and(|total| downloaded < total) => Attempt::Dropped {
+            message: "connection closed before the end of the file".to_string(),
+        },
+        None => Attempt::Complete { total },
+    })
+}
@@ -0,0 +1,33 @@
+use std::fmt;
+
+/// Write an error in the backend's structured format: `[ERROR_CODE]param1:value1;param2:value2`
+///
+/// Native errors use the same shape as the FastAPI `ApplicationError`, so the
+/// frontend can run them through `translateBackendError` unchanged.
+pub fn write_coded(
+    f: &mut fmt::Formatter<'_>,
+    code: &str,
+    params: &[(&str, &dyn fmt::Display)],
+) -> fmt::Result {
+    write!(f, "[{}]", code)?;
+    for (index, (key, value)) in params.iter().enumerate() {
+        if index > 0 {
+            f.write_str(";")?;
+        }
+        write!(f, "{}:{}", key, value)?;
+    }
+    Ok(())
+}
+
+/// Serialize an error type as its `Display` string so Tauri hands the
+/// structured `[ERROR_CODE]...` message to the frontend.
+#[macro_export]
+macro_rules! serialize_as_display {
+    ($ty:ty) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+    };
+}
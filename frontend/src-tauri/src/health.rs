@@ -0,0 +1,188 @@
+//! Health check of a backend via its `/health` endpoint
+//!
+//! A failed check is a result, not an error: the caller gets the class of
+//! failure (name resolution, refused connection, TLS, timeout, HTTP status)
+//! so the start page can tell "not started" from "wrong address" or
+//! "certificate problem".
+
+use std::error::Error as _;
+use std::io;
+use std::time::{Duration, Instant};
+
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::compat::{self, Compatibility};
+
+/// Timeout when the caller does not give one
+pub const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+const MIN_TIMEOUT: Duration = Duration::from_millis(100);
+const MAX_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The `/health` payload (backend `HealthResponse`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendHealth {
+    /// ok, degraded or down
+    pub status: String,
+    pub version: String,
+    pub timestamp: String,
+    #[serde(default = "default_true")]
+    pub database: bool,
+    #[serde(default)]
+    pub tts_engines: Vec<String>,
+    /// Processing a long-running operation
+    #[serde(default)]
+    pub busy: bool,
+    #[serde(default)]
+    pub active_jobs: u32,
+    /// Newest API revision served; not reported before 1.2.0
+    #[serde(default)]
+    pub api_version: Option<u32>,
+    #[serde(default)]
+    pub min_api_version: Option<u32>,
+    #[serde(default)]
+    pub has_tts_engine: Option<bool>,
+    #[serde(default)]
+    pub has_text_engine: Option<bool>,
+    #[serde(default)]
+    pub has_stt_engine: Option<bool>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Why a health check failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthErrorClass {
+    /// The backend URL does not parse
+    InvalidUrl,
+    /// The host name does not resolve
+    Dns,
+    /// Nothing listens on the port
+    Refused,
+    /// TLS handshake or certificate failure
+    Tls,
+    Timeout,
+    /// Any other network failure, e.g. a reset connection
+    Connection,
+    /// The backend answered with a non-2xx status
+    HttpStatus,
+    /// A 2xx answer that is not a health payload, e.g. another service on the port
+    InvalidResponse,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthError {
+    pub class: HealthErrorClass,
+    /// Set for `HttpStatus`
+    pub http_status: Option<u16>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    /// The `/health` URL that was checked
+    pub url: String,
+    /// Answered with a valid health payload
+    pub reachable: bool,
+    /// Round trip until the full response was read; `None` if there was no response
+    pub latency_ms: Option<u64>,
+    pub health: Option<BackendHealth>,
+    /// Whether this app can work with the backend; set when it is reachable
+    pub compatibility: Option<Compatibility>,
+    pub error: Option<HealthError>,
+}
+
+/// A requested timeout, or the default, kept within sensible bounds
+pub fn clamp_timeout(timeout: Option<Duration>) -> Duration {
+    timeout.unwrap_or(DEFAULT_HEALTH_TIMEOUT).clamp(MIN_TIMEOUT, MAX_TIMEOUT)
+}
+
+/// Sort a reqwest error into a class by walking its source chain
+fn classify(error: &reqwest::Error) -> HealthErrorClass {
+    if error.is_builder() {
+        return HealthErrorClass::InvalidUrl;
+    }
+    if error.is_timeout() {
+        return HealthErrorClass::Timeout;
+    }
+    let mut source = error.source();
+    while let Some(cause) = source {
+        if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+            match io_error.kind() {
+                io::ErrorKind::ConnectionRefused => return HealthErrorClass::Refused,
+                io::ErrorKind::TimedOut => return HealthErrorClass::Timeout,
+                _ => {}
+            }
+        }
+        // hyper and the TLS backends only describe these in their messages
+        let message = cause.to_string().to_ascii_lowercase();
+        if message.contains("dns error") || message.contains("failed to lookup address") {
+            return HealthErrorClass::Dns;
+        }
+        if message.contains("certificate") || message.contains("tls") || message.contains("ssl") || message.contains("handshake") {
+            return HealthErrorClass::Tls;
+        }
+        source = cause.source();
+    }
+    HealthErrorClass::Connection
+}
+
+/// Check the backend at `backend_url`, giving up after `timeout`; `headers` go with the request
+pub async fn check(backend_url: &str, timeout: Duration, headers: HeaderMap) -> HealthCheck {
+    let url = format!("{}/health", backend_url.trim_end_matches('/'));
+    let failed = |latency_ms: Option<u64>, class, http_status, message| HealthCheck {
+        url: url.clone(),
+        reachable: false,
+        latency_ms,
+        health: None,
+        compatibility: None,
+        error: Some(HealthError { class, http_status, message }),
+    };
+
+    let client = match reqwest::Client::builder()
+        .default_headers(headers)
+        .timeout(timeout)
+        .connect_timeout(timeout)
+        .build() {
+        Ok(client) => client,
+        Err(e) => return failed(None, HealthErrorClass::Connection, None, e.to_string()),
+    };
+    let started = Instant::now();
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => return failed(None, classify(&e), None, e.to_string()),
+    };
+    let status = response.status();
+    let body = response.bytes().await;
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => return failed(latency_ms, classify(&e), None, e.to_string()),
+    };
+
+    if !status.is_success() {
+        let detail = String::from_utf8_lossy(&body);
+        let message = match detail.trim() {
+            "" => status.to_string(),
+            detail => format!("{}: {}", status, detail.chars().take(200).collect::<String>()),
+        };
+        return failed(latency_ms, HealthErrorClass::HttpStatus, Some(status.as_u16()), message);
+    }
+    match serde_json::from_slice::<BackendHealth>(&body) {
+        Ok(health) => HealthCheck {
+            url: url.clone(),
+            reachable: true,
+            latency_ms,
+            compatibility: Some(compat::evaluate(&health)),
+            health: Some(health),
+            error: None,
+        },
+        Err(e) => failed(latency_ms, HealthErrorClass::InvalidResponse, None, e.to_string()),
+    }
+}
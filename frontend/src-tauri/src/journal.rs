@@ -0,0 +1,269 @@
+//! Autosave journal for unsaved project edits
+//!
+//! The frontend hands over the current project after each edit; a background
+//! task writes it to `$APPLOCALDATA/journal` every `AUTOSAVE_INTERVAL` when it
+//! has changed. Saving (or discarding) the project removes its journal, so any
+//! journal still there at startup holds work lost to a crash or kill.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::atomic::write_atomic;
+use crate::diff::{self, DiffSummary};
+use crate::project::{ProjectData, ProjectError};
+use crate::save;
+use crate::state::AppState;
+
+/// Directory inside the app local data directory
+pub const JOURNAL_DIR: &str = "journal";
+/// How often pending edits are written
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+const JOURNAL_VERSION: u32 = 1;
+
+/// One journal file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalFile {
+    pub version: u32,
+    /// `None` for a project that was never saved
+    pub project_path: Option<String>,
+    /// RFC 3339
+    pub saved_at: String,
+    /// Revision of the project file the edits started from
+    pub base_revision: Option<String>,
+    /// The project JSON as the frontend last sent it
+    pub content: String,
+}
+
+/// Edits pending for the journal, kept in `AppState`
+#[derive(Debug, Default)]
+pub struct Autosave {
+    dir: Option<PathBuf>,
+    /// Names the journal of a project without a path, unique per app run
+    session: String,
+    project_path: Option<String>,
+    content: Option<String>,
+    /// Bumped on every update; `written` is the generation last on disk
+    generation: u64,
+    written: u64,
+    /// Journals found at startup that have not been recovered or discarded
+    recoverable: Vec<(String, JournalFile)>,
+}
+
+/// Journal file name for a project: stable per path, per run for unsaved projects
+fn journal_id(session: &str, project_path: Option<&str>) -> String {
+    match project_path {
+        Some(path) => save::revision(path.as_bytes())[..16].to_string(),
+        None => format!("untitled-{}", session),
+    }
+}
+
+impl Autosave {
+    /// Use `dir` for journals and pick up the ones a previous run left behind
+    pub fn load(dir: PathBuf) -> Self {
+        let recoverable = scan(&dir);
+        Self {
+            dir: Some(dir),
+            session: uuid::Uuid::new_v4().to_string(),
+            recoverable,
+            ..Self::default()
+        }
+    }
+
+    fn current_id(&self) -> String {
+        journal_id(&self.session, self.project_path.as_deref())
+    }
+
+    fn journal_path(&self, id: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("{}.json", id)))
+    }
+
+    fn remove_journal(&self, id: &str) {
+        if let Some(path) = self.journal_path(id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Record the latest unsaved state of the open project
+    pub fn update(&mut self, project_path: Option<String>, content: String) {
+        if project_path != self.project_path {
+            // Save As or switching projects: the old journal no longer applies
+            self.remove_journal(&self.current_id());
+            self.project_path = project_path;
+        }
+        self.content = Some(content);
+        self.generation += 1;
+    }
+
+    /// Everything is on disk (or thrown away); drop the journal
+    pub fn clear(&mut self) {
+        self.remove_journal(&self.current_id());
+        self.content = None;
+        self.written = self.generation;
+    }
+
+    /// The project at `path` was saved
+    pub fn saved(&mut self, path: &str) {
+        if self.project_path.is_none() || self.project_path.as_deref() == Some(path) {
+            self.clear();
+            self.project_path = Some(path.to_string());
+        }
+    }
+
+    /// Whether there are unsaved edits to the project at `path`
+    pub fn has_unsaved(&self, path: &str) -> bool {
+        self.content.is_some() && self.project_path.as_deref() == Some(path)
+    }
+
+    /// What needs writing, if anything changed since the last write
+    fn pending(&self) -> Option<(u64, PathBuf, Option<String>, String)> {
+        if self.generation == self.written {
+            return None;
+        }
+        let content = self.content.clone()?;
+        let path = self.journal_path(&self.current_id())?;
+        Some((self.generation, path, self.project_path.clone(), content))
+    }
+
+    pub fn recoverable(&self) -> &[(String, JournalFile)] {
+        &self.recoverable
+    }
+
+    pub fn recoverable_journal(&self, id: &str) -> Option<&JournalFile> {
+        self.recoverable.iter().find(|(found, _)| found == id).map(|(_, journal)| journal)
+    }
+
+    fn take_recoverable(&mut self, id: &str) -> Option<JournalFile> {
+        let index = self.recoverable.iter().position(|(found, _)| found == id)?;
+        Some(self.recoverable.remove(index).1)
+    }
+
+    /// Make a leftover journal the open project's unsaved state
+    pub fn recover(&mut self, id: &str) -> Option<JournalFile> {
+        let journal = self.take_recoverable(id)?;
+        self.update(journal.project_path.clone(), journal.content.clone());
+        // An unsaved project's journal is renamed to this run's; a path keeps its file
+        if self.current_id() != id {
+            self.remove_journal(id);
+        }
+        Some(journal)
+    }
+
+    /// Throw a leftover journal away
+    pub fn discard(&mut self, id: &str) -> bool {
+        if self.take_recoverable(id).is_none() {
+            return false;
+        }
+        // Unless this run is journaling the same project right now
+        if self.current_id() != id || self.content.is_none() {
+            self.remove_journal(id);
+        }
+        true
+    }
+}
+
+/// Journals in `dir`; unreadable files are left alone
+fn scan(dir: &Path) -> Vec<(String, JournalFile)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut journals: Vec<(String, JournalFile)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let journal: JournalFile = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+            (journal.version <= JOURNAL_VERSION).then_some((id, journal))
+        })
+        .collect();
+    journals.sort_by(|a, b| b.1.saved_at.cmp(&a.1.saved_at));
+    journals
+}
+
+/// Write the journal whenever there are new edits; runs for the life of the app
+pub async fn run_autosave(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(AUTOSAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let state = app.state::<AppState>();
+        let Some((generation, path, project_path, content)) = state.autosave().pending() else {
+            continue;
+        };
+        let journal = JournalFile {
+            version: JOURNAL_VERSION,
+            base_revision: project_path.as_deref().and_then(|path| state.get_project_revision(path)),
+            project_path,
+            saved_at: chrono::Utc::now().to_rfc3339(),
+            content,
+        };
+        let written = serde_json::to_vec(&journal).ok().is_some_and(|data| {
+            path.parent().is_some_and(|dir| fs::create_dir_all(dir).is_ok()) && write_atomic(&path, &data).is_ok()
+        });
+        if written {
+            let mut autosave = state.autosave();
+            // A save or Save As in the meantime already dropped this journal; don't bring it back
+            if autosave.content.is_none() || autosave.journal_path(&autosave.current_id()).as_ref() != Some(&path) {
+                let _ = fs::remove_file(&path);
+            } else {
+                autosave.written = autosave.written.max(generation);
+            }
+        }
+    }
+}
+
+/// What recovering a journal would change compared to the file on disk
+fn summarize(recovered: &ProjectData, on_disk: Option<&ProjectData>) -> DiffSummary {
+    match on_disk {
+        Some(on_disk) => diff::diff(on_disk, recovered).summary,
+        // Nothing on disk: every chapter and segment is new
+        None => diff::diff(&ProjectData { chapters: Vec::new(), ..recovered.clone() }, recovered).summary,
+    }
+}
+
+/// A journal offered for recovery
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCandidate {
+    pub id: String,
+    pub project_path: Option<String>,
+    /// Project name from the journal, if it parses
+    pub name: Option<String>,
+    pub saved_at: String,
+    /// Whether the project file still exists
+    pub file_exists: bool,
+    /// The file changed after the journaled edits started, so recovering would drop those changes
+    pub file_changed: bool,
+    /// `None` when the journal content does not parse
+    pub summary: Option<DiffSummary>,
+}
+
+pub fn describe(id: &str, journal: &JournalFile) -> RecoveryCandidate {
+    let recovered = ProjectData::from_json(&journal.content).ok();
+    let disk = journal.project_path.as_deref().and_then(|path| fs::read_to_string(path).ok());
+    let on_disk = disk.as_deref().and_then(|text| ProjectData::from_json(text).ok());
+    let file_changed = match (&disk, &journal.base_revision) {
+        (Some(disk), Some(base)) => save::revision(disk.as_bytes()) != *base,
+        _ => false,
+    };
+    RecoveryCandidate {
+        id: id.to_string(),
+        project_path: journal.project_path.clone(),
+        name: recovered.as_ref().map(|project| project.name.clone()),
+        saved_at: journal.saved_at.clone(),
+        file_exists: disk.is_some(),
+        file_changed,
+        summary: recovered.as_ref().map(|project| summarize(project, on_disk.as_ref())),
+    }
+}
+
+/// The journaled project, checked against the schema
+pub fn parse(journal: &JournalFile) -> Result<(ProjectData, u32), ProjectError> {
+    ProjectData::parse(&journal.content)
+}
@@ -0,0 +1,153 @@
+//! Project files handed to the app on launch
+//!
+//! Paths come from the command line (`audiobook-maker book.abm`, which is also
+//! how Windows and Linux file associations start the app), from a second
+//! launch forwarded by the single-instance plugin, or from macOS "open with"
+//! events. The first launch's paths wait in `AppState` until the frontend asks
+//! for them; later ones are emitted as `OPEN_PROJECT_EVENT`.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::bundle::BUNDLE_EXTENSION;
+use crate::project::PROJECT_EXTENSION;
+use crate::state::AppState;
+
+/// Emitted with an `OpenRequest` when a project is opened from outside the app
+pub const OPEN_PROJECT_EVENT: &str = "project://open";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenKind {
+    /// A `.abm` project file, for `open_project_file`
+    Project,
+    /// A `.abmproj` bundle, for `import_project_bundle`
+    Bundle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenRequest {
+    /// Absolute path
+    pub path: String,
+    pub kind: OpenKind,
+}
+
+impl OpenRequest {
+    /// A request for `path` if it has one of the project extensions; relative paths are resolved against `cwd`
+    pub fn from_path(path: &Path, cwd: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let kind = match extension.as_str() {
+            PROJECT_EXTENSION => OpenKind::Project,
+            BUNDLE_EXTENSION => OpenKind::Bundle,
+            _ => return None,
+        };
+        let path = if path.is_absolute() { path.to_path_buf() } else { cwd.join(path) };
+        Some(Self { path: path.display().to_string(), kind })
+    }
+}
+
+/// Project files among the arguments of a launch; `args` includes the program name
+///
+/// Options are skipped so platform or debugging flags don't get opened as files,
+/// and `--` ends option parsing for file names starting with a dash.
+pub fn parse_args<I, S>(args: I, cwd: &Path) -> Vec<OpenRequest>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut options = true;
+    args.into_iter()
+        .skip(1)
+        .map(Into::into)
+        .filter(|arg| {
+            if options && arg == "--" {
+                options = false;
+                return false;
+            }
+            !(options && arg.starts_with('-'))
+        })
+        .filter_map(|arg| {
+            // Some desktop environments pass file URLs for `%U`
+            let path = match arg.strip_prefix("file://") {
+                Some(rest) => PathBuf::from(percent_decode(rest)),
+                None => PathBuf::from(arg),
+            };
+            OpenRequest::from_path(&path, cwd)
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes of a file URL path; invalid escapes are kept as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                index += 3;
+            }
+            None => {
+                out.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Open requests waiting for the frontend, kept in `AppState`
+#[derive(Debug, Default)]
+pub struct LaunchQueue {
+    /// Set once the frontend has collected the pending requests and listens for the event
+    ready: bool,
+    pending: Vec<OpenRequest>,
+}
+
+impl LaunchQueue {
+    /// Queue `requests` until the frontend is ready; returns the ones to emit right away
+    fn offer(&mut self, requests: Vec<OpenRequest>) -> Vec<OpenRequest> {
+        if self.ready {
+            return requests;
+        }
+        for request in requests {
+            if !self.pending.contains(&request) {
+                self.pending.push(request);
+            }
+        }
+        Vec::new()
+    }
+
+    /// Everything queued so far; later requests are emitted instead
+    pub fn take(&mut self) -> Vec<OpenRequest> {
+        self.ready = true;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Hand `requests` to the frontend: emitted if it is already listening, queued otherwise
+pub fn forward(app: &AppHandle, requests: Vec<OpenRequest>) {
+    let now = app.state::<AppState>().launch().offer(requests);
+    for request in now {
+        if let Err(e) = app.emit(OPEN_PROJECT_EVENT, &request) {
+            eprintln!("Failed to forward {}: {}", request.path, e);
+        }
+    }
+}
+
+/// Bring the main window to the front, e.g. when a second launch was redirected here
+pub fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
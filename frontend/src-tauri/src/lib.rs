@@ -1,14 +1,52 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
+pub mod atomic;
+pub mod audio;
+pub mod bundle;
+pub mod client;
 pub mod commands;
+pub mod compat;
+pub mod diff;
+pub mod download;
+pub mod error;
+pub mod health;
+pub mod journal;
+pub mod launch;
+pub mod monitor;
+pub mod presets;
+pub mod profiles;
+pub mod project;
+pub mod recent;
+pub mod save;
 pub mod state;
+pub mod tunnel;
+pub mod vault;
+pub mod watcher;
 
+use std::path::Path;
+
+use journal::Autosave;
+use presets::PresetStore;
+use profiles::ProfileStore;
+use recent::RecentProjects;
 use state::AppState;
+use vault::Vault;
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    #[allow(unused_mut)]
+    let mut builder = tauri::Builder::default();
+    // Registered first so a second launch exits before setting anything up;
+    // the project files it was started with open in this instance instead
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            launch::forward(app, launch::parse_args(argv, Path::new(&cwd)));
+            launch::focus_main_window(app);
+        }));
+    }
+    builder
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
@@ -16,7 +54,30 @@ pub fn run() {
         .setup(|app| {
             // Initialize app state
             let state = AppState::new();
+            match app.path().app_config_dir() {
+                Ok(dir) => {
+                    state.set_export_presets(PresetStore::load(dir.join(presets::PRESETS_FILE)));
+                    state.set_backend_profiles(ProfileStore::load(dir.join(profiles::PROFILES_FILE)));
+                    state.set_recent_projects(RecentProjects::load(dir.join(recent::RECENT_FILE)));
+                }
+                Err(e) => eprintln!("No app config directory, presets, profiles and recent projects will not be saved: {}", e),
+            }
+            match app.path().app_data_dir() {
+                Ok(dir) => state.set_vault(Vault::new(dir.join(vault::VAULT_FILE))),
+                Err(e) => eprintln!("No app data directory, secrets cannot be stored: {}", e),
+            }
+            // Journals left by a run that did not get to save are offered for recovery
+            match app.path().app_local_data_dir() {
+                Ok(dir) => state.set_autosave(Autosave::load(dir.join(journal::JOURNAL_DIR))),
+                Err(e) => eprintln!("No app local data directory, unsaved edits will not be journaled: {}", e),
+            }
             app.manage(state);
+            // `audiobook-maker book.abm`, or a double-clicked project on Windows and Linux
+            let cwd = std::env::current_dir().unwrap_or_default();
+            launch::forward(app.handle(), launch::parse_args(std::env::args(), &cwd));
+            tauri::async_runtime::spawn(journal::run_autosave(app.handle().clone()));
+            tauri::async_runtime::spawn(watcher::run_watcher(app.handle().clone()));
+            tauri::async_runtime::spawn(monitor::run_monitor(app.handle().clone()));
 
             // Configure window (will be shown by frontend when ready)
             #[cfg(debug_assertions)]
@@ -34,12 +95,95 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::ping,
             commands::check_backend_health,
+            commands::get_backend_monitor_stats,
             commands::open_project_file,
             commands::save_project_file,
+            commands::list_project_backups,
+            commands::restore_project_backup,
+            commands::update_autosave,
+            commands::discard_autosave,
+            commands::list_recoverable_projects,
+            commands::recover_project,
+            commands::discard_recovery,
+            commands::take_open_requests,
+            commands::diff_project_files,
+            commands::merge_project_files,
+            commands::list_recent_projects,
+            commands::pin_recent_project,
+            commands::remove_recent_project,
+            commands::export_project_bundle,
+            commands::import_project_bundle,
+            commands::import_project_file,
+            commands::import_project_data,
             commands::export_audio,
+            commands::check_acx,
+            commands::export_m4b,
+            commands::read_audio_tags,
+            commands::export_chapter_markers,
+            commands::export_split,
+            commands::export_podcast_feed,
+            commands::init_backend_profiles,
+            commands::list_backend_profiles,
+            commands::get_backend_profile,
+            commands::create_backend_profile,
+            commands::update_backend_profile,
+            commands::delete_backend_profile,
+            commands::activate_backend_profile,
+            commands::open_backend_tunnel,
+            commands::close_backend_tunnel,
+            commands::list_backend_tunnels,
+            commands::get_vault_status,
+            commands::unlock_vault,
+            commands::lock_vault,
+            commands::set_secret,
+            commands::delete_secret,
+            commands::rotate_vault_passphrase,
+            commands::api_list_projects,
+            commands::api_get_project,
+            commands::api_get_chapter,
+            commands::api_update_segment,
+            commands::api_freeze_segment,
+            commands::api_list_speakers,
+            commands::api_generate_segment,
+            commands::api_generate_chapter,
+            commands::api_list_tts_jobs,
+            commands::api_get_tts_job,
+            commands::api_cancel_tts_job,
+            commands::api_resume_tts_job,
+            commands::api_analyze_segment,
+            commands::api_analyze_chapter,
+            commands::api_list_quality_jobs,
+            commands::api_cancel_quality_job,
+            commands::api_start_export,
+            commands::api_get_export_progress,
+            commands::api_cancel_export,
+            commands::api_list_pronunciation_rules,
+            commands::api_create_pronunciation_rule,
+            commands::api_update_pronunciation_rule,
+            commands::api_delete_pronunciation_rule,
+            commands::list_export_presets,
+            commands::get_export_preset,
+            commands::create_export_preset,
+            commands::update_export_preset,
+            commands::delete_export_preset,
+            commands::export_export_presets,
+            commands::import_export_presets,
+            commands::download_export,
             commands::get_app_info,
             commands::show_main_window,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, _event| {
+            // macOS hands double-clicked files to the running app instead of argv
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                let requests = urls
+                    .iter()
+                    .filter_map(|url| url.to_file_path().ok())
+                    .filter_map(|path| launch::OpenRequest::from_path(&path, Path::new("/")))
+                    .collect();
+                launch::forward(_app, requests);
+            }
+        });
 }
\ No newline at end of file
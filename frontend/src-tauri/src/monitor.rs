@@ -0,0 +1,283 @@
+//! Background health monitor for the active backend
+//!
+//! One task probes the backend the app is connected to, so
+//! `AppState::backend_running` is current without the webview polling. While
+//! the backend answers it is probed every `POLL_INTERVAL`; after a failure the
+//! retries start at `FIRST_RETRY` and back off exponentially to `MAX_RETRY`.
+//! Every delay gets some jitter so restarted clients don't probe in step. The
+//! `backend://*` events go out only when the status changes.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::compat::{Compatibility, Incompatible};
+use crate::health::{self, HealthCheck};
+use crate::profiles::BackendProfile;
+use crate::state::AppState;
+
+/// Emitted with a `StatusChange` when the backend answers with status "ok"
+pub const BACKEND_ONLINE_EVENT: &str = "backend://online";
+/// Emitted with a `StatusChange` when the backend stops answering
+pub const BACKEND_OFFLINE_EVENT: &str = "backend://offline";
+/// Emitted with a `StatusChange` when the backend answers but reports a problem
+pub const BACKEND_DEGRADED_EVENT: &str = "backend://degraded";
+
+/// Probe interval while the backend answers
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// First retry after a failed probe; doubles with each further failure
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+/// Delays vary by up to this fraction either way
+const JITTER: f64 = 0.2;
+/// Status changes within this window count towards `flaps_last_hour`
+const FLAP_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackendStatus {
+    /// Not probed yet
+    #[default]
+    Unknown,
+    Online,
+    /// Answers, but its health status is not "ok"
+    Degraded,
+    Offline,
+}
+
+impl BackendStatus {
+    pub fn of(check: &HealthCheck) -> Self {
+        match &check.health {
+            Some(health) if health.status == "ok" => BackendStatus::Online,
+            Some(_) => BackendStatus::Degraded,
+            None => BackendStatus::Offline,
+        }
+    }
+
+    fn event(self) -> Option<&'static str> {
+        match self {
+            BackendStatus::Unknown => None,
+            BackendStatus::Online => Some(BACKEND_ONLINE_EVENT),
+            BackendStatus::Degraded => Some(BACKEND_DEGRADED_EVENT),
+            BackendStatus::Offline => Some(BACKEND_OFFLINE_EVENT),
+        }
+    }
+}
+
+/// Payload of the `backend://*` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+    pub backend_url: String,
+    pub status: BackendStatus,
+    pub previous: BackendStatus,
+    /// The check that changed the status
+    pub check: HealthCheck,
+}
+
+/// Uptime and flap statistics of the active backend since it became active
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorStats {
+    /// `None` until the first check
+    pub backend_url: Option<String>,
+    pub status: BackendStatus,
+    pub status_since: Option<String>,
+    /// From the first check until now
+    pub monitored_ms: u64,
+    pub online_ms: u64,
+    pub degraded_ms: u64,
+    pub offline_ms: u64,
+    /// Share of the monitored time the backend answered, online or degraded, in percent
+    pub uptime_percent: Option<f64>,
+    pub checks: u64,
+    pub failed_checks: u64,
+    pub consecutive_failures: u32,
+    /// Status changes, not counting the first check
+    pub transitions: u64,
+    pub flaps_last_hour: usize,
+    pub last_checked_at: Option<String>,
+    pub last_latency_ms: Option<u64>,
+}
+
+/// What the monitor has seen of the active backend
+#[derive(Debug, Default)]
+pub struct BackendMonitor {
+    url: Option<String>,
+    status: BackendStatus,
+    status_since: Option<(Instant, DateTime<Utc>)>,
+    /// Time in each status, up to the start of the current one
+    online: Duration,
+    degraded: Duration,
+    offline: Duration,
+    checks: u64,
+    failed_checks: u64,
+    consecutive_failures: u32,
+    transitions: u64,
+    /// When the status changed, within `FLAP_WINDOW`
+    flaps: VecDeque<Instant>,
+    last_checked_at: Option<DateTime<Utc>>,
+    last_latency_ms: Option<u64>,
+    /// From the last check the backend answered
+    compatibility: Option<Compatibility>,
+}
+
+impl BackendMonitor {
+    /// Count a check of `url`, starting over if it is not the backend seen so far;
+    /// returns the previous status if this one differs
+    pub fn record(&mut self, url: &str, check: &HealthCheck) -> Option<BackendStatus> {
+        if self.url.as_deref() != Some(url) {
+            *self = BackendMonitor { url: Some(url.to_string()), ..BackendMonitor::default() };
+        }
+        let now = Instant::now();
+        self.checks += 1;
+        self.last_checked_at = Some(Utc::now());
+        self.last_latency_ms = check.latency_ms;
+        if let Some(compatibility) = &check.compatibility {
+            self.compatibility = Some(compatibility.clone());
+        }
+        if check.reachable {
+            self.consecutive_failures = 0;
+        } else {
+            self.failed_checks += 1;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+
+        let status = BackendStatus::of(check);
+        if status == self.status {
+            return None;
+        }
+        if let Some((since, _)) = self.status_since {
+            *self.time_in(self.status) += now - since;
+        }
+        let previous = std::mem::replace(&mut self.status, status);
+        self.status_since = Some((now, Utc::now()));
+        if previous != BackendStatus::Unknown {
+            self.transitions += 1;
+            self.flaps.push_back(now);
+            self.prune_flaps(now);
+        }
+        Some(previous)
+    }
+
+    /// Forget status changes older than `FLAP_WINDOW`, so a flapping backend nobody asks about stays bounded
+    fn prune_flaps(&mut self, now: Instant) {
+        while self.flaps.front().is_some_and(|at| now - *at > FLAP_WINDOW) {
+            self.flaps.pop_front();
+        }
+    }
+
+    fn time_in(&mut self, status: BackendStatus) -> &mut Duration {
+        match status {
+            BackendStatus::Online => &mut self.online,
+            BackendStatus::Degraded => &mut self.degraded,
+            // Nothing is counted before the first check
+            BackendStatus::Offline | BackendStatus::Unknown => &mut self.offline,
+        }
+    }
+
+    /// `Err` if the last answer from `url` came from a backend this app must not use
+    pub fn ensure_compatible(&self, url: &str) -> Result<(), Incompatible> {
+        match &self.compatibility {
+            Some(compatibility) if self.url.as_deref() == Some(url) => compatibility.ensure(),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn stats(&mut self) -> MonitorStats {
+        let now = Instant::now();
+        self.prune_flaps(now);
+        let (mut online, mut degraded, mut offline) = (self.online, self.degraded, self.offline);
+        if let Some((since, _)) = self.status_since {
+            match self.status {
+                BackendStatus::Online => online += now - since,
+                BackendStatus::Degraded => degraded += now - since,
+                BackendStatus::Offline => offline += now - since,
+                BackendStatus::Unknown => {}
+            }
+        }
+        let monitored = online + degraded + offline;
+        MonitorStats {
+            backend_url: self.url.clone(),
+            status: self.status,
+            status_since: self.status_since.map(|(_, at)| at.to_rfc3339()),
+            monitored_ms: monitored.as_millis() as u64,
+            online_ms: online.as_millis() as u64,
+            degraded_ms: degraded.as_millis() as u64,
+            offline_ms: offline.as_millis() as u64,
+            uptime_percent: (!monitored.is_zero())
+                .then(|| (online + degraded).as_secs_f64() / monitored.as_secs_f64() * 100.0),
+            checks: self.checks,
+            failed_checks: self.failed_checks,
+            consecutive_failures: self.consecutive_failures,
+            transitions: self.transitions,
+            flaps_last_hour: self.flaps.len(),
+            last_checked_at: self.last_checked_at.map(|at| at.to_rfc3339()),
+            last_latency_ms: self.last_latency_ms,
+        }
+    }
+}
+
+/// Check the active backend with its profile's timeout and headers, unless given a timeout;
+/// returns the URL that was checked with the result
+pub async fn check_active(state: &AppState, timeout: Option<Duration>) -> (String, HealthCheck) {
+    let profile = state.backend_profiles().active().cloned();
+    let backend_url = state.get_backend_url();
+    let timeout = timeout.or_else(|| profile.as_ref().and_then(BackendProfile::health_timeout));
+    let headers = profile.map(|profile| profile.header_map(&state.vault())).unwrap_or_default();
+    let check = health::check(&backend_url, health::clamp_timeout(timeout), headers).await;
+    (backend_url, check)
+}
+
+/// Take a check of the active backend into account, announcing a change of status
+pub fn report(app: &AppHandle, backend_url: &str, check: &HealthCheck) {
+    let state = app.state::<AppState>();
+    // The app switched backends while this check ran
+    if state.get_backend_url() != backend_url {
+        return;
+    }
+    state.set_backend_running(check.reachable);
+    let Some(previous) = state.backend_monitor().record(backend_url, check) else {
+        return;
+    };
+    let status = BackendStatus::of(check);
+    let Some(event) = status.event() else {
+        return;
+    };
+    let change = StatusChange { backend_url: backend_url.to_string(), status, previous, check: check.clone() };
+    if let Err(e) = app.emit(event, &change) {
+        eprintln!("Failed to report backend status: {}", e);
+    }
+}
+
+/// Time until the next probe after `consecutive_failures` failed ones
+fn next_delay(consecutive_failures: u32) -> Duration {
+    let delay = match consecutive_failures {
+        0 => POLL_INTERVAL,
+        failures => FIRST_RETRY.saturating_mul(1 << (failures - 1).min(16)).min(MAX_RETRY),
+    };
+    delay.mul_f64(1.0 + JITTER * (fastrand::f64() * 2.0 - 1.0))
+}
+
+/// Probe the active backend for the life of the app, right away when it changes
+pub async fn run_monitor(app: AppHandle) {
+    let state = app.state::<AppState>();
+    loop {
+        let changed = state.backend_changed.notified();
+        tokio::pin!(changed);
+        let (backend_url, check) = check_active(&state, None).await;
+        report(&app, &backend_url, &check);
+        let delay = next_delay(state.backend_monitor().consecutive_failures());
+        tokio::select! {
+            _ = &mut changed => {}
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Named export presets, persisted as JSON in the app config directory
+//!
+//! Built-in presets are compiled in and read-only; only user presets are
+//! written to disk.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::write_atomic;
+use crate::audio::format::{BitrateMode, EncodeOptions, EncodeSettings, ExportFormat};
+use crate::audio::loudness::{LoudnessOptions, LoudnessPreset};
+use crate::audio::split::{render_name, DEFAULT_NAME_TEMPLATE};
+use crate::audio::tags::AudioMetadata;
+use crate::audio::ExportError;
+use crate::error::write_coded;
+use crate::serialize_as_display;
+
+/// File name inside the app config directory
+pub const PRESETS_FILE: &str = "export-presets.json";
+const PRESETS_VERSION: u32 = 1;
+
+/// Input assumed when checking a preset that leaves sample rate/channels to the source
+const NOMINAL_SAMPLE_RATE: u32 = 44100;
+const NOMINAL_CHANNELS: u16 = 1;
+
+/// Errors raised while managing export presets
+#[derive(Debug)]
+pub enum PresetError {
+    /// The preset's encoder settings are invalid, passed through as-is
+    Export(ExportError),
+    NotFound { id: String },
+    /// Built-in presets cannot be changed or deleted
+    ReadOnly { id: String },
+    EmptyName,
+    DuplicateName { name: String },
+    /// An imported file is not a preset export
+    InvalidImport { path: String, message: String },
+    Io { path: String, message: String },
+}
+
+impl PresetError {
+    fn io(path: &Path, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.display().to_string(), message: error.to_string() }
+    }
+}
+
+impl From<ExportError> for PresetError {
+    fn from(error: ExportError) -> Self {
+        Self::Export(error)
+    }
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Export(error) => fmt::Display::fmt(error, f),
+            Self::NotFound { id } => write_coded(f, "PRESET_NOT_FOUND", &[("id", id)]),
+            Self::ReadOnly { id } => write_coded(f, "PRESET_READ_ONLY", &[("id", id)]),
+            Self::EmptyName => write_coded(f, "PRESET_EMPTY_NAME", &[]),
+            Self::DuplicateName { name } => write_coded(f, "PRESET_DUPLICATE_NAME", &[("name", name)]),
+            Self::InvalidImport { path, message } => {
+                write_coded(f, "PRESET_IMPORT_INVALID", &[("path", path), ("error", message)])
+            }
+            Self::Io { path, message } => {
+                write_coded(f, "PRESET_IO_FAILED", &[("path", path), ("error", message)])
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+serialize_as_display!(PresetError);
+
+/// Output container; the codec follows from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetContainer {
+    Mp3,
+    M4a,
+    /// Single-file audiobook with chapters (AAC)
+    M4b,
+    Flac,
+    Opus,
+    Wav,
+}
+
+impl PresetContainer {
+    pub fn export_format(self) -> ExportFormat {
+        match self {
+            Self::Mp3 => ExportFormat::Mp3,
+            Self::M4a | Self::M4b => ExportFormat::M4a,
+            Self::Flac => ExportFormat::Flac,
+            Self::Opus => ExportFormat::Opus,
+            Self::Wav => ExportFormat::Wav,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportPreset {
+    /// Assigned on create/import; ignored in requests
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub builtin: bool,
+    pub container: PresetContainer,
+    /// Bitrate, sample rate, channels, bit depth and loudness target
+    #[serde(default)]
+    pub encode: EncodeOptions,
+    /// File name template for multi-file exports
+    pub name_template: Option<String>,
+    /// Pre-filled book metadata; fields set on the project take precedence
+    #[serde(default)]
+    pub metadata: AudioMetadata,
+}
+
+impl ExportPreset {
+    /// The settings the encoder will use for this preset
+    pub fn settings(&self) -> Result<EncodeSettings, ExportError> {
+        EncodeSettings::resolve(
+            self.container.export_format(),
+            &self.encode,
+            self.encode.sample_rate.unwrap_or(NOMINAL_SAMPLE_RATE),
+            self.encode.channels.unwrap_or(NOMINAL_CHANNELS),
+        )
+    }
+
+    fn validate(&self) -> Result<(), PresetError> {
+        if self.name.trim().is_empty() {
+            return Err(PresetError::EmptyName);
+        }
+        self.settings()?;
+        if let Some(template) = &self.name_template {
+            render_name(template, 1, "", "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Presets shipped with the app
+pub fn builtin_presets() -> Vec<ExportPreset> {
+    let audiobook = AudioMetadata { genre: Some("Audiobook".to_string()), ..Default::default() };
+    vec![
+        ExportPreset {
+            id: "builtin-acx-mp3".to_string(),
+            name: "ACX MP3".to_string(),
+            builtin: true,
+            container: PresetContainer::Mp3,
+            encode: EncodeOptions {
+                bitrate_mode: Some(BitrateMode::Cbr),
+                bitrate: Some(192),
+                sample_rate: Some(44100),
+                channels: Some(1),
+                loudness: Some(LoudnessOptions { preset: Some(LoudnessPreset::Acx), ..Default::default() }),
+                ..Default::default()
+            },
+            name_template: Some(DEFAULT_NAME_TEMPLATE.to_string()),
+            metadata: audiobook.clone(),
+        },
+        ExportPreset {
+            id: "builtin-m4b-64k-mono".to_string(),
+            name: "M4B 64k mono".to_string(),
+            builtin: true,
+            container: PresetContainer::M4b,
+            encode: EncodeOptions {
+                bitrate_mode: Some(BitrateMode::Cbr),
+                bitrate: Some(64),
+                sample_rate: Some(44100),
+                channels: Some(1),
+                ..Default::default()
+            },
+            name_template: None,
+            metadata: audiobook.clone(),
+        },
+        ExportPreset {
+            id: "builtin-archive-flac".to_string(),
+            name: "Archive FLAC".to_string(),
+            builtin: true,
+            container: PresetContainer::Flac,
+            // Keep the source rate and channels, 24 bit
+            encode: EncodeOptions { bit_depth: Some(24), ..Default::default() },
+            name_template: Some(DEFAULT_NAME_TEMPLATE.to_string()),
+            metadata: audiobook,
+        },
+    ]
+}
+
+/// On-disk (and import/export) format
+#[derive(Debug, Serialize, Deserialize)]
+struct PresetFile {
+    version: u32,
+    presets: Vec<ExportPreset>,
+}
+
+/// User presets plus where they are persisted
+#[derive(Debug, Default)]
+pub struct PresetStore {
+    path: Option<PathBuf>,
+    presets: Vec<ExportPreset>,
+}
+
+impl PresetStore {
+    /// Load user presets from `path`; a missing file is an empty store and an
+    /// unreadable one is moved aside so it is not overwritten
+    pub fn load(path: PathBuf) -> Self {
+        let presets = match fs::read(&path) {
+            Ok(data) => match serde_json::from_slice::<PresetFile>(&data) {
+                Ok(file) => file.presets,
+                Err(e) => {
+                    eprintln!("Ignoring unreadable export presets {}: {}", path.display(), e);
+                    let _ = fs::rename(&path, path.with_extension("json.corrupt"));
+                    Vec::new()
+                }
+            },
+            Err(_) => Vec::new(),
+        };
+        Self { path: Some(path), presets }
+    }
+
+    /// Built-in presets first, then the user's in creation order
+    pub fn list(&self) -> Vec<ExportPreset> {
+        builtin_presets().into_iter().chain(self.presets.iter().cloned()).collect()
+    }
+
+    pub fn get(&self, id: &str) -> Result<ExportPreset, PresetError> {
+        self.list()
+            .into_iter()
+            .find(|preset| preset.id == id)
+            .ok_or_else(|| PresetError::NotFound { id: id.to_string() })
+    }
+
+    fn check_name(&self, name: &str, except_id: Option<&str>) -> Result<(), PresetError> {
+        let taken = self
+            .list()
+            .iter()
+            .any(|preset| Some(preset.id.as_str()) != except_id && preset.name.trim().eq_ignore_ascii_case(name.trim()));
+        if taken {
+            return Err(PresetError::DuplicateName { name: name.to_string() });
+        }
+        Ok(())
+    }
+
+    /// Strip what a request may not set and check the preset can be encoded
+    fn normalize(mut preset: ExportPreset) -> Result<ExportPreset, PresetError> {
+        preset.name = preset.name.trim().to_string();
+        preset.builtin = false;
+        // Raw PCM layout describes a particular input, not an export choice
+        preset.encode.input = None;
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    pub fn create(&mut self, preset: ExportPreset) -> Result<ExportPreset, PresetError> {
+        let mut preset = Self::normalize(preset)?;
+        self.check_name(&preset.name, None)?;
+        preset.id = uuid::Uuid::new_v4().to_string();
+        let previous = self.presets.clone();
+        self.presets.push(preset.clone());
+        self.save_or_revert(previous)?;
+        Ok(preset)
+    }
+
+    pub fn update(&mut self, preset: ExportPreset) -> Result<ExportPreset, PresetError> {
+        let id = preset.id.clone();
+        let index = self.user_index(&id)?;
+        let mut preset = Self::normalize(preset)?;
+        self.check_name(&preset.name, Some(&id))?;
+        preset.id = id;
+        let previous = self.presets.clone();
+        self.presets[index] = preset.clone();
+        self.save_or_revert(previous)?;
+        Ok(preset)
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<(), PresetError> {
+        let index = self.user_index(id)?;
+        let previous = self.presets.clone();
+        self.presets.remove(index);
+        self.save_or_revert(previous)
+    }
+
+    fn user_index(&self, id: &str) -> Result<usize, PresetError> {
+        if builtin_presets().iter().any(|preset| preset.id == id) {
+            return Err(PresetError::ReadOnly { id: id.to_string() });
+        }
+        self.presets
+            .iter()
+            .position(|preset| preset.id == id)
+            .ok_or_else(|| PresetError::NotFound { id: id.to_string() })
+    }
+
+    /// Write the given presets (all user presets if `ids` is `None`) to `path`
+    pub fn export(&self, path: &Path, ids: Option<&[String]>) -> Result<usize, PresetError> {
+        let presets: Vec<ExportPreset> = match ids {
+            Some(ids) => ids.iter().map(|id| self.get(id)).collect::<Result<_, _>>()?,
+            None => self.presets.clone(),
+        };
+        let count = presets.len();
+        write_file(path, presets)?;
+        Ok(count)
+    }
+
+    /// Add every preset from `path` as a new user preset, renaming on name clashes
+    pub fn import(&mut self, path: &Path) -> Result<Vec<ExportPreset>, PresetError> {
+        let data = fs::read(path).map_err(|e| PresetError::io(path, e))?;
+        let file: PresetFile = serde_json::from_slice(&data).map_err(|e| PresetError::InvalidImport {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+
+        // Validate everything first so a bad entry does not leave half an import behind
+        let presets = file.presets.into_iter().map(Self::normalize).collect::<Result<Vec<_>, _>>()?;
+        let previous = self.presets.clone();
+        let mut imported = Vec::with_capacity(presets.len());
+        for mut preset in presets {
+            let base = preset.name.clone();
+            let mut suffix = 2;
+            while self.check_name(&preset.name, None).is_err() {
+                preset.name = format!("{} ({})", base, suffix);
+                suffix += 1;
+            }
+            preset.id = uuid::Uuid::new_v4().to_string();
+            self.presets.push(preset.clone());
+            imported.push(preset);
+        }
+        self.save_or_revert(previous)?;
+        Ok(imported)
+    }
+
+    /// Persist the current list, or go back to `previous` if that fails so
+    /// memory and disk stay in sync
+    fn save_or_revert(&mut self, previous: Vec<ExportPreset>) -> Result<(), PresetError> {
+        let result = match &self.path {
+            Some(path) => write_file(path, self.presets.clone()),
+            None => Ok(()),
+        };
+        if result.is_err() {
+            self.presets = previous;
+        }
+        result
+    }
+}
+
+fn write_file(path: &Path, presets: Vec<ExportPreset>) -> Result<(), PresetError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| PresetError::io(dir, e))?;
+    }
+    let file = PresetFile { version: PRESETS_VERSION, presets };
+    let json = serde_json::to_vec_pretty(&file).map_err(|e| PresetError::io(path, e))?;
+    write_atomic(path, &json).map_err(|e| PresetError::io(path, e))
+}
@@ -0,0 +1,424 @@
+//! Backend connection profiles, persisted as JSON in the app config directory
+//!
+//! Profiles used to live in webview localStorage; `init` imports that list
+//! once and from then on this file is the only copy.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::write_atomic;
+use crate::compat::Incompatible;
+use crate::error::write_coded;
+use crate::serialize_as_display;
+use crate::tunnel::{TunnelConfig, TunnelError};
+use crate::vault::{self, Vault};
+
+/// File name inside the app config directory
+pub const PROFILES_FILE: &str = "backend-profiles.json";
+const PROFILES_VERSION: u32 = 1;
+/// The profile created when there is none
+pub const DEFAULT_BACKEND_URL: &str = "http://127.0.0.1:8765";
+const DEFAULT_PROFILE_NAME: &str = "Local Backend";
+/// Bounds for the per-profile timeouts
+const MIN_TIMEOUT_MS: u64 = 100;
+const MAX_TIMEOUT_MS: u64 = 600_000;
+
+/// Errors raised while managing backend profiles
+#[derive(Debug)]
+pub enum ProfileError {
+    NotFound { id: String },
+    EmptyName,
+    DuplicateName { name: String },
+    /// Not an absolute http(s) URL
+    InvalidUrl { url: String },
+    InvalidHeader { name: String },
+    InvalidTimeout { value: u64 },
+    /// A tunnel field that is missing or out of range
+    InvalidTunnel { field: String },
+    /// The profile's SSH tunnel could not be started
+    Tunnel(TunnelError),
+    /// The version handshake refused the profile's backend
+    Incompatible(Incompatible),
+    Io { path: String, message: String },
+}
+
+impl ProfileError {
+    fn io(path: &Path, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.display().to_string(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound { id } => write_coded(f, "PROFILE_NOT_FOUND", &[("id", id)]),
+            Self::EmptyName => write_coded(f, "PROFILE_EMPTY_NAME", &[]),
+            Self::DuplicateName { name } => write_coded(f, "PROFILE_DUPLICATE_NAME", &[("name", name)]),
+            Self::InvalidUrl { url } => write_coded(f, "PROFILE_INVALID_URL", &[("url", url)]),
+            Self::InvalidHeader { name } => write_coded(f, "PROFILE_INVALID_HEADER", &[("name", name)]),
+            Self::InvalidTimeout { value } => {
+                write_coded(f, "PROFILE_INVALID_TIMEOUT", &[("value", value), ("min", &MIN_TIMEOUT_MS), ("max", &MAX_TIMEOUT_MS)])
+            }
+            Self::InvalidTunnel { field } => write_coded(f, "PROFILE_INVALID_TUNNEL", &[("field", field)]),
+            Self::Tunnel(e) => e.fmt(f),
+            Self::Incompatible(e) => e.fmt(f),
+            Self::Io { path, message } => {
+                write_coded(f, "PROFILE_IO_FAILED", &[("path", path), ("error", message)])
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+serialize_as_display!(ProfileError);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendProfile {
+    /// Assigned on create; ignored in create requests
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Base URL without a trailing slash, e.g. `http://127.0.0.1:8765`
+    pub url: String,
+    #[serde(default)]
+    pub is_default: bool,
+    /// RFC 3339; set when the profile is activated
+    #[serde(default)]
+    pub last_connected: Option<String>,
+    /// RFC 3339; kept from the stored profile on update
+    #[serde(default)]
+    pub created_at: String,
+    /// Timeout for health checks; `None` uses the app default
+    #[serde(default)]
+    pub health_timeout_ms: Option<u64>,
+    /// Timeout for other backend requests; `None` uses the app default
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+    /// Sent with every request, e.g. credentials for a reverse proxy
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Reach the backend through this SSH server; `url` is then the backend's
+    /// address as seen from the server
+    #[serde(default)]
+    pub tunnel: Option<TunnelConfig>,
+}
+
+impl BackendProfile {
+    pub fn health_timeout(&self) -> Option<Duration> {
+        self.health_timeout_ms.map(Duration::from_millis)
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// `headers` for reqwest, with `{{secret:name}}` references filled in from `vault`;
+    /// a header whose secret is not available is left out
+    pub fn header_map(&self, vault: &Vault) -> HeaderMap {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+                if !vault::has_reference(value) {
+                    return Some((name, HeaderValue::from_str(value).ok()?));
+                }
+                let resolved = vault
+                    .resolve(value)
+                    .map_err(|e| eprintln!("Leaving out header {} of profile {}: {}", name, self.name, e))
+                    .ok()?;
+                let mut value = HeaderValue::from_str(&resolved).ok()?;
+                value.set_sensitive(true);
+                Some((name, value))
+            })
+            .collect()
+    }
+}
+
+/// A profile as the frontend kept it in localStorage
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyProfile {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub last_connected: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// On-disk format
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileFile {
+    version: u32,
+    /// The localStorage profiles were taken over (or there were none)
+    #[serde(default)]
+    legacy_imported: bool,
+    profiles: Vec<BackendProfile>,
+}
+
+/// `url` as stored: trimmed, no trailing slash, absolute http(s) with a host
+fn normalize_url(url: &str) -> Result<String, ProfileError> {
+    let url = url.trim().trim_end_matches('/').to_string();
+    let invalid = || ProfileError::InvalidUrl { url: url.clone() };
+    let parsed = reqwest::Url::parse(&url).map_err(|_| invalid())?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(url)
+}
+
+fn check_timeout(timeout_ms: Option<u64>) -> Result<(), ProfileError> {
+    match timeout_ms {
+        Some(value) if !(MIN_TIMEOUT_MS..=MAX_TIMEOUT_MS).contains(&value) => Err(ProfileError::InvalidTimeout { value }),
+        _ => Ok(()),
+    }
+}
+
+/// Profiles plus where they are persisted
+#[derive(Debug, Default)]
+pub struct ProfileStore {
+    path: Option<PathBuf>,
+    legacy_imported: bool,
+    profiles: Vec<BackendProfile>,
+    /// The profile whose URL is `AppState::backend_url`; not persisted
+    active: Option<String>,
+}
+
+impl ProfileStore {
+    /// Load profiles from `path`; a missing file is an empty store and an
+    /// unreadable one is moved aside so it is not overwritten
+    pub fn load(path: PathBuf) -> Self {
+        let file = match fs::read(&path) {
+            Ok(data) => match serde_json::from_slice::<ProfileFile>(&data) {
+                Ok(file) => file,
+                Err(e) => {
+                    eprintln!("Ignoring unreadable backend profiles {}: {}", path.display(), e);
+                    let _ = fs::rename(&path, path.with_extension("json.corrupt"));
+                    // The localStorage copy may still be there and is better than nothing
+                    ProfileFile::default()
+                }
+            },
+            Err(_) => ProfileFile::default(),
+        };
+        Self { path: Some(path), legacy_imported: file.legacy_imported, profiles: file.profiles, active: None }
+    }
+
+    pub fn list(&self) -> Vec<BackendProfile> {
+        self.profiles.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Result<BackendProfile, ProfileError> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .cloned()
+            .ok_or_else(|| ProfileError::NotFound { id: id.to_string() })
+    }
+
+    /// The activated profile, if it still exists
+    pub fn active(&self) -> Option<&BackendProfile> {
+        let id = self.active.as_deref()?;
+        self.profiles.iter().find(|profile| profile.id == id)
+    }
+
+    /// Take over the localStorage profiles (once), then make sure there is at least one profile
+    pub fn init(&mut self, legacy: Vec<LegacyProfile>) -> Result<Vec<BackendProfile>, ProfileError> {
+        let previous = self.profiles.clone();
+        let was_imported = self.legacy_imported;
+        let mut changed = false;
+        if !self.legacy_imported {
+            for old in legacy {
+                // Skip broken entries rather than failing the whole import
+                let Ok(url) = normalize_url(&old.url) else {
+                    continue;
+                };
+                let name = old.name.trim().to_string();
+                let duplicate = self.profiles.iter().any(|profile| {
+                    Some(&profile.id) == old.id.as_ref() || profile.name.eq_ignore_ascii_case(&name)
+                });
+                if name.is_empty() || duplicate {
+                    continue;
+                }
+                self.profiles.push(BackendProfile {
+                    id: old.id.filter(|id| !id.is_empty()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                    name,
+                    url,
+                    is_default: old.is_default,
+                    last_connected: old.last_connected,
+                    created_at: old.created_at.unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                    health_timeout_ms: None,
+                    request_timeout_ms: None,
+                    headers: BTreeMap::new(),
+                    tunnel: None,
+                });
+            }
+            self.legacy_imported = true;
+            changed = true;
+        }
+        if self.profiles.is_empty() {
+            self.profiles.push(BackendProfile {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                url: DEFAULT_BACKEND_URL.to_string(),
+                is_default: true,
+                last_connected: None,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                health_timeout_ms: None,
+                request_timeout_ms: None,
+                headers: BTreeMap::new(),
+                tunnel: None,
+            });
+            changed = true;
+        }
+        // At most one default, as the frontend always enforced
+        let mut seen_default = false;
+        for profile in &mut self.profiles {
+            if profile.is_default && seen_default {
+                profile.is_default = false;
+                changed = true;
+            }
+            seen_default |= profile.is_default;
+        }
+        if changed {
+            if let Err(e) = self.save_or_revert(previous) {
+                self.legacy_imported = was_imported;
+                return Err(e);
+            }
+        }
+        Ok(self.list())
+    }
+
+    fn check_name(&self, name: &str, except_id: Option<&str>) -> Result<(), ProfileError> {
+        let taken = self
+            .profiles
+            .iter()
+            .any(|profile| Some(profile.id.as_str()) != except_id && profile.name.trim().eq_ignore_ascii_case(name.trim()));
+        if taken {
+            return Err(ProfileError::DuplicateName { name: name.to_string() });
+        }
+        Ok(())
+    }
+
+    /// Trim and check what a request sets
+    fn normalize(mut profile: BackendProfile) -> Result<BackendProfile, ProfileError> {
+        profile.name = profile.name.trim().to_string();
+        if profile.name.is_empty() {
+            return Err(ProfileError::EmptyName);
+        }
+        profile.url = normalize_url(&profile.url)?;
+        check_timeout(profile.health_timeout_ms)?;
+        check_timeout(profile.request_timeout_ms)?;
+        if let Some(tunnel) = profile.tunnel.take() {
+            let tunnel = tunnel.normalized().map_err(|field| ProfileError::InvalidTunnel { field: field.to_string() })?;
+            profile.tunnel = Some(tunnel);
+        }
+        for (name, value) in &profile.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err() {
+                return Err(ProfileError::InvalidHeader { name: name.clone() });
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Only one profile is the default
+    fn take_default(&mut self, id: &str) {
+        for profile in &mut self.profiles {
+            if profile.id != id {
+                profile.is_default = false;
+            }
+        }
+    }
+
+    pub fn create(&mut self, profile: BackendProfile) -> Result<BackendProfile, ProfileError> {
+        let mut profile = Self::normalize(profile)?;
+        self.check_name(&profile.name, None)?;
+        profile.id = uuid::Uuid::new_v4().to_string();
+        profile.created_at = chrono::Utc::now().to_rfc3339();
+        profile.last_connected = None;
+        let previous = self.profiles.clone();
+        self.profiles.push(profile.clone());
+        if profile.is_default {
+            self.take_default(&profile.id);
+        }
+        self.save_or_revert(previous)?;
+        Ok(profile)
+    }
+
+    pub fn update(&mut self, profile: BackendProfile) -> Result<BackendProfile, ProfileError> {
+        let index = self.index(&profile.id)?;
+        let mut profile = Self::normalize(profile)?;
+        self.check_name(&profile.name, Some(&profile.id))?;
+        profile.created_at = self.profiles[index].created_at.clone();
+        profile.last_connected = self.profiles[index].last_connected.clone();
+        let previous = self.profiles.clone();
+        self.profiles[index] = profile.clone();
+        if profile.is_default {
+            self.take_default(&profile.id);
+        }
+        self.save_or_revert(previous)?;
+        Ok(profile)
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<(), ProfileError> {
+        let index = self.index(id)?;
+        let previous = self.profiles.clone();
+        self.profiles.remove(index);
+        self.save_or_revert(previous)?;
+        if self.active.as_deref() == Some(id) {
+            self.active = None;
+        }
+        Ok(())
+    }
+
+    /// Make `id` the profile in use and note when it was connected to
+    pub fn activate(&mut self, id: &str) -> Result<BackendProfile, ProfileError> {
+        let index = self.index(id)?;
+        let previous = self.profiles.clone();
+        self.profiles[index].last_connected = Some(chrono::Utc::now().to_rfc3339());
+        self.save_or_revert(previous)?;
+        self.active = Some(id.to_string());
+        Ok(self.profiles[index].clone())
+    }
+
+    fn index(&self, id: &str) -> Result<usize, ProfileError> {
+        self.profiles
+            .iter()
+            .position(|profile| profile.id == id)
+            .ok_or_else(|| ProfileError::NotFound { id: id.to_string() })
+    }
+
+    /// Persist the current list, or go back to `previous` if that fails so
+    /// memory and disk stay in sync
+    fn save_or_revert(&mut self, previous: Vec<BackendProfile>) -> Result<(), ProfileError> {
+        let result = match &self.path {
+            Some(path) => write_file(path, self.legacy_imported, self.profiles.clone()),
+            None => Ok(()),
+        };
+        if result.is_err() {
+            self.profiles = previous;
+        }
+        result
+    }
+}
+
+fn write_file(path: &Path, legacy_imported: bool, profiles: Vec<BackendProfile>) -> Result<(), ProfileError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ProfileError::io(dir, e))?;
+    }
+    let file = ProfileFile { version: PROFILES_VERSION, legacy_imported, profiles };
+    let json = serde_json::to_vec_pretty(&file).map_err(|e| ProfileError::io(path, e))?;
+    write_atomic(path, &json).map_err(|e| ProfileError::io(path, e))
+}
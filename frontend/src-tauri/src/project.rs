@@ -0,0 +1,481 @@
+//! Project file schema (`.abm`)
+//!
+//! Files carry a `schemaVersion`; older files are upgraded on load by running
+//! the migration chain on the raw JSON before it is deserialized. Every struct
+//! keeps fields it does not know in `extra`, so a file written by a newer
+//! build (or edited by hand) loses nothing when saved again.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::write_coded;
+use crate::serialize_as_display;
+
+/// Upgrades a file from the version at its index + 1 to the next one
+type Migration = fn(&mut Map<String, Value>);
+
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2];
+
+/// File extension of project files
+pub const PROJECT_EXTENSION: &str = "abm";
+
+/// Version written by this build
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Errors raised while reading or checking a project file
+#[derive(Debug)]
+pub enum ProjectError {
+    /// The file is not valid JSON
+    Syntax { line: usize, column: usize, message: String },
+    /// The JSON does not match the schema
+    Schema { field: String, message: String },
+    /// The file was written by a newer version of the app
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// The native file dialog could not be shown
+    Dialog { message: String },
+    /// The path is not in the recent projects list
+    NotRecent { path: String },
+    /// The file was modified by something else since it was opened or last saved
+    ChangedOnDisk { path: String },
+    /// There is no backup with that number
+    BackupNotFound { path: String },
+    /// No leftover autosave journal with that id
+    NotRecoverable { id: String },
+    Io { path: String, message: String },
+}
+
+impl ProjectError {
+    pub fn io(path: impl Into<String>, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.into(), message: error.to_string() }
+    }
+
+    fn schema(field: impl Into<String>, message: impl fmt::Display) -> Self {
+        Self::Schema { field: field.into(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { line, column, message } => write_coded(
+                f,
+                "PROJECT_SYNTAX_ERROR",
+                &[("line", line), ("column", column), ("error", message)],
+            ),
+            Self::Schema { field, message } => {
+                write_coded(f, "PROJECT_SCHEMA_ERROR", &[("field", field), ("error", message)])
+            }
+            Self::UnsupportedVersion { found, supported } => write_coded(
+                f,
+                "PROJECT_UNSUPPORTED_VERSION",
+                &[("found", found), ("supported", supported)],
+            ),
+            Self::Dialog { message } => write_coded(f, "PROJECT_DIALOG_FAILED", &[("error", message)]),
+            Self::NotRecent { path } => write_coded(f, "PROJECT_NOT_RECENT", &[("path", path)]),
+            Self::ChangedOnDisk { path } => write_coded(f, "PROJECT_CHANGED_ON_DISK", &[("path", path)]),
+            Self::BackupNotFound { path } => write_coded(f, "PROJECT_BACKUP_NOT_FOUND", &[("path", path)]),
+            Self::NotRecoverable { id } => write_coded(f, "PROJECT_NOT_RECOVERABLE", &[("id", id)]),
+            Self::Io { path, message } => {
+                write_coded(f, "PROJECT_IO_FAILED", &[("path", path), ("error", message)])
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+serialize_as_display!(ProjectError);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentType {
+    /// Text rendered to speech
+    #[default]
+    Standard,
+    /// Silence of `pause_duration` ms
+    Divider,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    pub id: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub segment_type: SegmentType,
+    /// Milliseconds of silence for divider segments
+    #[serde(default)]
+    pub pause_duration: u64,
+    #[serde(default)]
+    pub order_index: u32,
+    #[serde(default)]
+    pub tts_engine: String,
+    #[serde(default)]
+    pub tts_model_name: String,
+    #[serde(default)]
+    pub tts_speaker_name: Option<String>,
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub audio_path: Option<String>,
+    /// Seconds
+    #[serde(default)]
+    pub start_time: f64,
+    #[serde(default)]
+    pub end_time: f64,
+    /// pending, queued, processing, completed or failed
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub is_frozen: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub order_index: u32,
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakerSample {
+    pub id: String,
+    #[serde(default)]
+    pub file_name: String,
+    #[serde(default)]
+    pub file_path: String,
+    #[serde(default)]
+    pub transcript: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Speaker {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub gender: Option<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub is_active: bool,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub samples: Vec<SpeakerSample>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleScope {
+    /// Applies to this project with one engine
+    ProjectEngine,
+    /// Applies to every project using the engine
+    Engine,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PronunciationRule {
+    pub id: String,
+    pub pattern: String,
+    pub replacement: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    pub scope: RuleScope,
+    pub engine_name: String,
+    pub language: String,
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Per-project overrides of the global settings; `None` means use the global value
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSettings {
+    pub pause_between_segments: Option<u64>,
+    pub default_divider_duration: Option<u64>,
+    pub tts_engine: Option<String>,
+    pub tts_model_name: Option<String>,
+    pub tts_speaker_name: Option<String>,
+    pub language: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectData {
+    /// Only trusted after `from_json`/`from_value`, which migrate and stamp it
+    #[serde(default)]
+    pub schema_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+    #[serde(default)]
+    pub speakers: Vec<Speaker>,
+    #[serde(default)]
+    pub pronunciation_rules: Vec<PronunciationRule>,
+    #[serde(default)]
+    pub settings: ProjectSettings,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `pause_between_segments` -> `pauseBetweenSegments`, the same rule as the backend's `to_camel`
+fn to_camel(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' && !out.is_empty() {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn camel_keys(object: &mut Map<String, Value>) {
+    let keys: Vec<String> = object.keys().filter(|key| key.contains('_')).cloned().collect();
+    for key in keys {
+        let camel = to_camel(&key);
+        if let Some(value) = object.remove(&key) {
+            // An existing camelCase key wins over its snake_case twin
+            object.entry(camel).or_insert(value);
+        }
+    }
+}
+
+fn objects_in(value: Option<&mut Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    value
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// Version 1 had no `schemaVersion` and stored chapters and settings as the
+/// backend returned them, partly in snake_case; the project name could be `title`
+fn migrate_v1_to_v2(project: &mut Map<String, Value>) {
+    if !project.contains_key("name") {
+        if let Some(title) = project.remove("title") {
+            project.insert("name".to_string(), title);
+        }
+    }
+    for chapter in objects_in(project.get_mut("chapters")) {
+        camel_keys(chapter);
+        for segment in objects_in(chapter.get_mut("segments")) {
+            camel_keys(segment);
+        }
+    }
+    if let Some(settings) = project.get_mut("settings") {
+        match settings.as_object_mut() {
+            Some(settings) => camel_keys(settings),
+            // v1 wrote `null` for projects without settings
+            None => *settings = Value::Object(Map::new()),
+        }
+    }
+}
+
+impl ProjectData {
+    /// Parse a project file of any supported version, migrate it and check it
+    pub fn from_json(text: &str) -> Result<Self, ProjectError> {
+        Self::parse(text).map(|(project, _)| project)
+    }
+
+    /// Like `from_json`, also returning the schema version the file was written with
+    pub fn parse(text: &str) -> Result<(Self, u32), ProjectError> {
+        let value: Value = serde_json::from_str(text).map_err(|e| ProjectError::Syntax {
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string(),
+        })?;
+        Self::from_value(value)
+    }
+
+    pub fn from_value(value: Value) -> Result<(Self, u32), ProjectError> {
+        let Value::Object(mut object) = value else {
+            return Err(ProjectError::schema("$", "expected an object"));
+        };
+
+        let version = match object.get("schemaVersion") {
+            None => 1,
+            Some(version) => version
+                .as_u64()
+                .filter(|version| *version >= 1)
+                .ok_or_else(|| ProjectError::schema("schemaVersion", "expected a positive integer"))?
+                as u32,
+        };
+        if version > SCHEMA_VERSION {
+            return Err(ProjectError::UnsupportedVersion { found: version, supported: SCHEMA_VERSION });
+        }
+        for migration in &MIGRATIONS[version as usize - 1..] {
+            migration(&mut object);
+        }
+        object.insert("schemaVersion".to_string(), SCHEMA_VERSION.into());
+
+        let project: Self = serde_path_to_error::deserialize(Value::Object(object)).map_err(|e| {
+            let field = e.path().to_string();
+            ProjectError::schema(field, e.into_inner())
+        })?;
+        project.validate()?;
+        Ok((project, version))
+    }
+
+    /// Pretty-printed JSON at the current schema version
+    pub fn to_json(&self) -> Result<String, ProjectError> {
+        let mut project = self.clone();
+        project.schema_version = SCHEMA_VERSION;
+        serde_json::to_string_pretty(&project).map_err(|e| ProjectError::schema("$", e))
+    }
+
+    /// Checks serde cannot express: ids present and unique, sane timings
+    pub fn validate(&self) -> Result<(), ProjectError> {
+        if self.name.trim().is_empty() {
+            return Err(ProjectError::schema("name", "must not be empty"));
+        }
+
+        fn check_id(seen: &mut HashSet<String>, id: &str, field: String) -> Result<(), ProjectError> {
+            if id.is_empty() {
+                return Err(ProjectError::schema(field, "must not be empty"));
+            }
+            if !seen.insert(id.to_string()) {
+                return Err(ProjectError::schema(field, format!("duplicate id {}", id)));
+            }
+            Ok(())
+        }
+
+        let mut chapter_ids = HashSet::new();
+        let mut segment_ids = HashSet::new();
+        for (c, chapter) in self.chapters.iter().enumerate() {
+            check_id(&mut chapter_ids, &chapter.id, format!("chapters[{}].id", c))?;
+            for (s, segment) in chapter.segments.iter().enumerate() {
+                check_id(&mut segment_ids, &segment.id, format!("chapters[{}].segments[{}].id", c, s))?;
+                if segment.end_time < segment.start_time {
+                    return Err(ProjectError::schema(
+                        format!("chapters[{}].segments[{}].endTime", c, s),
+                        "ends before it starts",
+                    ));
+                }
+            }
+        }
+
+        let mut speaker_ids = HashSet::new();
+        for (index, speaker) in self.speakers.iter().enumerate() {
+            check_id(&mut speaker_ids, &speaker.id, format!("speakers[{}].id", index))?;
+        }
+        let mut rule_ids = HashSet::new();
+        for (index, rule) in self.pronunciation_rules.iter().enumerate() {
+            check_id(&mut rule_ids, &rule.id, format!("pronunciationRules[{}].id", index))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn v1_files_are_migrated_and_keep_unknown_fields() {
+        let v1 = json!({
+            "title": "Moby Dick",
+            "coverArt": "cover.png",
+            "settings": null,
+            "chapters": [{
+                "id": "c1",
+                "title": "Loomings",
+                "order_index": 0,
+                "chapter_notes": "keep me",
+                "segments": [{
+                    "id": "s1",
+                    "text": "Call me Ishmael.",
+                    "segment_type": "standard",
+                    "tts_engine": "xtts",
+                    "tts_speaker_name": "Narrator",
+                    "start_time": 0.0,
+                    "end_time": 1.5,
+                    "mood": "calm",
+                }],
+            }],
+        });
+
+        let (project, version) = ProjectData::from_value(v1).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(project.schema_version, SCHEMA_VERSION);
+        assert_eq!(project.name, "Moby Dick");
+        assert_eq!(project.settings, ProjectSettings::default());
+        assert_eq!(project.extra["coverArt"], "cover.png");
+
+        let chapter = &project.chapters[0];
+        assert_eq!(chapter.extra["chapterNotes"], "keep me");
+        let segment = &chapter.segments[0];
+        assert_eq!(segment.tts_engine, "xtts");
+        assert_eq!(segment.tts_speaker_name.as_deref(), Some("Narrator"));
+        assert_eq!(segment.end_time, 1.5);
+        assert_eq!(segment.extra["mood"], "calm");
+
+        // Saving and loading again is lossless and needs no migration
+        let (reloaded, version) = ProjectData::parse(&project.to_json().unwrap()).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert_eq!(reloaded, project);
+    }
+
+    #[test]
+    fn camel_case_keys_win_over_their_snake_case_twins() {
+        let mut chapter = json!({ "orderIndex": 2, "order_index": 1 }).as_object().unwrap().clone();
+        camel_keys(&mut chapter);
+        assert_eq!(Value::Object(chapter), json!({ "orderIndex": 2 }));
+    }
+
+    #[test]
+    fn newer_files_are_rejected() {
+        let result = ProjectData::from_value(json!({ "schemaVersion": SCHEMA_VERSION + 1, "name": "Next" }));
+        assert!(matches!(result, Err(ProjectError::UnsupportedVersion { .. })));
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let result = ProjectData::from_value(json!({
+            "schemaVersion": SCHEMA_VERSION,
+            "name": "Twice",
+            "chapters": [{ "id": "c1" }, { "id": "c1" }],
+        }));
+        assert!(matches!(result, Err(ProjectError::Schema { .. })));
+    }
+}
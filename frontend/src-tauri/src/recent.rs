@@ -0,0 +1,119 @@
+//! Most-recently-used project files, persisted in the app config directory
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::atomic::write_atomic;
+use crate::project::ProjectError;
+
+/// File name inside the app config directory
+pub const RECENT_FILE: &str = "recent-projects.json";
+/// Unpinned entries kept; pinned ones are never dropped
+const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentProject {
+    pub path: String,
+    /// Project name at the time it was last opened or saved
+    pub name: String,
+    #[serde(default)]
+    pub pinned: bool,
+    /// RFC 3339
+    pub last_opened: String,
+    /// Whether the file is still there; filled in when listing
+    #[serde(default, skip_deserializing)]
+    pub exists: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct RecentProjects {
+    path: Option<PathBuf>,
+    entries: Vec<RecentProject>,
+}
+
+/// Compare paths the way the file system does, so `./a.abm` and `/x/a.abm` are one entry
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+impl RecentProjects {
+    /// Load the list from `path`; a missing or unreadable file starts an empty list
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), entries }
+    }
+
+    /// Pinned entries first, then the most recently opened
+    pub fn list(&self) -> Vec<RecentProject> {
+        let mut entries = self.entries.clone();
+        for entry in &mut entries {
+            entry.exists = Path::new(&entry.path).is_file();
+        }
+        // RFC 3339 timestamps in UTC sort chronologically as strings
+        entries.sort_by(|a, b| b.pinned.cmp(&a.pinned).then_with(|| b.last_opened.cmp(&a.last_opened)));
+        entries
+    }
+
+    /// Move `path` to the top of the list, keeping its pin
+    pub fn touch(&mut self, path: &str, name: &str) -> Result<(), ProjectError> {
+        let pinned = self
+            .entries
+            .iter()
+            .position(|entry| same_file(&entry.path, path))
+            .map(|index| self.entries.remove(index).pinned)
+            .unwrap_or(false);
+        self.entries.push(RecentProject {
+            path: path.to_string(),
+            name: name.to_string(),
+            pinned,
+            last_opened: chrono::Utc::now().to_rfc3339(),
+            exists: true,
+        });
+
+        self.entries.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
+        let mut unpinned = 0;
+        self.entries.retain(|entry| {
+            if !entry.pinned {
+                unpinned += 1;
+            }
+            entry.pinned || unpinned <= MAX_RECENT
+        });
+        self.save()
+    }
+
+    pub fn set_pinned(&mut self, path: &str, pinned: bool) -> Result<(), ProjectError> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| same_file(&entry.path, path))
+            .ok_or_else(|| ProjectError::NotRecent { path: path.to_string() })?;
+        entry.pinned = pinned;
+        self.save()
+    }
+
+    pub fn remove(&mut self, path: &str) -> Result<(), ProjectError> {
+        self.entries.retain(|entry| !same_file(&entry.path, path));
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), ProjectError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let display = path.display().to_string();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| ProjectError::io(&display, e))?;
+        }
+        let json = serde_json::to_vec_pretty(&self.entries).map_err(|e| ProjectError::io(&display, e))?;
+        write_atomic(path, &json).map_err(|e| ProjectError::io(&display, e))
+    }
+}
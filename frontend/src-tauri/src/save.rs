@@ -0,0 +1,258 @@
+//! Crash-safe saving of project files with rotating backups
+//!
+//! New content goes to a temp file in the same directory and is fsynced before
+//! anything else is touched, so a full disk fails the save without harming the
+//! existing file. The previous version then becomes `<name>.bak.1` (older ones
+//! move up to `.bak.N`) and the temp file is renamed over the project.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::atomic::AtomicFile;
+use crate::project::{ProjectData, ProjectError};
+
+/// Backups kept when the caller does not say otherwise
+pub const DEFAULT_KEEP_BACKUPS: u32 = 5;
+/// Upper bound for the configurable number of backups
+const MAX_KEEP_BACKUPS: u32 = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedProject {
+    pub path: String,
+    /// SHA-256 of the written file
+    pub revision: String,
+    /// Where the previous version went, if there was one and backups are enabled
+    pub backup_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBackup {
+    /// 1 is the most recent
+    pub index: u32,
+    pub path: String,
+    /// When this version was saved, RFC 3339
+    pub saved_at: Option<String>,
+    pub size: u64,
+    /// Project name, if the backup still parses
+    pub name: Option<String>,
+}
+
+/// Content hash used to notice changes made behind the app's back
+pub fn revision(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// `<stem>.bak.<index>` next to the project file
+pub fn backup_path(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".bak.{}", index));
+    path.with_file_name(name)
+}
+
+/// Copy `from` to `to` with its modification time, which dates the backup
+///
+/// Not a hard link: an editor that rewrites the project in place would change the backup too.
+fn preserve(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    let modified = fs::metadata(from)?.modified()?;
+    File::options().write(true).open(to)?.set_modified(modified)
+}
+
+/// Shift `.bak.1..` up by one, dropping the oldest, and copy `path` to `.bak.1`
+fn rotate(path: &Path, keep: u32) -> io::Result<Option<PathBuf>> {
+    if keep == 0 || !path.is_file() {
+        return Ok(None);
+    }
+    // Also clears backups left over from a higher setting
+    let mut index = keep;
+    while backup_path(path, index).exists() {
+        fs::remove_file(backup_path(path, index))?;
+        index += 1;
+    }
+    for index in (1..keep).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    let first = backup_path(path, 1);
+    preserve(path, &first)?;
+    Ok(Some(first))
+}
+
+/// Write `data` to `path`, keeping `keep` backups of earlier versions
+///
+/// `expected` is the revision the caller last saw; if the file on disk has a
+/// different one, it was changed elsewhere and is left alone unless `force`.
+pub fn save(path: &Path, data: &[u8], expected: Option<&str>, force: bool, keep: u32) -> Result<SavedProject, ProjectError> {
+    let display = path.display().to_string();
+    if let (Some(expected), false) = (expected, force) {
+        match fs::read(path) {
+            Ok(current) if revision(&current) != expected => {
+                return Err(ProjectError::ChangedOnDisk { path: display });
+            }
+            // A file deleted in the meantime has nothing left to protect
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(ProjectError::io(&display, e)),
+        }
+    }
+
+    let mut file = AtomicFile::create(path).map_err(|e| ProjectError::io(&display, e))?;
+    file.file().write_all(data).map_err(|e| ProjectError::io(&display, e))?;
+    let backup = rotate(path, keep.min(MAX_KEEP_BACKUPS)).map_err(|e| ProjectError::io(&display, e))?;
+    file.commit().map_err(|e| ProjectError::io(&display, e))?;
+
+    Ok(SavedProject {
+        path: display,
+        revision: revision(data),
+        backup_path: backup.map(|path| path.display().to_string()),
+    })
+}
+
+/// Backups of the project at `path`, most recent first
+pub fn list_backups(path: &Path) -> Result<Vec<ProjectBackup>, ProjectError> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut prefix = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    prefix.push_str(".bak.");
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ProjectError::io(dir.display().to_string(), e)),
+    };
+    let mut backups: Vec<ProjectBackup> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let index: u32 = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            let meta = entry.metadata().ok().filter(|meta| meta.is_file())?;
+            let saved_at = meta
+                .modified()
+                .ok()
+                .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339());
+            let name = fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|text| ProjectData::from_json(&text).ok())
+                .map(|project| project.name);
+            Some(ProjectBackup {
+                index,
+                path: entry.path().display().to_string(),
+                saved_at,
+                size: meta.len(),
+                name,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|backup| backup.index);
+    Ok(backups)
+}
+
+/// Put backup `index` back in place of `path`, returning its content
+///
+/// The version being replaced is rotated into the backups like on any save,
+/// so a restore can itself be undone.
+pub fn restore_backup(path: &Path, index: u32, keep: u32) -> Result<(String, SavedProject), ProjectError> {
+    let backup = backup_path(path, index);
+    let content = fs::read_to_string(&backup).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => ProjectError::BackupNotFound { path: backup.display().to_string() },
+        _ => ProjectError::io(backup.display().to_string(), e),
+    })?;
+    // Refuse to restore something that would not open
+    ProjectData::parse(&content)?;
+    let saved = save(path, content.as_bytes(), None, true, keep.max(1))?;
+    Ok((content, saved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_project() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("save-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("book.abm")
+    }
+
+    fn project_json(name: &str) -> String {
+        format!(r#"{{"schemaVersion":2,"name":"{}"}}"#, name)
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn saving_rotates_backups_and_drops_the_oldest() {
+        let path = temp_project();
+        for version in 1..=4 {
+            save(&path, project_json(&format!("v{}", version)).as_bytes(), None, false, 2).unwrap();
+        }
+        assert_eq!(read(path.clone()), project_json("v4"));
+        assert_eq!(read(backup_path(&path, 1)), project_json("v3"));
+        assert_eq!(read(backup_path(&path, 2)), project_json("v2"));
+        assert!(!backup_path(&path, 3).exists());
+
+        let names: Vec<_> = list_backups(&path).unwrap().into_iter().map(|backup| backup.name).collect();
+        assert_eq!(names, [Some("v3".to_string()), Some("v2".to_string())]);
+
+        // Lowering the setting clears the backups above it
+        save(&path, project_json("v5").as_bytes(), None, false, 1).unwrap();
+        assert_eq!(read(backup_path(&path, 1)), project_json("v4"));
+        assert!(!backup_path(&path, 2).exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn changes_made_elsewhere_are_not_overwritten() {
+        let path = temp_project();
+        let saved = save(&path, project_json("mine").as_bytes(), None, false, 1).unwrap();
+        fs::write(&path, project_json("theirs")).unwrap();
+
+        let result = save(&path, project_json("mine again").as_bytes(), Some(&saved.revision), false, 1);
+        assert!(matches!(result, Err(ProjectError::ChangedOnDisk { .. })));
+        assert_eq!(read(path.clone()), project_json("theirs"));
+
+        save(&path, project_json("mine again").as_bytes(), Some(&saved.revision), true, 1).unwrap();
+        assert_eq!(read(path.clone()), project_json("mine again"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn restoring_a_backup_can_be_undone() {
+        let path = temp_project();
+        save(&path, project_json("old").as_bytes(), None, false, 0).unwrap();
+        save(&path, project_json("new").as_bytes(), None, false, 3).unwrap();
+
+        let (content, saved) = restore_backup(&path, 1, 3).unwrap();
+        assert_eq!(content, project_json("old"));
+        assert_eq!(saved.revision, revision(content.as_bytes()));
+        assert_eq!(read(path.clone()), project_json("old"));
+        assert_eq!(read(backup_path(&path, 1)), project_json("new"));
+
+        restore_backup(&path, 1, 3).unwrap();
+        assert_eq!(read(path.clone()), project_json("new"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn broken_or_missing_backups_are_not_restored() {
+        let path = temp_project();
+        save(&path, project_json("good").as_bytes(), None, false, 1).unwrap();
+        fs::write(backup_path(&path, 1), "{ not json").unwrap();
+
+        assert!(matches!(restore_backup(&path, 1, 1), Err(ProjectError::Syntax { .. })));
+        assert!(matches!(restore_backup(&path, 2, 1), Err(ProjectError::BackupNotFound { .. })));
+        assert_eq!(read(path.clone()), project_json("good"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
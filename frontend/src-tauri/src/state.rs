@@ -1,10 +1,37 @@
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use tokio::sync::Notify;
+
+use crate::journal::Autosave;
+use crate::launch::LaunchQueue;
+use crate::monitor::BackendMonitor;
+use crate::presets::PresetStore;
+use crate::profiles::{BackendProfile, ProfileStore};
+use crate::recent::RecentProjects;
+use crate::tunnel::TunnelManager;
+use crate::vault::Vault;
 
 #[derive(Debug, Default)]
 pub struct AppState {
     pub backend_url: Mutex<String>,
     pub backend_running: Mutex<bool>,
+    /// Signalled when `backend_url` points somewhere else, for the health monitor
+    pub backend_changed: Notify,
+    pub backend_monitor: Mutex<BackendMonitor>,
     pub last_project_path: Mutex<Option<String>>,
+    /// Signalled when `last_project_path` points somewhere else, for the file watcher
+    pub project_path_changed: Notify,
+    pub export_presets: Mutex<PresetStore>,
+    pub backend_profiles: Mutex<ProfileStore>,
+    pub recent_projects: Mutex<RecentProjects>,
+    pub vault: Mutex<Vault>,
+    pub tunnels: Mutex<TunnelManager>,
+    /// Content hash of each project file as this app last read or wrote it
+    pub project_revisions: Mutex<HashMap<String, String>>,
+    pub autosave: Mutex<Autosave>,
+    /// Project files from the command line or other launches, until the frontend takes them
+    pub launch: Mutex<LaunchQueue>,
 }
 
 impl AppState {
@@ -12,13 +39,27 @@ impl AppState {
         Self {
             backend_url: Mutex::new("http://127.0.0.1:8765".to_string()),
             backend_running: Mutex::new(false),
+            backend_changed: Notify::new(),
+            backend_monitor: Mutex::new(BackendMonitor::default()),
             last_project_path: Mutex::new(None),
+            project_path_changed: Notify::new(),
+            export_presets: Mutex::new(PresetStore::default()),
+            backend_profiles: Mutex::new(ProfileStore::default()),
+            recent_projects: Mutex::new(RecentProjects::default()),
+            vault: Mutex::new(Vault::default()),
+            tunnels: Mutex::new(TunnelManager::default()),
+            project_revisions: Mutex::new(HashMap::new()),
+            autosave: Mutex::new(Autosave::default()),
+            launch: Mutex::new(LaunchQueue::default()),
         }
     }
 
     pub fn set_backend_url(&self, url: String) {
         if let Ok(mut backend_url) = self.backend_url.lock() {
-            *backend_url = url;
+            if *backend_url != url {
+                *backend_url = url;
+                self.backend_changed.notify_one();
+            }
         }
     }
 
@@ -40,9 +81,16 @@ impl AppState {
             .unwrap_or(false)
     }
 
+    pub fn backend_monitor(&self) -> MutexGuard<'_, BackendMonitor> {
+        self.backend_monitor.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     pub fn set_last_project_path(&self, path: Option<String>) {
         if let Ok(mut last_path) = self.last_project_path.lock() {
-            *last_path = path;
+            if *last_path != path {
+                *last_path = path;
+                self.project_path_changed.notify_one();
+            }
         }
     }
 
@@ -51,4 +99,78 @@ impl AppState {
             .ok()
             .and_then(|path| path.clone())
     }
-}
\ No newline at end of file
+
+    pub fn set_export_presets(&self, store: PresetStore) {
+        *self.export_presets() = store;
+    }
+
+    /// The preset store; a panic while it was held leaves it usable
+    pub fn export_presets(&self) -> MutexGuard<'_, PresetStore> {
+        self.export_presets.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_backend_profiles(&self, store: ProfileStore) {
+        *self.backend_profiles() = store;
+    }
+
+    pub fn backend_profiles(&self) -> MutexGuard<'_, ProfileStore> {
+        self.backend_profiles.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_recent_projects(&self, recent: RecentProjects) {
+        *self.recent_projects() = recent;
+    }
+
+    pub fn recent_projects(&self) -> MutexGuard<'_, RecentProjects> {
+        self.recent_projects.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_vault(&self, vault: Vault) {
+        *self.vault() = vault;
+    }
+
+    pub fn vault(&self) -> MutexGuard<'_, Vault> {
+        self.vault.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn tunnels(&self) -> MutexGuard<'_, TunnelManager> {
+        self.tunnels.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The profile `url` belongs to: the active one if it is the URL in use, else one
+    /// with that URL or with a tunnel listening there
+    pub fn backend_profile_for(&self, url: &str) -> Option<BackendProfile> {
+        let url = url.trim_end_matches('/');
+        let in_use = self.get_backend_url().trim_end_matches('/') == url;
+        let tunnel = self.tunnels().list().into_iter().find(|tunnel| tunnel.local_url == url).map(|tunnel| tunnel.profile_id);
+        let profiles = self.backend_profiles();
+        if let Some(active) = profiles.active().filter(|_| in_use) {
+            return Some(active.clone());
+        }
+        profiles.list().into_iter().find(|profile| profile.url == url || tunnel.as_deref() == Some(profile.id.as_str()))
+    }
+
+    pub fn set_project_revision(&self, path: &str, revision: String) {
+        if let Ok(mut revisions) = self.project_revisions.lock() {
+            revisions.insert(path.to_string(), revision);
+        }
+    }
+
+    pub fn get_project_revision(&self, path: &str) -> Option<String> {
+        self.project_revisions.lock()
+            .ok()
+            .and_then(|revisions| revisions.get(path).cloned())
+    }
+
+    pub fn set_autosave(&self, autosave: Autosave) {
+        *self.autosave() = autosave;
+    }
+
+    pub fn autosave(&self) -> MutexGuard<'_, Autosave> {
+        self.autosave.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn launch(&self) -> MutexGuard<'_, LaunchQueue> {
+        self.launch.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
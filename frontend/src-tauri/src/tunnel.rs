@@ -0,0 +1,1023 @@
+//! SSH tunnels to backends that are only reachable through an SSH server
+//!
+//! A profile with a `tunnel` reaches its backend the way `ssh -L` would: the
+//! profile URL is the backend's address as seen from the SSH server, and the
+//! app talks to `127.0.0.1` on an ephemeral local port instead. Each tunnel
+//! runs on its own thread with libssh2 in non-blocking mode and carries all
+//! local connections over one session.
+//!
+//! The server's key has to be in known_hosts already and only key
+//! authentication is tried, so nothing ever prompts. Keepalives hold the
+//! session open; when it drops it is set up again with backoff, while the
+//! local port stays bound so the URL the app uses stays valid. Every change
+//! of state is passed to the tunnel's `on_change` callback.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use ssh2::{Channel, CheckResult, ErrorCode, HashType, KnownHostFileKind, Session};
+use tokio::sync::watch;
+use zeroize::Zeroizing;
+
+use crate::error::write_coded;
+use crate::profiles::BackendProfile;
+use crate::serialize_as_display;
+use crate::vault::Vault;
+
+/// Emitted with a `TunnelStatus` whenever a tunnel changes state
+pub const TUNNEL_STATE_EVENT: &str = "tunnel://state";
+
+/// How long `open_backend_tunnel` waits for the first connection
+pub const OPEN_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_SSH_PORT: u16 = 22;
+const DEFAULT_KEEPALIVE_SECS: u32 = 30;
+/// For the TCP connect and each step of the SSH handshake
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// First reconnect after a failure; doubles with each further one
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+/// Delays vary by up to this fraction either way
+const JITTER: f64 = 0.2;
+/// Pause of the forwarding loop when no connection had anything to do
+const IDLE_WAIT: Duration = Duration::from_millis(5);
+/// How often a waiting tunnel thread looks whether it was stopped
+const STOP_POLL: Duration = Duration::from_millis(100);
+const BUFFER_SIZE: usize = 32 * 1024;
+/// libssh2's LIBSSH2_ERROR_EAGAIN: a non-blocking call has to be repeated
+const ERROR_EAGAIN: i32 = -37;
+/// libssh2's LIBSSH2_ERROR_CHANNEL_FAILURE: the server refused to open the channel
+const ERROR_CHANNEL_FAILURE: i32 = -21;
+/// libssh2's LIBSSH2_ERROR_SOCKET_DISCONNECT: the server closed the connection
+const ERROR_SOCKET_DISCONNECT: i32 = -13;
+
+/// Errors raised while setting up or running an SSH tunnel
+#[derive(Debug, Clone)]
+pub enum TunnelError {
+    /// The profile does not exist or has no tunnel
+    NotConfigured { profile_id: String },
+    /// The profile URL has no host to forward to
+    InvalidTarget { url: String },
+    /// The key passphrase refers to a secret that is not available
+    SecretUnavailable { message: String },
+    Bind { message: String },
+    Connect { server: String, message: String },
+    Handshake { server: String, message: String },
+    /// The server's key is not in known_hosts
+    UnknownHostKey { server: String, fingerprint: String, known_hosts: String },
+    /// known_hosts has another key for the server
+    HostKeyMismatch { server: String, fingerprint: String, known_hosts: String },
+    KnownHosts { path: String, message: String },
+    Auth { server: String, username: String, message: String },
+    /// The session dropped after it was set up
+    SessionLost { server: String, message: String },
+    /// Stopped before it connected
+    Closed { server: String },
+    /// Still not connected when the caller stopped waiting
+    Timeout { server: String },
+}
+
+impl TunnelError {
+    /// Retrying will not help until the profile or known_hosts changes
+    pub fn is_fatal(&self) -> bool {
+        !matches!(
+            self,
+            Self::Connect { .. } | Self::Handshake { .. } | Self::SessionLost { .. } | Self::Timeout { .. }
+        )
+    }
+}
+
+impl fmt::Display for TunnelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotConfigured { profile_id } => write_coded(f, "TUNNEL_NOT_CONFIGURED", &[("profileId", profile_id)]),
+            Self::InvalidTarget { url } => write_coded(f, "TUNNEL_INVALID_TARGET", &[("url", url)]),
+            Self::SecretUnavailable { message } => write_coded(f, "TUNNEL_SECRET_UNAVAILABLE", &[("error", message)]),
+            Self::Bind { message } => write_coded(f, "TUNNEL_BIND_FAILED", &[("error", message)]),
+            Self::Connect { server, message } => {
+                write_coded(f, "TUNNEL_CONNECT_FAILED", &[("server", server), ("error", message)])
+            }
+            Self::Handshake { server, message } => {
+                write_coded(f, "TUNNEL_HANDSHAKE_FAILED", &[("server", server), ("error", message)])
+            }
+            Self::UnknownHostKey { server, fingerprint, known_hosts } => write_coded(
+                f,
+                "TUNNEL_UNKNOWN_HOST_KEY",
+                &[("server", server), ("fingerprint", fingerprint), ("knownHosts", known_hosts)],
+            ),
+            Self::HostKeyMismatch { server, fingerprint, known_hosts } => write_coded(
+                f,
+                "TUNNEL_HOST_KEY_MISMATCH",
+                &[("server", server), ("fingerprint", fingerprint), ("knownHosts", known_hosts)],
+            ),
+            Self::KnownHosts { path, message } => {
+                write_coded(f, "TUNNEL_KNOWN_HOSTS_FAILED", &[("path", path), ("error", message)])
+            }
+            Self::Auth { server, username, message } => write_coded(
+                f,
+                "TUNNEL_AUTH_FAILED",
+                &[("server", server), ("username", username), ("error", message)],
+            ),
+            Self::SessionLost { server, message } => {
+                write_coded(f, "TUNNEL_SESSION_LOST", &[("server", server), ("error", message)])
+            }
+            Self::Closed { server } => write_coded(f, "TUNNEL_CLOSED", &[("server", server)]),
+            Self::Timeout { server } => write_coded(f, "TUNNEL_TIMEOUT", &[("server", server)]),
+        }
+    }
+}
+
+impl std::error::Error for TunnelError {}
+
+serialize_as_display!(TunnelError);
+
+fn default_ssh_port() -> u16 {
+    DEFAULT_SSH_PORT
+}
+
+fn default_keepalive_secs() -> u32 {
+    DEFAULT_KEEPALIVE_SECS
+}
+
+/// The SSH server a profile's backend is reached through
+///
+/// An https backend is still spoken to by its profile URL's scheme, so its
+/// certificate has to be valid for 127.0.0.1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelConfig {
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    pub username: String,
+    /// Private key file, OpenSSH or PEM
+    pub key_path: String,
+    /// Better a `{{secret:name}}` reference than the passphrase itself, which
+    /// would be stored with the profile
+    #[serde(default)]
+    pub key_passphrase: Option<String>,
+    /// `None` uses `~/.ssh/known_hosts`
+    #[serde(default)]
+    pub known_hosts_path: Option<String>,
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_secs: u32,
+}
+
+impl TunnelConfig {
+    /// Trimmed, or the name of the first field that is missing or out of range
+    pub fn normalized(mut self) -> Result<Self, &'static str> {
+        self.host = self.host.trim().to_string();
+        self.username = self.username.trim().to_string();
+        self.key_path = self.key_path.trim().to_string();
+        self.known_hosts_path = self.known_hosts_path.map(|path| path.trim().to_string()).filter(|path| !path.is_empty());
+        self.key_passphrase = self.key_passphrase.filter(|passphrase| !passphrase.is_empty());
+        if self.host.is_empty() {
+            return Err("host");
+        }
+        if self.port == 0 {
+            return Err("port");
+        }
+        if self.username.is_empty() {
+            return Err("username");
+        }
+        if self.key_path.is_empty() {
+            return Err("keyPath");
+        }
+        if self.keepalive_secs == 0 {
+            return Err("keepaliveSecs");
+        }
+        Ok(self)
+    }
+
+    /// `user@host:port`
+    pub fn server(&self) -> String {
+        format!("{}@{}:{}", self.username, self.host, self.port)
+    }
+
+    fn known_hosts_file(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.known_hosts_path {
+            return Some(PathBuf::from(path));
+        }
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(Path::new(&home).join(".ssh").join("known_hosts"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TunnelState {
+    /// First attempt to set up the session
+    Connecting,
+    Connected,
+    /// Waiting to try again after the session dropped or could not be set up
+    Reconnecting,
+    /// Gave up; the profile or known_hosts has to change first
+    Failed,
+    Stopped,
+}
+
+/// Payload of `tunnel://state`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelStatus {
+    pub profile_id: String,
+    pub state: TunnelState,
+    /// `user@host:port` of the SSH server
+    pub server: String,
+    /// The profile URL with the local end of the tunnel as host and port
+    pub local_url: String,
+    pub local_port: u16,
+    /// Failed attempts since the session was last up
+    pub attempt: u32,
+    /// Local connections being forwarded
+    pub connections: usize,
+    /// Why the tunnel is not connected
+    pub error: Option<TunnelError>,
+    /// RFC 3339; when `state` last changed
+    pub since: String,
+}
+
+#[derive(Debug)]
+struct Tunnel {
+    config: TunnelConfig,
+    /// The profile URL it forwards to
+    url: String,
+    stop: Arc<AtomicBool>,
+    status: watch::Receiver<TunnelStatus>,
+}
+
+impl Tunnel {
+    fn is_running(&self) -> bool {
+        !matches!(self.status.borrow().state, TunnelState::Failed | TunnelState::Stopped)
+    }
+}
+
+/// The running tunnels, at most one per profile
+#[derive(Debug, Default)]
+pub struct TunnelManager {
+    tunnels: HashMap<String, Tunnel>,
+}
+
+impl TunnelManager {
+    /// Start the profile's tunnel, or keep the one that is running with the same
+    /// settings; `on_change` is called on the tunnel's thread with each new state
+    pub fn start(
+        &mut self,
+        profile: &BackendProfile,
+        vault: &Vault,
+        on_change: impl Fn(&TunnelStatus) + Send + 'static,
+    ) -> Result<watch::Receiver<TunnelStatus>, TunnelError> {
+        let config = profile
+            .tunnel
+            .clone()
+            .ok_or_else(|| TunnelError::NotConfigured { profile_id: profile.id.clone() })?;
+        if let Some(tunnel) = self.tunnels.get(&profile.id) {
+            if tunnel.is_running() && tunnel.config == config && tunnel.url == profile.url {
+                return Ok(tunnel.status.clone());
+            }
+        }
+        self.stop(&profile.id);
+
+        let target = target(&profile.url)?;
+        let passphrase = config
+            .key_passphrase
+            .as_deref()
+            .map(|passphrase| vault.resolve(passphrase))
+            .transpose()
+            .map_err(|e| TunnelError::SecretUnavailable { message: e.to_string() })?;
+        let bind_error = |e: io::Error| TunnelError::Bind { message: e.to_string() };
+        let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(bind_error)?;
+        listener.set_nonblocking(true).map_err(bind_error)?;
+        let local_port = listener.local_addr().map_err(bind_error)?.port();
+
+        let status = TunnelStatus {
+            profile_id: profile.id.clone(),
+            state: TunnelState::Connecting,
+            server: config.server(),
+            local_url: local_url(&profile.url, local_port)?,
+            local_port,
+            attempt: 0,
+            connections: 0,
+            error: None,
+            since: chrono::Utc::now().to_rfc3339(),
+        };
+        let (sender, receiver) = watch::channel(status);
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = Worker {
+            config: config.clone(),
+            target,
+            passphrase,
+            listener,
+            stop: stop.clone(),
+            status: sender,
+            on_change: Box::new(on_change),
+        };
+        std::thread::Builder::new()
+            .name(format!("ssh-tunnel-{}", config.host))
+            .spawn(move || worker.run())
+            .map_err(bind_error)?;
+        self.tunnels.insert(profile.id.clone(), Tunnel { config, url: profile.url.clone(), stop, status: receiver.clone() });
+        Ok(receiver)
+    }
+
+    /// Stop the profile's tunnel; its thread winds down in the background
+    pub fn stop(&mut self, profile_id: &str) -> bool {
+        match self.tunnels.remove(profile_id) {
+            Some(tunnel) => {
+                tunnel.stop.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop every tunnel but the profile's
+    pub fn stop_others(&mut self, profile_id: &str) {
+        let others: Vec<String> = self.tunnels.keys().filter(|id| *id != profile_id).cloned().collect();
+        for id in others {
+            self.stop(&id);
+        }
+    }
+
+    pub fn list(&self) -> Vec<TunnelStatus> {
+        self.tunnels.values().map(|tunnel| tunnel.status.borrow().clone()).collect()
+    }
+}
+
+/// Wait until the tunnel is connected or has given the reason it is not
+pub async fn wait_connected(
+    mut status: watch::Receiver<TunnelStatus>,
+    timeout: Duration,
+) -> Result<TunnelStatus, TunnelError> {
+    let waited = tokio::time::timeout(timeout, async {
+        // Errs only when the thread is gone, which the state shows as well
+        let _ = status.wait_for(|status| status.state != TunnelState::Connecting).await.map(|_| ());
+    })
+    .await;
+    let current = status.borrow().clone();
+    match (waited, current.state) {
+        (Err(_), _) => Err(TunnelError::Timeout { server: current.server }),
+        (Ok(()), TunnelState::Connected) => Ok(current),
+        (Ok(()), _) => Err(current.error.unwrap_or(TunnelError::Closed { server: current.server })),
+    }
+}
+
+/// Host and port of the backend as the SSH server sees it
+fn target(url: &str) -> Result<(String, u16), TunnelError> {
+    let invalid = || TunnelError::InvalidTarget { url: url.to_string() };
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    let host = parsed.host_str().ok_or_else(invalid)?;
+    let port = parsed.port_or_known_default().ok_or_else(invalid)?;
+    Ok((host.trim_start_matches('[').trim_end_matches(']').to_string(), port))
+}
+
+fn local_url(url: &str, local_port: u16) -> Result<String, TunnelError> {
+    let invalid = || TunnelError::InvalidTarget { url: url.to_string() };
+    let mut parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    parsed.set_host(Some("127.0.0.1")).map_err(|_| invalid())?;
+    parsed.set_port(Some(local_port)).map_err(|_| invalid())?;
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+fn is_again(error: &ssh2::Error) -> bool {
+    error.code() == ErrorCode::Session(ERROR_EAGAIN)
+}
+
+fn would_block(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock
+}
+
+/// Time until the next attempt after `failures` failed ones
+fn retry_delay(failures: u32) -> Duration {
+    let delay = FIRST_RETRY.saturating_mul(1 << failures.saturating_sub(1).min(16)).min(MAX_RETRY);
+    delay.mul_f64(1.0 + JITTER * (fastrand::f64() * 2.0 - 1.0))
+}
+
+/// One tunnel's thread
+struct Worker {
+    config: TunnelConfig,
+    target: (String, u16),
+    passphrase: Option<Zeroizing<String>>,
+    /// Bound for the life of the tunnel, so its port outlives reconnects
+    listener: TcpListener,
+    stop: Arc<AtomicBool>,
+    status: watch::Sender<TunnelStatus>,
+    on_change: Box<dyn Fn(&TunnelStatus) + Send>,
+}
+
+impl Worker {
+    fn run(self) {
+        (self.on_change)(&self.status.borrow());
+        let mut failures = 0;
+        while !self.stopped() {
+            let error = match self.connect() {
+                Ok(session) => {
+                    failures = 0;
+                    self.set_state(TunnelState::Connected, 0, None);
+                    match self.forward(&session) {
+                        Ok(()) => break,
+                        Err(e) => e,
+                    }
+                }
+                Err(e) => e,
+            };
+            if self.stopped() {
+                break;
+            }
+            if error.is_fatal() {
+                self.set_state(TunnelState::Failed, failures, Some(error));
+                return;
+            }
+            failures += 1;
+            self.set_state(TunnelState::Reconnecting, failures, Some(error));
+            self.sleep(retry_delay(failures));
+        }
+        self.set_state(TunnelState::Stopped, failures, None);
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        while !self.stopped() {
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(STOP_POLL));
+        }
+    }
+
+    fn set_state(&self, state: TunnelState, attempt: u32, error: Option<TunnelError>) {
+        self.status.send_modify(|status| {
+            status.state = state;
+            status.attempt = attempt;
+            status.error = error;
+            status.connections = 0;
+            status.since = chrono::Utc::now().to_rfc3339();
+        });
+        (self.on_change)(&self.status.borrow());
+    }
+
+    /// Connect, verify the server and authenticate; the session comes back non-blocking
+    fn connect(&self) -> Result<Session, TunnelError> {
+        let server = self.config.server();
+        let tcp = connect_tcp(&self.config.host, self.config.port)
+            .map_err(|e| TunnelError::Connect { server: server.clone(), message: e.to_string() })?;
+        let _ = tcp.set_nodelay(true);
+
+        let handshake_error = |e: ssh2::Error| TunnelError::Handshake { server: server.clone(), message: e.to_string() };
+        let mut session = Session::new().map_err(handshake_error)?;
+        session.set_tcp_stream(tcp);
+        session.set_timeout(CONNECT_TIMEOUT.as_millis() as u32);
+        session.handshake().map_err(handshake_error)?;
+        self.verify_host_key(&session)?;
+
+        let auth_error = |message: String| TunnelError::Auth {
+            server: server.clone(),
+            username: self.config.username.clone(),
+            message,
+        };
+        let passphrase = self.passphrase.as_deref().map(String::as_str);
+        session
+            .userauth_pubkey_file(&self.config.username, None, Path::new(&self.config.key_path), passphrase)
+            .map_err(|e| auth_error(e.to_string()))?;
+        if !session.authenticated() {
+            return Err(auth_error("key not accepted".to_string()));
+        }
+
+        session.set_keepalive(true, self.config.keepalive_secs);
+        session.set_blocking(false);
+        Ok(session)
+    }
+
+    fn verify_host_key(&self, session: &Session) -> Result<(), TunnelError> {
+        let server = self.config.server();
+        let (key, _) = session
+            .host_key()
+            .ok_or_else(|| TunnelError::Handshake { server: server.clone(), message: "no host key".to_string() })?;
+        let fingerprint = session
+            .host_key_hash(HashType::Sha256)
+            .map(|hash| format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)))
+            .unwrap_or_default();
+        let path = self.config.known_hosts_file().ok_or_else(|| TunnelError::KnownHosts {
+            path: "~/.ssh/known_hosts".to_string(),
+            message: "no home directory".to_string(),
+        })?;
+        let known_hosts_error = |message: String| TunnelError::KnownHosts { path: path.display().to_string(), message };
+
+        let mut known_hosts = session.known_hosts().map_err(|e| known_hosts_error(e.to_string()))?;
+        match fs::read_to_string(&path) {
+            // Line by line: libssh2 gives up on the whole file at the first
+            // entry it cannot parse, such as a key type it does not know
+            Ok(content) => {
+                for line in content.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')) {
+                    let _ = known_hosts.read_str(line, KnownHostFileKind::OpenSSH);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(known_hosts_error(e.to_string())),
+        }
+        let known_hosts_path = path.display().to_string();
+        match known_hosts.check_port(&self.config.host, self.config.port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::NotFound => Err(TunnelError::UnknownHostKey { server, fingerprint, known_hosts: known_hosts_path }),
+            CheckResult::Mismatch => Err(TunnelError::HostKeyMismatch { server, fingerprint, known_hosts: known_hosts_path }),
+            CheckResult::Failure => Err(known_hosts_error("check failed".to_string())),
+        }
+    }
+
+    /// Forward local connections until the session drops (`Err`) or the tunnel is stopped
+    fn forward(&self, session: &Session) -> Result<(), TunnelError> {
+        let lost = |message: String| TunnelError::SessionLost { server: self.config.server(), message };
+        let mut waiting: VecDeque<TcpStream> = VecDeque::new();
+        let mut connections: Vec<Connection> = Vec::new();
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut next_keepalive = Instant::now();
+
+        while !self.stopped() {
+            let mut busy = false;
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        waiting.push_back(stream);
+                    }
+                    busy = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(e) => eprintln!("SSH tunnel via {} cannot accept a connection: {}", self.config.server(), e),
+            }
+
+            // libssh2 opens one channel at a time; an open that would block is repeated as is
+            if !waiting.is_empty() {
+                match session.channel_direct_tcpip(&self.target.0, self.target.1, None) {
+                    Ok(channel) => {
+                        if let Some(local) = waiting.pop_front() {
+                            connections.push(Connection::new(local, channel));
+                        }
+                        busy = true;
+                    }
+                    Err(e) if is_again(&e) => {}
+                    Err(e) if e.code() == ErrorCode::Session(ERROR_CHANNEL_FAILURE) => {
+                        eprintln!("{} refused to forward to {}:{}: {}", self.config.server(), self.target.0, self.target.1, e);
+                        waiting.pop_front();
+                        busy = true;
+                    }
+                    Err(e) => return Err(lost(e.to_string())),
+                }
+            }
+
+            let open = connections.len();
+            let mut disconnected = None;
+            connections.retain_mut(|connection| {
+                if !connection.finished {
+                    match connection.pump(&mut buffer) {
+                        Ok(progress) => busy |= progress,
+                        Err(e) => {
+                            // libssh2 takes note when a read finds the server gone, but
+                            // keepalives go on reporting success afterwards
+                            if ssh2::Error::last_session_error(session)
+                                .is_some_and(|error| error.code() == ErrorCode::Session(ERROR_SOCKET_DISCONNECT))
+                            {
+                                disconnected = Some(e.to_string());
+                            }
+                            eprintln!("Closing a connection through {}: {}", self.config.server(), e);
+                            connection.finished = true;
+                            // Could be the session rather than the connection; find out now
+                            next_keepalive = Instant::now();
+                        }
+                    }
+                    connection.finished |= connection.is_done();
+                }
+                !(connection.finished && connection.close())
+            });
+            if let Some(message) = disconnected {
+                return Err(lost(message));
+            }
+            if connections.len() != open {
+                self.status.send_modify(|status| status.connections = connections.len());
+            }
+
+            if Instant::now() >= next_keepalive {
+                match session.keepalive_send() {
+                    Ok(seconds) => next_keepalive = Instant::now() + Duration::from_secs(seconds.max(1).into()),
+                    Err(e) if is_again(&e) => {}
+                    Err(e) => return Err(lost(e.to_string())),
+                }
+            }
+            if !busy {
+                std::thread::sleep(IDLE_WAIT);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn connect_tcp(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "host has no address");
+    let addresses: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+/// A local connection and the channel it is forwarded over
+struct Connection {
+    local: TcpStream,
+    channel: Channel,
+    to_remote: Vec<u8>,
+    to_local: Vec<u8>,
+    local_eof: bool,
+    /// `local_eof` was passed on to the backend
+    eof_sent: bool,
+    remote_eof: bool,
+    local_shut: bool,
+    finished: bool,
+}
+
+impl Connection {
+    fn new(local: TcpStream, channel: Channel) -> Self {
+        Self {
+            local,
+            channel,
+            to_remote: Vec::new(),
+            to_local: Vec::new(),
+            local_eof: false,
+            eof_sent: false,
+            remote_eof: false,
+            local_shut: false,
+            finished: false,
+        }
+    }
+
+    /// Move what can be moved without blocking; `Ok(true)` if anything was
+    fn pump(&mut self, buffer: &mut [u8]) -> io::Result<bool> {
+        let mut progress = false;
+
+        if !self.local_eof && self.to_remote.is_empty() {
+            match self.local.read(buffer) {
+                Ok(0) => {
+                    self.local_eof = true;
+                    progress = true;
+                }
+                Ok(read) => {
+                    self.to_remote.extend_from_slice(&buffer[..read]);
+                    progress = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.to_remote.is_empty() {
+            match self.channel.write(&self.to_remote) {
+                Ok(written) => {
+                    self.to_remote.drain(..written);
+                    progress |= written > 0;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(e) => return Err(e),
+            }
+        } else if self.local_eof && !self.eof_sent {
+            match self.channel.send_eof() {
+                Ok(()) => {
+                    self.eof_sent = true;
+                    progress = true;
+                }
+                Err(e) if is_again(&e) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        if !self.remote_eof && self.to_local.is_empty() {
+            match self.channel.read(buffer) {
+                Ok(0) => {
+                    if self.channel.eof() {
+                        self.remote_eof = true;
+                        progress = true;
+                    }
+                }
+                Ok(read) => {
+                    self.to_local.extend_from_slice(&buffer[..read]);
+                    progress = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.to_local.is_empty() {
+            match self.local.write(&self.to_local) {
+                Ok(written) => {
+                    self.to_local.drain(..written);
+                    progress |= written > 0;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(e) => return Err(e),
+            }
+        } else if self.remote_eof && !self.local_shut {
+            // Nothing more is coming from the backend
+            let _ = self.local.shutdown(Shutdown::Write);
+            self.local_shut = true;
+        }
+        Ok(progress)
+    }
+
+    fn is_done(&self) -> bool {
+        self.local_eof && self.remote_eof && self.to_local.is_empty()
+    }
+
+    /// Close the channel; `false` while that would block. Freeing a channel
+    /// that is not closed yet would block, or leak it in non-blocking mode
+    fn close(&mut self) -> bool {
+        !matches!(self.channel.close(), Err(e) if is_again(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use russh::keys::ssh_key::rand_core::OsRng;
+    use russh::keys::ssh_key::LineEnding;
+    use russh::keys::{Algorithm, HashAlg, PrivateKey, PublicKey};
+    use russh::server::{Auth, Handler, Msg, RunningServerHandle, Server as _, Session as ServerSession};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::Notify;
+
+    use super::*;
+
+    #[test]
+    fn target_is_the_backend_as_the_server_sees_it() {
+        assert_eq!(target("http://10.0.0.5:8765").unwrap(), ("10.0.0.5".to_string(), 8765));
+        assert_eq!(target("https://backend.internal/api").unwrap(), ("backend.internal".to_string(), 443));
+        assert_eq!(target("http://[fd00::5]:8765").unwrap(), ("fd00::5".to_string(), 8765));
+        assert!(matches!(target("backend:8765"), Err(TunnelError::InvalidTarget { .. })));
+    }
+
+    #[test]
+    fn local_url_keeps_scheme_and_path() {
+        assert_eq!(local_url("http://10.0.0.5:8765", 40000).unwrap(), "http://127.0.0.1:40000");
+        assert_eq!(local_url("https://[fd00::5]/backend/", 40000).unwrap(), "https://127.0.0.1:40000/backend");
+        assert_eq!(local_url("http://[::1]:8765", 40001).unwrap(), "http://127.0.0.1:40001");
+    }
+
+    /// SSH server standing in for sshd: accepts one client key and forwards direct-tcpip channels
+    #[derive(Clone)]
+    struct StandIn {
+        client_key: PublicKey,
+    }
+
+    impl russh::server::Server for StandIn {
+        type Handler = Self;
+
+        fn new_client(&mut self, _: Option<std::net::SocketAddr>) -> Self {
+            self.clone()
+        }
+    }
+
+    impl Handler for StandIn {
+        type Error = russh::Error;
+
+        async fn auth_publickey(&mut self, _: &str, key: &PublicKey) -> Result<Auth, Self::Error> {
+            Ok(if key.key_data() == self.client_key.key_data() { Auth::Accept } else { Auth::reject() })
+        }
+
+        async fn channel_open_direct_tcpip(
+            &mut self,
+            channel: russh::Channel<Msg>,
+            host: &str,
+            port: u32,
+            _: &str,
+            _: u32,
+            _: &mut ServerSession,
+        ) -> Result<bool, Self::Error> {
+            let Ok(mut backend) = tokio::net::TcpStream::connect((host, port as u16)).await else {
+                return Ok(false);
+            };
+            tokio::spawn(async move {
+                let mut stream = channel.into_stream();
+                let _ = tokio::io::copy_bidirectional(&mut stream, &mut backend).await;
+            });
+            Ok(true)
+        }
+    }
+
+    /// Keys and known_hosts in a temporary directory, and the stand-in server behind a relay
+    struct Fixture {
+        dir: PathBuf,
+        host_key: PrivateKey,
+        /// The relay's port, which the tunnel connects to
+        port: u16,
+        /// Drops every relayed connection at once, like a network or server going away
+        cut: Arc<Notify>,
+        server: RunningServerHandle,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("tunnel-test-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&dir).unwrap();
+            let client = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+            fs::write(dir.join("id_ed25519"), client.to_openssh(LineEnding::LF).unwrap().as_bytes()).unwrap();
+            let host_key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let server_address = listener.local_addr().unwrap();
+            let config = Arc::new(russh::server::Config {
+                keys: vec![host_key.clone()],
+                auth_rejection_time: Duration::from_millis(10),
+                auth_rejection_time_initial: Some(Duration::ZERO),
+                ..Default::default()
+            });
+            let mut stand_in = StandIn { client_key: client.public_key().clone() };
+            let (handle_sender, handle) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move {
+                let running = stand_in.run_on_socket(config, &listener);
+                let _ = handle_sender.send(running.handle());
+                let _ = running.await;
+            });
+
+            let relay = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = relay.local_addr().unwrap().port();
+            let cut = Arc::new(Notify::new());
+            let relay_cut = cut.clone();
+            tokio::spawn(async move {
+                while let Ok((mut client, _)) = relay.accept().await {
+                    let cut = relay_cut.clone();
+                    tokio::spawn(async move {
+                        let Ok(mut server) = tokio::net::TcpStream::connect(server_address).await else {
+                            return;
+                        };
+                        tokio::select! {
+                            _ = tokio::io::copy_bidirectional(&mut client, &mut server) => {}
+                            _ = cut.notified() => {}
+                        }
+                    });
+                }
+            });
+
+            Self { dir, host_key, port, cut, server: handle.await.unwrap() }
+        }
+
+        /// known_hosts with `key` for the server
+        fn trust(&self, key: &PublicKey) {
+            let line = format!("[127.0.0.1]:{} {}\n", self.port, key.to_openssh().unwrap());
+            fs::write(self.dir.join("known_hosts"), line).unwrap();
+        }
+
+        fn fingerprint(&self) -> String {
+            self.host_key.public_key().fingerprint(HashAlg::Sha256).to_string()
+        }
+
+        fn profile(&self, backend_port: u16) -> BackendProfile {
+            serde_json::from_value(serde_json::json!({
+                "id": "remote",
+                "name": "Remote",
+                "url": format!("http://127.0.0.1:{}", backend_port),
+                "tunnel": {
+                    "host": "127.0.0.1",
+                    "port": self.port,
+                    "username": "tester",
+                    "keyPath": self.dir.join("id_ed25519"),
+                    "knownHostsPath": self.dir.join("known_hosts"),
+                    "keepaliveSecs": 1,
+                },
+            }))
+            .unwrap()
+        }
+
+        /// A tunnel to an echo backend, once it is connected
+        async fn connected_tunnel(&self) -> (TunnelManager, watch::Receiver<TunnelStatus>, TunnelStatus) {
+            self.trust(self.host_key.public_key());
+            let mut tunnels = TunnelManager::default();
+            let status = tunnels.start(&self.profile(echo_backend().await), &Vault::default(), |_| {}).unwrap();
+            let connected = wait_connected(status.clone(), Duration::from_secs(10)).await.unwrap();
+            (tunnels, status, connected)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            self.server.shutdown(String::new());
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Echoes whatever it receives; returns its port
+    async fn echo_backend() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = stream.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+        port
+    }
+
+    async fn connect(local_port: u16) -> tokio::net::TcpStream {
+        tokio::net::TcpStream::connect(("127.0.0.1", local_port)).await.unwrap()
+    }
+
+    async fn round_trip(stream: &mut tokio::net::TcpStream, message: &[u8]) -> Vec<u8> {
+        stream.write_all(message).await.unwrap();
+        let mut answer = vec![0; message.len()];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut answer)).await.unwrap().unwrap();
+        answer
+    }
+
+    async fn wait_for_state(status: &mut watch::Receiver<TunnelStatus>, state: TunnelState) -> TunnelStatus {
+        let reached = tokio::time::timeout(Duration::from_secs(10), status.wait_for(|status| status.state == state))
+            .await
+            .ok()
+            .and_then(|reached| reached.ok().map(|status| status.clone()));
+        reached.unwrap_or_else(|| panic!("tunnel is still {:?}, not {:?}", status.borrow().state, state))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn unknown_host_key_is_fatal() {
+        let fixture = Fixture::new().await;
+        let mut tunnels = TunnelManager::default();
+        let status = tunnels.start(&fixture.profile(echo_backend().await), &Vault::default(), |_| {}).unwrap();
+
+        match wait_connected(status.clone(), Duration::from_secs(10)).await {
+            Err(TunnelError::UnknownHostKey { fingerprint, .. }) => assert_eq!(fingerprint, fixture.fingerprint()),
+            other => panic!("expected an unknown host key, got {:?}", other),
+        }
+        assert_eq!(status.borrow().state, TunnelState::Failed);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mismatched_host_key_is_fatal() {
+        let fixture = Fixture::new().await;
+        fixture.trust(PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap().public_key());
+        let mut tunnels = TunnelManager::default();
+        let status = tunnels.start(&fixture.profile(echo_backend().await), &Vault::default(), |_| {}).unwrap();
+
+        match wait_connected(status.clone(), Duration::from_secs(10)).await {
+            Err(TunnelError::HostKeyMismatch { fingerprint, .. }) => assert_eq!(fingerprint, fixture.fingerprint()),
+            other => panic!("expected a host key mismatch, got {:?}", other),
+        }
+        assert_eq!(status.borrow().state, TunnelState::Failed);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn forwards_through_the_local_port() {
+        let fixture = Fixture::new().await;
+        let (mut tunnels, mut status, connected) = fixture.connected_tunnel().await;
+        assert_eq!(connected.local_url, format!("http://127.0.0.1:{}", connected.local_port));
+
+        let (mut first, mut second) = (connect(connected.local_port).await, connect(connected.local_port).await);
+        assert_eq!(round_trip(&mut first, b"first").await, b"first");
+        assert_eq!(round_trip(&mut second, b"second").await, b"second");
+        assert_eq!(round_trip(&mut first, b"first again").await, b"first again");
+
+        assert!(tunnels.stop("remote"));
+        wait_for_state(&mut status, TunnelState::Stopped).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reconnects_on_the_same_port_after_the_session_drops() {
+        let fixture = Fixture::new().await;
+        let (_tunnels, mut status, connected) = fixture.connected_tunnel().await;
+
+        // Idle, so only the keepalive can notice
+        fixture.cut.notify_waiters();
+        let reconnecting = wait_for_state(&mut status, TunnelState::Reconnecting).await;
+        assert!(matches!(reconnecting.error, Some(TunnelError::SessionLost { .. })));
+        let reconnected = wait_for_state(&mut status, TunnelState::Connected).await;
+        assert_eq!(reconnected.local_port, connected.local_port);
+        let mut stream = connect(reconnected.local_port).await;
+        assert_eq!(round_trip(&mut stream, b"after reconnecting").await, b"after reconnecting");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn open_connections_notice_the_server_disconnecting() {
+        let fixture = Fixture::new().await;
+        let (_tunnels, mut status, connected) = fixture.connected_tunnel().await;
+        let mut stream = connect(connected.local_port).await;
+        assert_eq!(round_trip(&mut stream, b"before").await, b"before");
+
+        // The server says goodbye but keeps its end of the socket open, so keepalives still go through
+        fixture.server.shutdown("going away".to_string());
+        let reconnecting = wait_for_state(&mut status, TunnelState::Reconnecting).await;
+        assert!(matches!(reconnecting.error, Some(TunnelError::SessionLost { .. })));
+    }
+}
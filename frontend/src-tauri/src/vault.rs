@@ -0,0 +1,439 @@
+//! Encrypted store for backend tokens and other secrets, in the app data directory
+//!
+//! The secrets are kept as one JSON map, encrypted with XChaCha20-Poly1305
+//! under a key derived from the user's passphrase with Argon2id. Unlocking
+//! decrypts the map into memory until the vault is locked again or the app
+//! exits. Values never go back to the webview: profile headers refer to
+//! them as `{{secret:name}}` and are filled in on the Rust side, right
+//! before a request goes out.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::atomic::write_atomic;
+use crate::error::write_coded;
+use crate::serialize_as_display;
+
+/// File name inside the app data directory
+pub const VAULT_FILE: &str = "credentials.vault";
+const VAULT_VERSION: u32 = 1;
+/// Bound into every ciphertext, so a blob cannot be passed off as another kind of data
+const ASSOCIATED_DATA: &[u8] = b"audiobook-maker credential vault v1";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+/// Argon2id cost for new vaults (OWASP's 64 MiB / 3 passes / 1 lane)
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+/// Upper bounds for the settings read back from a file, so a tampered one cannot ask for more than 1 GiB and 16 passes
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+const MAX_NAME_LEN: usize = 64;
+const REFERENCE_START: &str = "{{secret:";
+const REFERENCE_END: &str = "}}";
+
+/// Errors raised while managing the vault
+#[derive(Debug)]
+pub enum VaultError {
+    /// There is no app data directory to keep the vault in
+    Unavailable,
+    Locked,
+    EmptyPassphrase,
+    WrongPassphrase,
+    /// The file is not a vault this app can read
+    Corrupt { path: String, message: String },
+    /// Names are 1-64 letters, digits, `.`, `_` or `-`
+    InvalidName { name: String },
+    NotFound { name: String },
+    Io { path: String, message: String },
+}
+
+impl VaultError {
+    pub fn io(path: &Path, error: impl fmt::Display) -> Self {
+        Self::Io { path: path.display().to_string(), message: error.to_string() }
+    }
+
+    fn corrupt(path: &Path, error: impl fmt::Display) -> Self {
+        Self::Corrupt { path: path.display().to_string(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable => write_coded(f, "VAULT_UNAVAILABLE", &[]),
+            Self::Locked => write_coded(f, "VAULT_LOCKED", &[]),
+            Self::EmptyPassphrase => write_coded(f, "VAULT_EMPTY_PASSPHRASE", &[]),
+            Self::WrongPassphrase => write_coded(f, "VAULT_WRONG_PASSPHRASE", &[]),
+            Self::Corrupt { path, message } => write_coded(f, "VAULT_CORRUPT", &[("path", path), ("error", message)]),
+            Self::InvalidName { name } => write_coded(f, "VAULT_INVALID_SECRET_NAME", &[("name", name)]),
+            Self::NotFound { name } => write_coded(f, "VAULT_SECRET_NOT_FOUND", &[("name", name)]),
+            Self::Io { path, message } => write_coded(f, "VAULT_IO_FAILED", &[("path", path), ("error", message)]),
+        }
+    }
+}
+
+impl std::error::Error for VaultError {}
+
+serialize_as_display!(VaultError);
+
+/// Argon2id settings a vault was created with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Kdf {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    /// Base64
+    salt: String,
+}
+
+/// On-disk layout; `ciphertext` decrypts to a `Secrets` JSON object
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultFile {
+    version: u32,
+    kdf: Kdf,
+    /// Base64, 24 bytes
+    nonce: String,
+    /// Base64, includes the Poly1305 tag
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Secrets<S: Ord> {
+    secrets: BTreeMap<S, S>,
+}
+
+/// A decrypted vault: its key and secrets, wiped from memory when dropped
+pub struct Opened {
+    key: Zeroizing<[u8; KEY_LEN]>,
+    kdf: Kdf,
+    secrets: BTreeMap<String, Zeroizing<String>>,
+}
+
+impl fmt::Debug for Opened {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Opened").field("secrets", &self.secrets.keys().collect::<Vec<_>>()).finish_non_exhaustive()
+    }
+}
+
+/// What the webview may know about the vault
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    /// A vault file exists; unlocking creates one otherwise
+    pub exists: bool,
+    pub unlocked: bool,
+    /// Names of the stored secrets; empty while locked
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Vault {
+    path: Option<PathBuf>,
+    opened: Option<Opened>,
+}
+
+impl Vault {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path: Some(path), opened: None }
+    }
+
+    pub fn path(&self) -> Result<PathBuf, VaultError> {
+        self.path.clone().ok_or(VaultError::Unavailable)
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        VaultStatus {
+            exists: self.path.as_ref().is_some_and(|path| path.exists()),
+            unlocked: self.opened.is_some(),
+            names: self.opened.iter().flat_map(|opened| opened.secrets.keys().cloned()).collect(),
+        }
+    }
+
+    /// Keep a vault decrypted by `open`
+    pub fn unlock(&mut self, opened: Opened) {
+        self.opened = Some(opened);
+    }
+
+    pub fn lock(&mut self) {
+        self.opened = None;
+    }
+
+    pub fn set(&mut self, name: &str, value: Zeroizing<String>) -> Result<(), VaultError> {
+        validate_name(name)?;
+        let path = self.path()?;
+        let opened = self.opened.as_mut().ok_or(VaultError::Locked)?;
+        let previous = opened.secrets.insert(name.to_string(), value);
+        let result = write_file(&path, opened);
+        if result.is_err() {
+            match previous {
+                Some(previous) => opened.secrets.insert(name.to_string(), previous),
+                None => opened.secrets.remove(name),
+            };
+        }
+        result
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), VaultError> {
+        let path = self.path()?;
+        let opened = self.opened.as_mut().ok_or(VaultError::Locked)?;
+        let previous = opened.secrets.remove(name).ok_or_else(|| VaultError::NotFound { name: name.to_string() })?;
+        let result = write_file(&path, opened);
+        if result.is_err() {
+            opened.secrets.insert(name.to_string(), previous);
+        }
+        result
+    }
+
+    /// Re-encrypt the vault under `new_passphrase` and leave it unlocked; `current` must open it
+    ///
+    /// Works on the secrets in memory while unlocked, so nothing stored since unlocking is lost.
+    /// Derives two keys; run it off the async runtime, with the vault lock held throughout.
+    pub fn rotate(&mut self, current: &str, new_passphrase: &str) -> Result<(), VaultError> {
+        if current.is_empty() {
+            return Err(VaultError::EmptyPassphrase);
+        }
+        let path = self.path()?;
+        let opened = match self.opened.as_mut() {
+            Some(opened) => {
+                let key = derive_key(current, &opened.kdf, &path)?;
+                if !keys_match(&key, &opened.key) {
+                    return Err(VaultError::WrongPassphrase);
+                }
+                opened
+            }
+            None => self.opened.insert(open(&path, current)?),
+        };
+        let (key, kdf) = new_key(new_passphrase, &path)?;
+        let previous = (std::mem::replace(&mut opened.key, key), std::mem::replace(&mut opened.kdf, kdf));
+        let result = write_file(&path, opened);
+        if result.is_err() {
+            (opened.key, opened.kdf) = previous;
+        }
+        result
+    }
+
+    /// Fill in the `{{secret:name}}` references in `value`
+    pub fn resolve(&self, value: &str) -> Result<Zeroizing<String>, VaultError> {
+        let mut resolved = Zeroizing::new(String::with_capacity(value.len()));
+        let mut rest = value;
+        while let Some(start) = rest.find(REFERENCE_START) {
+            let after = &rest[start + REFERENCE_START.len()..];
+            let Some(end) = after.find(REFERENCE_END) else {
+                break;
+            };
+            let name = &after[..end];
+            let opened = self.opened.as_ref().ok_or(VaultError::Locked)?;
+            let secret = opened.secrets.get(name).ok_or_else(|| VaultError::NotFound { name: name.to_string() })?;
+            resolved.push_str(&rest[..start]);
+            resolved.push_str(secret);
+            rest = &after[end + REFERENCE_END.len()..];
+        }
+        resolved.push_str(rest);
+        Ok(resolved)
+    }
+}
+
+/// Whether `value` refers to a secret
+pub fn has_reference(value: &str) -> bool {
+    value.contains(REFERENCE_START)
+}
+
+fn validate_name(name: &str) -> Result<(), VaultError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(VaultError::InvalidName { name: name.to_string() })
+    }
+}
+
+fn derive_key(passphrase: &str, kdf: &Kdf, path: &Path) -> Result<Zeroizing<[u8; KEY_LEN]>, VaultError> {
+    if kdf.memory_kib > MAX_MEMORY_KIB || kdf.iterations > MAX_ITERATIONS || kdf.parallelism > MAX_PARALLELISM {
+        return Err(VaultError::corrupt(path, "key derivation settings are out of range"));
+    }
+    let salt = BASE64.decode(&kdf.salt).map_err(|e| VaultError::corrupt(path, e))?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN))
+        .map_err(|e| VaultError::corrupt(path, e))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| VaultError::corrupt(path, e))?;
+    Ok(key)
+}
+
+/// Compare without an early exit, so the time taken says nothing about where two keys differ
+fn keys_match(a: &[u8; KEY_LEN], b: &[u8; KEY_LEN]) -> bool {
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Derive a key for `passphrase` with a fresh salt
+fn new_key(passphrase: &str, path: &Path) -> Result<(Zeroizing<[u8; KEY_LEN]>, Kdf), VaultError> {
+    if passphrase.is_empty() {
+        return Err(VaultError::EmptyPassphrase);
+    }
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = Kdf { memory_kib: MEMORY_KIB, iterations: ITERATIONS, parallelism: PARALLELISM, salt: BASE64.encode(salt) };
+    let key = derive_key(passphrase, &kdf, path)?;
+    Ok((key, kdf))
+}
+
+fn write_file(path: &Path, opened: &Opened) -> Result<(), VaultError> {
+    // Borrowed, so the only plaintext copy is the buffer that gets wiped
+    let secrets = Secrets {
+        secrets: opened.secrets.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect(),
+    };
+    let plaintext = Zeroizing::new(serde_json::to_vec(&secrets).map_err(|e| VaultError::io(path, e))?);
+
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(opened.key.as_ref().into())
+        .encrypt(&nonce, Payload { msg: &plaintext, aad: ASSOCIATED_DATA })
+        .map_err(|e| VaultError::io(path, e))?;
+    let file = VaultFile {
+        version: VAULT_VERSION,
+        kdf: opened.kdf.clone(),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| VaultError::io(dir, e))?;
+    }
+    let json = serde_json::to_vec_pretty(&file).map_err(|e| VaultError::io(path, e))?;
+    write_atomic(path, &json).map_err(|e| VaultError::io(path, e))
+}
+
+/// Decrypt the vault at `path`, or create an empty one there if there is none
+///
+/// Deriving the key takes a good fraction of a second on purpose; run it off the async runtime.
+pub fn open(path: &Path, passphrase: &str) -> Result<Opened, VaultError> {
+    if passphrase.is_empty() {
+        return Err(VaultError::EmptyPassphrase);
+    }
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let (key, kdf) = new_key(passphrase, path)?;
+            let opened = Opened { key, kdf, secrets: BTreeMap::new() };
+            write_file(path, &opened)?;
+            return Ok(opened);
+        }
+        Err(e) => return Err(VaultError::io(path, e)),
+    };
+    let file: VaultFile = serde_json::from_slice(&data).map_err(|e| VaultError::corrupt(path, e))?;
+    if file.version > VAULT_VERSION {
+        return Err(VaultError::corrupt(path, format!("version {} is newer than this app supports", file.version)));
+    }
+    let nonce = BASE64.decode(&file.nonce).map_err(|e| VaultError::corrupt(path, e))?;
+    if nonce.len() != 24 {
+        return Err(VaultError::corrupt(path, "bad nonce"));
+    }
+    let ciphertext = BASE64.decode(&file.ciphertext).map_err(|e| VaultError::corrupt(path, e))?;
+    let key = derive_key(passphrase, &file.kdf, path)?;
+    // Authentication fails for a wrong key and for tampered data alike
+    let plaintext = Zeroizing::new(
+        XChaCha20Poly1305::new(key.as_ref().into())
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: ASSOCIATED_DATA })
+            .map_err(|_| VaultError::WrongPassphrase)?,
+    );
+    let secrets: Secrets<String> = serde_json::from_slice(&plaintext).map_err(|e| VaultError::corrupt(path, e))?;
+    Ok(Opened {
+        key,
+        kdf: file.kdf,
+        secrets: secrets.secrets.into_iter().map(|(name, value)| (name, Zeroizing::new(value))).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault_path() -> PathBuf {
+        std::env::temp_dir().join(format!("vault-test-{}", uuid::Uuid::new_v4())).join(VAULT_FILE)
+    }
+
+    fn reopen(path: &Path, passphrase: &str) -> Result<Vault, VaultError> {
+        let mut vault = Vault::new(path.to_path_buf());
+        vault.unlock(open(path, passphrase)?);
+        Ok(vault)
+    }
+
+    #[test]
+    fn secrets_survive_locking_and_reopening() {
+        let path = vault_path();
+        let mut vault = reopen(&path, "correct horse").unwrap();
+        vault.set("token", Zeroizing::new("s3cret".to_string())).unwrap();
+        vault.lock();
+        assert!(matches!(vault.resolve("Bearer {{secret:token}}"), Err(VaultError::Locked)));
+
+        let vault = reopen(&path, "correct horse").unwrap();
+        assert_eq!(vault.resolve("Bearer {{secret:token}}").unwrap().as_str(), "Bearer s3cret");
+        assert_eq!(vault.status().names, ["token"]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let path = vault_path();
+        reopen(&path, "correct horse").unwrap();
+        assert!(matches!(open(&path, "battery staple"), Err(VaultError::WrongPassphrase)));
+        assert!(matches!(open(&path, ""), Err(VaultError::EmptyPassphrase)));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rotation_keeps_secrets_stored_since_unlocking() {
+        let path = vault_path();
+        let mut vault = reopen(&path, "old").unwrap();
+        vault.set("token", Zeroizing::new("s3cret".to_string())).unwrap();
+
+        assert!(matches!(vault.rotate("wrong", "new"), Err(VaultError::WrongPassphrase)));
+        assert!(vault.status().unlocked);
+        vault.rotate("old", "new").unwrap();
+        vault.set("other", Zeroizing::new("more".to_string())).unwrap();
+
+        assert!(matches!(open(&path, "old"), Err(VaultError::WrongPassphrase)));
+        let vault = reopen(&path, "new").unwrap();
+        assert_eq!(vault.status().names, ["other", "token"]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rotation_opens_a_locked_vault() {
+        let path = vault_path();
+        reopen(&path, "old").unwrap();
+        let mut vault = Vault::new(path.clone());
+        assert!(matches!(vault.rotate("wrong", "new"), Err(VaultError::WrongPassphrase)));
+        vault.rotate("old", "new").unwrap();
+        assert!(vault.status().unlocked);
+        assert!(open(&path, "new").is_ok());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn oversized_key_derivation_settings_are_rejected() {
+        let path = vault_path();
+        reopen(&path, "correct horse").unwrap();
+        let mut file: VaultFile = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        file.kdf.memory_kib = u32::MAX;
+        fs::write(&path, serde_json::to_vec(&file).unwrap()).unwrap();
+        assert!(matches!(open(&path, "correct horse"), Err(VaultError::Corrupt { .. })));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
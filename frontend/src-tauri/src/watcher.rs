@@ -0,0 +1,176 @@
+//! Watches the open project file for changes made outside the app
+//!
+//! The watch is on the file's directory rather than the file itself: scripts,
+//! editors and sync clients (and this app) usually replace a file by renaming
+//! a temp file over it, which would end a watch on the old inode. Events are
+//! collected until things are quiet for `DEBOUNCE`, then the file's content
+//! hash is compared with the revision this app last read or wrote, so our own
+//! saves and no-op touches stay silent.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::save;
+use crate::state::AppState;
+
+/// Emitted with a `ProjectChange`
+pub const PROJECT_CHANGED_EVENT: &str = "project://changed";
+/// Quiet period after the last event before the file is looked at
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "kind")]
+pub enum ProjectChange {
+    /// The content differs from what the app last read or wrote
+    Modified {
+        path: String,
+        revision: String,
+        /// The app has edits of its own that reloading would lose
+        has_unsaved_edits: bool,
+    },
+    Deleted { path: String },
+    /// Moved away, as far as the platform tells; `new_path` is where it went
+    Renamed { path: String, new_path: String },
+}
+
+/// What a burst of events amounted to
+#[derive(Debug, Default)]
+struct Burst {
+    /// Something happened to the project file
+    relevant: bool,
+    /// The project file was renamed to this path
+    renamed_to: Option<PathBuf>,
+    /// Saw the first half of a rename away from the project file
+    renaming: bool,
+}
+
+impl Burst {
+    fn note(&mut self, event: &Event, name: &OsString) {
+        let is_target = |path: &Path| path.file_name() == Some(name.as_os_str());
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 && is_target(&event.paths[0]) => {
+                self.renamed_to = Some(event.paths[1].clone());
+            }
+            // Platforms that report the two halves of a rename separately
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) if event.paths.iter().any(|path| is_target(path)) => {
+                self.renaming = true;
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) if self.renaming => {
+                self.renaming = false;
+                self.renamed_to = event.paths.iter().find(|path| !is_target(path)).cloned();
+            }
+            EventKind::Access(_) => return,
+            _ => {}
+        }
+        self.relevant |= event.paths.iter().any(|path| is_target(path));
+    }
+}
+
+/// Keep a watch on `AppState::last_project_path`, following it as it changes; runs for the life of the app
+pub async fn run_watcher(app: AppHandle) {
+    let state = app.state::<AppState>();
+    loop {
+        let changed = state.project_path_changed.notified();
+        tokio::pin!(changed);
+        if let Some(path) = state.get_last_project_path() {
+            tokio::select! {
+                _ = &mut changed => continue,
+                _ = watch(&app, PathBuf::from(path)) => {}
+            }
+        }
+        changed.await;
+    }
+}
+
+/// Report changes to `path` until the watch fails
+async fn watch(app: &AppHandle, path: PathBuf) {
+    let Some(name) = path.file_name().map(OsString::from) else {
+        return;
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Cannot watch {}: {}", path.display(), e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        eprintln!("Cannot watch {}: {}", dir.display(), e);
+        return;
+    }
+
+    let display = path.display().to_string();
+    let state = app.state::<AppState>();
+    // Revision last reported as modified, so one external change is one event
+    let mut reported: Option<String> = None;
+    let mut gone = false;
+
+    while let Some(first) = rx.recv().await {
+        let mut burst = Burst::default();
+        let mut next = Some(first);
+        loop {
+            match next.take() {
+                Some(Ok(event)) => burst.note(&event, &name),
+                Some(Err(e)) => eprintln!("Watching {}: {}", display, e),
+                None => {}
+            }
+            match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                Ok(Some(event)) => next = Some(event),
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+        if !burst.relevant {
+            continue;
+        }
+
+        let change = match tokio::fs::read(&path).await {
+            Ok(data) => {
+                gone = false;
+                let revision = save::revision(&data);
+                let known = state.get_project_revision(&display);
+                if known.as_deref() == Some(revision.as_str()) || reported.as_deref() == Some(revision.as_str()) {
+                    continue;
+                }
+                reported = Some(revision.clone());
+                ProjectChange::Modified {
+                    path: display.clone(),
+                    revision,
+                    has_unsaved_edits: state.autosave().has_unsaved(&display),
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if gone {
+                    continue;
+                }
+                gone = true;
+                reported = None;
+                match burst.renamed_to {
+                    Some(to) => ProjectChange::Renamed { path: display.clone(), new_path: to.display().to_string() },
+                    None => ProjectChange::Deleted { path: display.clone() },
+                }
+            }
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", display, e);
+                continue;
+            }
+        };
+        if let Err(e) = app.emit(PROJECT_CHANGED_EVENT, &change) {
+            eprintln!("Failed to report change of {}: {}", display, e);
+        }
+    }
+}
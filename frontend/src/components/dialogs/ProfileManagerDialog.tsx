@@ -1,7 +1,8 @@
 /**
  * ProfileManagerDialog - Backend Profile CRUD Management
  *
- * Allows users to create, edit, and delete backend connection profiles.
+ * Allows users to create, edit, and delete backend connection profiles,
+ * including per-profile timeouts, extra HTTP headers and an SSH tunnel.
  */
 
 import { useState, useEffect } from 'react'
@@ -41,12 +42,13 @@ import {
   deleteProfile,
   validateUrl,
 } from '@services/backendProfiles'
-import type { BackendProfile } from '@types'
+import type { BackendProfile, TunnelConfig } from '@types'
 import { useConfirm } from '@hooks/useConfirm'
 import { useError } from '@hooks/useError'
 import { useSnackbar } from '@hooks/useSnackbar'
 import { useTranslation } from 'react-i18next'
 import { logger } from '@utils/logger'
+import { translateBackendError } from '@utils/translateBackendError'
 
 interface ProfileManagerDialogProps {
   open: boolean
@@ -54,22 +56,125 @@ interface ProfileManagerDialogProps {
   onProfilesChanged: () => void
 }
 
+interface HeaderRow {
+  name: string
+  value: string
+}
+
+/** Tunnel fields as typed; numbers stay strings until saved */
+interface TunnelFormData {
+  host: string
+  port: string
+  username: string
+  keyPath: string
+  keyPassphrase: string
+  knownHostsPath: string
+  keepaliveSecs: string
+}
+
 interface ProfileFormData {
   name: string
   url: string
   isDefault: boolean
+  /** Milliseconds as typed; empty = app default */
+  healthTimeoutMs: string
+  requestTimeoutMs: string
+  headers: HeaderRow[]
+  useTunnel: boolean
+  tunnel: TunnelFormData
+}
+
+interface ProfileFormErrors {
+  name?: string
+  url?: string
+  healthTimeoutMs?: string
+  requestTimeoutMs?: string
+  headers?: string
+  tunnel?: Partial<Record<keyof TunnelFormData, string>>
+}
+
+/** Timeout bounds enforced by the Tauri core (profiles.rs) */
+const MIN_TIMEOUT_MS = 100
+const MAX_TIMEOUT_MS = 600_000
+
+/** RFC 9110 token characters */
+const HEADER_NAME_PATTERN = /^[!#$%&'*+.^_`|~0-9A-Za-z-]+$/
+
+const EMPTY_TUNNEL: TunnelFormData = {
+  host: '',
+  port: '22',
+  username: '',
+  keyPath: '',
+  keyPassphrase: '',
+  knownHostsPath: '',
+  keepaliveSecs: '30',
+}
+
+const EMPTY_FORM: ProfileFormData = {
+  name: '',
+  url: '',
+  isDefault: false,
+  healthTimeoutMs: '',
+  requestTimeoutMs: '',
+  headers: [],
+  useTunnel: false,
+  tunnel: EMPTY_TUNNEL,
+}
+
+function toTunnelForm(tunnel: TunnelConfig): TunnelFormData {
+  return {
+    host: tunnel.host,
+    port: String(tunnel.port ?? 22),
+    username: tunnel.username,
+    keyPath: tunnel.keyPath,
+    keyPassphrase: tunnel.keyPassphrase ?? '',
+    knownHostsPath: tunnel.knownHostsPath ?? '',
+    keepaliveSecs: String(tunnel.keepaliveSecs ?? 30),
+  }
+}
+
+function toTunnelConfig(tunnel: TunnelFormData): TunnelConfig {
+  return {
+    host: tunnel.host.trim(),
+    port: Number(tunnel.port),
+    username: tunnel.username.trim(),
+    keyPath: tunnel.keyPath.trim(),
+    keyPassphrase: tunnel.keyPassphrase || null,
+    knownHostsPath: tunnel.knownHostsPath.trim() || null,
+    keepaliveSecs: Number(tunnel.keepaliveSecs),
+  }
+}
+
+function isIntegerIn(value: string, min: number, max: number): boolean {
+  const number = Number(value)
+  return value.trim() !== '' && Number.isInteger(number) && number >= min && number <= max
+}
+
+function parseTimeout(value: string): number | null {
+  return value.trim() ? Number(value) : null
+}
+
+/** The profile fields edited by the form */
+function toProfileData(form: ProfileFormData) {
+  return {
+    name: form.name,
+    url: form.url,
+    isDefault: form.isDefault,
+    healthTimeoutMs: parseTimeout(form.healthTimeoutMs),
+    requestTimeoutMs: parseTimeout(form.requestTimeoutMs),
+    headers: Object.fromEntries(
+      form.headers.filter((header) => header.name.trim()).map((header) => [header.name.trim(), header.value])
+    ),
+    tunnel: form.useTunnel ? toTunnelConfig(form.tunnel) : null,
+  }
 }
 
 export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: ProfileManagerDialogProps) {
   const { t } = useTranslation()
   const [profiles, setProfiles] = useState<BackendProfile[]>([])
   const [editingProfileId, setEditingProfileId] = useState<string | null>(null)
-  const [formData, setFormData] = useState<ProfileFormData>({
-    name: '',
-    url: '',
-    isDefault: false,
-  })
-  const [formErrors, setFormErrors] = useState<{ name?: string; url?: string }>({})
+  const [formData, setFormData] = useState<ProfileFormData>(EMPTY_FORM)
+  const [formErrors, setFormErrors] = useState<ProfileFormErrors>({})
   const [isFormVisible, setIsFormVisible] = useState(false)
 
   // Confirmation dialog hook
@@ -80,7 +185,9 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
   // Load profiles on mount and when dialog opens
   useEffect(() => {
     if (open) {
-      setProfiles(loadProfiles())
+      loadProfiles()
+        .then(setProfiles)
+        .catch((err) => logger.error('[ProfileManager] Failed to load profiles:', err))
       setIsFormVisible(false)
       setEditingProfileId(null)
     }
@@ -89,7 +196,7 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
   // Handle add new profile
   const handleAdd = () => {
     setEditingProfileId(null)
-    setFormData({ name: '', url: 'http://127.0.0.1:8765', isDefault: false })
+    setFormData({ ...EMPTY_FORM, url: 'http://127.0.0.1:8765' })
     setFormErrors({})
     setIsFormVisible(true)
   }
@@ -101,6 +208,11 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
       name: profile.name,
       url: profile.url,
       isDefault: profile.isDefault,
+      healthTimeoutMs: profile.healthTimeoutMs?.toString() ?? '',
+      requestTimeoutMs: profile.requestTimeoutMs?.toString() ?? '',
+      headers: Object.entries(profile.headers ?? {}).map(([name, value]) => ({ name, value })),
+      useTunnel: !!profile.tunnel,
+      tunnel: profile.tunnel ? toTunnelForm(profile.tunnel) : EMPTY_TUNNEL,
     })
     setFormErrors({})
     setIsFormVisible(true)
@@ -119,14 +231,14 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
     if (confirmed) {
       logger.group('🔧 Profile Manager', 'Deleting profile', { profileId: profile.id, name: profile.name }, '#FF5722')
       try {
-        deleteProfile(profile.id)
-        setProfiles(loadProfiles())
+        await deleteProfile(profile.id)
+        setProfiles(await loadProfiles())
         onProfilesChanged()
         showSnackbar(t('profileManager.deleted'), { severity: 'success' })
       } catch (err) {
         await showError(
           t('profileManager.deleteTitle'),
-          err instanceof Error ? err.message : t('profileManager.deleteFailed')
+          err instanceof Error ? translateBackendError(err.message, t) : t('profileManager.deleteFailed')
         )
       }
     }
@@ -136,20 +248,20 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
   const handleToggleDefault = async (profile: BackendProfile) => {
     logger.group('🔧 Profile Manager', 'Toggling default profile', { profileId: profile.id, name: profile.name, newDefault: !profile.isDefault }, '#2196F3')
     try {
-      updateProfile(profile.id, { isDefault: !profile.isDefault })
-      setProfiles(loadProfiles())
+      await updateProfile(profile.id, { isDefault: !profile.isDefault })
+      setProfiles(await loadProfiles())
       onProfilesChanged()
     } catch (err) {
       await showError(
         t('profileManager.updateFailed'),
-        err instanceof Error ? err.message : t('profileManager.updateError')
+        err instanceof Error ? translateBackendError(err.message, t) : t('profileManager.updateError')
       )
     }
   }
 
   // Validate form
   const validateForm = (): boolean => {
-    const errors: { name?: string; url?: string } = {}
+    const errors: ProfileFormErrors = {}
 
     // Validate name
     if (!formData.name.trim()) {
@@ -170,6 +282,50 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
       errors.url = urlValidation.error
     }
 
+    // Validate timeouts (empty = app default)
+    for (const field of ['healthTimeoutMs', 'requestTimeoutMs'] as const) {
+      const timeout = parseTimeout(formData[field])
+      if (timeout !== null && !(Number.isInteger(timeout) && timeout >= MIN_TIMEOUT_MS && timeout <= MAX_TIMEOUT_MS)) {
+        errors[field] = t('profileManager.timeoutInvalid', { min: MIN_TIMEOUT_MS, max: MAX_TIMEOUT_MS })
+      }
+    }
+
+    // Validate headers (rows without a name are dropped on save)
+    const headerNames = new Set<string>()
+    for (const header of formData.headers) {
+      const name = header.name.trim()
+      if (!name) continue
+      if (!HEADER_NAME_PATTERN.test(name) || /[\r\n]/.test(header.value)) {
+        errors.headers = t('profileManager.headerInvalid', { name })
+        break
+      }
+      if (headerNames.has(name.toLowerCase())) {
+        errors.headers = t('profileManager.headerDuplicate', { name })
+        break
+      }
+      headerNames.add(name.toLowerCase())
+    }
+
+    // Validate the SSH tunnel
+    if (formData.useTunnel) {
+      const tunnel = formData.tunnel
+      const tunnelErrors: ProfileFormErrors['tunnel'] = {}
+      for (const field of ['host', 'username', 'keyPath'] as const) {
+        if (!tunnel[field].trim()) {
+          tunnelErrors[field] = t('profileManager.tunnel.required')
+        }
+      }
+      if (!isIntegerIn(tunnel.port, 1, 65535)) {
+        tunnelErrors.port = t('profileManager.tunnel.portInvalid')
+      }
+      if (!isIntegerIn(tunnel.keepaliveSecs, 1, 86400)) {
+        tunnelErrors.keepaliveSecs = t('profileManager.tunnel.keepaliveInvalid')
+      }
+      if (Object.keys(tunnelErrors).length > 0) {
+        errors.tunnel = tunnelErrors
+      }
+    }
+
     setFormErrors(errors)
     return Object.keys(errors).length === 0
   }
@@ -187,26 +343,46 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
     try {
       if (editingProfileId) {
         // Update existing
-        updateProfile(editingProfileId, formData)
+        await updateProfile(editingProfileId, toProfileData(formData))
         showSnackbar(t('profileManager.updated'), { severity: 'success' })
       } else {
         // Create new - add lastConnected: null
-        saveProfile({ ...formData, lastConnected: null })
+        await saveProfile({ ...toProfileData(formData), lastConnected: null })
         showSnackbar(t('profileManager.created'), { severity: 'success' })
       }
 
-      setProfiles(loadProfiles())
+      setProfiles(await loadProfiles())
       setIsFormVisible(false)
       setEditingProfileId(null)
       onProfilesChanged()
     } catch (err) {
       await showError(
         editingProfileId ? t('profileManager.updateFailed') : t('profileManager.createFailed'),
-        err instanceof Error ? err.message : t('profileManager.saveFailed')
+        err instanceof Error ? translateBackendError(err.message, t) : t('profileManager.saveFailed')
       )
     }
   }
 
+  // Header rows
+  const handleHeaderChange = (index: number, changes: Partial<HeaderRow>) => {
+    setFormData({
+      ...formData,
+      headers: formData.headers.map((header, i) => (i === index ? { ...header, ...changes } : header)),
+    })
+  }
+
+  const handleHeaderAdd = () => {
+    setFormData({ ...formData, headers: [...formData.headers, { name: '', value: '' }] })
+  }
+
+  const handleHeaderRemove = (index: number) => {
+    setFormData({ ...formData, headers: formData.headers.filter((_, i) => i !== index) })
+  }
+
+  const handleTunnelChange = (changes: Partial<TunnelFormData>) => {
+    setFormData({ ...formData, tunnel: { ...formData.tunnel, ...changes } })
+  }
+
   // Handle cancel
   const handleCancel = () => {
     setIsFormVisible(false)
@@ -318,6 +494,145 @@ export function ProfileManagerDialog({ open, onClose, onProfilesChanged }: Profi
                 placeholder="http://127.0.0.1:8765"
               />
 
+              <Typography variant="subtitle2">{t('profileManager.timeouts')}</Typography>
+              <Box sx={{ display: 'flex', gap: 2 }}>
+                <TextField
+                  label={t('profileManager.healthTimeout')}
+                  type="number"
+                  value={formData.healthTimeoutMs}
+                  onChange={(e) => setFormData({ ...formData, healthTimeoutMs: e.target.value })}
+                  error={!!formErrors.healthTimeoutMs}
+                  helperText={formErrors.healthTimeoutMs || t('profileManager.timeoutHint')}
+                  fullWidth
+                />
+                <TextField
+                  label={t('profileManager.requestTimeout')}
+                  type="number"
+                  value={formData.requestTimeoutMs}
+                  onChange={(e) => setFormData({ ...formData, requestTimeoutMs: e.target.value })}
+                  error={!!formErrors.requestTimeoutMs}
+                  helperText={formErrors.requestTimeoutMs || t('profileManager.timeoutHint')}
+                  fullWidth
+                />
+              </Box>
+
+              <Typography variant="subtitle2">{t('profileManager.headers')}</Typography>
+              {formData.headers.map((header, index) => (
+                <Box key={index} sx={{ display: 'flex', gap: 2, alignItems: 'center' }}>
+                  <TextField
+                    label={t('profileManager.headerName')}
+                    value={header.name}
+                    onChange={(e) => handleHeaderChange(index, { name: e.target.value })}
+                    size="small"
+                    sx={{ flex: 1 }}
+                  />
+                  <TextField
+                    label={t('profileManager.headerValue')}
+                    value={header.value}
+                    onChange={(e) => handleHeaderChange(index, { value: e.target.value })}
+                    size="small"
+                    sx={{ flex: 2 }}
+                    placeholder="{{secret:name}}"
+                  />
+                  <IconButton size="small" onClick={() => handleHeaderRemove(index)} color="error">
+                    <DeleteIcon fontSize="small" />
+                  </IconButton>
+                </Box>
+              ))}
+              {formErrors.headers && (
+                <Typography variant="caption" color="error">
+                  {formErrors.headers}
+                </Typography>
+              )}
+              <Box>
+                <Button size="small" startIcon={<AddIcon />} onClick={handleHeaderAdd}>
+                  {t('profileManager.addHeader')}
+                </Button>
+                <Typography variant="caption" color="text.secondary" display="block">
+                  {t('profileManager.headersHint', { reference: '{{secret:name}}' })}
+                </Typography>
+              </Box>
+
+              <FormControlLabel
+                control={
+                  <Checkbox
+                    checked={formData.useTunnel}
+                    onChange={(e) => setFormData({ ...formData, useTunnel: e.target.checked })}
+                  />
+                }
+                label={t('profileManager.tunnel.enable')}
+              />
+              {formData.useTunnel && (
+                <Box sx={{ display: 'flex', flexDirection: 'column', gap: 2 }}>
+                  <Typography variant="caption" color="text.secondary">
+                    {t('profileManager.tunnel.hint')}
+                  </Typography>
+                  <Box sx={{ display: 'flex', gap: 2 }}>
+                    <TextField
+                      label={t('profileManager.tunnel.host')}
+                      value={formData.tunnel.host}
+                      onChange={(e) => handleTunnelChange({ host: e.target.value })}
+                      error={!!formErrors.tunnel?.host}
+                      helperText={formErrors.tunnel?.host}
+                      sx={{ flex: 3 }}
+                      placeholder="ssh.example.com"
+                    />
+                    <TextField
+                      label={t('profileManager.tunnel.port')}
+                      type="number"
+                      value={formData.tunnel.port}
+                      onChange={(e) => handleTunnelChange({ port: e.target.value })}
+                      error={!!formErrors.tunnel?.port}
+                      helperText={formErrors.tunnel?.port}
+                      sx={{ flex: 1 }}
+                    />
+                  </Box>
+                  <TextField
+                    label={t('profileManager.tunnel.username')}
+                    value={formData.tunnel.username}
+                    onChange={(e) => handleTunnelChange({ username: e.target.value })}
+                    error={!!formErrors.tunnel?.username}
+                    helperText={formErrors.tunnel?.username}
+                    fullWidth
+                  />
+                  <TextField
+                    label={t('profileManager.tunnel.keyPath')}
+                    value={formData.tunnel.keyPath}
+                    onChange={(e) => handleTunnelChange({ keyPath: e.target.value })}
+                    error={!!formErrors.tunnel?.keyPath}
+                    helperText={formErrors.tunnel?.keyPath || t('profileManager.tunnel.keyPathHint')}
+                    fullWidth
+                    placeholder="/home/user/.ssh/id_ed25519"
+                  />
+                  <TextField
+                    label={t('profileManager.tunnel.keyPassphrase')}
+                    value={formData.tunnel.keyPassphrase}
+                    onChange={(e) => handleTunnelChange({ keyPassphrase: e.target.value })}
+                    helperText={t('profileManager.tunnel.keyPassphraseHint', { reference: '{{secret:name}}' })}
+                    fullWidth
+                    placeholder="{{secret:name}}"
+                  />
+                  <Box sx={{ display: 'flex', gap: 2 }}>
+                    <TextField
+                      label={t('profileManager.tunnel.knownHostsPath')}
+                      value={formData.tunnel.knownHostsPath}
+                      onChange={(e) => handleTunnelChange({ knownHostsPath: e.target.value })}
+                      helperText={t('profileManager.tunnel.knownHostsPathHint')}
+                      sx={{ flex: 3 }}
+                    />
+                    <TextField
+                      label={t('profileManager.tunnel.keepaliveSecs')}
+                      type="number"
+                      value={formData.tunnel.keepaliveSecs}
+                      onChange={(e) => handleTunnelChange({ keepaliveSecs: e.target.value })}
+                      error={!!formErrors.tunnel?.keepaliveSecs}
+                      helperText={formErrors.tunnel?.keepaliveSecs}
+                      sx={{ flex: 1 }}
+                    />
+                  </Box>
+                </Box>
+              )}
+
               <FormControlLabel
                 control={
                   <Checkbox
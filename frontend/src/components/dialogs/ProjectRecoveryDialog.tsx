@@ -0,0 +1,124 @@
+/**
+ * ProjectRecoveryDialog - Unsaved edits found at startup
+ *
+ * Lists the autosave journals a previous run left behind, with what changed
+ * against the project file, and lets the user recover or discard each one.
+ * Closing the dialog keeps the journals for the next start.
+ */
+
+import {
+  Dialog,
+  DialogTitle,
+  DialogContent,
+  DialogActions,
+  Button,
+  Typography,
+  Box,
+  Alert,
+  Paper,
+} from '@mui/material'
+import { Restore as RestoreIcon, Delete as DeleteIcon } from '@mui/icons-material'
+import { useTranslation } from 'react-i18next'
+import type { TFunction } from 'i18next'
+import type { ProjectDiffSummary, RecoveryCandidate } from '@types'
+
+interface ProjectRecoveryDialogProps {
+  open: boolean
+  candidates: RecoveryCandidate[]
+  /** Id of the journal being recovered or discarded */
+  busyId: string | null
+  onRecover: (id: string) => void
+  onDiscard: (id: string) => void
+  onClose: () => void
+}
+
+/** The non-zero counts of a diff summary, e.g. "2 chapters added, 5 texts changed" */
+function describeChanges(summary: ProjectDiffSummary, t: TFunction): string {
+  const changes = (Object.entries(summary) as [keyof ProjectDiffSummary, number][])
+    .filter(([, count]) => count > 0)
+    .map(([kind, count]) => t(`appLayout.recovery.changes.${kind}`, { count }))
+  return changes.length > 0 ? changes.join(', ') : t('appLayout.recovery.noChanges')
+}
+
+export function ProjectRecoveryDialog({
+  open,
+  candidates,
+  busyId,
+  onRecover,
+  onDiscard,
+  onClose,
+}: ProjectRecoveryDialogProps) {
+  const { t } = useTranslation()
+
+  return (
+    <Dialog open={open} onClose={onClose} maxWidth="sm" fullWidth>
+      <DialogTitle>{t('appLayout.recovery.title')}</DialogTitle>
+
+      <DialogContent>
+        <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+          {t('appLayout.recovery.description')}
+        </Typography>
+
+        <Box sx={{ display: 'flex', flexDirection: 'column', gap: 2 }}>
+          {candidates.map((candidate) => (
+            <Paper key={candidate.id} variant="outlined" sx={{ p: 2 }}>
+              <Typography variant="subtitle2">
+                {candidate.name ?? t('appLayout.recovery.unnamed')}
+              </Typography>
+              <Typography variant="caption" color="text.secondary" display="block" sx={{ fontFamily: 'monospace' }}>
+                {candidate.projectPath ?? t('appLayout.recovery.neverSaved')}
+              </Typography>
+              <Typography variant="caption" color="text.secondary" display="block">
+                {t('appLayout.recovery.savedAt', { date: new Date(candidate.savedAt).toLocaleString() })}
+              </Typography>
+
+              <Typography variant="body2" sx={{ mt: 1 }}>
+                {candidate.summary
+                  ? describeChanges(candidate.summary, t)
+                  : t('appLayout.recovery.unreadable')}
+              </Typography>
+
+              {candidate.projectPath && !candidate.fileExists && (
+                <Alert severity="info" sx={{ mt: 1 }}>
+                  {t('appLayout.recovery.fileMissing')}
+                </Alert>
+              )}
+              {candidate.fileChanged && (
+                <Alert severity="warning" sx={{ mt: 1 }}>
+                  {t('appLayout.recovery.fileChanged')}
+                </Alert>
+              )}
+
+              <Box sx={{ display: 'flex', gap: 1, mt: 2, justifyContent: 'flex-end' }}>
+                <Button
+                  size="small"
+                  color="error"
+                  startIcon={<DeleteIcon />}
+                  onClick={() => onDiscard(candidate.id)}
+                  disabled={busyId !== null}
+                >
+                  {t('appLayout.recovery.discard')}
+                </Button>
+                <Button
+                  size="small"
+                  variant="contained"
+                  startIcon={<RestoreIcon />}
+                  onClick={() => onRecover(candidate.id)}
+                  disabled={busyId !== null || !candidate.summary}
+                >
+                  {t('appLayout.recovery.recover')}
+                </Button>
+              </Box>
+            </Paper>
+          ))}
+        </Box>
+      </DialogContent>
+
+      <DialogActions>
+        <Button onClick={onClose} disabled={busyId !== null}>
+          {t('appLayout.recovery.later')}
+        </Button>
+      </DialogActions>
+    </Dialog>
+  )
+}
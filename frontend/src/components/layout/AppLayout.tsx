@@ -3,6 +3,7 @@ import { useState, useEffect, useRef, useCallback } from 'react'
 import { ErrorBoundary } from '@components/ErrorBoundary'
 import { ProjectDialog } from '@components/dialogs/ProjectDialog'
 import { ChapterDialog } from '@components/dialogs/ChapterDialog'
+import { ProjectRecoveryDialog } from '@components/dialogs/ProjectRecoveryDialog'
 import { useProjectsList } from '@hooks/useProjectsQuery'
 import { useChapter } from '@hooks/useChaptersQuery'
 import { useActiveTTSJobs } from '@hooks/useTTSQuery'
@@ -23,7 +24,15 @@ import SpeakersView from '@pages/SpeakersView'
 import ImportView from '@pages/ImportView'
 import { useNavigationShortcuts } from '@hooks/useNavigationShortcuts'
 import { ViewTransition } from '@components/layout/ViewTransition'
-import { useAppLayoutSession, useAppLayoutDialogs, useAudioPlayback } from '@hooks/appLayout'
+import {
+  useAppLayoutSession,
+  useAppLayoutDialogs,
+  useAudioPlayback,
+  useOpenRequests,
+  useProjectAutosave,
+  useProjectRecovery,
+} from '@hooks/appLayout'
+import type { OpenProjectFile } from '@types'
 import { useError } from '@hooks/useError'
 
 export default function AppLayout() {
@@ -46,6 +55,7 @@ export default function AppLayout() {
 
   // Navigation state
   const currentView = useNavigationStore((state) => state.currentView)
+  const navigateTo = useNavigationStore((state) => state.navigateTo)
 
   // Error hook for audio playback errors
   const { showError, ErrorDialog: AudioErrorDialog } = useError()
@@ -134,6 +144,38 @@ export default function AppLayout() {
     setExpandedProjects: setExpandedProjectsState,
   })
 
+  // ============================================================================
+  // PROJECT FILES OPENED FROM OUTSIDE (extracted hook)
+  // ============================================================================
+
+  // The project file the selected project stands for; its edits go to the autosave journal
+  const [openFile, setOpenFile] = useState<OpenProjectFile | null>(null)
+
+  const handleProjectImported = useCallback((projectId: string, file?: OpenProjectFile) => {
+    setOpenFile(file ?? null)
+    handleSelectProject(projectId)
+    navigateTo('main')
+  }, [handleSelectProject, navigateTo])
+
+  const handleProjectFileClosed = useCallback(() => setOpenFile(null), [])
+
+  useOpenRequests({
+    onProjectImported: handleProjectImported,
+    showSnackbar,
+  })
+
+  useProjectAutosave({
+    openFile,
+    selectedProjectId,
+    selectedProject,
+    onClosed: handleProjectFileClosed,
+  })
+
+  const recovery = useProjectRecovery({
+    onProjectRecovered: handleProjectImported,
+    showSnackbar,
+  })
+
   // ============================================================================
   // SSE EVENT HANDLING
   // ============================================================================
@@ -364,6 +406,16 @@ export default function AppLayout() {
         nextOrderIndex={selectedProject?.chapters.length || 0}
       />
 
+      {/* Unsaved project edits left by a previous run */}
+      <ProjectRecoveryDialog
+        open={recovery.dialogOpen}
+        candidates={recovery.candidates}
+        busyId={recovery.busyId}
+        onRecover={recovery.recover}
+        onDiscard={recovery.discard}
+        onClose={recovery.closeDialog}
+      />
+
       {/* Confirmation Dialog */}
       <ConfirmDialog />
 
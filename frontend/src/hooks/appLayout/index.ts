@@ -1,3 +1,5 @@
 export { useAppLayoutSession } from './useAppLayoutSession'
 export { useAppLayoutDialogs } from './useAppLayoutDialogs'  // .tsx file
-export { useAudioPlayback } from './useAudioPlayback'
\ No newline at end of file
+export { useAudioPlayback } from './useAudioPlayback'
+export { useOpenRequests } from './useOpenRequests'export { useProjectAutosave } from './useProjectAutosave'
+export { useProjectRecovery } from './useProjectRecovery'
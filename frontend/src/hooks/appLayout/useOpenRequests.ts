@@ -0,0 +1,110 @@
+import { useEffect, useRef } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import { useQueryClient } from '@tanstack/react-query'
+import { useTranslation } from 'react-i18next'
+import type { AlertColor } from '@mui/material'
+import { queryKeys } from '@services/queryKeys'
+import { translateBackendError } from '@utils/translateBackendError'
+import { fileIdsOf } from '@utils/projectFile'
+import type { ImportedProjectFile, OpenProjectFile } from '@types'
+import { logger } from '@utils/logger'
+
+/** Emitted by the Tauri side when a project file is opened from outside the app */
+const OPEN_PROJECT_EVENT = 'project://open'
+
+interface OpenRequest {
+  path: string
+  kind: 'project' | 'bundle'
+}
+
+interface UseOpenRequestsOptions {
+  /** Select a project that was just imported; `file` is set for project files, whose edits are journaled */
+  onProjectImported: (projectId: string, file?: OpenProjectFile) => void
+  showSnackbar: (message: string, options?: { severity?: AlertColor }) => void
+}
+
+/**
+ * Hook for opening project files handed to the app from outside
+ *
+ * Responsibilities:
+ * - Listen for `project://open` (double-clicked files, later launches)
+ * - Collect the files the app was launched with once the listener is in place
+ * - Import bundles and `.abm` project files onto the active backend and
+ *   select the new project
+ */
+export function useOpenRequests({ onProjectImported, showSnackbar }: UseOpenRequestsOptions): void {
+  const { t } = useTranslation()
+  const queryClient = useQueryClient()
+
+  // Refs keep the listener registered once while the callbacks change
+  const handlersRef = useRef({ onProjectImported, showSnackbar, t })
+  useEffect(() => {
+    handlersRef.current = { onProjectImported, showSnackbar, t }
+  }, [onProjectImported, showSnackbar, t])
+
+  useEffect(() => {
+    let disposed = false
+    let unlisten: (() => void) | null = null
+
+    const open = async (request: OpenRequest) => {
+      const { onProjectImported, showSnackbar, t } = handlersRef.current
+      logger.info('[OpenRequests] Opening', { path: request.path, kind: request.kind })
+      try {
+        if (request.kind === 'bundle') {
+          const summary = await invoke<{ projectId: string; project: { name: string } }>(
+            'import_project_bundle',
+            { path: request.path }
+          )
+          await queryClient.invalidateQueries({ queryKey: queryKeys.projects.lists() })
+          onProjectImported(summary.projectId)
+          showSnackbar(t('appLayout.openRequests.bundleImported', { name: summary.project.name }), { severity: 'success' })
+        } else {
+          // Projects live on the backend; the file is recreated there as a new project
+          const opened = await invoke<ImportedProjectFile>('import_project_file', { path: request.path })
+          await queryClient.invalidateQueries({ queryKey: queryKeys.projects.lists() })
+          onProjectImported(opened.import.projectId, {
+            projectId: opened.import.projectId,
+            path: opened.path,
+            base: opened.project,
+            fileIds: fileIdsOf(opened.import.ids),
+          })
+          showSnackbar(t('appLayout.openRequests.projectOpened', { name: opened.project.name }), { severity: 'success' })
+        }
+      } catch (err: unknown) {
+        logger.error('[OpenRequests] Failed to open', { path: request.path, error: err })
+        const message = translateBackendError(err instanceof Error ? err.message : String(err), t)
+        showSnackbar(t('appLayout.openRequests.failed', { path: request.path, message }), { severity: 'error' })
+      }
+    }
+
+    const setup = async () => {
+      try {
+        const stop = await listen<OpenRequest>(OPEN_PROJECT_EVENT, (event) => {
+          open(event.payload)
+        })
+        if (disposed) {
+          stop()
+          return
+        }
+        unlisten = stop
+        // Only after listening, so nothing opened in between is lost
+        const pending = await invoke<OpenRequest[]>('take_open_requests')
+        for (const request of pending) {
+          await open(request)
+        }
+      } catch (error) {
+        logger.warn('[OpenRequests] Open requests not available (running in browser)', { error })
+      }
+    }
+
+    setup()
+
+    return () => {
+      disposed = true
+      if (unlisten) {
+        unlisten()
+      }
+    }
+  }, [queryClient])
+}
@@ -0,0 +1,76 @@
+import { useEffect, useRef } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import type { Project, OpenProjectFile } from '@types'
+import { toProjectFile } from '@utils/projectFile'
+import { logger } from '@utils/logger'
+
+interface UseProjectAutosaveOptions {
+  /** The project file the selected project stands for, if any */
+  openFile: OpenProjectFile | null
+  selectedProjectId: string | null
+  selectedProject: Project | undefined
+  /** The project file was closed (another project was selected) */
+  onClosed: () => void
+}
+
+/**
+ * Hook for journaling edits to a project opened from a project file
+ *
+ * Responsibilities:
+ * - Send the project in its file shape to the autosave journal on every edit
+ * - Discard the journal when the project is closed
+ *
+ * The first state seen after opening is the baseline and is not journaled,
+ * so opening a file without editing it leaves nothing to recover.
+ */
+export function useProjectAutosave({
+  openFile,
+  selectedProjectId,
+  selectedProject,
+  onClosed,
+}: UseProjectAutosaveOptions): void {
+  const baselineRef = useRef<string | null>(null)
+  const lastSentRef = useRef<string | null>(null)
+  const wasSelectedRef = useRef(false)
+
+  // A new file starts a new baseline
+  useEffect(() => {
+    baselineRef.current = null
+    lastSentRef.current = null
+    wasSelectedRef.current = false
+  }, [openFile])
+
+  // Journal edits
+  useEffect(() => {
+    if (!openFile || !selectedProject || selectedProject.id !== openFile.projectId) return
+
+    const content = JSON.stringify(toProjectFile(openFile, selectedProject))
+    if (baselineRef.current === null) {
+      baselineRef.current = content
+      return
+    }
+    if (content === (lastSentRef.current ?? baselineRef.current)) return
+
+    lastSentRef.current = content
+    invoke('update_autosave', { path: openFile.path, content }).catch((error) => {
+      logger.warn('[ProjectAutosave] Failed to update the autosave journal', { error })
+    })
+  }, [openFile, selectedProject])
+
+  // Closing the project throws its unsaved edits away
+  useEffect(() => {
+    if (!openFile) return
+    if (selectedProjectId === openFile.projectId) {
+      wasSelectedRef.current = true
+      return
+    }
+    // Selection can lag behind the import by a render
+    if (!wasSelectedRef.current) return
+
+    logger.info('[ProjectAutosave] Project file closed', { path: openFile.path })
+    invoke('discard_autosave').catch((error) => {
+      logger.warn('[ProjectAutosave] Failed to discard the autosave journal', { error })
+    })
+    onClosed()
+  }, [openFile, selectedProjectId, onClosed])
+}
@@ -0,0 +1,103 @@
+import { useEffect, useState, useCallback } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { useQueryClient } from '@tanstack/react-query'
+import { useTranslation } from 'react-i18next'
+import type { AlertColor } from '@mui/material'
+import type { OpenProjectFile, ProjectImportSummary, RecoveredProject, RecoveryCandidate } from '@types'
+import { queryKeys } from '@services/queryKeys'
+import { fileIdsOf } from '@utils/projectFile'
+import { translateBackendError } from '@utils/translateBackendError'
+import { logger } from '@utils/logger'
+
+interface UseProjectRecoveryOptions {
+  /** Select a recovered project and journal its further edits */
+  onProjectRecovered: (projectId: string, file: OpenProjectFile) => void
+  showSnackbar: (message: string, options?: { severity?: AlertColor }) => void
+}
+
+interface UseProjectRecoveryReturn {
+  /** Journals a previous run left behind that are still undecided */
+  candidates: RecoveryCandidate[]
+  /** Id of the journal being recovered or discarded */
+  busyId: string | null
+  dialogOpen: boolean
+  recover: (id: string) => Promise<void>
+  discard: (id: string) => Promise<void>
+  closeDialog: () => void
+}
+
+/**
+ * Hook for recovering unsaved project edits after a crash
+ *
+ * Responsibilities:
+ * - List the autosave journals a previous run left behind, once at startup
+ * - Recover one onto the active backend as a new project and select it
+ * - Discard the ones the user does not want
+ */
+export function useProjectRecovery({ onProjectRecovered, showSnackbar }: UseProjectRecoveryOptions): UseProjectRecoveryReturn {
+  const { t } = useTranslation()
+  const queryClient = useQueryClient()
+  const [candidates, setCandidates] = useState<RecoveryCandidate[]>([])
+  const [dialogOpen, setDialogOpen] = useState(false)
+  const [busyId, setBusyId] = useState<string | null>(null)
+
+  useEffect(() => {
+    invoke<RecoveryCandidate[]>('list_recoverable_projects')
+      .then((found) => {
+        setCandidates(found)
+        setDialogOpen(found.length > 0)
+      })
+      .catch((error) => {
+        logger.warn('[ProjectRecovery] Recovery not available (running in browser)', { error })
+      })
+  }, [])
+
+  const settle = useCallback((id: string) => {
+    setCandidates((current) => {
+      const remaining = current.filter((candidate) => candidate.id !== id)
+      if (remaining.length === 0) setDialogOpen(false)
+      return remaining
+    })
+  }, [])
+
+  const recover = useCallback(async (id: string) => {
+    setBusyId(id)
+    try {
+      const recovered = await invoke<RecoveredProject>('recover_project', { id })
+      const summary = await invoke<ProjectImportSummary>('import_project_data', { project: recovered.project })
+      await queryClient.invalidateQueries({ queryKey: queryKeys.projects.lists() })
+      settle(id)
+      onProjectRecovered(summary.projectId, {
+        projectId: summary.projectId,
+        path: recovered.path,
+        base: recovered.project,
+        fileIds: fileIdsOf(summary.ids),
+      })
+      showSnackbar(t('appLayout.recovery.recovered', { name: recovered.project.name }), { severity: 'success' })
+    } catch (err: unknown) {
+      logger.error('[ProjectRecovery] Failed to recover', { id, error: err })
+      const message = translateBackendError(err instanceof Error ? err.message : String(err), t)
+      showSnackbar(t('appLayout.recovery.failed', { message }), { severity: 'error' })
+    } finally {
+      setBusyId(null)
+    }
+  }, [queryClient, settle, onProjectRecovered, showSnackbar, t])
+
+  const discard = useCallback(async (id: string) => {
+    setBusyId(id)
+    try {
+      await invoke('discard_recovery', { id })
+      settle(id)
+    } catch (err: unknown) {
+      logger.error('[ProjectRecovery] Failed to discard', { id, error: err })
+      const message = translateBackendError(err instanceof Error ? err.message : String(err), t)
+      showSnackbar(t('appLayout.recovery.failed', { message }), { severity: 'error' })
+    } finally {
+      setBusyId(null)
+    }
+  }, [settle, showSnackbar, t])
+
+  const closeDialog = useCallback(() => setDialogOpen(false), [])
+
+  return { candidates, busyId, dialogOpen, recover, discard, closeDialog }
+}
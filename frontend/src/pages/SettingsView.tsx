@@ -36,7 +36,6 @@ import { useConfirm } from '@hooks/useConfirm'
 import { useSnackbar } from '@hooks/useSnackbar'
 import { logger } from '@utils/logger'
 import { translateBackendError } from '@utils/translateBackendError'
-import { initializeDefaultProfile } from '@services/backendProfiles'
 import {
   ViewContainer,
   ViewHeader,
@@ -137,21 +136,10 @@ const SettingsView = memo(() => {
         await resetMutation.mutateAsync()
 
         // 2. Clear localStorage (frontend state)
-        // Important: Preserve backend-profiles key by saving and restoring it
-        const backendProfilesKey = 'audiobook-maker:backend-profiles'
-        const backendProfiles = localStorage.getItem(backendProfilesKey)
-
-        // Clear everything
+        // Backend profiles are stored by the Tauri core and are not affected
         localStorage.clear()
         sessionStorage.clear()
 
-        // Restore backend profiles if they existed, otherwise initialize default
-        if (backendProfiles) {
-          localStorage.setItem(backendProfilesKey, backendProfiles)
-        } else {
-          initializeDefaultProfile()
-        }
-
         showSnackbar(t('settings.messages.reset'), { severity: 'success' })
 
         // 3. Reload page to reinitialize all stores with defaults
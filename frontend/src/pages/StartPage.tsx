@@ -25,12 +25,13 @@ import {
   CheckCircle as CheckCircleIcon,
   Error as ErrorIcon,
   Settings as SettingsIcon,
+  Warning as WarningIcon,
 } from '@mui/icons-material'
 import { useBackendHealth } from '@hooks/useBackendHealth'
 import {
   loadProfiles,
-  initializeDefaultProfile,
-  markProfileAsConnected,
+  initializeProfiles,
+  activateProfile,
 } from '@services/backendProfiles'
 import type { BackendProfile } from '@types'
 import { useAppStore } from '@store/appStore'
@@ -39,7 +40,10 @@ import { fetchSettings, updateSettings } from '@services/settingsApi'
 import { useTranslation } from 'react-i18next'
 import type { TFunction } from 'i18next'
 import { logger } from '@utils/logger'
+import { translateBackendError } from '@utils/translateBackendError'
 import { useError } from '@hooks/useError'
+import { useConfirm } from '@hooks/useConfirm'
+import { tauriAPI, type BackendCompatibility } from '@services/tauri-api'
 
 export default function StartPage() {
   const { t } = useTranslation()
@@ -48,6 +52,7 @@ export default function StartPage() {
   const loadSettings = useAppStore((state) => state.loadSettings)
   const updateEngineAvailability = useAppStore((state) => state.updateEngineAvailability)
   const { showError, ErrorDialog } = useError()
+  const { confirm, ConfirmDialog } = useConfirm()
 
   // Profile management state
   const [profiles, setProfiles] = useState<BackendProfile[]>([])
@@ -56,31 +61,46 @@ export default function StartPage() {
 
   // Initialize profiles on mount
   useEffect(() => {
-    initializeDefaultProfile()
-    const loadedProfiles = loadProfiles()
-    setProfiles(loadedProfiles)
-
-    // Auto-select default profile or first available
-    const defaultProfile = loadedProfiles.find((p) => p.isDefault)
-    const profileToSelect = defaultProfile || loadedProfiles[0]
-    if (profileToSelect) {
-      setSelectedProfileId(profileToSelect.id)
-    }
+    initializeProfiles()
+      .then((loadedProfiles) => {
+        setProfiles(loadedProfiles)
+
+        // Auto-select default profile or first available
+        const defaultProfile = loadedProfiles.find((p) => p.isDefault)
+        const profileToSelect = defaultProfile || loadedProfiles[0]
+        if (profileToSelect) {
+          setSelectedProfileId(profileToSelect.id)
+        }
+      })
+      .catch((err) => {
+        logger.error('[StartPage] Failed to load backend profiles:', err)
+      })
   }, [])
 
-  // Listen to localStorage changes from other tabs
+  const selectedProfile = profiles.find((p) => p.id === selectedProfileId)
+
+  // A profile with an SSH tunnel is reached through the tunnel's local end
+  const [tunnelUrl, setTunnelUrl] = useState<string | null>(null)
   useEffect(() => {
-    const handleStorageChange = (e: StorageEvent) => {
-      if (e.key === 'audiobook-maker:backend-profiles') {
-        setProfiles(loadProfiles())
-      }
+    setTunnelUrl(null)
+    if (!selectedProfile?.tunnel) return
+
+    let cancelled = false
+    tauriAPI
+      .openBackendTunnel(selectedProfile.id)
+      .then((status) => {
+        if (!cancelled) setTunnelUrl(status.localUrl)
+      })
+      .catch((err) => {
+        logger.error('[StartPage] Failed to open SSH tunnel:', err)
+        if (!cancelled) showError(t('startPage.tunnel.title'), translateBackendError(String(err), t))
+      })
+    return () => {
+      cancelled = true
     }
-
-    window.addEventListener('storage', handleStorageChange)
-    return () => window.removeEventListener('storage', handleStorageChange)
-  }, [])
-
-  const selectedProfile = profiles.find((p) => p.id === selectedProfileId)
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [selectedProfile?.id, selectedProfile?.tunnel])
+  const backendUrl = selectedProfile?.tunnel ? tunnelUrl : selectedProfile?.url ?? null
 
   // Backend health check (with polling)
   const {
@@ -95,7 +115,7 @@ export default function StartPage() {
     isLoading,
     error
   } = useBackendHealth(
-    selectedProfile?.url || null,
+    backendUrl,
     {
       polling: true,
       interval: 5000,
@@ -112,12 +132,12 @@ export default function StartPage() {
         'Profile Name': selectedProfile?.name || 'None',
         'Backend Online': isOnline,
         'Backend Version': version || 'Unknown',
-        'Profile URL': selectedProfile?.url || 'N/A'
+        'Profile URL': backendUrl || 'N/A'
       },
       '#2196F3' // Blue for connection operations
     )
 
-    if (!selectedProfile || !isOnline) {
+    if (!selectedProfile || !backendUrl || !isOnline) {
       logger.group(
         '⚠️ Connection Blocked',
         'Missing requirements for connection',
@@ -136,18 +156,75 @@ export default function StartPage() {
       'Establishing backend connection',
       {
         'Profile': selectedProfile.name,
-        'URL': selectedProfile.url,
+        'URL': backendUrl,
         'Version': version || 'unknown'
       },
       '#2196F3' // Blue for connection
     )
 
-    // Update last connected timestamp
-    markProfileAsConnected(selectedProfile.id)
+    // Version handshake: refuse backends this app cannot work with, warn about doubtful ones
+    let compatibility: BackendCompatibility | null
+    try {
+      compatibility = await tauriAPI.checkBackendCompatibility(backendUrl)
+    } catch (err) {
+      logger.error('[StartPage] Version handshake failed:', err)
+      await showError(t('startPage.compatibility.title'), translateBackendError(String(err), t))
+      return
+    }
+    if (!compatibility) {
+      // The backend went away between the health poll and the handshake
+      await showError(t('startPage.compatibility.title'), t('startPage.compatibility.unverified'))
+      return
+    }
+    if (compatibility.verdict !== 'compatible') {
+      const message = `${t(`startPage.compatibility.${compatibility.verdict}`, {
+        clientVersion: compatibility.clientVersion,
+        backendVersion: compatibility.backendVersion,
+      })} (${compatibility.message})`
+
+      logger.group(
+        compatibility.blocking ? '⛔ Connection Blocked' : '⚠️ Version Mismatch',
+        'Backend version handshake',
+        {
+          'Client Version': compatibility.clientVersion,
+          'Backend Version': compatibility.backendVersion,
+          'Verdict': compatibility.verdict,
+          'Reason': compatibility.message
+        },
+        compatibility.blocking ? '#F44336' : '#FF9800'
+      )
+
+      if (compatibility.blocking) {
+        await showError(t('startPage.compatibility.title'), message)
+        return
+      }
+      const proceed = await confirm(
+        t('startPage.compatibility.title'),
+        `${message} ${t('startPage.compatibility.connectAnyway')}`,
+        {
+          icon: <WarningIcon color="warning" />,
+          confirmText: t('startPage.connect'),
+          cancelText: t('common.cancel'),
+        }
+      )
+      if (!proceed) return
+    }
+
+    // Make it the active backend for the Tauri core (also updates last connected timestamp)
+    try {
+      await activateProfile(selectedProfile.id)
+    } catch (err) {
+      logger.error('[StartPage] Failed to activate profile:', err)
+      await showError(
+        t('startPage.activateFailed'),
+        translateBackendError(err instanceof Error ? err.message : String(err), t)
+      )
+      return
+    }
 
     // Set backend connection in store
     // Use version if available, otherwise default to "unknown"
-    setBackendConnection(selectedProfile, version || 'unknown')
+    setBackendConnection({ ...selectedProfile, url: backendUrl }, version || 'unknown')
 
     // Update engine availability for feature-gating
     // Note: hasAudioEngine comes from engine.status SSE events, not health check
@@ -212,6 +289,7 @@ export default function StartPage() {
   return (
     <>
       <ErrorDialog />
+      <ConfirmDialog />
       <Box
         sx={{
           display: 'flex',
@@ -422,9 +500,9 @@ export default function StartPage() {
       <ProfileManagerDialog
         open={manageDialogOpen}
         onClose={() => setManageDialogOpen(false)}
-        onProfilesChanged={() => {
+        onProfilesChanged={async () => {
           // Reload profiles when changed
-          const loadedProfiles = loadProfiles()
+          const loadedProfiles = await loadProfiles()
           setProfiles(loadedProfiles)
 
           // If current selection was deleted, select first available
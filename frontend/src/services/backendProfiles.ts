@@ -1,15 +1,19 @@
 /**
  * Backend Profile Management Service
  *
- * Handles CRUD operations for backend connection profiles,
- * stored in localStorage for persistence.
+ * Handles CRUD operations for backend connection profiles.
+ * Profiles are stored by the Tauri core in the app config directory
+ * (backend-profiles.json); localStorage is only read once to import
+ * profiles saved by older versions.
  */
 
+import { invoke } from '@tauri-apps/api/core'
 import type { BackendProfile, ApiBackendProfile } from '@types'
 import { transformBackendProfile } from '@/types/api'
 import { logger } from '@utils/logger'
 
-const STORAGE_KEY = 'audiobook-maker:backend-profiles'
+/** Where older versions kept the profiles */
+const LEGACY_STORAGE_KEY = 'audiobook-maker:backend-profiles'
 
 /**
  * Internal: Convert BackendProfile to ApiBackendProfile for serialization
@@ -23,91 +27,67 @@ function toApiProfile(profile: BackendProfile): ApiBackendProfile {
 }
 
 /**
- * Internal: Persist profiles to localStorage
+ * Internal: Invoke a profile command, turning rejections into Errors
  */
-function persist(profiles: BackendProfile[]): void {
+async function call<T>(command: string, args?: Record<string, unknown>): Promise<T> {
   try {
-    const apiProfiles = profiles.map(toApiProfile)
-    localStorage.setItem(STORAGE_KEY, JSON.stringify(apiProfiles))
+    return await invoke<T>(command, args)
   } catch (error) {
-    if (error instanceof DOMException && error.name === 'QuotaExceededError') {
-      logger.error('[BackendProfiles] localStorage quota exceeded')
-      throw new Error('Storage limit reached. Please delete some backend profiles.')
-    } else {
-      logger.error('[BackendProfiles] Failed to save to localStorage:', error)
-      throw new Error('Failed to save profile to storage')
-    }
+    logger.error(`[BackendProfiles] ${command} failed:`, error)
+    throw error instanceof Error ? error : new Error(String(error))
   }
 }
 
 /**
- * Load all profiles from localStorage
+ * Internal: Profiles saved in localStorage by older versions
  */
-export function loadProfiles(): BackendProfile[] {
-  const stored = localStorage.getItem(STORAGE_KEY)
+function readLegacyProfiles(): ApiBackendProfile[] {
+  const stored = localStorage.getItem(LEGACY_STORAGE_KEY)
   if (!stored) return []
 
   try {
-    const parsed: ApiBackendProfile[] = JSON.parse(stored)
-    return parsed.map(transformBackendProfile)
+    const parsed = JSON.parse(stored)
+    return Array.isArray(parsed) ? parsed : []
   } catch (error) {
-    logger.error('[BackendProfiles] Failed to parse profiles from localStorage:', error)
+    logger.error('[BackendProfiles] Failed to parse legacy profiles from localStorage:', error)
     return []
   }
 }
 
 /**
- * Save a new profile
+ * Load all profiles
  */
-export function saveProfile(data: Omit<BackendProfile, 'id' | 'createdAt'>): BackendProfile {
-  const profiles = loadProfiles()
-
-  // If setting as default, unset all others
-  if (data.isDefault) {
-    profiles.forEach((p) => (p.isDefault = false))
-  }
-
-  const newProfile: BackendProfile = {
-    ...data,
-    id: crypto.randomUUID(),
-    createdAt: new Date(),
-  }
-
-  profiles.push(newProfile)
-  persist(profiles)
+export async function loadProfiles(): Promise<BackendProfile[]> {
+  const profiles = await call<ApiBackendProfile[]>('list_backend_profiles')
+  return profiles.map(transformBackendProfile)
+}
 
-  return newProfile
+/**
+ * Save a new profile
+ */
+export async function saveProfile(data: Omit<BackendProfile, 'id' | 'createdAt'>): Promise<BackendProfile> {
+  const profile = await call<ApiBackendProfile>('create_backend_profile', {
+    profile: toApiProfile({ ...data, id: '', createdAt: new Date() }),
+  })
+  return transformBackendProfile(profile)
 }
 
 /**
  * Update an existing profile
  */
-export function updateProfile(id: string, updates: Partial<BackendProfile>): BackendProfile {
-  const profiles = loadProfiles()
-  const index = profiles.findIndex((p) => p.id === id)
-
-  if (index === -1) {
-    throw new Error(`Profile with id "${id}" not found`)
-  }
-
-  // If setting as default, unset all others
-  if (updates.isDefault) {
-    profiles.forEach((p) => (p.isDefault = false))
-  }
-
-  profiles[index] = { ...profiles[index], ...updates }
-  persist(profiles)
-
-  return profiles[index]
+export async function updateProfile(id: string, updates: Partial<BackendProfile>): Promise<BackendProfile> {
+  const current = transformBackendProfile(await call<ApiBackendProfile>('get_backend_profile', { id }))
+  const profile = await call<ApiBackendProfile>('update_backend_profile', {
+    profile: toApiProfile({ ...current, ...updates, id }),
+  })
+  return transformBackendProfile(profile)
 }
 
 /**
  * Delete a profile
  */
-export function deleteProfile(id: string): void {
-  let profiles = loadProfiles()
-  profiles = profiles.filter((p) => p.id !== id)
-  persist(profiles)
+export async function deleteProfile(id: string): Promise<void> {
+  await call<void>('delete_backend_profile', { id })
 }
 
 /**
@@ -138,27 +118,25 @@ export function validateUrl(url: string): { valid: boolean; error?: string } {
 }
 
 /**
- * Update the lastConnected timestamp for a profile
+ * Make a profile the active backend for the Tauri core and update its
+ * lastConnected timestamp
  */
-export function markProfileAsConnected(id: string): void {
-  updateProfile(id, { lastConnected: new Date() })
+export async function activateProfile(id: string): Promise<BackendProfile> {
+  const profile = await call<ApiBackendProfile>('activate_backend_profile', { id })
+  return transformBackendProfile(profile)
 }
 
 /**
- * Initialize default profile on first launch
+ * Initialize profiles on launch
  *
- * Creates a "Local Development" profile if no profiles exist.
+ * The first time, profiles saved in localStorage by older versions are
+ * imported. Creates a "Local Backend" profile if no profiles exist.
  */
-export function initializeDefaultProfile(): void {
-  const profiles = loadProfiles()
-
-  if (profiles.length === 0) {
-    logger.info('[BackendProfiles] No profiles found, creating default "Local Backend" profile')
-    saveProfile({
-      name: 'Local Backend',
-      url: 'http://127.0.0.1:8765',
-      isDefault: true,
-      lastConnected: null,
-    })
+export async function initializeProfiles(): Promise<BackendProfile[]> {
+  const legacy = readLegacyProfiles()
+  if (legacy.length > 0) {
+    logger.info(`[BackendProfiles] Offering ${legacy.length} localStorage profile(s) for import`)
   }
+  const profiles = await call<ApiBackendProfile[]>('init_backend_profiles', { legacy })
+  return profiles.map(transformBackendProfile)
 }
@@ -5,27 +5,79 @@ import { logger } from '@utils/logger';
  * Provides native file system operations for Tauri desktop app
  */
 
+/**
+ * Progress events streamed by the native `download_export` command
+ */
+export type DownloadEvent =
+  | { event: 'started'; data: { totalBytes: number | null; resumedFrom: number } }
+  | { event: 'progress'; data: { downloadedBytes: number; totalBytes: number | null } }
+  | { event: 'retrying'; data: { attempt: number; downloadedBytes: number } }
+  | { event: 'verifying' }
+  | { event: 'finished'; data: { path: string; totalBytes: number; sha256: string } };
+
+export interface DownloadSummary {
+  path: string;
+  totalBytes: number;
+  sha256: string;
+  resumed: boolean;
+}
+
+/**
+ * Verdict of the version handshake with a backend (Rust `compat::Compatibility`)
+ */
+export interface BackendCompatibility {
+  clientVersion: string;
+  clientApiVersion: number;
+  backendVersion: string;
+  backendApi: { min: number; max: number } | null;
+  verdict: 'compatible' | 'upgradeClient' | 'upgradeBackend';
+  /** The app should not connect; otherwise a verdict other than 'compatible' is a warning */
+  blocking: boolean;
+  message: string;
+}
+
+/**
+ * State of a profile's SSH tunnel (Rust `tunnel::TunnelStatus`), also the
+ * payload of the `tunnel://state` event
+ */
+export interface TunnelStatus {
+  profileId: string;
+  state: 'connecting' | 'connected' | 'reconnecting' | 'failed' | 'stopped';
+  /** user@host:port of the SSH server */
+  server: string;
+  /** Where the backend is reached through the tunnel */
+  localUrl: string;
+  localPort: number;
+  attempt: number;
+  connections: number;
+  /** Why the tunnel is not connected ([CODE]key:value;...) */
+  error: string | null;
+  since: string;
+}
+
 class TauriAPIService {
   /**
    * Download exported audio file from backend using native Tauri dialog
    *
-   * This replaces the browser-based download method which doesn't work in Tauri.
-   * Uses frontend-only implementation with Tauri plugins (dialog + fs).
+   * The file is streamed to disk by the Rust `download_export` command, so the
+   * audio never passes through webview memory. Interrupted downloads are resumed.
    *
    * @param jobId Export job ID from backend
    * @param backendUrl Backend base URL (e.g., "http://localhost:8765")
    * @param defaultFilename Suggested filename for save dialog
+   * @param onProgress Optional callback for download progress events
    * @returns Path where the file was saved, or null if cancelled
    */
   async downloadExportedAudio(
     jobId: string,
     backendUrl: string,
-    defaultFilename: string
+    defaultFilename: string,
+    onProgress?: (event: DownloadEvent) => void
   ): Promise<string | null> {
     try {
       // Import dynamically to avoid issues in non-Tauri environments
       const { save } = await import('@tauri-apps/plugin-dialog');
-      const { writeFile } = await import('@tauri-apps/plugin-fs');
+      const { invoke, Channel } = await import('@tauri-apps/api/core');
 
       // 1. Show native save dialog
       const savePath = await save({
@@ -44,41 +96,66 @@ class TauriAPIService {
         return null;
       }
 
-      // 2. Download file from backend via HTTP
-      const downloadUrl = `${backendUrl}/api/audio/export/${jobId}/download`;
+      // 2. Stream file from backend to disk natively
       logger.group(
         '📤 Export',
         'Downloading from backend',
-        { url: downloadUrl },
+        { url: `${backendUrl}/api/audio/export/${jobId}/download` },
         '#2196F3'
       );
 
-      const response = await fetch(downloadUrl);
-
-      if (!response.ok) {
-        throw new Error(`Backend returned error: ${response.status} ${response.statusText}`);
-      }
-
-      // 3. Convert response to binary data
-      const blob = await response.blob();
-      const arrayBuffer = await blob.arrayBuffer();
-      const uint8Array = new Uint8Array(arrayBuffer);
+      const onEvent = new Channel<DownloadEvent>();
+      onEvent.onmessage = (event) => {
+        if (event.event === 'retrying') {
+          logger.warn('[TauriAPI] Export download interrupted, resuming', event.data);
+        }
+        onProgress?.(event);
+      };
 
-      // 4. Write file using Tauri FS plugin
-      await writeFile(savePath, uint8Array);
+      const summary = await invoke<DownloadSummary>('download_export', {
+        jobId,
+        path: savePath,
+        backendUrl,
+        onEvent,
+      });
 
       logger.group(
         '📤 Export',
         'File saved successfully',
-        { path: savePath },
+        { path: summary.path, bytes: summary.totalBytes, sha256: summary.sha256 },
         '#4CAF50'
       );
-      return savePath;
+      return summary.path;
     } catch (error) {
       logger.error('[TauriAPI] Failed to download exported audio:', error);
       throw error;
     }
   }
+
+  /**
+   * Check whether this app can work with the backend's version
+   *
+   * @param backendUrl Backend base URL (e.g., "http://localhost:8765")
+   * @returns The verdict, or null if the backend is not reachable
+   */
+  async checkBackendCompatibility(backendUrl: string): Promise<BackendCompatibility | null> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    const check = await invoke<{ compatibility: BackendCompatibility | null }>('check_backend_health', {
+      backendUrl,
+    });
+    return check.compatibility;
+  }
+
+  /**
+   * Open the profile's SSH tunnel (or reuse the running one) and wait until it is connected
+   *
+   * @param profileId Profile with a tunnel
+   * @returns The tunnel, whose localUrl reaches the backend
+   */
+  async openBackendTunnel(profileId: string): Promise<TunnelStatus> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    return invoke<TunnelStatus>('open_backend_tunnel', { profileId });
+  }
 }
 
 // Export singleton instance
@@ -3975,6 +3975,18 @@ export interface components {
              * @default 0
              */
             activeJobs: number;
+            /**
+             * Apiversion
+             * @description Newest API revision the backend serves
+             * @default 1
+             */
+            apiVersion: number;
+            /**
+             * Minapiversion
+             * @description Oldest API revision the backend still serves
+             * @default 1
+             */
+            minApiVersion: number;
             /**
              * Hasttsengine
              * @description At least one enabled TTS engine exists (via engine.status)
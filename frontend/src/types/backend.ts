@@ -2,6 +2,33 @@
  * Backend-related TypeScript Interfaces
  */
 
+/**
+ * SSH server a backend is reached through (Rust `tunnel::TunnelConfig`)
+ *
+ * With a tunnel, the profile URL is the backend's address as seen from the
+ * SSH server; the app connects to the tunnel's local URL instead.
+ */
+export interface TunnelConfig {
+  host: string
+
+  /** Defaults to 22 */
+  port?: number
+
+  username: string
+
+  /** Private key file (OpenSSH or PEM) */
+  keyPath: string
+
+  /** Key passphrase, preferably a `{{secret:name}}` vault reference */
+  keyPassphrase?: string | null
+
+  /** null/undefined = ~/.ssh/known_hosts; the server's key must be listed there */
+  knownHostsPath?: string | null
+
+  /** Defaults to 30 */
+  keepaliveSecs?: number
+}
+
 /**
  * Backend Profile
  *
@@ -27,10 +54,22 @@ export interface BackendProfile {
 
   /** Timestamp when this profile was created */
   createdAt: Date
+
+  /** Health check timeout in ms (null/undefined = app default) */
+  healthTimeoutMs?: number | null
+
+  /** Timeout for other backend requests in ms (null/undefined = app default) */
+  requestTimeoutMs?: number | null
+
+  /** Extra HTTP headers sent with every request (e.g. reverse proxy credentials) */
+  headers?: Record<string, string>
+
+  /** Reach the backend through this SSH server */
+  tunnel?: TunnelConfig | null
 }
 
 /**
- * API Backend Profile (dates as ISO strings from localStorage)
+ * API Backend Profile (dates as ISO strings, as sent by the Tauri core)
  *
  * Same as BackendProfile but with Date fields as strings for serialization.
  */
@@ -52,6 +91,18 @@ export interface ApiBackendProfile {
 
   /** ISO timestamp when this profile was created */
   createdAt: string
+
+  /** Health check timeout in ms (null/undefined = app default) */
+  healthTimeoutMs?: number | null
+
+  /** Timeout for other backend requests in ms (null/undefined = app default) */
+  requestTimeoutMs?: number | null
+
+  /** Extra HTTP headers sent with every request (e.g. reverse proxy credentials) */
+  headers?: Record<string, string>
+
+  /** Reach the backend through this SSH server */
+  tunnel?: TunnelConfig | null
 }
 
 /**
@@ -89,6 +140,8 @@ export interface BackendHealthResponse {
   ttsEngines: string[]
   busy: boolean  // True if backend is processing long-running operations
   activeJobs: number  // Number of active generation/export jobs
+  apiVersion?: number  // Newest API revision served (absent before 1.2.0)
+  minApiVersion?: number  // Oldest API revision still served
 
   // Engine availability (for feature-gating)
   hasTtsEngine: boolean
@@ -406,7 +406,21 @@ export interface PlaybackWindow {
 }
 
 // Re-export backend types for convenience
-export type { BackendProfile, ApiBackendProfile, SessionState, BackendHealthResponse } from './backend'
+export type { BackendProfile, ApiBackendProfile, TunnelConfig, SessionState, BackendHealthResponse } from './backend'
+
+// Re-export project file types for convenience
+export type {
+  ProjectFileData,
+  ProjectFileChapter,
+  ProjectFileSegment,
+  ProjectIdMap,
+  ProjectImportSummary,
+  ImportedProjectFile,
+  ProjectDiffSummary,
+  RecoveryCandidate,
+  RecoveredProject,
+  OpenProjectFile,
+} from './projectFile'
 
 // Re-export navigation types for convenience
 export type { ViewType, NavigationState } from './navigation'
@@ -0,0 +1,121 @@
+/**
+ * Project File Types
+ *
+ * The `.abm` project file schema as the Tauri core reads and writes it
+ * (Rust `project::ProjectData`), and the results of the commands that import
+ * or recover project files. Fields the app does not edit are carried along
+ * untouched, so they are typed loosely.
+ */
+
+export interface ProjectFileSegment {
+  id: string
+  text: string
+  segmentType: 'standard' | 'divider'
+  pauseDuration: number
+  orderIndex: number
+  ttsEngine: string
+  ttsModelName: string
+  ttsSpeakerName: string | null
+  language: string
+  isFrozen: boolean
+  [key: string]: unknown
+}
+
+export interface ProjectFileChapter {
+  id: string
+  title: string
+  orderIndex: number
+  segments: ProjectFileSegment[]
+  [key: string]: unknown
+}
+
+export interface ProjectFileData {
+  schemaVersion: number
+  name: string
+  description?: string | null
+  chapters: ProjectFileChapter[]
+  [key: string]: unknown
+}
+
+/** Old ID to new ID, per kind of object (Rust `bundle::IdMap`) */
+export interface ProjectIdMap {
+  projects: Record<string, string>
+  chapters: Record<string, string>
+  segments: Record<string, string>
+  speakers: Record<string, string>
+  samples: Record<string, string>
+}
+
+/** A project recreated on the backend (Rust `bundle::ImportSummary`) */
+export interface ProjectImportSummary {
+  projectId: string
+  /** The imported project with the IDs the backend assigned */
+  project: ProjectFileData
+  ids: ProjectIdMap
+  audioFiles: number
+  reusedSpeakers: string[]
+  rulesImported: number
+  rulesSkipped: number
+}
+
+/** Result of `import_project_file` */
+export interface ImportedProjectFile {
+  path: string
+  /** The project as the file has it, with the file's IDs */
+  project: ProjectFileData
+  import: ProjectImportSummary
+}
+
+/** Chapter/segment changes between two versions of a project (Rust `diff::DiffSummary`) */
+export interface ProjectDiffSummary {
+  projectFields: number
+  settings: number
+  chaptersAdded: number
+  chaptersRemoved: number
+  chaptersRenamed: number
+  chaptersMoved: number
+  segmentsAdded: number
+  segmentsRemoved: number
+  segmentsMoved: number
+  textChanged: number
+  speakerChanged: number
+  segmentsChanged: number
+}
+
+/** Unsaved edits a previous run left in the autosave journal */
+export interface RecoveryCandidate {
+  id: string
+  /** null if the project was never saved to a file */
+  projectPath: string | null
+  name: string | null
+  /** ISO timestamp of the last journal write */
+  savedAt: string
+  fileExists: boolean
+  /** The file changed after the journaled edits started */
+  fileChanged: boolean
+  /** Changes against the file on disk; null if the journal does not parse */
+  summary: ProjectDiffSummary | null
+}
+
+/** Result of `recover_project` */
+export interface RecoveredProject {
+  path: string | null
+  project: ProjectFileData
+  savedAt: string
+}
+
+/**
+ * A backend project that stands for a project file
+ *
+ * Its edits are journaled with the file's IDs so they can be compared with
+ * (and recovered onto) the file.
+ */
+export interface OpenProjectFile {
+  projectId: string
+  /** null for a recovered project that was never saved */
+  path: string | null
+  /** The project as the file (or the recovered journal) has it */
+  base: ProjectFileData
+  /** Backend ID to file ID for chapters and segments */
+  fileIds: Record<string, string>
+}
@@ -19,6 +19,8 @@ export interface HealthUpdateData {
   ttsEngines: string[]
   busy: boolean
   activeJobs: number
+  apiVersion?: number
+  minApiVersion?: number
 
   // Engine availability (for feature-gating)
   // NOTE: These are now Optional (null) because engine availability
@@ -0,0 +1,69 @@
+/**
+ * Project File Helpers
+ *
+ * Turn the backend project that stands for an `.abm` file back into the
+ * file's shape, so the autosave journal can be compared with the file.
+ */
+
+import type { Project, OpenProjectFile, ProjectFileData, ProjectFileChapter, ProjectFileSegment, ProjectIdMap } from '@types'
+
+/**
+ * Backend ID to file ID for chapters and segments, from an import's ID map
+ */
+export function fileIdsOf(ids: ProjectIdMap): Record<string, string> {
+  const fileIds: Record<string, string> = {}
+  for (const map of [ids.chapters, ids.segments]) {
+    for (const [fileId, backendId] of Object.entries(map)) {
+      fileIds[backendId] = fileId
+    }
+  }
+  return fileIds
+}
+
+/**
+ * The project as it would be saved to its file
+ *
+ * Chapters and segments that came from the file keep its IDs and the fields
+ * the app does not edit; ones created since take the backend's.
+ */
+export function toProjectFile(file: OpenProjectFile, project: Project): ProjectFileData {
+  const { base, fileIds } = file
+  const baseChapters = new Map(base.chapters.map((chapter) => [chapter.id, chapter]))
+  const baseSegments = new Map(
+    base.chapters.flatMap((chapter) => chapter.segments).map((segment) => [segment.id, segment])
+  )
+
+  const chapters: ProjectFileChapter[] = project.chapters.map((chapter) => {
+    const id = fileIds[chapter.id] ?? chapter.id
+    const segments: ProjectFileSegment[] = chapter.segments.map((segment) => {
+      const segmentId = fileIds[segment.id] ?? segment.id
+      return {
+        ...baseSegments.get(segmentId),
+        id: segmentId,
+        text: segment.text,
+        segmentType: segment.segmentType,
+        pauseDuration: segment.pauseDuration,
+        orderIndex: segment.orderIndex,
+        ttsEngine: segment.ttsEngine,
+        ttsModelName: segment.ttsModelName,
+        ttsSpeakerName: segment.ttsSpeakerName,
+        language: segment.language,
+        isFrozen: segment.isFrozen,
+      }
+    })
+    return {
+      ...baseChapters.get(id),
+      id,
+      title: chapter.title,
+      orderIndex: chapter.orderIndex,
+      segments,
+    }
+  })
+
+  return {
+    ...base,
+    name: project.title,
+    description: project.description ?? null,
+    chapters,
+  }
+}
@@ -134,6 +134,8 @@ export function translateBackendError(errorMessage: string, t: TFunction): strin
     SEGMENT_REORDER_FAILED: 'segments.errors.reorderFailed',
     SEGMENT_MOVE_FAILED: 'segments.errors.moveFailed',
     SEGMENT_FREEZE_FAILED: 'segments.errors.freezeFailed',
+    SEGMENT_INVALID_AUDIO_FILE: 'segments.errors.invalidAudioFile',
+    SEGMENT_AUDIO_UPLOAD_FAILED: 'segments.errors.audioUploadFailed',
 
     // Chapter errors
     CHAPTER_NOT_FOUND: 'chapters.errors.notFound',
@@ -167,6 +169,10 @@ export function translateBackendError(errorMessage: string, t: TFunction): strin
     // Health errors
     HEALTH_CHECK_FAILED: 'health.errors.checkFailed',
 
+    // Version handshake (Tauri core)
+    COMPAT_UPGRADE_CLIENT: 'startPage.compatibility.upgradeClient',
+    COMPAT_UPGRADE_BACKEND: 'startPage.compatibility.upgradeBackend',
+
     // Export errors
     EXPORT_CHAPTER_NOT_FOUND: 'export.errors.chapterNotFound',
     EXPORT_PROJECT_NOT_FOUND: 'export.errors.projectNotFound',
@@ -328,6 +334,106 @@ export function translateBackendError(errorMessage: string, t: TFunction): strin
     JOB_CANNOT_CANCEL: 'errors.job.cannotCancel',
     JOB_NOT_RUNNING: 'errors.job.notRunning',
     JOB_DELETE_FAILED: 'errors.job.deleteFailed',
+
+    // Audio export (Tauri core)
+    EXPORT_UNSUPPORTED_FORMAT: 'export.errors.unsupportedFormat',
+    EXPORT_INVALID_BITRATE: 'export.errors.invalidBitrate',
+    EXPORT_INVALID_SAMPLE_RATE: 'export.errors.invalidSampleRate',
+    EXPORT_INVALID_CHANNELS: 'export.errors.invalidChannels',
+    EXPORT_INVALID_BIT_DEPTH: 'export.errors.invalidBitDepth',
+    EXPORT_INVALID_QUALITY: 'export.errors.invalidQuality',
+    EXPORT_INVALID_LOUDNESS_TARGET: 'export.errors.invalidLoudnessTarget',
+    EXPORT_LOUDNESS_FAILED: 'export.errors.loudnessFailed',
+    EXPORT_MISSING_CHAPTER_AUDIO: 'export.errors.missingChapterAudio',
+    EXPORT_NO_CHAPTERS: 'export.errors.noChapters',
+    EXPORT_INVALID_SPLIT_DURATION: 'export.errors.invalidSplitDuration',
+    EXPORT_TIMELINE_MISMATCH: 'export.errors.timelineMismatch',
+    EXPORT_INVALID_NAME_TEMPLATE: 'export.errors.invalidNameTemplate',
+    EXPORT_DUPLICATE_FILE_NAME: 'export.errors.duplicateFileName',
+    EXPORT_INVALID_FEED_URL: 'export.errors.invalidFeedUrl',
+    EXPORT_INVALID_RELEASE_DATE: 'export.errors.invalidReleaseDate',
+    EXPORT_NO_AUDIO_FILES: 'export.errors.noAudioFiles',
+    EXPORT_INVALID_COVER: 'export.errors.invalidCover',
+    EXPORT_DECODE_FAILED: 'export.errors.decodeFailed',
+    EXPORT_ENCODE_FAILED: 'export.errors.encodeFailed',
+    EXPORT_TAG_FAILED: 'export.errors.tagFailed',
+    EXPORT_IO_FAILED: 'export.errors.ioFailed',
+
+    // Export presets
+    PRESET_NOT_FOUND: 'errors.preset.notFound',
+    PRESET_READ_ONLY: 'errors.preset.readOnly',
+    PRESET_EMPTY_NAME: 'errors.preset.emptyName',
+    PRESET_DUPLICATE_NAME: 'errors.preset.duplicateName',
+    PRESET_IMPORT_INVALID: 'errors.preset.importInvalid',
+    PRESET_IO_FAILED: 'errors.preset.ioFailed',
+
+    // Project files
+    PROJECT_SYNTAX_ERROR: 'errors.project.syntaxError',
+    PROJECT_SCHEMA_ERROR: 'errors.project.schemaError',
+    PROJECT_UNSUPPORTED_VERSION: 'errors.project.unsupportedVersion',
+    PROJECT_DIALOG_FAILED: 'errors.project.dialogFailed',
+    PROJECT_NOT_RECENT: 'errors.project.notRecent',
+    PROJECT_CHANGED_ON_DISK: 'errors.project.changedOnDisk',
+    PROJECT_BACKUP_NOT_FOUND: 'errors.project.backupNotFound',
+    PROJECT_NOT_RECOVERABLE: 'errors.project.notRecoverable',
+    PROJECT_IO_FAILED: 'errors.project.ioFailed',
+
+    // Project bundles
+    BUNDLE_INVALID_ARCHIVE: 'errors.bundle.invalidArchive',
+    BUNDLE_UNSUPPORTED_VERSION: 'errors.bundle.unsupportedVersion',
+    BUNDLE_MISSING_ENTRY: 'errors.bundle.missingEntry',
+    BUNDLE_UNLISTED_ENTRY: 'errors.bundle.unlistedEntry',
+    BUNDLE_CHECKSUM_MISMATCH: 'errors.bundle.checksumMismatch',
+    BUNDLE_IO_FAILED: 'errors.bundle.ioFailed',
+
+    // Backend profiles
+    PROFILE_NOT_FOUND: 'errors.profile.notFound',
+    PROFILE_EMPTY_NAME: 'errors.profile.emptyName',
+    PROFILE_DUPLICATE_NAME: 'errors.profile.duplicateName',
+    PROFILE_INVALID_URL: 'errors.profile.invalidUrl',
+    PROFILE_INVALID_HEADER: 'errors.profile.invalidHeader',
+    PROFILE_INVALID_TIMEOUT: 'errors.profile.invalidTimeout',
+    PROFILE_INVALID_TUNNEL: 'errors.profile.invalidTunnel',
+    PROFILE_IO_FAILED: 'errors.profile.ioFailed',
+
+    // Credential vault
+    VAULT_UNAVAILABLE: 'errors.vault.unavailable',
+    VAULT_LOCKED: 'errors.vault.locked',
+    VAULT_EMPTY_PASSPHRASE: 'errors.vault.emptyPassphrase',
+    VAULT_WRONG_PASSPHRASE: 'errors.vault.wrongPassphrase',
+    VAULT_CORRUPT: 'errors.vault.corrupt',
+    VAULT_INVALID_SECRET_NAME: 'errors.vault.invalidSecretName',
+    VAULT_SECRET_NOT_FOUND: 'errors.vault.secretNotFound',
+    VAULT_IO_FAILED: 'errors.vault.ioFailed',
+
+    // SSH tunnels
+    TUNNEL_NOT_CONFIGURED: 'errors.tunnel.notConfigured',
+    TUNNEL_INVALID_TARGET: 'errors.tunnel.invalidTarget',
+    TUNNEL_SECRET_UNAVAILABLE: 'errors.tunnel.secretUnavailable',
+    TUNNEL_BIND_FAILED: 'errors.tunnel.bindFailed',
+    TUNNEL_CONNECT_FAILED: 'errors.tunnel.connectFailed',
+    TUNNEL_HANDSHAKE_FAILED: 'errors.tunnel.handshakeFailed',
+    TUNNEL_UNKNOWN_HOST_KEY: 'errors.tunnel.unknownHostKey',
+    TUNNEL_HOST_KEY_MISMATCH: 'errors.tunnel.hostKeyMismatch',
+    TUNNEL_KNOWN_HOSTS_FAILED: 'errors.tunnel.knownHostsFailed',
+    TUNNEL_AUTH_FAILED: 'errors.tunnel.authFailed',
+    TUNNEL_SESSION_LOST: 'errors.tunnel.sessionLost',
+    TUNNEL_CLOSED: 'errors.tunnel.closed',
+    TUNNEL_TIMEOUT: 'errors.tunnel.timeout',
+
+    // Backend API client
+    API_VALIDATION_FAILED: 'errors.api.validationFailed',
+    API_HTTP_STATUS: 'errors.api.httpStatus',
+    API_TIMEOUT: 'errors.api.timeout',
+    API_REQUEST_FAILED: 'errors.api.requestFailed',
+    API_INVALID_RESPONSE: 'errors.api.invalidResponse',
+
+    // Native downloads
+    DOWNLOAD_HTTP_STATUS: 'errors.download.httpStatus',
+    DOWNLOAD_REQUEST_FAILED: 'errors.download.requestFailed',
+    DOWNLOAD_SIZE_MISMATCH: 'errors.download.sizeMismatch',
+    DOWNLOAD_CHECKSUM_MISMATCH: 'errors.download.checksumMismatch',
+    DOWNLOAD_IO_FAILED: 'errors.download.ioFailed',
   }
 
   const i18nKey = errorCodeMap[parsed.code]